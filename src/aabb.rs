@@ -0,0 +1,236 @@
+use crate::{Axis, Float, Interval, Point3, Ray, Vec3};
+
+/// Axis-aligned bounding box.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aabb {
+    /// Corner with the smallest coordinates.
+    min: Point3,
+
+    /// Corner with the largest coordinates.
+    max: Point3,
+}
+
+impl Aabb {
+    /// Box containing nothing. Its union with any box is that box.
+    pub const EMPTY: Self = Self {
+        min: Point3::new(Float::INFINITY, Float::INFINITY, Float::INFINITY),
+        max: Point3::new(
+            Float::NEG_INFINITY,
+            Float::NEG_INFINITY,
+            Float::NEG_INFINITY,
+        ),
+    };
+
+    /// Box containing everything.
+    pub const UNIVERSE: Self = Self {
+        min: Point3::new(
+            Float::NEG_INFINITY,
+            Float::NEG_INFINITY,
+            Float::NEG_INFINITY,
+        ),
+        max: Point3::new(Float::INFINITY, Float::INFINITY, Float::INFINITY),
+    };
+
+    /// Creates the smallest box containing two corner points, in any order.
+    pub fn new(a: &Point3, b: &Point3) -> Self {
+        Self {
+            min: Vec3::min(a, b),
+            max: Vec3::max(a, b),
+        }
+    }
+
+    /// Creates the smallest box containing all of the given points.
+    pub fn from_points<'a, I>(points: I) -> Self
+    where
+        I: IntoIterator<Item = &'a Point3>,
+    {
+        points.into_iter().fold(Self::EMPTY, |bbox, p| {
+            Self::surrounding(&bbox, &Self::new(p, p))
+        })
+    }
+
+    /// Creates the smallest box containing two boxes.
+    pub fn surrounding(a: &Self, b: &Self) -> Self {
+        Self {
+            min: Vec3::min(&a.min, &b.min),
+            max: Vec3::max(&a.max, &b.max),
+        }
+    }
+
+    /// Retrieves the corner with the smallest coordinates.
+    pub fn min(&self) -> &Point3 {
+        &self.min
+    }
+
+    /// Retrieves the corner with the largest coordinates.
+    pub fn max(&self) -> &Point3 {
+        &self.max
+    }
+
+    /// Determines whether the box contains no points.
+    pub fn is_empty(&self) -> bool {
+        self.min.x() > self.max.x() || self.min.y() > self.max.y() || self.min.z() > self.max.z()
+    }
+
+    /// Determines whether the point lies inside the box or on its boundary.
+    pub fn contains(&self, p: &Point3) -> bool {
+        Axis::ALL
+            .into_iter()
+            .all(|axis| self.interval(axis).contains(p[axis]))
+    }
+
+    /// Range of coordinates covered by the box along `axis`.
+    pub fn interval(&self, axis: Axis) -> Interval {
+        Interval::new(self.min[axis], self.max[axis])
+    }
+
+    /// Extent of the box along each axis.
+    pub fn extent(&self) -> Vec3 {
+        self.max - self.min
+    }
+
+    /// Center point of the box.
+    pub fn centroid(&self) -> Point3 {
+        0.5 * (self.min + self.max)
+    }
+
+    /// Axis along which the box is largest.
+    pub fn longest_axis(&self) -> Axis {
+        let extent = self.extent();
+        if extent.x() > extent.y() && extent.x() > extent.z() {
+            Axis::X
+        } else if extent.y() > extent.z() {
+            Axis::Y
+        } else {
+            Axis::Z
+        }
+    }
+
+    /// Surface area of the box.
+    pub fn surface_area(&self) -> Float {
+        if self.is_empty() {
+            return 0.0;
+        }
+        let e = self.extent();
+        2.0 * (e.x() * e.y() + e.y() * e.z() + e.z() * e.x())
+    }
+
+    /// Clips the ray parameter interval to the part of the ray inside the box
+    /// (slab test). Returns `None` when the ray misses the box.
+    #[cfg(not(feature = "simd"))]
+    pub fn clip(&self, ray: &Ray, ray_t: &Interval) -> Option<Interval> {
+        let (mut t0, mut t1) = (ray_t.min(), ray_t.max());
+
+        for axis in Axis::ALL {
+            let inv_d = 1.0 / ray.direction()[axis];
+            let mut ta = (self.min[axis] - ray.origin()[axis]) * inv_d;
+            let mut tb = (self.max[axis] - ray.origin()[axis]) * inv_d;
+            if inv_d < 0.0 {
+                std::mem::swap(&mut ta, &mut tb);
+            }
+
+            // NaN from 0 * inf (ray lying in a slab plane) leaves the bounds untouched.
+            if ta > t0 {
+                t0 = ta;
+            }
+            if tb < t1 {
+                t1 = tb;
+            }
+            if t1 < t0 {
+                return None;
+            }
+        }
+
+        Some(Interval::new(t0, t1))
+    }
+
+    /// Clips the ray parameter interval to the part of the ray inside the box
+    /// (slab test). Returns `None` when the ray misses the box.
+    #[cfg(feature = "simd")]
+    pub fn clip(&self, ray: &Ray, ray_t: &Interval) -> Option<Interval> {
+        use crate::vec3::lanes::Simd;
+
+        // The padding lane spans the whole real line so it never constrains the result.
+        let load = |v: &Vec3, pad: Float| Simd::new([v[0], v[1], v[2], pad]);
+        let origin = load(ray.origin(), 0.0);
+        let inv_d = Simd::splat(1.0) / load(ray.direction(), 1.0);
+        let ta = (load(&self.min, Float::NEG_INFINITY) - origin) * inv_d;
+        let tb = (load(&self.max, Float::INFINITY) - origin) * inv_d;
+
+        let negative = inv_d.cmp_lt(Simd::splat(0.0));
+        let near = negative.blend(tb, ta);
+        let far = negative.blend(ta, tb);
+
+        // NaN from 0 * inf (ray lying in a slab plane) leaves the bounds untouched.
+        let near = near.is_nan().blend(Simd::splat(Float::NEG_INFINITY), near);
+        let far = far.is_nan().blend(Simd::splat(Float::INFINITY), far);
+
+        let t0 = near.to_array().into_iter().fold(ray_t.min(), Float::max);
+        let t1 = far.to_array().into_iter().fold(ray_t.max(), Float::min);
+        (t0 <= t1).then(|| Interval::new(t0, t1))
+    }
+
+    /// Determines whether the ray passes through the box within the interval.
+    pub fn hit(&self, ray: &Ray, ray_t: &Interval) -> bool {
+        self.clip(ray, ray_t).is_some()
+    }
+}
+
+impl Default for Aabb {
+    fn default() -> Self {
+        Self::EMPTY
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Aabb;
+    use crate::{Axis, Float, Interval, Point3, Ray, Vec3};
+
+    #[test]
+    fn aabb_construction() {
+        let bbox = Aabb::new(&Point3::new(1.0, -1.0, 2.0), &Point3::new(-1.0, 1.0, 0.0));
+        assert_eq!(*bbox.min(), Point3::new(-1.0, -1.0, 0.0));
+        assert_eq!(*bbox.max(), Point3::new(1.0, 1.0, 2.0));
+        assert_eq!(bbox.centroid(), Point3::new(0.0, 0.0, 1.0));
+        assert_eq!(bbox.surface_area(), 24.0);
+        assert!(bbox.contains(&Point3::new(1.0, 0.0, 0.5)));
+        assert!(!bbox.contains(&Point3::new(0.0, 0.0, 2.5)));
+
+        let other = Aabb::new(&Point3::new(5.0, 0.0, 0.0), &Point3::new(6.0, 0.5, 0.5));
+        let union = Aabb::surrounding(&bbox, &other);
+        assert_eq!(*union.max(), Point3::new(6.0, 1.0, 2.0));
+        assert_eq!(union.longest_axis(), Axis::X);
+        assert_eq!(union.interval(Axis::Y), Interval::new(-1.0, 1.0));
+
+        assert!(Aabb::EMPTY.is_empty());
+        assert_eq!(Aabb::surrounding(&Aabb::EMPTY, &bbox), bbox);
+
+        let points = [Point3::new(0.0, 0.0, 0.0), Point3::new(1.0, 2.0, 3.0)];
+        let bbox = Aabb::from_points(points.iter());
+        assert_eq!(*bbox.max(), Point3::new(1.0, 2.0, 3.0));
+        assert_eq!(bbox.longest_axis(), Axis::Z);
+    }
+
+    #[test]
+    fn aabb_hit() {
+        let bbox = Aabb::new(&Point3::new(-1.0, -1.0, -1.0), &Point3::new(1.0, 1.0, 1.0));
+        let bounds = Interval::new(0.0, Float::INFINITY);
+
+        let ray = Ray::new(Point3::new(0.0, 0.0, -5.0), Vec3::new(0.0, 0.0, 1.0));
+        let t = bbox.clip(&ray, &bounds).unwrap();
+        assert_eq!((t.min(), t.max()), (4.0, 6.0));
+
+        // Misses, rays pointing away, and intervals ending before the box.
+        let ray = Ray::new(Point3::new(0.0, 2.0, -5.0), Vec3::new(0.0, 0.0, 1.0));
+        assert!(!bbox.hit(&ray, &bounds));
+        let ray = Ray::new(Point3::new(0.0, 0.0, -5.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(!bbox.hit(&ray, &bounds));
+        let ray = Ray::new(Point3::new(0.0, 0.0, -5.0), Vec3::new(0.0, 0.0, 1.0));
+        assert!(!bbox.hit(&ray, &Interval::new(0.0, 3.0)));
+
+        // Rays starting inside the box.
+        let ray = Ray::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(1.0, 1.0, 0.0));
+        assert!(bbox.hit(&ray, &bounds));
+    }
+}
@@ -1,21 +1,73 @@
 // use std::cmp::Ordering;
 
-/// Tolerance for approximate comparisons betweens `f64` values.
-const F64_TOLERANCE: f64 = 1e-8;
+/// Tolerance of approximate comparisons. Two values are almost equal when
+/// they are within `absolute` of each other, which matters near zero, within
+/// `relative` of their magnitude, which matters at large scales, or at most
+/// `ulps` representable values apart.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tolerance<T> {
+    /// Largest absolute difference.
+    absolute: T,
+
+    /// Largest difference relative to the larger magnitude.
+    relative: T,
+
+    /// Largest distance in units in the last place.
+    ulps: u32,
+}
+
+impl<T: Copy> Tolerance<T> {
+    /// Creates a new tolerance with the given absolute and relative bounds.
+    pub const fn new(absolute: T, relative: T) -> Self {
+        Self {
+            absolute,
+            relative,
+            ulps: 0,
+        }
+    }
+
+    /// Set the largest distance in units in the last place.
+    pub fn with_ulps(mut self, ulps: u32) -> Self {
+        self.ulps = ulps;
+        self
+    }
+
+    /// Retrieve the largest absolute difference.
+    pub fn absolute(&self) -> T {
+        self.absolute
+    }
+
+    /// Retrieve the largest relative difference.
+    pub fn relative(&self) -> T {
+        self.relative
+    }
+
+    /// Retrieve the largest distance in units in the last place.
+    pub fn ulps(&self) -> u32 {
+        self.ulps
+    }
+}
 
-/// Tolerance for approximate comparisons between `f32` values.
-const F32_TOLERANCE: f32 = 1e-6;
+/// Approximate partial equality.
+pub trait AlmostPartialEq<Rhs = Self>: Sized {
+    /// Tolerance of `almost_eq` and `almost_zero`.
+    const TOLERANCE: Tolerance<Self>;
+
+    /// Value is almost equal to another value within `tolerance`.
+    fn almost_eq_with(&self, other: Rhs, tolerance: &Tolerance<Self>) -> bool;
+
+    /// Value is almost zero within the absolute bound of `tolerance`.
+    fn almost_zero_with(&self, tolerance: &Tolerance<Self>) -> bool;
 
-/// Naive approximate partial equality.
-pub trait AlmostPartialEq<Rhs = Self>
-where
-    Rhs: Sized,
-{
     /// Value is almost equal to another value.
-    fn almost_eq(&self, other: Rhs) -> bool;
+    fn almost_eq(&self, other: Rhs) -> bool {
+        self.almost_eq_with(other, &Self::TOLERANCE)
+    }
 
     /// Value is almost equal to zero.
-    fn almost_zero(&self) -> bool;
+    fn almost_zero(&self) -> bool {
+        self.almost_zero_with(&Self::TOLERANCE)
+    }
 
     /// Value is almost not equal to another value.
     fn almost_ne(&self, other: Rhs) -> bool {
@@ -53,25 +105,58 @@ where
 //     }
 // }
 
-impl AlmostPartialEq for f64 {
-    fn almost_eq(&self, other: Self) -> bool {
-        (self - other) < F64_TOLERANCE
-    }
-
-    fn almost_zero(&self) -> bool {
-        f64::abs(*self) < F64_TOLERANCE
-    }
+macro_rules! almost_float {
+    ($t:ty, $signed:ty, $absolute:expr, $relative:expr) => {
+        impl Tolerance<$t> {
+            /// Determines whether `a` and `b` are within tolerance, with
+            /// `scale` as the magnitude for the relative bound.
+            fn accepts(&self, a: $t, b: $t, scale: $t) -> bool {
+                if a == b {
+                    return true;
+                }
+                let diff = (a - b).abs();
+                if diff <= self.absolute || diff <= self.relative * scale {
+                    return true;
+                }
+                // Finite values of the same sign are ordered like their bits.
+                let same_sign = a.is_sign_negative() == b.is_sign_negative();
+                a.is_finite()
+                    && b.is_finite()
+                    && same_sign
+                    && (a.to_bits() as $signed - b.to_bits() as $signed).unsigned_abs()
+                        <= self.ulps as _
+            }
+
+            /// Determines whether the components of `u` and `v` are within
+            /// tolerance, relative to their largest magnitude, so that small
+            /// components of large vectors are compared at the scale of the
+            /// vectors.
+            pub fn accepts_all(&self, u: &[$t], v: &[$t]) -> bool {
+                let scale = u.iter().chain(v).fold(0.0, |m: $t, x| m.max(x.abs()));
+                u.iter().zip(v).all(|(&a, &b)| self.accepts(a, b, scale))
+            }
+        }
+
+        impl AlmostPartialEq for $t {
+            const TOLERANCE: Tolerance<$t> = Tolerance {
+                absolute: $absolute,
+                relative: $relative,
+                ulps: 4,
+            };
+
+            fn almost_eq_with(&self, other: Self, tolerance: &Tolerance<Self>) -> bool {
+                tolerance.accepts(*self, other, self.abs().max(other.abs()))
+            }
+
+            fn almost_zero_with(&self, tolerance: &Tolerance<Self>) -> bool {
+                self.abs() <= tolerance.absolute
+            }
+        }
+    };
 }
 
-impl AlmostPartialEq for f32 {
-    fn almost_eq(&self, other: Self) -> bool {
-        (self - other) < F32_TOLERANCE
-    }
-
-    fn almost_zero(&self) -> bool {
-        f32::abs(*self) < F32_TOLERANCE
-    }
-}
+almost_float!(f64, i64, 1e-8, 1e-8);
+almost_float!(f32, i32, 1e-6, 1e-6);
 
 // impl AlmostPartialOrd for f64 {
 //     fn almost_partial_cmp(&self, other: Self) -> Ordering {
@@ -96,3 +181,38 @@ impl AlmostPartialEq for f32 {
 //         }
 //     }
 // }
+
+#[cfg(test)]
+mod tests {
+    use super::{AlmostPartialEq, Tolerance};
+
+    #[test]
+    fn almost_symmetric() {
+        assert!(!0.0.almost_eq(1.0));
+        assert!(!1.0.almost_eq(0.0));
+        assert!(!(-5.0f32).almost_eq(5.0));
+        assert!(1.0.almost_eq(1.0 + 1e-9));
+        assert!((1.0 + 1e-9).almost_eq(1.0));
+        assert!(f64::INFINITY.almost_eq(f64::INFINITY));
+        assert!(!f64::NAN.almost_eq(f64::NAN));
+    }
+
+    #[test]
+    fn almost_relative() {
+        // Large values differ by more than the absolute bound.
+        assert!(1e6.almost_eq(1e6 + 1e-4));
+        assert!(!1e6.almost_eq(1e6 + 1.0));
+        assert!(!1e-6.almost_zero());
+        assert!(1e-6f32.almost_zero());
+
+        let exact = Tolerance::new(0.0, 0.0);
+        assert!(!1.0.almost_eq_with(1.0 + f64::EPSILON, &exact));
+        assert!(1.0.almost_eq_with(1.0 + f64::EPSILON, &exact.with_ulps(1)));
+        assert!(!1.0.almost_eq_with(1.0 + 2.0 * f64::EPSILON, &exact.with_ulps(1)));
+        assert!(!(-0.5).almost_eq_with(0.5, &exact.with_ulps(u32::MAX)));
+
+        let loose = Tolerance::new(0.1, 0.0);
+        assert!(1.0.almost_eq_with(1.05, &loose));
+        assert!(0.05.almost_zero_with(&loose));
+    }
+}
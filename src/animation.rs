@@ -0,0 +1,470 @@
+use std::error::Error;
+use std::ops::{Add, Mul, Sub};
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::camera::Camera;
+use crate::hittable::{Hittable, HittableList};
+use crate::instance::Instance;
+use crate::renderer::Renderer;
+use crate::trace;
+use crate::transform::Transform;
+use crate::util::random;
+use crate::{image, Float, Point3, Vec3};
+
+/// Values that can be blended between keyframes, such as `Float` and `Vec3`.
+pub trait Animatable:
+    Clone + Add<Output = Self> + Sub<Output = Self> + Mul<Float, Output = Self>
+{
+}
+
+impl<T> Animatable for T where T: Clone + Add<Output = T> + Sub<Output = T> + Mul<Float, Output = T> {}
+
+/// Method used to blend between neighbouring keyframes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Interpolation {
+    /// Straight lines between keyframes.
+    #[default]
+    Linear,
+
+    /// Smooth curve through every keyframe (Catmull–Rom spline), with
+    /// tangents scaled by the keyframe spacing so uneven timing stays smooth.
+    CatmullRom,
+}
+
+/// Value at a point in time.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Keyframe<T> {
+    /// Time of the keyframe in seconds.
+    pub time: Float,
+
+    /// Value at `time`.
+    pub value: T,
+}
+
+impl<T> Keyframe<T> {
+    /// Creates a new keyframe.
+    pub fn new(time: Float, value: T) -> Self {
+        Self { time, value }
+    }
+}
+
+/// Animated value interpolated between keyframes. Before the first and after
+/// the last keyframe, the value holds.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Track<T: Animatable> {
+    /// Keyframes ordered by time.
+    keys: Vec<Keyframe<T>>,
+
+    /// Blending between keyframes.
+    interpolation: Interpolation,
+}
+
+impl<T: Animatable> Track<T> {
+    /// Creates a new linearly interpolated track. Panics if there are no keyframes.
+    pub fn new(mut keys: Vec<Keyframe<T>>) -> Self {
+        assert!(!keys.is_empty(), "track needs at least one keyframe");
+        keys.sort_by(|a, b| a.time.total_cmp(&b.time));
+        Self {
+            keys,
+            interpolation: Interpolation::Linear,
+        }
+    }
+
+    /// Sets the blending between keyframes.
+    pub fn with_interpolation(mut self, interpolation: Interpolation) -> Self {
+        self.interpolation = interpolation;
+        self
+    }
+
+    /// Creates a track that holds a single value at all times.
+    pub fn constant(value: T) -> Self {
+        Self::new(vec![Keyframe::new(0.0, value)])
+    }
+
+    /// Retrieves the keyframes ordered by time.
+    pub fn keys(&self) -> &[Keyframe<T>] {
+        &self.keys
+    }
+
+    /// Samples the track at `time`.
+    pub fn sample(&self, time: Float) -> T {
+        let next = self.keys.partition_point(|key| key.time <= time);
+        if next == 0 {
+            return self.keys[0].value.clone();
+        }
+        if next == self.keys.len() {
+            return self.keys[next - 1].value.clone();
+        }
+
+        let (i, j) = (next - 1, next);
+        let (a, b) = (&self.keys[i], &self.keys[j]);
+        let dt = b.time - a.time;
+        let t = (time - a.time) / dt;
+
+        match self.interpolation {
+            Interpolation::Linear => a.value.clone() + (b.value.clone() - a.value.clone()) * t,
+            Interpolation::CatmullRom => {
+                // Cubic Hermite segment with tangents scaled to this segment's duration.
+                let (ma, mb) = (self.tangent(i) * dt, self.tangent(j) * dt);
+                let (t2, t3) = (t * t, t * t * t);
+                a.value.clone() * (2.0 * t3 - 3.0 * t2 + 1.0)
+                    + ma * (t3 - 2.0 * t2 + t)
+                    + b.value.clone() * (-2.0 * t3 + 3.0 * t2)
+                    + mb * (t3 - t2)
+            }
+        }
+    }
+
+    /// Rate of change at keyframe `i`: the slope between its neighbours, or
+    /// the one-sided slope at either end of the track.
+    fn tangent(&self, i: usize) -> T {
+        let prev = &self.keys[i.saturating_sub(1)];
+        let next = &self.keys[(i + 1).min(self.keys.len() - 1)];
+        (next.value.clone() - prev.value.clone()) * (1.0 / (next.time - prev.time))
+    }
+}
+
+/// Keyframed object placement: scale, then rotation about a fixed axis, then
+/// translation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransformTrack {
+    /// Per-axis scale factors.
+    pub scale: Track<Vec3>,
+
+    /// Axis of rotation.
+    pub axis: Vec3,
+
+    /// Counter-clockwise rotation about `axis` in degrees.
+    pub rotation: Track<Float>,
+
+    /// Offset of the object.
+    pub translation: Track<Vec3>,
+}
+
+impl TransformTrack {
+    /// Creates a track that keeps an object at the identity placement.
+    pub fn identity() -> Self {
+        Self {
+            scale: Track::constant(Vec3::new(1.0, 1.0, 1.0)),
+            axis: Vec3::new(0.0, 1.0, 0.0),
+            rotation: Track::constant(0.0),
+            translation: Track::constant(Vec3::new(0.0, 0.0, 0.0)),
+        }
+    }
+
+    /// Creates a track that only moves an object.
+    pub fn translation(translation: Track<Vec3>) -> Self {
+        Self {
+            translation,
+            ..Self::identity()
+        }
+    }
+
+    /// Creates a track that spins an object about `axis`.
+    pub fn rotation(axis: &Vec3, rotation: Track<Float>) -> Self {
+        Self {
+            axis: *axis,
+            rotation,
+            ..Self::identity()
+        }
+    }
+
+    /// Samples the placement at `time`.
+    pub fn sample(&self, time: Float) -> Transform {
+        Transform::scaling(&self.scale.sample(time))
+            .then(&Transform::rotation(&self.axis, self.rotation.sample(time)))
+            .then(&Transform::translation(&self.translation.sample(time)))
+    }
+}
+
+/// Keyframed camera viewpoint.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CameraTrack {
+    /// Point the camera is looking from.
+    pub look_from: Track<Point3>,
+
+    /// Point the camera is looking at.
+    pub look_at: Track<Point3>,
+
+    /// Vertical FOV in degrees. Without a track, the base camera's FOV holds.
+    pub vfov: Option<Track<Float>>,
+}
+
+impl CameraTrack {
+    /// Creates a new camera track.
+    pub fn new(look_from: Track<Point3>, look_at: Track<Point3>) -> Self {
+        Self {
+            look_from,
+            look_at,
+            vfov: None,
+        }
+    }
+
+    /// Animates the vertical FOV.
+    pub fn with_vfov(mut self, vfov: Track<Float>) -> Self {
+        self.vfov = Some(vfov);
+        self
+    }
+
+    /// Creates a track that holds the viewpoint of `camera`.
+    pub fn fixed(camera: &Camera) -> Self {
+        Self::new(
+            Track::constant(camera.look_from),
+            Track::constant(camera.look_at),
+        )
+    }
+
+    /// Creates a smooth camera move through the given `(time, look_from,
+    /// look_at)` keyframes.
+    pub fn path(keys: &[(Float, Point3, Point3)]) -> Self {
+        let look_from = keys
+            .iter()
+            .map(|&(t, from, _)| Keyframe::new(t, from))
+            .collect();
+        let look_at = keys
+            .iter()
+            .map(|&(t, _, at)| Keyframe::new(t, at))
+            .collect();
+        Self::new(
+            Track::new(look_from).with_interpolation(Interpolation::CatmullRom),
+            Track::new(look_at).with_interpolation(Interpolation::CatmullRom),
+        )
+    }
+
+    /// Creates a turntable orbit around `center` at the height and distance of
+    /// `look_from`, completing one revolution every `period` seconds.
+    pub fn orbit(look_from: &Point3, center: &Point3, period: Float) -> Self {
+        // Dense keyframes keep the linear interpolation close to the circle.
+        const STEPS: usize = 64;
+        let offset = look_from - center;
+        let keys = (0..=STEPS)
+            .map(|i| {
+                let time = period * i as Float / STEPS as Float;
+                let degrees = 360.0 * i as Float / STEPS as Float;
+                let p = Transform::rotation(&Vec3::new(0.0, 1.0, 0.0), degrees).vector(&offset);
+                Keyframe::new(time, center + p)
+            })
+            .collect();
+
+        Self::new(Track::new(keys), Track::constant(*center))
+    }
+}
+
+/// Scene animated over time: a camera path plus objects with keyframed
+/// transforms.
+pub struct Timeline {
+    /// Camera viewpoint over time.
+    camera: CameraTrack,
+
+    /// Shared geometry and its placement over time.
+    objects: Vec<(Arc<dyn Hittable>, TransformTrack)>,
+}
+
+impl Timeline {
+    /// Creates a new timeline with no objects.
+    pub fn new(camera: CameraTrack) -> Self {
+        Self {
+            camera,
+            objects: Vec::new(),
+        }
+    }
+
+    /// Adds an object placed by `track`.
+    pub fn add(&mut self, object: Arc<dyn Hittable>, track: TransformTrack) {
+        self.objects.push((object, track));
+    }
+
+    /// Adds an object that does not move.
+    pub fn add_static(&mut self, object: Arc<dyn Hittable>) {
+        self.add(object, TransformTrack::identity());
+    }
+
+    /// Builds the world at `time`. Object IDs follow insertion order.
+    pub fn world(&self, time: Float) -> HittableList<Instance> {
+        trace::span!(DEBUG, "scene_build", objects = self.objects.len());
+
+        let mut world = HittableList::new();
+        for (object, track) in &self.objects {
+            world.add(Instance::new(object.clone(), track.sample(time)));
+        }
+        world
+    }
+
+    /// Builds the camera at `time` from the non-animated settings of `base`.
+    pub fn camera(&self, base: &Camera, time: Float) -> Camera {
+        let vfov = self
+            .camera
+            .vfov
+            .as_ref()
+            .map_or(base.vfov, |track| track.sample(time));
+        base.with_view(
+            self.camera.look_from.sample(time),
+            self.camera.look_at.sample(time),
+            vfov,
+        )
+    }
+
+    /// Renders `frames` frames at `fps` frames per second into `dir`, named
+    /// `{name}0000.ppm`, `{name}0001.ppm`, and so on. Every frame reseeds the
+    /// random generator from `seed` and its frame number, so any single frame
+    /// can be re-rendered identically on its own. If the renderer is cancelled,
+    /// the sequence stops without writing the partial frame.
+    #[allow(clippy::too_many_arguments)]
+    pub fn render_sequence<P>(
+        &self,
+        renderer: &Renderer,
+        base: &Camera,
+        frames: u32,
+        fps: Float,
+        seed: u64,
+        dir: P,
+        name: &str,
+    ) -> Result<(), Box<dyn Error>>
+    where
+        P: AsRef<Path>,
+    {
+        trace::span!(INFO, "render_sequence", frames);
+
+        for frame in 0..frames {
+            trace::span!(INFO, "frame", frame);
+            let time = frame as Float / fps;
+            let camera = self.camera(base, time);
+            let world = self.world(time);
+
+            random::seed(frame_seed(seed, frame));
+            let data = frame_renderer(renderer, frame).render(&camera, &world);
+            if renderer.is_cancelled() {
+                break;
+            }
+
+            let (width, height) = camera.dim();
+            let path = dir.as_ref().join(format!("{name}{frame:04}.ppm"));
+            image::create_ppm(path, &data, width, height)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Derives a well-mixed per-frame seed from a sequence seed.
+pub fn frame_seed(seed: u64, frame: u32) -> u64 {
+    random::mix(seed.wrapping_add((frame as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15)))
+}
+
+/// Renderer for one frame of a sequence. Seeded renderers reseed every
+/// sample, so their seed has to change with the frame too, or every frame
+/// would show the same noise.
+fn frame_renderer(renderer: &Renderer, frame: u32) -> Renderer {
+    let settings = renderer.settings();
+    match settings.seed() {
+        Some(seed) => renderer
+            .clone()
+            .with_settings(settings.with_seed(frame_seed(seed, frame))),
+        None => renderer.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::Lambertian;
+    use crate::renderer::RenderSettings;
+    use crate::sphere::Sphere;
+    use crate::{Color, Interval, Ray};
+
+    #[test]
+    fn track_sample() {
+        let track = Track::new(vec![
+            Keyframe::new(2.0, 10.0),
+            Keyframe::new(0.0, 0.0),
+            Keyframe::new(1.0, 4.0),
+        ]);
+        assert_eq!(track.sample(-1.0), 0.0);
+        assert_eq!(track.sample(0.5), 2.0);
+        assert_eq!(track.sample(1.0), 4.0);
+        assert_eq!(track.sample(1.5), 7.0);
+        assert_eq!(track.sample(3.0), 10.0);
+    }
+
+    #[test]
+    fn track_catmull_rom() {
+        let keys = vec![
+            Keyframe::new(0.0, 0.0),
+            Keyframe::new(1.0, 1.0),
+            Keyframe::new(2.0, 0.0),
+            Keyframe::new(3.0, 1.0),
+        ];
+        let track = Track::new(keys.clone()).with_interpolation(Interpolation::CatmullRom);
+
+        // The curve passes through every keyframe.
+        for key in &keys {
+            assert!((track.sample(key.time) - key.value).abs() < 1e-6);
+        }
+
+        // The tangent is flat at the peak, so the curve bulges above the
+        // straight line on the way up and eases over the top.
+        assert!(track.sample(0.75) > Track::new(keys).sample(0.75));
+        assert!(track.sample(0.95) < 1.0 && track.sample(1.05) < 1.0);
+
+        // Evenly spaced collinear keyframes reduce to linear motion.
+        let line = Track::new(vec![
+            Keyframe::new(0.0, Vec3::new(0.0, 0.0, 0.0)),
+            Keyframe::new(1.0, Vec3::new(1.0, 2.0, 0.0)),
+            Keyframe::new(2.0, Vec3::new(2.0, 4.0, 0.0)),
+        ])
+        .with_interpolation(Interpolation::CatmullRom);
+        assert!(line.sample(0.5).almost_eq(&Vec3::new(0.5, 1.0, 0.0)));
+        assert!(line.sample(1.25).almost_eq(&Vec3::new(1.25, 2.5, 0.0)));
+    }
+
+    #[test]
+    fn timeline_world() {
+        let material = Arc::new(Lambertian::new(&Color::new(0.5, 0.5, 0.5)));
+        let sphere: Arc<dyn Hittable> =
+            Arc::new(Sphere::new(Point3::new(0.0, 0.0, 0.0), 1.0, material));
+
+        let camera = CameraTrack::new(
+            Track::constant(Point3::new(0.0, 0.0, 5.0)),
+            Track::constant(Point3::new(0.0, 0.0, 0.0)),
+        );
+        let mut timeline = Timeline::new(camera);
+        let path = Track::new(vec![
+            Keyframe::new(0.0, Vec3::new(0.0, 0.0, 0.0)),
+            Keyframe::new(1.0, Vec3::new(10.0, 0.0, 0.0)),
+        ]);
+        timeline.add(sphere, TransformTrack::translation(path));
+
+        let bounds = Interval::new(0.001, Float::INFINITY);
+        let ray = Ray::new(Point3::new(5.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(timeline.world(0.0).hit(&ray, &bounds).is_none());
+        assert!(timeline.world(0.5).hit(&ray, &bounds).is_some());
+    }
+
+    #[test]
+    fn frame_seeds() {
+        assert_eq!(frame_seed(7, 3), frame_seed(7, 3));
+        assert_ne!(frame_seed(7, 3), frame_seed(7, 4));
+        assert_ne!(frame_seed(7, 3), frame_seed(8, 3));
+
+        // Seeded renders still get different noise in every frame.
+        let material = Arc::new(Lambertian::new(&Color::new(0.5, 0.5, 0.5)));
+        let mut world = HittableList::new();
+        world.add(Sphere::new(Point3::new(0.0, 0.0, -2.0), 1.0, material));
+        let camera = Camera::new(
+            1.0,
+            4,
+            90.0,
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(0.0, 0.0, -1.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            0.0,
+            1.0,
+        )
+        .unwrap();
+        let renderer = Renderer::new(RenderSettings::new(2, 4).unwrap().with_seed(7));
+        let render = |frame| frame_renderer(&renderer, frame).render(&camera, &world);
+        assert_eq!(render(0), render(0));
+        assert_ne!(render(0), render(1));
+    }
+}
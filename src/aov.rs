@@ -0,0 +1,157 @@
+use crate::Color;
+
+/// Fraction of a pixel's samples that hit one object.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Coverage {
+    /// Object ID of the hit object.
+    pub id: usize,
+
+    /// Fraction of the pixel's samples, in [0, 1].
+    pub coverage: f32,
+}
+
+/// Accumulates the object IDs hit by a pixel's samples into coverages, ordered
+/// by decreasing coverage and truncated to the `ranks` largest. Samples that
+/// missed (`None`) count toward the total but are not listed.
+pub fn coverage(samples: &[Option<usize>], ranks: usize) -> Vec<Coverage> {
+    let mut entries: Vec<Coverage> = Vec::new();
+    for id in samples.iter().flatten() {
+        match entries.iter_mut().find(|entry| entry.id == *id) {
+            Some(entry) => entry.coverage += 1.0,
+            None => entries.push(Coverage {
+                id: *id,
+                coverage: 1.0,
+            }),
+        }
+    }
+
+    let total = samples.len() as f32;
+    for entry in entries.iter_mut() {
+        entry.coverage /= total;
+    }
+
+    // Ties are broken by ID so the order is deterministic.
+    entries.sort_by(|a, b| b.coverage.total_cmp(&a.coverage).then(a.id.cmp(&b.id)));
+    entries.truncate(ranks);
+    entries
+}
+
+/// Scrambles an object ID into a well-distributed 32-bit hash (MurmurHash3 finalizer).
+pub fn hash_id(id: usize) -> u32 {
+    let mut h = id as u64;
+    h ^= h >> 33;
+    h = h.wrapping_mul(0xff51_afd7_ed55_8ccd);
+    h ^= h >> 33;
+    h = h.wrapping_mul(0xc4ce_b9fe_1a85_ec53);
+    h ^= h >> 33;
+    h as u32
+}
+
+/// Encodes an object ID as a float the way Cryptomatte does: the hash bits are
+/// reinterpreted as an `f32`, with the exponent nudged away from zero and 255 so
+/// the value is never denormal, infinite, or NaN.
+pub fn id_to_float(id: usize) -> f32 {
+    let mut bits = hash_id(id);
+    let exponent = (bits >> 23) & 0xff;
+    if exponent == 0 || exponent == 0xff {
+        bits ^= 1 << 23;
+    }
+    f32::from_bits(bits)
+}
+
+/// Flat color identifying an object, derived from its hashed ID.
+pub fn id_color(id: usize) -> Color {
+    let [r, g, b, _] = hash_id(id).to_le_bytes();
+    Color::new(r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0)
+}
+
+/// Converts per-pixel coverages to an image of flat ID colors, blended by
+/// coverage so that object edges stay antialiased. Uncovered area is black.
+pub fn id_colors(pixels: &[Vec<Coverage>]) -> Vec<Color> {
+    pixels
+        .iter()
+        .map(|entries| {
+            entries
+                .iter()
+                .fold(Color::new(0.0, 0.0, 0.0), |acc, entry| {
+                    acc + entry.coverage * id_color(entry.id)
+                })
+        })
+        .collect()
+}
+
+/// Maps camera-space depths to [0, 1] between the `near` and `far` planes.
+/// Depths beyond the planes are clamped, and misses, at infinite depth, map to 1.
+pub fn normalize_depth(depth: &[f32], near: f32, far: f32) -> Vec<f32> {
+    let range = far - near;
+    depth
+        .iter()
+        .map(|d| {
+            if range > 0.0 {
+                ((d - near) / range).clamp(0.0, 1.0)
+            } else {
+                // Degenerate planes split the depths at `near`.
+                if *d > near {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn aov_coverage() {
+        let samples = [
+            Some(3),
+            None,
+            Some(1),
+            Some(3),
+            Some(1),
+            Some(3),
+            None,
+            Some(7),
+        ];
+        let entries = coverage(&samples, 2);
+        assert_eq!(
+            entries,
+            [
+                Coverage {
+                    id: 3,
+                    coverage: 0.375
+                },
+                Coverage {
+                    id: 1,
+                    coverage: 0.25
+                },
+            ]
+        );
+
+        assert!(coverage(&[None, None], 4).is_empty());
+    }
+
+    #[test]
+    fn aov_normalize_depth() {
+        let depth = [1.0, 2.0, 3.0, 10.0, f32::INFINITY];
+        assert_eq!(
+            normalize_depth(&depth, 1.0, 5.0),
+            [0.0, 0.25, 0.5, 1.0, 1.0]
+        );
+        assert_eq!(normalize_depth(&depth, 2.0, 2.0), [0.0, 0.0, 1.0, 1.0, 1.0]);
+    }
+
+    #[test]
+    fn aov_id_encoding() {
+        for id in 0..10_000 {
+            let f = id_to_float(id);
+            assert!(f.is_normal());
+        }
+        assert_ne!(hash_id(1), hash_id(2));
+        assert_eq!(id_color(5), id_color(5));
+    }
+}
@@ -0,0 +1,324 @@
+use crate::aabb::Aabb;
+use crate::bvh::Bvh;
+use crate::diagnostic::{self, Diagnostic};
+use crate::hittable::{HitRecord, Hittable};
+use crate::material::{Material, MaterialKind};
+use crate::profile::{self, Stage};
+use crate::sphere::Sphere;
+use crate::triangle;
+use crate::{Float, Interval, Point3, Ray, Vec3};
+
+/// Index of a material in a `SceneArena`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MaterialId(u32);
+
+impl MaterialId {
+    /// Position of the material in the arena.
+    pub fn index(&self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// Geometry of an arena primitive.
+#[derive(Debug, Clone, Copy)]
+enum Shape {
+    Sphere { center: Point3, radius: Float },
+    Triangle { vertices: [Point3; 3] },
+}
+
+/// Primitive stored by value in an arena, with its material by index.
+#[derive(Debug, Clone, Copy)]
+struct Primitive {
+    shape: Shape,
+    material: MaterialId,
+}
+
+/// Stand-in material for primitives hit outside of their arena. It absorbs
+/// everything.
+struct Unresolved;
+
+impl Material for Unresolved {}
+
+impl Primitive {
+    /// Finds the closest hit within `ray_t` that `material` keeps.
+    fn intersect<'a>(
+        &self,
+        ray: &Ray,
+        ray_t: &Interval,
+        material: &'a dyn Material,
+    ) -> Option<HitRecord<'a>> {
+        match self.shape {
+            Shape::Sphere { center, radius } => Sphere::roots(&center, radius, ray)?
+                .into_iter()
+                .filter(|&t| ray_t.surrounds(t))
+                .map(|t| Sphere::record(&center, radius, ray, t, material))
+                .find(|rec| !rec.is_masked()),
+            Shape::Triangle {
+                vertices: [a, b, c],
+            } => {
+                let (t, u, v) = triangle::intersect(ray, &a, &b, &c)?;
+                if !ray_t.surrounds(t) {
+                    return None;
+                }
+                let normal = Vec3::cross(&(b - a), &(c - a)).unit();
+                let rec = HitRecord::new(&ray.at(t), &normal, t, ray, material).with_uv(u, v);
+                (!rec.is_masked()).then_some(rec)
+            }
+        }
+    }
+}
+
+impl Hittable for Primitive {
+    fn hit(&self, ray: &Ray, ray_t: &Interval) -> Option<HitRecord> {
+        // Arena scenes resolve the material themselves; see `ArenaScene::hit`.
+        self.intersect(ray, ray_t, &Unresolved)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        match &self.shape {
+            Shape::Sphere { center, radius } => {
+                let r = Vec3::new(*radius, *radius, *radius);
+                Aabb::new(&(center - r), &(center + r))
+            }
+            Shape::Triangle { vertices } => Aabb::from_points(vertices.iter()),
+        }
+    }
+}
+
+/// Scene builder storing primitives by value in one contiguous buffer, with
+/// materials stored by value in a table and referenced by index. Compared to a
+/// list of boxed objects each holding its own material, building allocates
+/// once per buffer rather than once per object, and traversal walks packed
+/// primitives instead of chasing pointers. Build it into an `ArenaScene` to
+/// render.
+#[derive(Clone, Default)]
+pub struct SceneArena {
+    /// Materials, indexed by `MaterialId`.
+    materials: Vec<MaterialKind>,
+
+    /// Primitives in insertion order.
+    primitives: Vec<Primitive>,
+}
+
+impl SceneArena {
+    /// Creates a new empty arena.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a new empty arena with room for `primitives` primitives.
+    pub fn with_capacity(primitives: usize) -> Self {
+        Self {
+            materials: Vec::new(),
+            primitives: Vec::with_capacity(primitives),
+        }
+    }
+
+    /// Adds a material, returning its ID for use by primitives. Materials in
+    /// the closed set of `MaterialKind` are shaded without dynamic dispatch.
+    pub fn add_material<M: Into<MaterialKind>>(&mut self, material: M) -> MaterialId {
+        self.materials.push(material.into());
+        MaterialId(self.materials.len() as u32 - 1)
+    }
+
+    /// Adds a sphere, returning its index, which hits report as object ID.
+    pub fn add_sphere(&mut self, center: Point3, radius: Float, material: MaterialId) -> usize {
+        self.push(Shape::Sphere { center, radius }, material)
+    }
+
+    /// Adds a triangle, front facing where the vertices appear
+    /// counter-clockwise, returning its index, which hits report as object ID.
+    pub fn add_triangle(&mut self, a: Point3, b: Point3, c: Point3, material: MaterialId) -> usize {
+        self.push(
+            Shape::Triangle {
+                vertices: [a, b, c],
+            },
+            material,
+        )
+    }
+
+    /// Number of primitives.
+    pub fn len(&self) -> usize {
+        self.primitives.len()
+    }
+
+    /// Determines whether the arena has no primitives.
+    pub fn is_empty(&self) -> bool {
+        self.primitives.is_empty()
+    }
+
+    /// Retrieves the material with the given ID.
+    pub fn material(&self, id: MaterialId) -> Option<&MaterialKind> {
+        self.materials.get(id.index())
+    }
+
+    /// Builds a hierarchy over the primitives.
+    pub fn build(self) -> ArenaScene {
+        let _profile = profile::scope(Stage::SceneBuild);
+        ArenaScene {
+            materials: self.materials,
+            bvh: Bvh::new(self.primitives),
+        }
+    }
+
+    /// Appends a primitive.
+    ///
+    /// # Panics
+    /// Panics if `material` is not from this arena.
+    fn push(&mut self, shape: Shape, material: MaterialId) -> usize {
+        assert!(
+            material.index() < self.materials.len(),
+            "material {} is not in the arena",
+            material.index()
+        );
+        self.primitives.push(Primitive { shape, material });
+        self.primitives.len() - 1
+    }
+}
+
+/// Scene built from a `SceneArena`. Hits are tagged with the index of the
+/// primitive in the arena as its object ID.
+pub struct ArenaScene {
+    /// Materials, indexed by `MaterialId`.
+    materials: Vec<MaterialKind>,
+
+    /// Hierarchy over the primitives.
+    bvh: Bvh<Primitive>,
+}
+
+impl ArenaScene {
+    /// Number of primitives.
+    pub fn len(&self) -> usize {
+        self.bvh.len()
+    }
+
+    /// Determines whether the scene has no primitives.
+    pub fn is_empty(&self) -> bool {
+        self.bvh.is_empty()
+    }
+
+    /// Material of a primitive.
+    fn material(&self, primitive: &Primitive) -> &dyn Material {
+        &self.materials[primitive.material.index()]
+    }
+}
+
+impl Hittable for ArenaScene {
+    fn hit(&self, ray: &Ray, ray_t: &Interval) -> Option<HitRecord> {
+        self.bvh
+            .hit_with(ray, ray_t, |primitive, bounds| {
+                primitive.intersect(ray, bounds, self.material(primitive))
+            })
+            .map(|(rec, id)| rec.with_object_id(id))
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bvh.bounding_box()
+    }
+
+    fn is_occluded(&self, ray: &Ray, ray_t: &Interval) -> bool {
+        self.bvh.occluded_with(ray, ray_t, |primitive, bounds| {
+            primitive
+                .intersect(ray, bounds, self.material(primitive))
+                .is_some()
+        })
+    }
+
+    fn validate(&self) -> Vec<Diagnostic> {
+        let mut diagnostics: Vec<Diagnostic> = self
+            .materials
+            .iter()
+            .flat_map(|material| material.validate())
+            .collect();
+        for (i, primitive) in self.bvh.objects().iter().enumerate() {
+            let valid = match &primitive.shape {
+                Shape::Sphere { center, radius } => {
+                    diagnostic::is_finite(center) && *radius > 0.0 && radius.is_finite()
+                }
+                Shape::Triangle { vertices } => vertices.iter().all(diagnostic::is_finite),
+            };
+            if !valid {
+                diagnostics.push(Diagnostic::error(&format!(
+                    "arena primitive {i} is not finite"
+                )));
+            }
+        }
+        diagnostics
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hittable::HittableList;
+    use crate::material::{Dielectric, Lambertian};
+    use crate::triangle::Triangle;
+    use crate::Color;
+    use std::sync::Arc;
+
+    #[test]
+    fn arena_matches_list() {
+        let diffuse: Arc<dyn Material> = Arc::new(Lambertian::new(&Color::new(0.5, 0.5, 0.5)));
+        let glass: Arc<dyn Material> = Arc::new(Dielectric::new(1.5));
+
+        let mut arena = SceneArena::with_capacity(101);
+        let ids = [
+            arena.add_material(diffuse.clone()),
+            arena.add_material(glass.clone()),
+        ];
+        let mut list: HittableList<Box<dyn Hittable>> = HittableList::new();
+        for i in 0..10 {
+            for j in 0..10 {
+                let center = Point3::new(i as Float * 1.5, j as Float * 1.5, -(i * j % 7) as Float);
+                let k = ((i + j) % 2) as usize;
+                arena.add_sphere(center, 0.6, ids[k]);
+                list.add(Box::new(Sphere::new(
+                    center,
+                    0.6,
+                    [&diffuse, &glass][k].clone(),
+                )));
+            }
+        }
+        let (a, b, c) = (
+            Point3::new(-5.0, -5.0, -20.0),
+            Point3::new(25.0, -5.0, -20.0),
+            Point3::new(-5.0, 25.0, -20.0),
+        );
+        assert_eq!(arena.add_triangle(a, b, c, ids[0]), 100);
+        list.add(Box::new(Triangle::new(a, b, c, diffuse.clone())));
+        assert_eq!(arena.len(), 101);
+
+        let scene = arena.build();
+        assert_eq!(scene.bounding_box(), list.bounding_box());
+        assert!(scene.validate().is_empty());
+
+        let bounds = Interval::new(0.001, Float::INFINITY);
+        for i in 0..50 {
+            let origin = Point3::new(i as Float * 0.3, 20.0 - i as Float * 0.4, 10.0);
+            let ray = Ray::new(origin, Vec3::new(0.05, -0.1, -1.0));
+
+            let hit = scene.hit(&ray, &bounds);
+            let expected = list.hit(&ray, &bounds);
+            assert_eq!(
+                hit.as_ref().map(|rec| (rec.t(), rec.object_id)),
+                expected.as_ref().map(|rec| (rec.t(), rec.object_id))
+            );
+            assert_eq!(
+                hit.map(|rec| rec.material.roughness()),
+                expected.map(|rec| rec.material.roughness())
+            );
+            assert_eq!(
+                scene.is_occluded(&ray, &bounds),
+                scene.hit(&ray, &bounds).is_some()
+            );
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn arena_foreign_material() {
+        let mut other = SceneArena::new();
+        let id = other.add_material(Lambertian::new(&Color::new(0.5, 0.5, 0.5)));
+        SceneArena::new().add_sphere(Point3::new(0.0, 0.0, 0.0), 1.0, id);
+    }
+}
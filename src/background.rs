@@ -0,0 +1,861 @@
+use std::error::Error as StdError;
+use std::path::Path;
+
+use crate::color::RGB_WAVELENGTHS;
+use crate::image::{self, ImageBuffer};
+use crate::onb::Onb;
+use crate::{consts::PI, util::random, Color, Error, Float, Point3, Ray, Vec3};
+
+/// Radiance arriving from infinitely far away, seen by rays that escape the world.
+pub trait Background: Send + Sync {
+    /// Determine the color seen along `ray`, including any sun disk.
+    fn color(&self, ray: &Ray) -> Color;
+
+    /// Determine the color seen along `ray`, leaving out the sun disk.
+    fn sky(&self, ray: &Ray) -> Color {
+        self.color(ray)
+    }
+
+    /// Sun disk that integrators may sample directly, if any.
+    fn sun(&self) -> Option<&Sun> {
+        None
+    }
+
+    /// Sample a direction towards the sky from `origin`, leaving out the sun
+    /// disk, roughly in proportion to its radiance. Returns the unit
+    /// direction, the radiance seen there and the probability density per
+    /// solid angle, or `None` if the sky is not importance sampled.
+    #[allow(unused)]
+    fn sample_sky(&self, origin: &Point3) -> Option<(Vec3, Color, Float)> {
+        None
+    }
+
+    /// Probability density per solid angle of `sample_sky` choosing the unit
+    /// direction `dir` from `origin`.
+    #[allow(unused)]
+    fn sky_pdf(&self, origin: &Point3, dir: &Vec3) -> Float {
+        0.0
+    }
+}
+
+/// Distant disk light, such as the sun, over a black sky.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sun {
+    /// Unit direction towards the center of the disk.
+    direction: Vec3,
+
+    /// Cosine of the angular radius of the disk.
+    cos_max: Float,
+
+    /// Radiance of the disk.
+    radiance: Color,
+}
+
+impl Sun {
+    /// Angular radius of the sun seen from the earth, in radians.
+    pub const ANGULAR_RADIUS: Float = 0.004_654;
+
+    /// Create a new disk light in `direction` with the given angular radius.
+    pub fn new(direction: Vec3, angular_radius: Float, radiance: Color) -> Self {
+        Self {
+            direction: direction.unit(),
+            cos_max: angular_radius.clamp(0.0, PI).cos(),
+            radiance,
+        }
+    }
+
+    /// Retrieve the unit direction towards the center of the disk.
+    pub fn direction(&self) -> &Vec3 {
+        &self.direction
+    }
+
+    /// Retrieve the radiance of the disk.
+    pub fn radiance(&self) -> &Color {
+        &self.radiance
+    }
+
+    /// Solid angle subtended by the disk.
+    pub fn solid_angle(&self) -> Float {
+        2.0 * PI * (1.0 - self.cos_max)
+    }
+
+    /// Determines whether the unit direction `dir` points into the disk.
+    pub fn contains(&self, dir: &Vec3) -> bool {
+        Vec3::dot(dir, &self.direction) >= self.cos_max
+    }
+
+    /// Sample a unit direction uniformly within the disk, with density
+    /// `1 / solid_angle`.
+    pub fn sample(&self) -> Vec3 {
+        let cos_theta = 1.0 - random::gen_unit() * (1.0 - self.cos_max);
+        let sin_theta = Float::sqrt(1.0 - cos_theta * cos_theta);
+        let phi = 2.0 * PI * random::gen_unit();
+        let local = Vec3::new(sin_theta * phi.cos(), sin_theta * phi.sin(), cos_theta);
+        Onb::from_normal(&self.direction).to_world(&local)
+    }
+
+    /// Radiance seen along the unit direction `dir`.
+    fn emitted(&self, dir: &Vec3) -> Color {
+        if self.contains(dir) {
+            self.radiance
+        } else {
+            Color::new(0.0, 0.0, 0.0)
+        }
+    }
+}
+
+impl Background for Sun {
+    fn color(&self, ray: &Ray) -> Color {
+        self.emitted(&ray.direction().unit())
+    }
+
+    fn sky(&self, _ray: &Ray) -> Color {
+        Color::new(0.0, 0.0, 0.0)
+    }
+
+    fn sun(&self) -> Option<&Sun> {
+        Some(self)
+    }
+}
+
+/// Vertical blend from `bottom` looking straight down to `top` looking straight up.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Gradient {
+    /// Color looking straight down.
+    bottom: Color,
+
+    /// Color looking straight up.
+    top: Color,
+}
+
+impl Gradient {
+    /// Create a new gradient background.
+    pub fn new(bottom: Color, top: Color) -> Self {
+        Self { bottom, top }
+    }
+}
+
+impl Default for Gradient {
+    /// White fading to light blue.
+    fn default() -> Self {
+        Self::new(Color::new(1.0, 1.0, 1.0), Color::new(0.5, 0.7, 1.0))
+    }
+}
+
+impl Background for Gradient {
+    fn color(&self, ray: &Ray) -> Color {
+        let unit_dir = ray.direction().unit();
+        let a = (0.5 * (unit_dir.y() + 1.0)) as f32;
+        (1.0 - a) * self.bottom + a * self.top
+    }
+}
+
+/// Same color in every direction.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Uniform {
+    /// Background color.
+    color: Color,
+}
+
+impl Uniform {
+    /// Create a new uniform background.
+    pub fn new(color: Color) -> Self {
+        Self { color }
+    }
+}
+
+impl Background for Uniform {
+    fn color(&self, _ray: &Ray) -> Color {
+        self.color
+    }
+}
+
+/// Piecewise-constant distribution over a row of cells, for picking cells in
+/// proportion to their weights.
+#[derive(Debug, Clone)]
+struct Distribution {
+    /// Cumulative weights, normalized to end at 1, starting with 0.
+    cdf: Vec<Float>,
+
+    /// Sum of the weights.
+    total: Float,
+}
+
+impl Distribution {
+    /// Create a new distribution. Without any positive weight, every cell is
+    /// equally likely.
+    fn new(weights: &[Float]) -> Self {
+        let mut cdf = Vec::with_capacity(weights.len() + 1);
+        let mut total = 0.0;
+        cdf.push(0.0);
+        for w in weights {
+            total += w.max(0.0);
+            cdf.push(total);
+        }
+
+        let n = weights.len() as Float;
+        for (i, c) in cdf.iter_mut().enumerate() {
+            *c = if total > 0.0 {
+                *c / total
+            } else {
+                i as Float / n
+            };
+        }
+        Self { cdf, total }
+    }
+
+    /// Pick the index of a cell from a uniform sample `u` in [0, 1).
+    fn sample(&self, u: Float) -> usize {
+        self.cdf
+            .partition_point(|c| *c <= u)
+            .clamp(1, self.cdf.len() - 1)
+            - 1
+    }
+
+    /// Probability of picking cell `i`.
+    fn probability(&self, i: usize) -> Float {
+        self.cdf[i + 1] - self.cdf[i]
+    }
+}
+
+/// Environment map in the equirectangular (latitude-longitude) layout. The
+/// image's top row looks straight up, its center looks down -z, and its
+/// columns turn clockwise seen from above. World space is y-up.
+///
+/// Directions are importance sampled by pixel brightness, so integrators
+/// find small, bright features such as a sun in an HDR capture directly
+/// instead of by chance.
+#[derive(Debug, Clone)]
+pub struct Environment {
+    /// Linear radiance image.
+    image: ImageBuffer,
+
+    /// Scale applied to the image.
+    intensity: f32,
+
+    /// Distribution of rows, by their total weight.
+    rows: Distribution,
+
+    /// Distribution of the pixels within each row.
+    pixels: Vec<Distribution>,
+}
+
+impl Environment {
+    /// Create a new environment map.
+    pub fn new(image: ImageBuffer) -> Self {
+        let (width, height) = image.dim();
+
+        // Weight pixels by the solid angle they cover, which shrinks towards the poles.
+        let pixels: Vec<Distribution> = (0..height)
+            .map(|y| {
+                let sin_theta = (PI * (y as Float + 0.5) / height as Float).sin();
+                let weights: Vec<Float> = (0..width)
+                    .map(|x| image.get(x, y).luminance().max(0.0) as Float * sin_theta)
+                    .collect();
+                Distribution::new(&weights)
+            })
+            .collect();
+        let row_weights: Vec<Float> = pixels.iter().map(|row| row.total).collect();
+
+        Self {
+            image,
+            intensity: 1.0,
+            rows: Distribution::new(&row_weights),
+            pixels,
+        }
+    }
+
+    /// Loads an environment map from a file (see `image::load`).
+    pub fn load<P>(path: P) -> Result<Self, Box<dyn StdError>>
+    where
+        P: AsRef<Path>,
+    {
+        Ok(Self::new(image::load(path)?))
+    }
+
+    /// Set the scale applied to the image.
+    pub fn with_intensity(mut self, intensity: f32) -> Self {
+        self.intensity = intensity;
+        self
+    }
+
+    /// Retrieve the radiance image.
+    pub fn image(&self) -> &ImageBuffer {
+        &self.image
+    }
+
+    /// Continuous pixel coordinates (x, y) seen in the unit direction `dir`.
+    fn pixel(&self, dir: &Vec3) -> (Float, Float) {
+        let (width, height) = self.image.dim();
+        let u = 0.5 + Float::atan2(dir.x(), -dir.z()) / (2.0 * PI);
+        let v = dir.y().clamp(-1.0, 1.0).acos() / PI;
+        (u * width as Float, v * height as Float)
+    }
+
+    /// Unit direction seen at the continuous pixel coordinates (x, y).
+    fn direction(&self, x: Float, y: Float) -> Vec3 {
+        let (width, height) = self.image.dim();
+        let phi = (x / width as Float - 0.5) * 2.0 * PI;
+        let theta = y / height as Float * PI;
+        Vec3::new(
+            theta.sin() * phi.sin(),
+            theta.cos(),
+            -theta.sin() * phi.cos(),
+        )
+    }
+
+    /// Probability density per solid angle of sampling within pixel (x, y),
+    /// seen at polar angle `theta`.
+    fn pixel_pdf(&self, x: usize, y: usize, theta: Float) -> Float {
+        let (width, height) = self.image.dim();
+        // Directions sampled next to the poles, particularly in single
+        // precision, can round onto them, where the density is unbounded.
+        let sin_theta = theta.sin().max(Float::EPSILON);
+
+        // Pixels cover equal areas of the (phi, theta) rectangle.
+        let p = self.rows.probability(y) * self.pixels[y].probability(x);
+        p * (width * height) as Float / (2.0 * PI * PI * sin_theta)
+    }
+}
+
+impl Background for Environment {
+    fn color(&self, ray: &Ray) -> Color {
+        let (x, y) = self.pixel(&ray.direction().unit());
+        let (width, height) = self.image.dim();
+
+        // Wrap around horizontally, and stop at the poles.
+        let texel = |i: i64, j: i64| {
+            let i = i.rem_euclid(width as i64) as u32;
+            let j = j.clamp(0, height as i64 - 1) as u32;
+            *self.image.get(i, j)
+        };
+        bilinear(x, y, texel) * self.intensity
+    }
+
+    fn sample_sky(&self, _origin: &Point3) -> Option<(Vec3, Color, Float)> {
+        let y = self.rows.sample(random::gen_unit());
+        let x = self.pixels[y].sample(random::gen_unit());
+
+        // Pick a point within the pixel uniformly. The density is looked up
+        // from the direction, so it matches `sky_pdf` even on pixel edges.
+        let dir = self.direction(
+            x as Float + random::gen_unit(),
+            y as Float + random::gen_unit(),
+        );
+        let pdf = self.sky_pdf(&Point3::new(0.0, 0.0, 0.0), &dir);
+        if pdf <= 0.0 {
+            return None;
+        }
+
+        let radiance = self.color(&Ray::new(Vec3::new(0.0, 0.0, 0.0), dir));
+        Some((dir, radiance, pdf))
+    }
+
+    fn sky_pdf(&self, _origin: &Point3, dir: &Vec3) -> Float {
+        let (x, y) = self.pixel(dir);
+        let (width, height) = self.image.dim();
+        let col = (x.max(0.0) as usize).min(width as usize - 1);
+        let row = (y.max(0.0) as usize).min(height as usize - 1);
+        self.pixel_pdf(col, row, dir.y().clamp(-1.0, 1.0).acos())
+    }
+}
+
+/// Environment map made of six square images, one per face of a cube around
+/// the scene, as often shipped with game assets. Faces follow the OpenGL
+/// convention: +x, -x, +y, -y, +z, -z, each seen from inside the cube with
+/// +y up on the side faces. World space is y-up. Filtering reaches across
+/// the faces' edges, so there are no seams. Unlike `Environment`, cube maps
+/// are not importance sampled.
+#[derive(Debug, Clone)]
+pub struct CubeMap {
+    /// Linear radiance images of the faces.
+    faces: [ImageBuffer; 6],
+
+    /// Scale applied to the images.
+    intensity: f32,
+}
+
+impl CubeMap {
+    /// Create a new cube map from its faces in the order +x, -x, +y, -y, +z, -z.
+    /// Faces must be square and of the same size.
+    pub fn new(faces: [ImageBuffer; 6]) -> Result<Self, Error> {
+        let (size, _) = faces[0].dim();
+        if let Some(face) = faces.iter().find(|face| face.dim() != (size, size)) {
+            let (width, height) = face.dim();
+            return Err(Error::new_image(&format!(
+                "cube map faces must be square and of the same size ({size}x{size} and {width}x{height} given)"
+            )));
+        }
+        if size == 0 {
+            return Err(Error::new_image("cube map faces must not be empty"));
+        }
+
+        Ok(Self {
+            faces,
+            intensity: 1.0,
+        })
+    }
+
+    /// Loads the faces of a cube map from six files (see `image::load`), in
+    /// the order +x, -x, +y, -y, +z, -z.
+    pub fn load<P>(paths: [P; 6]) -> Result<Self, Box<dyn StdError>>
+    where
+        P: AsRef<Path>,
+    {
+        let mut faces = Vec::with_capacity(6);
+        for path in paths {
+            faces.push(image::load(path)?);
+        }
+        let faces: [ImageBuffer; 6] = faces.try_into().expect("six faces were loaded");
+        Ok(Self::new(faces)?)
+    }
+
+    /// Set the scale applied to the images.
+    pub fn with_intensity(mut self, intensity: f32) -> Self {
+        self.intensity = intensity;
+        self
+    }
+
+    /// Face seen in the direction `dir`, with the face coordinates of `dir`
+    /// in [-1, 1], left to right and top to bottom.
+    fn project(dir: &Vec3) -> (usize, Float, Float) {
+        let (ax, ay, az) = (dir.x().abs(), dir.y().abs(), dir.z().abs());
+        let (face, sc, tc, ma) = if ax >= ay && ax >= az {
+            if dir.x() > 0.0 {
+                (0, -dir.z(), -dir.y(), ax)
+            } else {
+                (1, dir.z(), -dir.y(), ax)
+            }
+        } else if ay >= az {
+            if dir.y() > 0.0 {
+                (2, dir.x(), dir.z(), ay)
+            } else {
+                (3, dir.x(), -dir.z(), ay)
+            }
+        } else if dir.z() > 0.0 {
+            (4, dir.x(), -dir.y(), az)
+        } else {
+            (5, -dir.x(), -dir.y(), az)
+        };
+        (face, sc / ma, tc / ma)
+    }
+
+    /// Direction through the face coordinates (s, t) of a face. The inverse
+    /// of `project`, also defined beyond the face's edges.
+    fn unproject(face: usize, s: Float, t: Float) -> Vec3 {
+        match face {
+            0 => Vec3::new(1.0, -t, -s),
+            1 => Vec3::new(-1.0, -t, s),
+            2 => Vec3::new(s, 1.0, t),
+            3 => Vec3::new(s, -1.0, -t),
+            4 => Vec3::new(s, -t, 1.0),
+            _ => Vec3::new(-s, -t, -1.0),
+        }
+    }
+
+    /// Texel (i, j) of a face. Texels past the face's edges are taken from
+    /// the neighbouring face the same direction falls on.
+    fn texel(&self, face: usize, i: i64, j: i64) -> Color {
+        let (size, _) = self.faces[face].dim();
+        let n = size as i64;
+        if (0..n).contains(&i) && (0..n).contains(&j) {
+            return *self.faces[face].get(i as u32, j as u32);
+        }
+
+        let to_face = |k: i64| (k as Float + 0.5) / size as Float * 2.0 - 1.0;
+        let dir = Self::unproject(face, to_face(i), to_face(j));
+        let (face, s, t) = Self::project(&dir);
+        let to_texel = |c: Float| (((c + 1.0) * 0.5 * size as Float) as i64).clamp(0, n - 1);
+        *self.faces[face].get(to_texel(s) as u32, to_texel(t) as u32)
+    }
+}
+
+impl Background for CubeMap {
+    fn color(&self, ray: &Ray) -> Color {
+        let (face, s, t) = Self::project(ray.direction());
+        let (size, _) = self.faces[face].dim();
+        let x = (s + 1.0) * 0.5 * size as Float;
+        let y = (t + 1.0) * 0.5 * size as Float;
+        bilinear(x, y, |i, j| self.texel(face, i, j)) * self.intensity
+    }
+}
+
+/// Bilinearly interpolate the texels around continuous pixel coordinates
+/// (x, y), with texel centers at half-integers.
+fn bilinear(x: Float, y: Float, texel: impl Fn(i64, i64) -> Color) -> Color {
+    let (x, y) = (x - 0.5, y - 0.5);
+    let (i, j) = (x.floor() as i64, y.floor() as i64);
+    let (fx, fy) = ((x - i as Float) as f32, (y - j as Float) as f32);
+    let top = (1.0 - fx) * texel(i, j) + fx * texel(i + 1, j);
+    let bottom = (1.0 - fx) * texel(i, j + 1) + fx * texel(i + 1, j + 1);
+    (1.0 - fy) * top + fy * bottom
+}
+
+/// Perez sky luminance distribution coefficients.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Perez {
+    a: Float,
+    b: Float,
+    c: Float,
+    d: Float,
+    e: Float,
+}
+
+impl Perez {
+    /// Relative value at view zenith angle `theta` and angle `gamma` from the sun.
+    fn evaluate(&self, theta: Float, gamma: Float) -> Float {
+        let cos_gamma = gamma.cos();
+        (1.0 + self.a * Float::exp(self.b / theta.cos()))
+            * (1.0 + self.c * Float::exp(self.d * gamma) + self.e * cos_gamma * cos_gamma)
+    }
+}
+
+/// Preetham et al.'s analytic daylight model, "A Practical Analytic Model for
+/// Daylight" (1999). The sky is brightest around the sun and bluer away from
+/// it, reddening with turbidity and towards sunset. The sun disk is included
+/// with its true angular size and a color attenuated by the same atmosphere.
+/// World space is y-up; below the horizon the horizon color repeats.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PreethamSky {
+    /// Unit direction towards the sun.
+    sun_direction: Vec3,
+
+    /// Atmospheric haziness, from 2 for a very clear sky to 10 for haze.
+    turbidity: Float,
+
+    /// Scale from luminance in kcd/m² to color values.
+    exposure: Float,
+
+    /// Zenith luminance and chromaticity, as Y, x and y.
+    zenith: [Float; 3],
+
+    /// Distribution coefficients for Y, x and y.
+    perez: [Perez; 3],
+
+    /// Perez distribution at the zenith for Y, x and y, normalizing the sky
+    /// to the zenith values.
+    perez_zenith: [Float; 3],
+
+    /// Sun radiance after passing through the atmosphere, in kcd/m².
+    sun_luminance: Color,
+
+    /// Sun disk, scaled by the exposure.
+    sun: Sun,
+}
+
+impl PreethamSky {
+    /// Luminance of the sun outside the atmosphere, in kcd/m².
+    const SUN_LUMINANCE: f32 = 1.96e6;
+
+    /// Create a new sky lit by a sun in `sun_direction`. The sun is kept at or
+    /// above the horizon, where the model holds.
+    pub fn new(sun_direction: Vec3, turbidity: Float) -> Self {
+        let sun_direction = Vec3::new(
+            sun_direction.x(),
+            sun_direction.y().max(0.0),
+            sun_direction.z(),
+        )
+        .unit();
+        let turbidity = turbidity.clamp(1.0, 20.0);
+        let t = turbidity;
+        let theta_s = sun_direction.y().clamp(-1.0, 1.0).acos();
+
+        let perez = [
+            Perez {
+                a: 0.1787 * t - 1.4630,
+                b: -0.3554 * t + 0.4275,
+                c: -0.0227 * t + 5.3251,
+                d: 0.1206 * t - 2.5771,
+                e: -0.0670 * t + 0.3703,
+            },
+            Perez {
+                a: -0.0193 * t - 0.2592,
+                b: -0.0665 * t + 0.0008,
+                c: -0.0004 * t + 0.2125,
+                d: -0.0641 * t - 0.8989,
+                e: -0.0033 * t + 0.0452,
+            },
+            Perez {
+                a: -0.0167 * t - 0.2608,
+                b: -0.0950 * t + 0.0092,
+                c: -0.0079 * t + 0.2102,
+                d: -0.0441 * t - 1.6537,
+                e: -0.0109 * t + 0.0529,
+            },
+        ];
+
+        let chi = (4.0 / 9.0 - t / 120.0) * (PI - 2.0 * theta_s);
+        let luminance = (4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192;
+
+        // Cubic in the sun zenith angle for each power of turbidity.
+        let chromaticity = |m: [[Float; 4]; 3]| {
+            let cubic = |c: [Float; 4]| ((c[0] * theta_s + c[1]) * theta_s + c[2]) * theta_s + c[3];
+            t * t * cubic(m[0]) + t * cubic(m[1]) + cubic(m[2])
+        };
+        let x = chromaticity([
+            [0.00166, -0.00375, 0.00209, 0.0],
+            [-0.02903, 0.06377, -0.03202, 0.00394],
+            [0.11693, -0.21196, 0.06052, 0.25886],
+        ]);
+        let y = chromaticity([
+            [0.00275, -0.00610, 0.00317, 0.0],
+            [-0.04214, 0.08970, -0.04153, 0.00516],
+            [0.15346, -0.26756, 0.06670, 0.26688],
+        ]);
+
+        let sun_luminance = Self::SUN_LUMINANCE * Self::transmittance(theta_s, turbidity);
+        let exposure = 0.05;
+
+        Self {
+            sun_direction,
+            turbidity,
+            exposure,
+            zenith: [luminance.max(0.0), x, y],
+            perez,
+            perez_zenith: perez.map(|p| p.evaluate(0.0, theta_s)),
+            sun_luminance,
+            sun: Sun::new(
+                sun_direction,
+                Sun::ANGULAR_RADIUS,
+                sun_luminance * exposure as f32,
+            ),
+        }
+    }
+
+    /// Fraction of sunlight at each RGB wavelength left after Rayleigh and
+    /// aerosol scattering along the path through the atmosphere, for a sun at
+    /// zenith angle `theta_s`.
+    fn transmittance(theta_s: Float, turbidity: Float) -> Color {
+        // Relative optical mass of the air.
+        let m = 1.0 / (theta_s.cos() + 0.15 * Float::powf(93.885 - theta_s.to_degrees(), -1.253));
+        let beta = 0.04608 * turbidity - 0.04586;
+
+        let mut color = Color::new(0.0, 0.0, 0.0);
+        for (i, nm) in RGB_WAVELENGTHS.iter().enumerate() {
+            let um = nm / 1000.0;
+            let rayleigh = Float::exp(-0.008735 * um.powf(-4.08) * m);
+            let aerosol = Float::exp(-beta * um.powf(-1.3) * m);
+            color[i] = (rayleigh * aerosol) as f32;
+        }
+        color
+    }
+
+    /// Set the scale from sky luminance in kcd/m² to color values.
+    pub fn with_exposure(mut self, exposure: Float) -> Self {
+        self.exposure = exposure;
+        self.sun = Sun::new(
+            self.sun_direction,
+            Sun::ANGULAR_RADIUS,
+            self.sun_luminance * exposure as f32,
+        );
+        self
+    }
+
+    /// Retrieve the unit direction towards the sun.
+    pub fn sun_direction(&self) -> &Vec3 {
+        &self.sun_direction
+    }
+
+    /// Retrieve the turbidity.
+    pub fn turbidity(&self) -> Float {
+        self.turbidity
+    }
+}
+
+impl Background for PreethamSky {
+    fn color(&self, ray: &Ray) -> Color {
+        self.sky(ray) + self.sun.emitted(&ray.direction().unit())
+    }
+
+    fn sky(&self, ray: &Ray) -> Color {
+        // Keep just above the horizon, where the distribution blows up.
+        let unit_dir = ray.direction().unit();
+        let unit_dir = Vec3::new(unit_dir.x(), unit_dir.y().max(0.001), unit_dir.z()).unit();
+
+        let theta = unit_dir.y().acos();
+        let gamma = Vec3::dot(&unit_dir, &self.sun_direction)
+            .clamp(-1.0, 1.0)
+            .acos();
+        let channel =
+            |i: usize| self.zenith[i] * self.perez[i].evaluate(theta, gamma) / self.perez_zenith[i];
+        let (lum, x, y) = (channel(0) * self.exposure, channel(1), channel(2));
+
+        // xyY to XYZ.
+        Color::from_xyz(x / y * lum, lum, (1.0 - x - y) / y * lum)
+    }
+
+    fn sun(&self) -> Option<&Sun> {
+        Some(&self.sun)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ray(direction: Vec3) -> Ray {
+        Ray::new(Point3::new(0.0, 0.0, 0.0), direction)
+    }
+
+    fn luminance(c: &Color) -> f32 {
+        0.2126 * c.r() + 0.7152 * c.g() + 0.0722 * c.b()
+    }
+
+    #[test]
+    fn background_environment() {
+        // Columns are numbered in red, and rows are green on top, blue below.
+        let data = (0..8)
+            .map(|k| {
+                let (col, row) = (k % 4, k / 4);
+                Color::new(col as f32, (row == 0) as u8 as f32, (row == 1) as u8 as f32)
+            })
+            .collect();
+        let env = Environment::new(ImageBuffer::new(4, 2, data)).with_intensity(2.0);
+
+        let up = env.color(&ray(Vec3::new(0.0, 1.0, 0.0)));
+        assert_eq!((up.g(), up.b()), (2.0, 0.0));
+        let down = env.color(&ray(Vec3::new(0.0, -1.0, 0.0)));
+        assert_eq!((down.g(), down.b()), (0.0, 2.0));
+
+        // Looking down -z sees the middle of the image, and looking down +z
+        // its left and right edges, which wrap around.
+        let front = env.color(&ray(Vec3::new(0.0, 0.0, -1.0)));
+        assert!((front.r() - 3.0).abs() < 1e-5);
+        let back = env.color(&ray(Vec3::new(0.0, 0.0, 1.0)));
+        assert!((back.r() - 3.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn background_environment_sampling() {
+        // The densities of a uniform map integrate to one over the sphere.
+        let origin = Point3::new(0.0, 0.0, 0.0);
+        let uniform = Environment::new(ImageBuffer::new(8, 4, vec![Color::new(1.0, 1.0, 1.0); 32]));
+        let n = 4000;
+        let mut inverse_pdf = 0.0;
+        for _ in 0..n {
+            let (dir, _, pdf) = uniform.sample_sky(&origin).unwrap();
+            assert!((dir.len() - 1.0).abs() < 1e-4);
+            assert_eq!(uniform.sky_pdf(&origin, &dir), pdf);
+            inverse_pdf += 1.0 / pdf;
+        }
+        let sphere = inverse_pdf / n as Float;
+        assert!((sphere - 4.0 * PI).abs() < 0.05 * 4.0 * PI);
+
+        // Samples gather around a single bright pixel, just above the horizon.
+        let mut data = vec![Color::new(0.01, 0.01, 0.01); 32];
+        data[8 + 5] = Color::new(100.0, 100.0, 100.0);
+        let env = Environment::new(ImageBuffer::new(8, 4, data));
+        let bright = (0..n)
+            .filter(|_| env.sample_sky(&origin).unwrap().1.r() > 10.0)
+            .count();
+        assert!(bright > n * 9 / 10);
+    }
+
+    #[test]
+    fn background_cube_map() {
+        let face = |k: usize| ImageBuffer::new(2, 2, vec![Color::new(k as f32, 1.0, 0.0); 4]);
+        let faces = [0, 1, 2, 3, 4, 5].map(face);
+        let cube = CubeMap::new(faces.clone()).unwrap();
+
+        let axes = [
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec3::new(-1.0, 0.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            Vec3::new(0.0, -1.0, 0.0),
+            Vec3::new(0.0, 0.0, 1.0),
+            Vec3::new(0.0, 0.0, -1.0),
+        ];
+        for (k, axis) in axes.iter().enumerate() {
+            assert_eq!(cube.color(&ray(*axis)).r(), k as f32);
+        }
+
+        // Edges blend the faces meeting there, from either side.
+        for z in [0.999, 1.0, 1.001] {
+            let edge = cube.color(&ray(Vec3::new(1.0, 0.0, z)));
+            assert!((edge.r() - 2.0).abs() < 0.01);
+        }
+        let edge = cube.color(&ray(Vec3::new(0.0, 1.0, -1.0)));
+        assert!((edge.r() - 3.5).abs() < 1e-5);
+
+        let mut uneven = faces;
+        uneven[3] = ImageBuffer::new(2, 1, vec![Color::new(0.0, 0.0, 0.0); 2]);
+        assert!(CubeMap::new(uneven).is_err());
+    }
+
+    #[test]
+    fn background_gradient() {
+        let gradient = Gradient::default();
+        assert_eq!(
+            gradient.color(&ray(Vec3::new(0.0, 1.0, 0.0))),
+            Color::new(0.5, 0.7, 1.0)
+        );
+        assert_eq!(
+            gradient.color(&ray(Vec3::new(0.0, -3.0, 0.0))),
+            Color::new(1.0, 1.0, 1.0)
+        );
+    }
+
+    #[test]
+    fn background_sun() {
+        let sun = Sun::new(Vec3::new(1.0, 1.0, 0.0), 0.1, Color::new(2.0, 2.0, 2.0));
+        let expected = 2.0 * PI * (1.0 - Float::cos(0.1));
+        assert!((sun.solid_angle() - expected).abs() < 1e-6);
+
+        for _ in 0..100 {
+            let dir = sun.sample();
+            assert!((dir.len() - 1.0).abs() < 1e-5);
+            assert!(sun.contains(&dir));
+            assert_eq!(sun.color(&ray(dir)), Color::new(2.0, 2.0, 2.0));
+        }
+        assert_eq!(
+            sun.color(&ray(Vec3::new(0.0, 1.0, 0.0))),
+            Color::new(0.0, 0.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn background_preetham() {
+        let sun = Vec3::new(0.0, 0.5, -1.0);
+        let sky = PreethamSky::new(sun, 3.0);
+
+        let toward_sun = sky.color(&ray(sun));
+        let away = sky.color(&ray(Vec3::new(0.0, 0.5, 1.0)));
+        let zenith = sky.color(&ray(Vec3::new(0.0, 1.0, 0.0)));
+
+        // Positive, brightest near the sun, and blue overhead.
+        for c in [toward_sun, away, zenith] {
+            assert!(c.r() > 0.0 && c.g() > 0.0 && c.b() > 0.0);
+        }
+        assert!(luminance(&toward_sun) > luminance(&away));
+        assert!(zenith.b() > zenith.r());
+
+        // Below the horizon the horizon color repeats.
+        let horizon = sky.color(&ray(Vec3::new(1.0, 0.0, 0.0)));
+        let below = sky.color(&ray(Vec3::new(1.0, -0.5, 0.0)));
+        assert!((luminance(&horizon) - luminance(&below)).abs() < 0.05 * luminance(&horizon));
+
+        // Haze brightens and whitens the sky away from the sun.
+        let hazy = PreethamSky::new(sun, 8.0).color(&ray(Vec3::new(0.0, 0.5, 1.0)));
+        assert!(hazy.r() / hazy.b() > away.r() / away.b());
+    }
+
+    #[test]
+    fn background_preetham_sun() {
+        let noon = PreethamSky::new(Vec3::new(0.0, 1.0, 0.0), 3.0);
+        let sunset = PreethamSky::new(Vec3::new(0.0, 0.05, -1.0), 3.0);
+
+        // The disk is part of the sky color and matches the sampled sun.
+        let disk = ray(*noon.sun_direction());
+        let sun = noon.sun().unwrap();
+        assert!(sun.contains(noon.sun_direction()));
+        assert!((sun.solid_angle() - 6.8e-5).abs() < 1e-6);
+        assert!(noon
+            .color(&disk)
+            .almost_eq(&(noon.sky(&disk) + *sun.radiance())));
+
+        // Low suns are dimmer and redder through more atmosphere.
+        let (high, low) = (sun.radiance(), sunset.sun().unwrap().radiance());
+        assert!(low.g() < high.g());
+        assert!(low.r() / low.b() > high.r() / high.b());
+    }
+}
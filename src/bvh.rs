@@ -0,0 +1,443 @@
+use std::cell::Cell;
+
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable};
+use crate::profile::{self, Stage};
+use crate::trace;
+use crate::{Axis, Interval, Ray};
+
+/// Maximum number of objects stored in a leaf node.
+const MAX_LEAF_SIZE: usize = 4;
+
+thread_local! {
+    /// Node bounding boxes tested by traversals on this thread.
+    static NODE_TESTS: Cell<u64> = const { Cell::new(0) };
+}
+
+/// Returns the number of node bounding boxes tested by hierarchy traversals on
+/// this thread since the last call, and resets the count. Used to visualize
+/// traversal cost.
+pub fn take_node_tests() -> u64 {
+    NODE_TESTS.with(|tests| tests.replace(0))
+}
+
+/// Node of a bounding volume hierarchy, stored in a flat array.
+#[derive(Debug, Clone, Copy)]
+enum BvhNode {
+    /// Leaf referencing a contiguous range of objects.
+    Leaf {
+        bbox: Aabb,
+        start: usize,
+        count: usize,
+    },
+
+    /// Interior node referencing its two children by index.
+    Interior {
+        bbox: Aabb,
+        left: usize,
+        right: usize,
+    },
+}
+
+impl BvhNode {
+    /// Bounding box of everything under the node.
+    fn bbox(&self) -> &Aabb {
+        match self {
+            Self::Leaf { bbox, .. } | Self::Interior { bbox, .. } => bbox,
+        }
+    }
+}
+
+/// Bounding volume hierarchy over a set of hittable objects. Hits are tagged
+/// with the index of the object in the input as its object ID.
+pub struct Bvh<T: Hittable> {
+    /// Objects, reordered so that every leaf covers a contiguous range.
+    objects: Vec<T>,
+
+    /// Input index of each object in `objects`.
+    ids: Vec<usize>,
+
+    /// Nodes in depth-first order. The root is at index 0.
+    nodes: Vec<BvhNode>,
+}
+
+impl<T: Hittable> Bvh<T> {
+    /// Builds a hierarchy over the given objects by splitting at the median
+    /// centroid along the longest axis.
+    pub fn new(objects: Vec<T>) -> Self {
+        trace::span!(DEBUG, "bvh_build", objects = objects.len());
+        let _profile = profile::scope(Stage::BvhBuild);
+
+        let mut items: Vec<(Aabb, usize, T)> = objects
+            .into_iter()
+            .enumerate()
+            .map(|(id, object)| (object.bounding_box(), id, object))
+            .collect();
+
+        let mut nodes = Vec::new();
+        if !items.is_empty() {
+            Self::build(&mut items, 0, &mut nodes);
+        }
+        trace::event!(DEBUG, nodes = nodes.len(), "built bvh");
+
+        let (ids, objects) = items
+            .into_iter()
+            .map(|(_, id, object)| (id, object))
+            .unzip();
+        Self {
+            objects,
+            ids,
+            nodes,
+        }
+    }
+
+    /// Recursively builds the nodes for `items`, whose first element is at
+    /// `offset` in the final object order. Returns the index of the new node.
+    fn build(items: &mut [(Aabb, usize, T)], offset: usize, nodes: &mut Vec<BvhNode>) -> usize {
+        let bbox = items
+            .iter()
+            .fold(Aabb::EMPTY, |acc, (b, _, _)| Aabb::surrounding(&acc, b));
+
+        let index = nodes.len();
+        if items.len() <= MAX_LEAF_SIZE {
+            nodes.push(BvhNode::Leaf {
+                bbox,
+                start: offset,
+                count: items.len(),
+            });
+            return index;
+        }
+
+        // Split along the axis where the centroids are most spread out.
+        let centroids = items.iter().fold(Aabb::EMPTY, |acc, (b, _, _)| {
+            let c = b.centroid();
+            Aabb::surrounding(&acc, &Aabb::new(&c, &c))
+        });
+        let axis = centroids.longest_axis();
+
+        let mid = items.len() / 2;
+        items.select_nth_unstable_by(mid, |(a, _, _), (b, _, _)| {
+            a.centroid()[axis].total_cmp(&b.centroid()[axis])
+        });
+
+        // Reserve this node's slot, then fill it in once the children exist.
+        nodes.push(BvhNode::Leaf {
+            bbox,
+            start: offset,
+            count: 0,
+        });
+        let (lo, hi) = items.split_at_mut(mid);
+        let left = Self::build(lo, offset, nodes);
+        let right = Self::build(hi, offset + mid, nodes);
+        nodes[index] = BvhNode::Interior { bbox, left, right };
+
+        index
+    }
+
+    /// Number of objects in the hierarchy.
+    pub fn len(&self) -> usize {
+        self.objects.len()
+    }
+
+    /// Determines whether the hierarchy contains no objects.
+    pub fn is_empty(&self) -> bool {
+        self.objects.is_empty()
+    }
+
+    /// Retrieves the objects in traversal order.
+    pub fn objects(&self) -> &[T] {
+        &self.objects
+    }
+
+    /// Retrieves the bounding box of every node along with its depth, the
+    /// root being at depth 0, in depth-first order.
+    pub fn node_boxes(&self) -> Vec<(usize, Aabb)> {
+        let mut boxes = Vec::with_capacity(self.nodes.len());
+        let mut stack = if self.nodes.is_empty() {
+            Vec::new()
+        } else {
+            vec![(0, 0)]
+        };
+        while let Some((i, depth)) = stack.pop() {
+            let node = &self.nodes[i];
+            boxes.push((depth, *node.bbox()));
+            if let BvhNode::Interior { left, right, .. } = *node {
+                stack.push((right, depth + 1));
+                stack.push((left, depth + 1));
+            }
+        }
+        boxes
+    }
+
+    /// Exports the node bounding boxes down to `max_depth` as wireframe
+    /// boxes in the Wavefront OBJ format, grouped by depth, to inspect the
+    /// quality of the tree in a 3D viewer. Large, overlapping boxes deep in
+    /// the tree point at objects that split poorly.
+    pub fn to_obj(&self, max_depth: usize) -> String {
+        // Corners are numbered by bits: x in bit 0, y in bit 1, z in bit 2.
+        const EDGES: [[usize; 2]; 12] = [
+            [0, 1],
+            [2, 3],
+            [4, 5],
+            [6, 7],
+            [0, 2],
+            [1, 3],
+            [4, 6],
+            [5, 7],
+            [0, 4],
+            [1, 5],
+            [2, 6],
+            [3, 7],
+        ];
+
+        let mut boxes = self.node_boxes();
+        boxes.retain(|(depth, _)| *depth <= max_depth);
+        boxes.sort_by_key(|(depth, _)| *depth);
+
+        let mut obj = String::new();
+        let mut group = None;
+        for (n, (depth, bbox)) in boxes.iter().enumerate() {
+            if group != Some(*depth) {
+                group = Some(*depth);
+                obj.push_str(&format!("g depth_{depth}\n"));
+            }
+
+            let (min, max) = (bbox.min(), bbox.max());
+            for corner in 0..8 {
+                let pick = |bit: usize, axis: Axis| {
+                    if corner & bit == 0 {
+                        min[axis]
+                    } else {
+                        max[axis]
+                    }
+                };
+                obj.push_str(&format!(
+                    "v {} {} {}\n",
+                    pick(1, Axis::X),
+                    pick(2, Axis::Y),
+                    pick(4, Axis::Z)
+                ));
+            }
+
+            // OBJ indices start at 1.
+            let base = 8 * n + 1;
+            for [a, b] in EDGES {
+                obj.push_str(&format!("l {} {}\n", base + a, base + b));
+            }
+        }
+        obj
+    }
+
+    /// Finds the closest hit along with the input index of the hit object.
+    pub fn hit_indexed(&self, ray: &Ray, ray_t: &Interval) -> Option<(HitRecord, usize)> {
+        self.hit_with(ray, ray_t, |object, bounds| object.hit(ray, bounds))
+    }
+
+    /// Finds the closest hit like `hit_indexed`, testing the objects in the
+    /// leaves with `hit` instead of their own `hit`.
+    pub(crate) fn hit_with<'a, F>(
+        &'a self,
+        ray: &Ray,
+        ray_t: &Interval,
+        mut hit: F,
+    ) -> Option<(HitRecord<'a>, usize)>
+    where
+        F: FnMut(&'a T, &Interval) -> Option<HitRecord<'a>>,
+    {
+        if self.nodes.is_empty() {
+            return None;
+        }
+
+        let ray_t = ray.clip(ray_t);
+        let mut closest: Option<(HitRecord, usize)> = None;
+        let mut t_max = ray_t.max();
+
+        let mut stack = vec![0];
+        while let Some(i) = stack.pop() {
+            let node = &self.nodes[i];
+            NODE_TESTS.with(|tests| tests.set(tests.get() + 1));
+            let bounds = Interval::new(ray_t.min(), t_max);
+            if !node.bbox().hit(ray, &bounds) {
+                continue;
+            }
+
+            match *node {
+                BvhNode::Leaf { start, count, .. } => {
+                    for k in start..start + count {
+                        let bounds = Interval::new(ray_t.min(), t_max);
+                        if let Some(rec) = hit(&self.objects[k], &bounds) {
+                            t_max = rec.t();
+                            closest = Some((rec, self.ids[k]));
+                        }
+                    }
+                }
+                BvhNode::Interior { left, right, .. } => {
+                    stack.push(right);
+                    stack.push(left);
+                }
+            }
+        }
+
+        closest
+    }
+}
+
+impl<T: Hittable> Hittable for Bvh<T> {
+    fn hit(&self, ray: &Ray, ray_t: &Interval) -> Option<HitRecord> {
+        self.hit_indexed(ray, ray_t)
+            .map(|(rec, id)| rec.with_object_id(id))
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.nodes.first().map_or(Aabb::EMPTY, |node| *node.bbox())
+    }
+
+    fn is_occluded(&self, ray: &Ray, ray_t: &Interval) -> bool {
+        self.occluded_with(ray, ray_t, |object, bounds| object.is_occluded(ray, bounds))
+    }
+}
+
+impl<T: Hittable> Bvh<T> {
+    /// Determines whether anything blocks the ray like `is_occluded`, testing
+    /// the objects in the leaves with `occluded` instead of their own.
+    pub(crate) fn occluded_with<F>(&self, ray: &Ray, ray_t: &Interval, mut occluded: F) -> bool
+    where
+        F: FnMut(&T, &Interval) -> bool,
+    {
+        if self.nodes.is_empty() {
+            return false;
+        }
+
+        // Any hit will do, so the traversal order does not matter and the
+        // interval never shrinks.
+        let ray_t = &ray.clip(ray_t);
+        let mut stack = vec![0];
+        while let Some(i) = stack.pop() {
+            let node = &self.nodes[i];
+            NODE_TESTS.with(|tests| tests.set(tests.get() + 1));
+            if !node.bbox().hit(ray, ray_t) {
+                continue;
+            }
+
+            match *node {
+                BvhNode::Leaf { start, count, .. } => {
+                    let objects = &self.objects[start..start + count];
+                    if objects.iter().any(|object| occluded(object, ray_t)) {
+                        return true;
+                    }
+                }
+                BvhNode::Interior { left, right, .. } => {
+                    stack.push(right);
+                    stack.push(left);
+                }
+            }
+        }
+
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Bvh;
+    use crate::hittable::{Hittable, HittableList};
+    use crate::material::Lambertian;
+    use crate::sphere::Sphere;
+    use crate::{Color, Float, Interval, Point3, Ray, Vec3};
+    use std::sync::Arc;
+
+    #[test]
+    fn bvh_matches_list() {
+        let material = Arc::new(Lambertian::new(&Color::new(0.5, 0.5, 0.5)));
+
+        let mut spheres = Vec::new();
+        for i in 0..10 {
+            for j in 0..10 {
+                let center = Point3::new(i as Float * 1.5, j as Float * 1.5, -(i * j % 7) as Float);
+                spheres.push(Sphere::new(center, 0.6, material.clone()));
+            }
+        }
+
+        let mut list = HittableList::new();
+        for sphere in spheres.iter().cloned() {
+            list.add(sphere);
+        }
+        let bvh = Bvh::new(spheres);
+        assert_eq!(bvh.len(), 100);
+
+        let bounds = Interval::new(0.001, Float::INFINITY);
+        for i in 0..50 {
+            let origin = Point3::new(i as Float * 0.3, 20.0 - i as Float * 0.4, 10.0);
+            let ray = Ray::new(origin, Vec3::new(0.05, -0.1, -1.0));
+
+            let a = bvh.hit(&ray, &bounds).map(|rec| (rec.t(), rec.object_id));
+            let b = list.hit(&ray, &bounds).map(|rec| (rec.t(), rec.object_id));
+            assert_eq!(a, b);
+            assert_eq!(bvh.is_occluded(&ray, &bounds), b.is_some());
+            assert_eq!(list.is_occluded(&ray, &bounds), b.is_some());
+        }
+
+        let bbox = bvh.bounding_box();
+        assert_eq!(bbox, list.bounding_box());
+
+        assert!(super::take_node_tests() > 0);
+        assert_eq!(super::take_node_tests(), 0);
+    }
+
+    #[test]
+    fn bvh_ray_range() {
+        let material = Arc::new(Lambertian::new(&Color::new(0.5, 0.5, 0.5)));
+        let sphere = Sphere::new(Point3::new(0.0, 0.0, -5.0), 1.0, material);
+        let mut list = HittableList::new();
+        list.add(sphere.clone());
+        let bvh = Bvh::new(vec![sphere]);
+
+        let bounds = Interval::new(0.001, Float::INFINITY);
+        let ray = Ray::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        let short = ray.with_t_range(Interval::new(0.0, 3.0));
+        for world in [&list as &dyn Hittable, &bvh] {
+            assert!(world.hit(&ray, &bounds).is_some());
+            assert!(world.hit(&short, &bounds).is_none());
+            assert!(!world.is_occluded(&short, &bounds));
+        }
+    }
+
+    #[test]
+    fn bvh_to_obj() {
+        let material = Arc::new(Lambertian::new(&Color::new(0.5, 0.5, 0.5)));
+        let spheres: Vec<Sphere> = (0..8)
+            .map(|i| {
+                Sphere::new(
+                    Point3::new(3.0 * i as Float, 0.0, 0.0),
+                    1.0,
+                    material.clone(),
+                )
+            })
+            .collect();
+        let bvh = Bvh::new(spheres);
+
+        // Eight objects split into two leaves under the root.
+        let boxes = bvh.node_boxes();
+        assert_eq!(boxes.iter().map(|(d, _)| *d).collect::<Vec<_>>(), [0, 1, 1]);
+        assert_eq!(boxes[0].1, bvh.bounding_box());
+
+        // Every box has 8 corners and 12 edges, grouped by depth.
+        let count = |obj: &str, prefix: &str| obj.lines().filter(|l| l.starts_with(prefix)).count();
+        let obj = bvh.to_obj(usize::MAX);
+        assert_eq!(
+            (count(&obj, "g "), count(&obj, "v "), count(&obj, "l ")),
+            (2, 24, 36)
+        );
+        assert!(obj.ends_with("l 20 24\n"));
+        assert_eq!(count(&bvh.to_obj(0), "v "), 8);
+    }
+
+    #[test]
+    fn bvh_empty() {
+        let bvh: Bvh<Sphere> = Bvh::new(Vec::new());
+        let ray = Ray::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(bvh.is_empty());
+        assert!(bvh.hit(&ray, &Interval::UNIVERSE).is_none());
+        assert_eq!(super::take_node_tests(), 0);
+    }
+}
@@ -1,9 +1,147 @@
-use crate::{hittable::Hittable, util::random, Color, Error, Interval, Point3, Ray, Vec3};
+use crate::diagnostic::{self, Diagnostic};
+use crate::{consts::PI, Error, Float, Point3, Ray, Vec3};
+
+/// Sub-rectangle of the image, in pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CropWindow {
+    /// Leftmost column.
+    pub col: u32,
+
+    /// Topmost row.
+    pub row: u32,
+
+    /// Width in pixels.
+    pub width: u32,
+
+    /// Height in pixels.
+    pub height: u32,
+}
+
+impl CropWindow {
+    /// Creates a new crop window.
+    pub fn new(col: u32, row: u32, width: u32, height: u32) -> Self {
+        Self {
+            col,
+            row,
+            width,
+            height,
+        }
+    }
+
+    /// Determines whether the pixel at (row, col) is inside the window.
+    pub fn contains(&self, row: u32, col: u32) -> bool {
+        // Offsets from the corner cannot overflow, unlike the far edges.
+        row.checked_sub(self.row).is_some_and(|i| i < self.height)
+            && col.checked_sub(self.col).is_some_and(|j| j < self.width)
+    }
+}
+
+/// Imperfections and movements of a real lens, applied on top of the ideal
+/// thin lens. Image positions are measured from the image center in units of
+/// half the image height, so the top and bottom edges are at a distance of 1.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Lens {
+    /// Radial distortion coefficients `k1` and `k2` (Brown–Conrady). Points at
+    /// distance `r` from the center are moved to `r (1 + k1 r² + k2 r⁴)`, so
+    /// positive values give pincushion distortion and negative values barrel
+    /// distortion.
+    pub distortion: [Float; 2],
+
+    /// Offset of the lens barrel from the aperture, relative to the aperture
+    /// radius, per unit of image position. Off-axis, light through part of
+    /// the aperture is blocked by the barrel, which darkens the image toward
+    /// its edges and cuts out-of-focus highlights into cat's eyes.
+    pub cat_eye: Float,
+
+    /// Shift of the image (x, y), in image position units, with y up. The
+    /// view slides across the focus plane without turning, so vertical lines
+    /// stay parallel when photographing tall buildings.
+    pub shift: [Float; 2],
+
+    /// Tilt of the focus plane in degrees (x, y): around the horizontal axis,
+    /// moving its top away from the camera, and around the vertical axis,
+    /// moving its right side away. Tilting a plane of focus along the ground
+    /// gives the miniature look, or keeps a receding plane sharp throughout.
+    pub tilt: [Float; 2],
+}
+
+impl Lens {
+    /// Create an ideal lens, without distortion or vignetting.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the radial distortion coefficients.
+    pub fn with_distortion(mut self, k1: Float, k2: Float) -> Self {
+        self.distortion = [k1, k2];
+        self
+    }
+
+    /// Set the offset of the lens barrel per unit of image position.
+    pub fn with_cat_eye(mut self, cat_eye: Float) -> Self {
+        self.cat_eye = cat_eye.max(0.0);
+        self
+    }
+
+    /// Set the shift of the image, in image position units.
+    pub fn with_shift(mut self, x: Float, y: Float) -> Self {
+        self.shift = [x, y];
+        self
+    }
+
+    /// Set the tilt of the focus plane in degrees, kept short of a quarter turn.
+    pub fn with_tilt(mut self, x: Float, y: Float) -> Self {
+        self.tilt = [x, y].map(|a| a.clamp(-89.0, 89.0));
+        self
+    }
+
+    /// Scale applied to image positions at squared distance `r2` from the center.
+    fn distortion_scale(&self, r2: Float) -> Float {
+        let [k1, k2] = self.distortion;
+        1.0 + k1 * r2 + k2 * r2 * r2
+    }
+}
+
+/// Eye of a stereo pair.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Eye {
+    /// Left eye.
+    Left,
+
+    /// Right eye.
+    Right,
+}
+
+/// Omni-directional stereo (ODS) projection, for 360° stereo panoramas
+/// viewed in VR headsets. Each column of the equirectangular image is seen
+/// from an eye on a circle of diameter `interpupillary` around the camera,
+/// as if the viewer turned their head to face it. Longitude 0 is the view
+/// direction at the center of the image, and the camera's up is the pole.
+/// An aspect ratio of 2 keeps pixels square.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ods {
+    /// Eye seen from.
+    pub eye: Eye,
+
+    /// Distance between the eyes.
+    pub interpupillary: Float,
+}
+
+impl Ods {
+    /// Create a new ODS projection for one eye.
+    pub fn new(eye: Eye, interpupillary: Float) -> Self {
+        Self {
+            eye,
+            interpupillary,
+        }
+    }
+}
 
 /// Camera information that defines the viewport into worldspace.
+#[derive(Debug, Clone)]
 pub struct Camera {
     /// Image plane aspect ratio.
-    pub aspect_ratio: f64,
+    pub aspect_ratio: Float,
 
     /// Image plane width in pixels.
     pub image_width: u32,
@@ -11,14 +149,8 @@ pub struct Camera {
     /// Image plane height in pixels.
     pub image_height: u32,
 
-    /// Maximum number of ray bounces.
-    pub max_depth: u32,
-
-    /// Number of sampels to be taken per pixel region.
-    pub samples_per_pixel: u32,
-
     /// Vertical FOV.
-    pub vfov: f64,
+    pub vfov: Float,
 
     /// Point camera is looking from.
     pub look_from: Point3,
@@ -30,10 +162,10 @@ pub struct Camera {
     pub vup: Vec3,
 
     /// Amount of angle variation of rays through each pixel.
-    pub defocus_angle: f64,
+    pub defocus_angle: Float,
 
     /// Distance from `look_from` to plane of perfect focus.
-    pub focus_dist: f64,
+    pub focus_dist: Float,
 
     /// Camera coordinates.
     center: Point3,
@@ -48,11 +180,8 @@ pub struct Camera {
     pixel_delta_v: Vec3,
 
     /// Camera coordinate frame basis.
-    #[allow(unused)]
     u: Vec3,
-    #[allow(unused)]
     v: Vec3,
-    #[allow(unused)]
     w: Vec3,
 
     /// Horizontal radius of defocus disk.
@@ -60,24 +189,28 @@ pub struct Camera {
 
     /// Vertical radius of defocus disk.
     defocus_disk_v: Vec3,
+
+    /// Region of the image to trace. Everything is traced when unset.
+    crop: Option<CropWindow>,
+
+    /// Lens imperfections.
+    lens: Lens,
+
+    /// Omni-directional stereo projection replacing the perspective one, if any.
+    ods: Option<Ods>,
 }
 
 impl Camera {
-    // Use a non-zero lower bound to prevent shadow acne.
-    const INITIAL_T_BOUND: Interval = Interval::new(0.001, f64::INFINITY);
-
     /// Create a new camera.
     pub fn new(
-        aspect_ratio: f64,
+        aspect_ratio: Float,
         image_width: u32,
-        samples_per_pixel: u32,
-        max_depth: u32,
-        vfov: f64,
+        vfov: Float,
         look_from: Point3,
         look_at: Point3,
         vup: Vec3,
-        defocus_angle: f64,
-        focus_dist: f64,
+        defocus_angle: Float,
+        focus_dist: Float,
     ) -> Result<Self, Error> {
         if aspect_ratio <= 0.0 {
             return Err(Error::new_camera(&format!(
@@ -89,27 +222,17 @@ impl Camera {
                 "image_width must be greater than 0 (given {image_width})"
             )));
         }
-        if samples_per_pixel == 0 {
-            return Err(Error::new_camera(&format!(
-                "samples_per_pixel must be greater than 0 (given {samples_per_pixel})"
-            )));
-        }
-        if max_depth == 0 {
-            return Err(Error::new_camera(&format!(
-                "max_depth must be greater than 0 (given {samples_per_pixel})"
-            )));
-        }
 
         // Determine image height with the width and aspect ratio.
-        let image_height = f64::max(image_width as f64 / aspect_ratio, 1.0) as u32;
+        let image_height = Float::max(image_width as Float / aspect_ratio, 1.0) as u32;
 
         let center = look_from;
 
         // Viewport dimensions.
         let theta = vfov.to_radians();
-        let h = f64::tan(theta / 2.0);
+        let h = Float::tan(theta / 2.0);
         let viewport_height = 2.0 * h * focus_dist;
-        let viewport_width = viewport_height * (image_width as f64 / image_height as f64);
+        let viewport_width = viewport_height * (image_width as Float / image_height as Float);
 
         // Camera basis vectors.
         let w = (look_from - look_at).unit();
@@ -121,15 +244,15 @@ impl Camera {
         let viewport_v = viewport_height * -v;
 
         // Pixel delta vectors.
-        let pixel_delta_u = viewport_u / image_width as f64;
-        let pixel_delta_v = viewport_v / image_height as f64;
+        let pixel_delta_u = viewport_u / image_width as Float;
+        let pixel_delta_v = viewport_v / image_height as Float;
 
         // Upper-left pixel.
         let viewport_upper_left = center - (focus_dist * w) - viewport_u / 2.0 - viewport_v / 2.0;
         let pixel00_loc = viewport_upper_left + 0.5 * (pixel_delta_u + pixel_delta_v);
 
         // Camera defocus disk basis vectors.
-        let defocus_radius = focus_dist * f64::tan((defocus_angle / 2.0).to_radians());
+        let defocus_radius = focus_dist * Float::tan((defocus_angle / 2.0).to_radians());
         let defocus_disk_u = u * defocus_radius;
         let defocus_disk_v = v * defocus_radius;
 
@@ -137,8 +260,6 @@ impl Camera {
             aspect_ratio,
             image_width,
             image_height,
-            max_depth,
-            samples_per_pixel,
             vfov,
             look_from,
             look_at,
@@ -154,86 +275,452 @@ impl Camera {
             w,
             defocus_disk_u,
             defocus_disk_v,
+            crop: None,
+            lens: Lens::default(),
+            ods: None,
         })
     }
 
+    /// Restrict tracing to a window of the image, given in pixels.
+    pub fn with_crop(mut self, crop: CropWindow) -> Result<Self, Error> {
+        if crop.width == 0 || crop.height == 0 {
+            return Err(Error::new_camera("crop window must not be empty"));
+        }
+        let fits =
+            |start: u32, len: u32, size: u32| start.checked_add(len).is_some_and(|end| end <= size);
+        if !fits(crop.col, crop.width, self.image_width)
+            || !fits(crop.row, crop.height, self.image_height)
+        {
+            return Err(Error::new_camera(&format!(
+                "crop window {}x{} at ({}, {}) exceeds the {}x{} image",
+                crop.width, crop.height, crop.col, crop.row, self.image_width, self.image_height
+            )));
+        }
+
+        self.crop = Some(crop);
+        Ok(self)
+    }
+
+    /// Restrict tracing to a window of the image, given as fractions of the
+    /// image size from the upper-left corner `(x0, y0)` to the lower-right
+    /// corner `(x1, y1)`. The window is widened to whole pixels.
+    pub fn with_crop_normalized(
+        self,
+        x0: Float,
+        y0: Float,
+        x1: Float,
+        y1: Float,
+    ) -> Result<Self, Error> {
+        if !(0.0..=1.0).contains(&x0)
+            || !(0.0..=1.0).contains(&y0)
+            || !(x0..=1.0).contains(&x1)
+            || !(y0..=1.0).contains(&y1)
+        {
+            return Err(Error::new_camera(&format!(
+                "normalized crop window ({x0}, {y0}) to ({x1}, {y1}) must be ordered within [0, 1]"
+            )));
+        }
+
+        let (w, h) = (self.image_width as Float, self.image_height as Float);
+        let (col, row) = ((x0 * w).floor() as u32, (y0 * h).floor() as u32);
+        let (col_end, row_end) = ((x1 * w).ceil() as u32, (y1 * h).ceil() as u32);
+        let crop = CropWindow::new(
+            col,
+            row,
+            col_end.max(col + 1) - col,
+            row_end.max(row + 1) - row,
+        );
+        self.with_crop(crop)
+    }
+
+    /// Trace camera rays through a lens with the given imperfections.
+    pub fn with_lens(mut self, lens: Lens) -> Self {
+        self.lens = lens;
+        self
+    }
+
+    /// Retrieve the lens imperfections.
+    pub fn lens(&self) -> &Lens {
+        &self.lens
+    }
+
+    /// Trace camera rays with an omni-directional stereo projection instead
+    /// of the perspective one. Lens effects and defocus do not apply.
+    pub fn with_ods(mut self, ods: Ods) -> Self {
+        self.ods = Some(ods);
+        self
+    }
+
+    /// Retrieve the omni-directional stereo projection, if any.
+    pub fn ods(&self) -> Option<&Ods> {
+        self.ods.as_ref()
+    }
+
+    /// Retrieve the crop window, if any.
+    pub fn crop(&self) -> Option<&CropWindow> {
+        self.crop.as_ref()
+    }
+
+    /// Create a copy of the camera looking from `look_from` toward `look_at`
+    /// with the given vertical FOV, keeping all other settings.
+    pub fn with_view(&self, look_from: Point3, look_at: Point3, vfov: Float) -> Self {
+        Self::new(
+            self.aspect_ratio,
+            self.image_width,
+            vfov,
+            look_from,
+            look_at,
+            self.vup,
+            self.defocus_angle,
+            self.focus_dist,
+        )
+        .map(|camera| Self {
+            crop: self.crop,
+            lens: self.lens,
+            ods: self.ods,
+            ..camera
+        })
+        .expect("camera settings were validated on creation")
+    }
+
     /// Retrieve image plane pixel dimensions.
     pub fn dim(&self) -> (u32, u32) {
         (self.image_width, self.image_height)
     }
 
-    /// Render the image given a world of hittable objects.
-    pub fn render<T: Hittable>(&self, world: &T) -> Vec<Color> {
-        let mut data: Vec<Color> = Vec::new();
+    /// Retrieve the pixel dimensions of the crop window, or of the whole image
+    /// without one.
+    pub fn crop_dim(&self) -> (u32, u32) {
+        let crop = self.full_or_crop();
+        (crop.width, crop.height)
+    }
+
+    /// Project a point in worldspace onto the image plane, giving its
+    /// continuous (col, row) pixel coordinates, with pixel centers at whole
+    /// numbers. Returns `None` for points behind the camera. Lens shift is
+    /// accounted for; defocus, tilt, distortion and ODS are ignored.
+    pub fn project(&self, p: &Point3) -> Option<(Float, Float)> {
+        let depth = self.depth(p);
+        if depth <= 0.0 {
+            return None;
+        }
 
-        for row in 0..self.image_height {
-            for col in 0..self.image_width {
-                let mut pixel_color = Color::new(0.0, 0.0, 0.0);
+        let d = *p - self.center;
+        let offset = self.center + d * (self.focus_dist / depth) - self.shift() - self.pixel00_loc;
+        let col = Vec3::dot(&offset, &self.pixel_delta_u) / self.pixel_delta_u.len_sqr();
+        let row = Vec3::dot(&offset, &self.pixel_delta_v) / self.pixel_delta_v.len_sqr();
+        Some((col, row))
+    }
 
-                for _ in 0..self.samples_per_pixel {
-                    let ray = self.get_ray(row, col);
-                    pixel_color += Camera::ray_color(&ray, self.max_depth, world);
-                }
+    /// Distance from the camera to a point in worldspace along the viewing
+    /// direction, i.e. its camera-space depth. Negative behind the camera.
+    pub fn depth(&self, p: &Point3) -> Float {
+        -Vec3::dot(&(*p - self.center), &self.w)
+    }
 
-                data.push(pixel_color / self.samples_per_pixel as f32);
-            }
+    /// Reports problems with the camera settings that would produce NaN or
+    /// empty images.
+    pub fn validate(&self) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+        if ![self.u, self.v, self.w].iter().all(diagnostic::is_finite) {
+            diagnostics.push(Diagnostic::error(
+                "camera basis is degenerate: look_from equals look_at, or vup is parallel to the view direction",
+            ));
         }
+        if !(self.vfov > 0.0 && self.vfov < 180.0) {
+            diagnostics.push(Diagnostic::error(&format!(
+                "vfov must be within (0, 180) degrees (given {})",
+                self.vfov
+            )));
+        }
+        if !(self.focus_dist > 0.0 && self.focus_dist.is_finite()) {
+            diagnostics.push(Diagnostic::error(&format!(
+                "focus_dist must be positive and finite (given {})",
+                self.focus_dist
+            )));
+        }
+        diagnostics
+    }
+
+    /// Offset of the image window across the focus plane due to lens shift.
+    fn shift(&self) -> Vec3 {
+        let half_height = 0.5 * self.image_height as Float * self.pixel_delta_v.len();
+        let [x, y] = self.lens.shift;
+        half_height * (x * self.u + y * self.v)
+    }
 
-        data
+    /// Normal of the focus plane, scaled so its component along `w` is 1.
+    fn focus_normal(&self) -> Vec3 {
+        let [x, y] = self.lens.tilt.map(|a| a.to_radians().tan());
+        self.w + x * self.v + y * self.u
+    }
+
+    /// Crop window covering the whole image when none is set.
+    pub(crate) fn full_or_crop(&self) -> CropWindow {
+        self.crop
+            .unwrap_or(CropWindow::new(0, 0, self.image_width, self.image_height))
     }
 
     /// Constructs a viewing ray originating from the defocus disk and directed
-    /// to a randomly sampled point around the pixe located at (row, col).
-    fn get_ray(&self, row: u32, col: u32) -> Ray {
+    /// to the point offset by (dx, dy) pixels from the center of the pixel
+    /// located at (row, col). Vignetting by the lens barrel is ignored.
+    pub(crate) fn get_ray(&self, row: u32, col: u32, dx: Float, dy: Float) -> Ray {
+        let (ray, _) = self.trace_lens(row, col, dx, dy);
+        ray
+    }
+
+    /// Constructs a viewing ray like `get_ray`, or `None` if the lens barrel
+    /// blocks it.
+    pub(crate) fn get_ray_vignetted(
+        &self,
+        row: u32,
+        col: u32,
+        dx: Float,
+        dy: Float,
+    ) -> Option<Ray> {
+        let (ray, blocked) = self.trace_lens(row, col, dx, dy);
+        (!blocked).then_some(ray)
+    }
+
+    /// Constructs a viewing ray, refracted by the thin lens through a sampled
+    /// point of the aperture, and determines whether the barrel blocks it.
+    fn trace_lens(&self, row: u32, col: u32, dx: Float, dy: Float) -> (Ray, bool) {
+        if let Some(ods) = &self.ods {
+            return (self.trace_ods(ods, row, col, dx, dy), false);
+        }
+
         // Build a vector to the center of the pixel.
-        let pixel_u = col as f64 * self.pixel_delta_u;
-        let pixel_v = row as f64 * self.pixel_delta_v;
+        let pixel_u = col as Float * self.pixel_delta_u;
+        let pixel_v = row as Float * self.pixel_delta_v;
         let pixel_center = self.pixel00_loc + pixel_u + pixel_v;
 
-        // Sample the pixel.
-        let pixel_sample = pixel_center + self.pixel_sample_square();
+        // Offset the sample within the filter support, and shift the image.
+        let pixel_sample =
+            pixel_center + dx * self.pixel_delta_u + dy * self.pixel_delta_v + self.shift();
+
+        // Image position, in units of half the image height, with y up.
+        let half_height = 0.5 * self.image_height as Float;
+        let x = (col as Float + dx - (self.image_width as Float - 1.0) / 2.0) / half_height;
+        let y = ((self.image_height as Float - 1.0) / 2.0 - row as Float - dy) / half_height;
+
+        // Distortion moves the point of the focus plane the pixel sees.
+        let focus_center = self.center - self.focus_dist * self.w;
+        let scale = self.lens.distortion_scale(x * x + y * y);
+        let view = focus_center + scale * (pixel_sample - focus_center) - self.center;
+
+        // A tilted focus plane still passes through the focus center, so the
+        // pixel is sharp where its view meets the plane. Views missing the
+        // plane in front of the camera are focused at infinity.
+        let along = Vec3::dot(&self.focus_normal(), &view);
+        let focus_point = (along < 0.0).then(|| self.center - (self.focus_dist / along) * view);
+
+        // The lens focuses every ray through the aperture onto that point.
+        // Off-axis, the barrel is offset from the aperture and may block it.
+        let aperture = if self.defocus_angle > 0.0 || self.lens.cat_eye > 0.0 {
+            Vec3::random_on_unit_disk()
+        } else {
+            Vec3::new(0.0, 0.0, 0.0)
+        };
+        let barrel = Vec3::new(-self.lens.cat_eye * x, -self.lens.cat_eye * y, 0.0);
+        let blocked = self.lens.cat_eye > 0.0 && (aperture - barrel).len_sqr() > 1.0;
 
-        // Construct the ray to that pixel.
+        // Construct the ray to that point.
         let ray_origin = if self.defocus_angle <= 0.0 {
             self.center
         } else {
-            self.sample_defocus_disk()
+            self.center
+                + (aperture.x() * self.defocus_disk_u)
+                + (aperture.y() * self.defocus_disk_v)
+        };
+        let ray_direction = match focus_point {
+            Some(focus_point) => focus_point - ray_origin,
+            None => view,
         };
-        let ray_direction = pixel_sample - ray_origin;
 
-        Ray::new(ray_origin, ray_direction)
+        (Ray::new(ray_origin, ray_direction), blocked)
     }
 
-    /// Sample within a pixel square.
-    fn pixel_sample_square(&self) -> Vec3 {
-        let px = random::gen_unit() - 0.5;
-        let py = random::gen_unit() - 0.5;
+    /// Constructs a viewing ray of an omni-directional stereo panorama.
+    fn trace_ods(&self, ods: &Ods, row: u32, col: u32, dx: Float, dy: Float) -> Ray {
+        let longitude = ((col as Float + 0.5 + dx) / self.image_width as Float - 0.5) * 2.0 * PI;
+        let latitude = (0.5 - (row as Float + 0.5 + dy) / self.image_height as Float) * PI;
+
+        // The eye sits on the circle, to the side of the horizontal direction faced.
+        let ahead = longitude.sin() * self.u - longitude.cos() * self.w;
+        let side = longitude.cos() * self.u + longitude.sin() * self.w;
+        let offset = match ods.eye {
+            Eye::Left => -0.5 * ods.interpupillary,
+            Eye::Right => 0.5 * ods.interpupillary,
+        };
 
-        px * self.pixel_delta_u + py * self.pixel_delta_v
+        let direction = latitude.cos() * ahead + latitude.sin() * self.v;
+        Ray::new(self.center + offset * side, direction)
     }
+}
 
-    /// Determine the color of a ray.
-    fn ray_color<T: Hittable>(ray: &Ray, depth: u32, world: &T) -> Color {
-        if depth == 0 {
-            return Color::new(0.0, 0.0, 0.0);
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn small_camera() -> Camera {
+        Camera::new(
+            2.0,
+            8,
+            90.0,
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(0.0, 0.0, -1.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            0.0,
+            1.0,
+        )
+        .unwrap()
+    }
 
-        if let Some(rec) = world.hit(ray, &Self::INITIAL_T_BOUND) {
-            return if let Some((scattered, attenuation)) = rec.material.scatter(ray, &rec) {
-                attenuation * Camera::ray_color(&scattered, depth - 1, world)
-            } else {
-                Color::new(0.0, 0.0, 0.0)
-            };
-        }
+    #[test]
+    fn camera_crop() {
+        let camera = small_camera()
+            .with_crop(CropWindow::new(2, 1, 3, 2))
+            .unwrap();
+        assert_eq!(camera.crop_dim(), (3, 2));
+
+        let camera = camera.with_crop_normalized(0.5, 0.0, 1.0, 0.5).unwrap();
+        assert_eq!(camera.crop(), Some(&CropWindow::new(4, 0, 4, 2)));
+
+        let moved = camera.with_view(Point3::new(1.0, 0.0, 0.0), Point3::new(0.0, 0.0, 0.0), 60.0);
+        assert_eq!(moved.crop(), camera.crop());
+
+        assert!(small_camera()
+            .with_crop(CropWindow::new(6, 0, 3, 1))
+            .is_err());
+        assert!(small_camera()
+            .with_crop(CropWindow::new(u32::MAX, 0, 2, 1))
+            .is_err());
+        assert!(small_camera()
+            .with_crop(CropWindow::new(0, 1, 1, u32::MAX))
+            .is_err());
+
+        let edge = CropWindow::new(u32::MAX - 1, u32::MAX - 1, 8, 8);
+        assert!(edge.contains(u32::MAX, u32::MAX));
+        assert!(!edge.contains(0, u32::MAX));
+        assert!(small_camera()
+            .with_crop_normalized(0.5, 0.0, 0.2, 1.0)
+            .is_err());
+    }
+
+    #[test]
+    fn camera_lens() {
+        let distorted = small_camera().with_lens(Lens::new().with_distortion(0.1, 0.0));
+        let ideal = small_camera();
+
+        // Distortion leaves the center alone and pushes the corners outward.
+        let d = distorted.get_ray(1, 3, 0.5, 0.5).direction().unit();
+        let i = ideal.get_ray(1, 3, 0.5, 0.5).direction().unit();
+        assert!(d.almost_eq(&i));
+        let (x, y) = ideal
+            .project(&distorted.get_ray(0, 0, 0.0, 0.0).at(1.0))
+            .unwrap();
+        assert!(x < -0.1 && y < -0.1);
+
+        // Without vignetting nothing is blocked. With it, the center is still
+        // clear and the corners darken.
+        let blocked = |camera: &Camera, row, col| {
+            (0..1000)
+                .filter(|_| camera.get_ray_vignetted(row, col, 0.0, 0.0).is_none())
+                .count()
+        };
+        assert_eq!(blocked(&ideal, 0, 0), 0);
+        let vignetted = small_camera().with_lens(Lens::new().with_cat_eye(0.5));
+        assert_eq!(vignetted.lens().cat_eye, 0.5);
+        let corner = blocked(&vignetted, 0, 0);
+        assert!(corner > 100 && corner < 900);
+        assert!(blocked(&vignetted, 1, 3) < corner);
+    }
+
+    #[test]
+    fn camera_tilt_shift() {
+        let camera = |lens: Lens| {
+            Camera::new(
+                1.0,
+                8,
+                60.0,
+                Point3::new(0.0, 0.0, 0.0),
+                Point3::new(0.0, 0.0, -1.0),
+                Vec3::new(0.0, 1.0, 0.0),
+                2.0,
+                4.0,
+            )
+            .unwrap()
+            .with_lens(lens)
+        };
 
-        let unit_dir = ray.direction().unit();
-        let a = (0.5 * (unit_dir.y() + 1.0)) as f32;
-        (1.0 - a) * Color::new(1.0, 1.0, 1.0) + a * Color::new(0.5, 0.7, 1.0)
+        // Shifting up looks higher without turning: the center column of
+        // the shifted image sees what the upper rows saw.
+        let shifted = camera(Lens::new().with_shift(0.0, 0.5));
+        let p = shifted.get_ray(4, 4, 0.0, 0.0).at(1.0);
+        let (x, y) = camera(Lens::new()).project(&p).unwrap();
+        assert!((x - 4.0).abs() < 1e-3 && (y - 2.0).abs() < 1e-3);
+        let (x, y) = shifted.project(&p).unwrap();
+        assert!((x - 4.0).abs() < 1e-3 && (y - 4.0).abs() < 1e-3);
+
+        // Every ray through a pixel meets at its focus point. Tilted, the top
+        // of the image focuses farther away than the bottom.
+        let tilted = camera(Lens::new().with_tilt(30.0, 0.0));
+        let focus = |camera: &Camera, row| {
+            let p = camera.get_ray(row, 4, 0.0, 0.0).at(1.0);
+            for _ in 0..10 {
+                assert!(camera.get_ray(row, 4, 0.0, 0.0).at(1.0).almost_eq(&p));
+            }
+            camera.depth(&p)
+        };
+        assert!((focus(&camera(Lens::new()), 0) - 4.0).abs() < 1e-3);
+        assert!(focus(&tilted, 0) > 4.5);
+        assert!(focus(&tilted, 7) < 3.5);
+        assert!((focus(&tilted, 4) - 4.0).abs() < 0.5);
     }
 
-    /// Sample a ray from the defocus disk.
-    fn sample_defocus_disk(&self) -> Point3 {
-        let p = Vec3::random_on_unit_disk();
-        self.center + (p.x() * self.defocus_disk_u) + (p.y() * self.defocus_disk_v)
+    #[test]
+    fn camera_ods() {
+        let ods = |eye| {
+            Camera::new(
+                2.0,
+                8,
+                90.0,
+                Point3::new(0.0, 0.0, 0.0),
+                Point3::new(0.0, 0.0, -1.0),
+                Vec3::new(0.0, 1.0, 0.0),
+                0.0,
+                1.0,
+            )
+            .unwrap()
+            .with_ods(Ods::new(eye, 0.2))
+        };
+        let (left, right) = (ods(Eye::Left), ods(Eye::Right));
+        assert_eq!(left.ods().unwrap().eye, Eye::Left);
+
+        // The center of the image looks ahead, from eyes to either side.
+        let ray = left.get_ray(1, 3, 0.5, 0.5);
+        assert!(ray.direction().unit().almost_eq(&Vec3::new(0.0, 0.0, -1.0)));
+        assert!(ray.origin().almost_eq(&Point3::new(-0.1, 0.0, 0.0)));
+        let ray = right.get_ray(1, 3, 0.5, 0.5);
+        assert!(ray.origin().almost_eq(&Point3::new(0.1, 0.0, 0.0)));
+
+        // A quarter of the way across looks left, with the eyes in front
+        // and behind; the top row looks near the pole.
+        let ray = left.get_ray(1, 1, 0.5, 0.5);
+        assert!(ray.direction().unit().almost_eq(&Vec3::new(-1.0, 0.0, 0.0)));
+        assert!(ray.origin().almost_eq(&Point3::new(0.0, 0.0, 0.1)));
+        assert!(left.get_ray(0, 5, 0.0, 0.0).direction().unit().y() > 0.9);
+    }
+
+    #[test]
+    fn camera_project() {
+        let camera = small_camera();
+        for (row, col) in [(0, 0), (2, 5), (3, 7)] {
+            let ray = camera.get_ray(row, col, 0.25, -0.5);
+            let (x, y) = camera.project(&ray.at(3.0)).unwrap();
+            assert!((x - (col as Float + 0.25)).abs() < 1e-4);
+            assert!((y - (row as Float - 0.5)).abs() < 1e-4);
+        }
+        assert!(camera.project(&Point3::new(0.0, 0.0, 1.0)).is_none());
     }
 }
@@ -1,8 +1,8 @@
-use crate::almost::AlmostPartialEq;
+use crate::almost::{AlmostPartialEq, Tolerance};
 use std::fmt;
 use std::ops;
 
-use crate::Interval;
+use crate::{Error, Float, Interval};
 
 /// RGB color.
 #[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
@@ -35,14 +35,32 @@ impl Color {
         self[2]
     }
 
+    /// Relative luminance of the linear RGB color (Rec. 709 primaries).
+    pub fn luminance(&self) -> f32 {
+        0.2126 * self.r() + 0.7152 * self.g() + 0.0722 * self.b()
+    }
+
     /// Determines whether the given color is approximately all zero (black in color).
     pub fn almost_zero(&self) -> bool {
-        self.channels.iter().all(|&channel| channel.almost_zero())
+        self.almost_zero_with(&f32::TOLERANCE)
+    }
+
+    /// Determines whether the given color is all zero within `tolerance`.
+    pub fn almost_zero_with(&self, tolerance: &Tolerance<f32>) -> bool {
+        self.channels
+            .iter()
+            .all(|channel| channel.almost_zero_with(tolerance))
     }
 
     /// Determines whether two colors are approximately equal.
     pub fn almost_eq(&self, color: &Self) -> bool {
-        (self - color).almost_zero()
+        self.almost_eq_with(color, &f32::TOLERANCE)
+    }
+
+    /// Determines whether two colors are equal within `tolerance`, relative
+    /// to their brightest channel.
+    pub fn almost_eq_with(&self, color: &Self, tolerance: &Tolerance<f32>) -> bool {
+        tolerance.accepts_all(&self.channels, &color.channels)
     }
 }
 
@@ -56,9 +74,23 @@ impl Color {
         ]
     }
 
+    /// Convert to RGB48 representation with 16 bits per channel.
+    pub fn to_rgb48(&self) -> [u16; 3] {
+        [
+            Self::make_word(self.r()),
+            Self::make_word(self.g()),
+            Self::make_word(self.b()),
+        ]
+    }
+
     /// Make byte from a channel value.
     fn make_byte(channel: f32) -> u8 {
-        f64::floor(Self::INTENSITY.clamp(channel as f64) * 255.0) as u8
+        Float::floor(Self::INTENSITY.clamp(channel as Float) * 255.0) as u8
+    }
+
+    /// Make 16-bit word from a channel value, spanning the full range.
+    fn make_word(channel: f32) -> u16 {
+        Float::floor(Self::INTENSITY.clamp(channel as Float) * 65536.0) as u16
     }
 }
 
@@ -80,6 +112,183 @@ impl Color {
             Self::linear_to_gamma(self.b()),
         )
     }
+
+    /// Convert an sRGB-encoded color, as stored in most image files, to linear RGB.
+    pub fn srgb_to_linear(&self) -> Self {
+        let channel = |c: f32| {
+            if c <= 0.04045 {
+                c / 12.92
+            } else {
+                ((c + 0.055) / 1.055).powf(2.4)
+            }
+        };
+        Self::new(channel(self.r()), channel(self.g()), channel(self.b()))
+    }
+
+    /// Undo gamma correction, recovering the linear RGB color.
+    pub fn linearize(&self) -> Self {
+        let channel = |c: f32| if c > 0.0 { c * c } else { 0.0 };
+        Self::new(channel(self.r()), channel(self.g()), channel(self.b()))
+    }
+}
+
+impl Color {
+    /// Create a color from 8-bit channel values, mapping 0 to 0.0 and 255 to
+    /// 1.0. Values are taken as is; use `srgb_to_linear` on colors picked in
+    /// sRGB, e.g. from a color picker, before using them as albedos.
+    pub fn from_rgb8(r: u8, g: u8, b: u8) -> Self {
+        Self::new(r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0)
+    }
+
+    /// Parse a hexadecimal color of the form `#rrggbb` or `#rgb`, with or
+    /// without the leading `#`, into 8-bit channel values (see `from_rgb8`).
+    pub fn from_hex(hex: &str) -> Result<Self, Error> {
+        let digits = hex.strip_prefix('#').unwrap_or(hex);
+        let invalid = || Error::new_color(&format!("invalid hex color '{hex}'"));
+        if !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(invalid());
+        }
+
+        let channel = |i: usize, len: usize| {
+            let value = u8::from_str_radix(&digits[i * len..(i + 1) * len], 16).unwrap_or(0);
+            // Short digits repeat, so `f` is `ff`.
+            if len == 1 {
+                value * 17
+            } else {
+                value
+            }
+        };
+        match digits.len() {
+            3 => Ok(Self::from_rgb8(channel(0, 1), channel(1, 1), channel(2, 1))),
+            6 => Ok(Self::from_rgb8(channel(0, 2), channel(1, 2), channel(2, 2))),
+            _ => Err(invalid()),
+        }
+    }
+
+    /// Create a color from its hue in degrees, saturation, and value, each of
+    /// the latter in [0, 1]. Hues wrap around, so 360 is red again.
+    pub fn from_hsv(hue: f32, saturation: f32, value: f32) -> Self {
+        let h = hue.rem_euclid(360.0) / 60.0;
+        let chroma = value * saturation;
+        let x = chroma * (1.0 - (h % 2.0 - 1.0).abs());
+        let (r, g, b) = match h as u32 {
+            0 => (chroma, x, 0.0),
+            1 => (x, chroma, 0.0),
+            2 => (0.0, chroma, x),
+            3 => (0.0, x, chroma),
+            4 => (x, 0.0, chroma),
+            _ => (chroma, 0.0, x),
+        };
+        let m = value - chroma;
+        Self::new(r + m, g + m, b + m)
+    }
+
+    /// Convert to hue in degrees within [0, 360), saturation, and value. Grays
+    /// have a hue and saturation of 0.
+    pub fn to_hsv(&self) -> (f32, f32, f32) {
+        let (r, g, b) = (self.r(), self.g(), self.b());
+        let max = r.max(g).max(b);
+        let chroma = max - r.min(g).min(b);
+
+        let hue = if chroma <= 0.0 {
+            0.0
+        } else if max == r {
+            60.0 * ((g - b) / chroma).rem_euclid(6.0)
+        } else if max == g {
+            60.0 * ((b - r) / chroma + 2.0)
+        } else {
+            60.0 * ((r - g) / chroma + 4.0)
+        };
+        let saturation = if max > 0.0 { chroma / max } else { 0.0 };
+        (hue, saturation, max)
+    }
+}
+
+impl Color {
+    /// Converts CIE 1931 XYZ tristimulus values to linear sRGB.
+    pub fn from_xyz(x: Float, y: Float, z: Float) -> Self {
+        let r = 3.2404542 * x - 1.5371385 * y - 0.4985314 * z;
+        let g = -0.9692660 * x + 1.8760108 * y + 0.0415560 * z;
+        let b = 0.0556434 * x - 0.2040259 * y + 1.0572252 * z;
+        Self::new(r as f32, g as f32, b as f32)
+    }
+
+    /// Linear RGB color of a blackbody radiator at `temp` kelvin.
+    /// The result is normalized so that its largest channel is 1.
+    pub fn from_kelvin(temp: Float) -> Self {
+        let (mut x, mut y, mut z) = (0.0, 0.0, 0.0);
+        let mut lambda = VISIBLE_MIN_NM;
+        while lambda <= VISIBLE_MAX_NM {
+            let radiance = blackbody_normalized(lambda, temp);
+            let (xb, yb, zb) = cie_xyz(lambda);
+            x += radiance * xb;
+            y += radiance * yb;
+            z += radiance * zb;
+            lambda += 5.0;
+        }
+
+        let color = Self::from_xyz(x, y, z);
+        let color = Self::new(
+            f32::max(color.r(), 0.0),
+            f32::max(color.g(), 0.0),
+            f32::max(color.b(), 0.0),
+        );
+
+        let max = color.r().max(color.g()).max(color.b());
+        if max > 0.0 {
+            color / max
+        } else {
+            color
+        }
+    }
+}
+
+/// Lower bound of the visible spectrum in nanometers.
+pub const VISIBLE_MIN_NM: Float = 380.0;
+
+/// Upper bound of the visible spectrum in nanometers.
+pub const VISIBLE_MAX_NM: Float = 780.0;
+
+/// Representative wavelengths in nanometers for the red, green, and blue channels.
+pub const RGB_WAVELENGTHS: [Float; 3] = [650.0, 532.0, 450.0];
+
+/// Spectral radiance of a blackbody at wavelength `lambda` (nanometers) and
+/// temperature `temp` (kelvin), following Planck's law. In W·sr⁻¹·m⁻³.
+pub fn blackbody(lambda: Float, temp: Float) -> Float {
+    if temp <= 0.0 {
+        return 0.0;
+    }
+
+    const C: Float = 299792458.0;
+    const H: Float = 6.62606957e-34;
+    const KB: Float = 1.3806488e-23;
+
+    let l = lambda * 1e-9;
+    (2.0 * H * C * C) / (l.powi(5) * (Float::exp((H * C) / (l * KB * temp)) - 1.0))
+}
+
+/// Blackbody spectral radiance normalized so that its peak (given by Wien's
+/// displacement law) is 1.
+pub fn blackbody_normalized(lambda: Float, temp: Float) -> Float {
+    let lambda_max = 2.8977721e-3 / temp * 1e9;
+    blackbody(lambda, temp) / blackbody(lambda_max, temp)
+}
+
+/// CIE 1931 color matching functions at wavelength `lambda` (nanometers),
+/// using the multi-lobe Gaussian fit of Wyman, Sloan, and Shirley.
+pub fn cie_xyz(lambda: Float) -> (Float, Float, Float) {
+    let g = |mu: Float, sigma_lo: Float, sigma_hi: Float| {
+        let sigma = if lambda < mu { sigma_lo } else { sigma_hi };
+        let t = (lambda - mu) / sigma;
+        Float::exp(-0.5 * t * t)
+    };
+
+    let x =
+        1.056 * g(599.8, 37.9, 31.0) + 0.362 * g(442.0, 16.0, 26.7) - 0.065 * g(501.1, 20.4, 26.2);
+    let y = 0.821 * g(568.8, 46.9, 40.5) + 0.286 * g(530.9, 16.3, 31.1);
+    let z = 1.217 * g(437.0, 11.8, 36.0) + 0.681 * g(459.0, 26.0, 13.8);
+
+    (x, y, z)
 }
 
 impl fmt::Display for Color {
@@ -301,7 +510,8 @@ hadamard_divide_assign!(&Color);
 
 #[cfg(test)]
 mod tests {
-    use super::Color;
+    use super::{blackbody, blackbody_normalized, Color};
+    use crate::ErrorKind;
 
     #[test]
     fn color_channels() {
@@ -417,6 +627,58 @@ mod tests {
 
         let c = Color::new(0.4, 0.5, 0.6);
         assert_eq!(c.to_rgb24(), [102, 127, 153]);
+
+        let c = Color::new(0.0, 0.5, 2.0);
+        assert_eq!(c.to_rgb48(), [0, 32768, 65535]);
+    }
+
+    #[test]
+    fn color_from_hex() {
+        assert_eq!(Color::from_rgb8(255, 0, 51), Color::new(1.0, 0.0, 0.2));
+        assert_eq!(
+            Color::from_hex("#ff0033").unwrap(),
+            Color::from_rgb8(255, 0, 51)
+        );
+        assert_eq!(
+            Color::from_hex("FF0033").unwrap(),
+            Color::from_rgb8(255, 0, 51)
+        );
+        assert_eq!(
+            Color::from_hex("#f03").unwrap(),
+            Color::from_rgb8(255, 0, 51)
+        );
+
+        for bad in ["", "#", "#ff00", "#ff00334", "#gg0033", "#ff 033"] {
+            let error = Color::from_hex(bad).unwrap_err();
+            assert!(matches!(error.kind(), ErrorKind::Color(_)));
+        }
+    }
+
+    #[test]
+    fn color_hsv() {
+        assert!(Color::from_hsv(0.0, 1.0, 1.0).almost_eq(&Color::new(1.0, 0.0, 0.0)));
+        assert!(Color::from_hsv(120.0, 1.0, 0.5).almost_eq(&Color::new(0.0, 0.5, 0.0)));
+        assert!(Color::from_hsv(240.0, 0.5, 1.0).almost_eq(&Color::new(0.5, 0.5, 1.0)));
+        assert!(Color::from_hsv(360.0, 1.0, 1.0).almost_eq(&Color::new(1.0, 0.0, 0.0)));
+        assert!(Color::from_hsv(-60.0, 1.0, 1.0).almost_eq(&Color::new(1.0, 0.0, 1.0)));
+
+        assert_eq!(Color::new(0.5, 0.5, 0.5).to_hsv(), (0.0, 0.0, 0.5));
+        for hue in [0.0, 45.0, 150.0, 200.0, 300.0] {
+            let color = Color::from_hsv(hue, 0.8, 0.6);
+            let (h, s, v) = color.to_hsv();
+            assert!((h - hue).abs() < 1e-3 && (s - 0.8).abs() < 1e-5 && (v - 0.6).abs() < 1e-6);
+            assert!(Color::from_hsv(h, s, v).almost_eq(&color));
+        }
+    }
+
+    #[test]
+    fn color_srgb_to_linear() {
+        let c = Color::new(0.0, 0.5, 1.0).srgb_to_linear();
+        assert!(c.almost_eq(&Color::new(0.0, 0.214_041_14, 1.0)));
+
+        // The linear segment near black.
+        let c = Color::new(0.02, 0.02, 0.02).srgb_to_linear();
+        assert!(c.almost_eq(&Color::new(0.02 / 12.92, 0.02 / 12.92, 0.02 / 12.92)));
     }
 
     #[test]
@@ -431,4 +693,29 @@ mod tests {
         let c = Color::new(0.0, 1.0, 0.0);
         assert!(c.gamma_correct().almost_eq(&Color::new(0.0, 1.0, 0.0)));
     }
+
+    #[test]
+    fn color_blackbody() {
+        // Radiance peaks at the wavelength given by Wien's law.
+        assert!((blackbody_normalized(2.8977721e-3 / 5000.0 * 1e9, 5000.0) - 1.0).abs() < 1e-9);
+        assert!(blackbody_normalized(400.0, 5000.0) < 1.0);
+        assert_eq!(blackbody(500.0, 0.0), 0.0);
+
+        // Hotter bodies emit more at every wavelength.
+        assert!(blackbody(550.0, 6000.0) > blackbody(550.0, 3000.0));
+    }
+
+    #[test]
+    fn color_from_kelvin() {
+        let warm = Color::from_kelvin(2000.0);
+        assert!(warm.r() > warm.g() && warm.g() > warm.b());
+        assert_eq!(warm.r(), 1.0);
+
+        let neutral = Color::from_kelvin(6500.0);
+        assert!(neutral.r() > 0.9 && neutral.g() > 0.9 && neutral.b() > 0.9);
+
+        let cool = Color::from_kelvin(12000.0);
+        assert!(cool.b() > cool.g() && cool.g() > cool.r());
+        assert_eq!(cool.b(), 1.0);
+    }
 }
@@ -0,0 +1,335 @@
+use crate::{Color, Float, Vec3};
+
+/// Matrix stored as rows.
+type Matrix = [Vec3; 3];
+
+/// CIE 1931 xy chromaticity of the D65 white point.
+const D65: [Float; 2] = [0.3127, 0.3290];
+
+/// CIE 1931 xy chromaticity of the white point of the ACES color spaces.
+const ACES_WHITE: [Float; 2] = [0.32168, 0.33767];
+
+/// Cone response matrix of the Bradford chromatic adaptation transform.
+const BRADFORD: Matrix = [
+    Vec3::new(0.8951, 0.2664, -0.1614),
+    Vec3::new(-0.7502, 1.7135, 0.0367),
+    Vec3::new(0.0389, -0.0685, 1.0296),
+];
+
+/// CIE 1931 xy chromaticities of a color space's red, green, and blue
+/// primaries and its white point.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Chromaticities {
+    red: [Float; 2],
+    green: [Float; 2],
+    blue: [Float; 2],
+    white: [Float; 2],
+}
+
+impl Chromaticities {
+    /// Rec. 709 primaries, shared by sRGB.
+    const REC709: Self = Self {
+        red: [0.64, 0.33],
+        green: [0.30, 0.60],
+        blue: [0.15, 0.06],
+        white: D65,
+    };
+
+    /// ACES AP1 primaries, used by ACEScg.
+    const AP1: Self = Self {
+        red: [0.713, 0.293],
+        green: [0.165, 0.830],
+        blue: [0.128, 0.044],
+        white: ACES_WHITE,
+    };
+
+    /// DCI-P3 primaries with a D65 white point, used by Display P3.
+    const P3_D65: Self = Self {
+        red: [0.680, 0.320],
+        green: [0.265, 0.690],
+        blue: [0.150, 0.060],
+        white: D65,
+    };
+
+    /// Rec. 2020 primaries.
+    const REC2020: Self = Self {
+        red: [0.708, 0.292],
+        green: [0.170, 0.797],
+        blue: [0.131, 0.046],
+        white: D65,
+    };
+
+    /// Matrix taking linear RGB in these primaries to CIE XYZ, scaled so
+    /// that RGB white has a luminance of 1.
+    fn rgb_to_xyz(&self) -> Matrix {
+        let primaries = transpose(&[xyz(self.red), xyz(self.green), xyz(self.blue)]);
+        let scale = apply(&invert(&primaries), &xyz(self.white));
+        primaries.map(|row| Vec3::new(row[0] * scale[0], row[1] * scale[1], row[2] * scale[2]))
+    }
+}
+
+/// Linear color space in which scenes are authored and rendered.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum WorkingSpace {
+    /// Linear RGB with the sRGB (Rec. 709) primaries.
+    #[default]
+    LinearSrgb,
+
+    /// ACEScg: linear RGB with the wider ACES AP1 primaries, common in film
+    /// pipelines for rendering with saturated colors.
+    AcesCg,
+}
+
+impl WorkingSpace {
+    /// Chromaticities of the space.
+    fn chromaticities(&self) -> Chromaticities {
+        match self {
+            WorkingSpace::LinearSrgb => Chromaticities::REC709,
+            WorkingSpace::AcesCg => Chromaticities::AP1,
+        }
+    }
+}
+
+/// Display color space in which images are encoded for output.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutputSpace {
+    /// sRGB, the standard for monitors and the web.
+    #[default]
+    Srgb,
+
+    /// Display P3: the wider P3 primaries with the sRGB transfer function,
+    /// as used by recent Apple displays.
+    DisplayP3,
+
+    /// Rec. 2020 (standard dynamic range), for wide-gamut UHD video.
+    Rec2020,
+}
+
+impl OutputSpace {
+    /// Chromaticities of the space.
+    fn chromaticities(&self) -> Chromaticities {
+        match self {
+            OutputSpace::Srgb => Chromaticities::REC709,
+            OutputSpace::DisplayP3 => Chromaticities::P3_D65,
+            OutputSpace::Rec2020 => Chromaticities::REC2020,
+        }
+    }
+
+    /// Encode a linear channel value with the space's transfer function.
+    /// Negative values, out of the gamut, are clipped to 0.
+    pub fn encode(&self, channel: f32) -> f32 {
+        if channel.is_nan() || channel <= 0.0 {
+            return 0.0;
+        }
+        match self {
+            OutputSpace::Srgb | OutputSpace::DisplayP3 => {
+                if channel <= 0.0031308 {
+                    12.92 * channel
+                } else {
+                    1.055 * channel.powf(1.0 / 2.4) - 0.055
+                }
+            }
+            OutputSpace::Rec2020 => {
+                const ALPHA: f32 = 1.099_296_8;
+                const BETA: f32 = 0.018_053_97;
+                if channel < BETA {
+                    4.5 * channel
+                } else {
+                    ALPHA * channel.powf(0.45) - (ALPHA - 1.0)
+                }
+            }
+        }
+    }
+}
+
+/// Color management: converts linear colors from the working space to the
+/// output space's primaries, adapting the white point if needed, then
+/// applies the output space's transfer function for encoding. The default
+/// renders in linear sRGB and outputs sRGB. Unlike the plain gamma
+/// correction of `Color::gamma_correct`, the exact transfer functions are
+/// used.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ColorManagement {
+    /// Space of the rendered colors.
+    working: WorkingSpace,
+
+    /// Space of the encoded colors.
+    output: OutputSpace,
+
+    /// Matrix taking working space colors to linear output space colors.
+    conversion: Matrix,
+}
+
+impl ColorManagement {
+    /// Create a new conversion from `working` to `output`.
+    pub fn new(working: WorkingSpace, output: OutputSpace) -> Self {
+        let (from, to) = (working.chromaticities(), output.chromaticities());
+        let adapt = chromatic_adaptation(from.white, to.white);
+        let conversion = multiply(
+            &invert(&to.rgb_to_xyz()),
+            &multiply(&adapt, &from.rgb_to_xyz()),
+        );
+        Self {
+            working,
+            output,
+            conversion,
+        }
+    }
+
+    /// Retrieve the working space.
+    pub fn working(&self) -> WorkingSpace {
+        self.working
+    }
+
+    /// Retrieve the output space.
+    pub fn output(&self) -> OutputSpace {
+        self.output
+    }
+
+    /// Convert a working space color to linear RGB in the output space's
+    /// primaries. Colors outside of the output gamut get negative channels.
+    pub fn convert(&self, color: &Color) -> Color {
+        let rgb = Vec3::new(color.r() as Float, color.g() as Float, color.b() as Float);
+        let out = apply(&self.conversion, &rgb);
+        Color::new(out[0] as f32, out[1] as f32, out[2] as f32)
+    }
+
+    /// Convert a working space color to the output space and encode it, ready
+    /// to be quantized (see `Color::to_rgb24`).
+    pub fn encode(&self, color: &Color) -> Color {
+        let linear = self.convert(color);
+        Color::new(
+            self.output.encode(linear.r()),
+            self.output.encode(linear.g()),
+            self.output.encode(linear.b()),
+        )
+    }
+
+    /// Encode every color of an image (see `encode`).
+    pub fn encode_all(&self, data: &[Color]) -> Vec<Color> {
+        data.iter().map(|color| self.encode(color)).collect()
+    }
+}
+
+impl Default for ColorManagement {
+    /// Linear sRGB to sRGB.
+    fn default() -> Self {
+        Self::new(WorkingSpace::default(), OutputSpace::default())
+    }
+}
+
+/// CIE XYZ of a chromaticity with a luminance of 1.
+fn xyz([x, y]: [Float; 2]) -> Vec3 {
+    Vec3::new(x / y, 1.0, (1.0 - x - y) / y)
+}
+
+/// Bradford transform adapting XYZ colors seen under the white point `from`
+/// to how they appear under the white point `to`.
+fn chromatic_adaptation(from: [Float; 2], to: [Float; 2]) -> Matrix {
+    let (source, target) = (apply(&BRADFORD, &xyz(from)), apply(&BRADFORD, &xyz(to)));
+    let gain = [
+        Vec3::new(target[0] / source[0], 0.0, 0.0),
+        Vec3::new(0.0, target[1] / source[1], 0.0),
+        Vec3::new(0.0, 0.0, target[2] / source[2]),
+    ];
+    multiply(&invert(&BRADFORD), &multiply(&gain, &BRADFORD))
+}
+
+/// Applies a matrix to a vector.
+fn apply(m: &Matrix, v: &Vec3) -> Vec3 {
+    Vec3::new(
+        Vec3::dot(&m[0], v),
+        Vec3::dot(&m[1], v),
+        Vec3::dot(&m[2], v),
+    )
+}
+
+/// Multiplies two matrices, so that the product applies `b` first.
+fn multiply(a: &Matrix, b: &Matrix) -> Matrix {
+    let columns = transpose(b);
+    a.map(|row| apply(&columns, &row))
+}
+
+/// Transposes a matrix.
+fn transpose(m: &Matrix) -> Matrix {
+    [
+        Vec3::new(m[0][0], m[1][0], m[2][0]),
+        Vec3::new(m[0][1], m[1][1], m[2][1]),
+        Vec3::new(m[0][2], m[1][2], m[2][2]),
+    ]
+}
+
+/// Inverts a matrix, which must not be singular.
+fn invert(m: &Matrix) -> Matrix {
+    let columns = [
+        Vec3::cross(&m[1], &m[2]),
+        Vec3::cross(&m[2], &m[0]),
+        Vec3::cross(&m[0], &m[1]),
+    ];
+    let det = Vec3::dot(&m[0], &columns[0]);
+    transpose(&columns).map(|row| row / det)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: &Color, b: &Color, tolerance: f32) -> bool {
+        (0..3).all(|i| (a[i] - b[i]).abs() < tolerance)
+    }
+
+    #[test]
+    fn colorspace_matrices() {
+        // The derived sRGB matrix matches the published one.
+        let srgb = Chromaticities::REC709.rgb_to_xyz();
+        assert!((srgb[0][0] - 0.4124).abs() < 1e-3 && (srgb[1][1] - 0.7152).abs() < 1e-3);
+
+        // So does ACEScg to linear sRGB, with Bradford adaptation.
+        let aces = ColorManagement::new(WorkingSpace::AcesCg, OutputSpace::Srgb);
+        let red = aces.convert(&Color::new(1.0, 0.0, 0.0));
+        assert!(close(&red, &Color::new(1.705, -0.130, -0.024), 2e-3));
+
+        // White stays white between every pair of spaces.
+        let white = Color::new(1.0, 1.0, 1.0);
+        for working in [WorkingSpace::LinearSrgb, WorkingSpace::AcesCg] {
+            for output in [
+                OutputSpace::Srgb,
+                OutputSpace::DisplayP3,
+                OutputSpace::Rec2020,
+            ] {
+                let converted = ColorManagement::new(working, output).convert(&white);
+                assert!(close(&converted, &white, 1e-4));
+            }
+        }
+    }
+
+    #[test]
+    fn colorspace_gamut() {
+        // sRGB red fits within the wider gamuts, where it is less saturated.
+        let red = Color::new(1.0, 0.0, 0.0);
+        for output in [OutputSpace::DisplayP3, OutputSpace::Rec2020] {
+            let converted = ColorManagement::new(WorkingSpace::LinearSrgb, output).convert(&red);
+            assert!(converted.r() < 1.0 && converted.g() > 0.0 && converted.b() >= -1e-4);
+        }
+
+        // Saturated ACEScg green is outside of sRGB and gets clipped.
+        let green = Color::new(0.0, 1.0, 0.0);
+        let aces = ColorManagement::new(WorkingSpace::AcesCg, OutputSpace::Srgb);
+        assert!(aces.convert(&green).r() < 0.0);
+        assert_eq!(aces.encode(&green).r(), 0.0);
+    }
+
+    #[test]
+    fn colorspace_encode() {
+        let srgb = ColorManagement::default();
+        let gray = srgb.encode(&Color::new(0.5, 0.5, 0.5));
+        assert!((gray.r() - 0.7354).abs() < 1e-3);
+        assert_eq!(srgb.encode_all(&[Color::new(1.0, 0.0, 0.0)]).len(), 1);
+
+        // Both transfer functions are linear near black.
+        assert!((OutputSpace::Srgb.encode(0.001) - 0.01292).abs() < 1e-6);
+        assert!((OutputSpace::Rec2020.encode(0.01) - 0.045).abs() < 1e-6);
+        assert!((OutputSpace::Rec2020.encode(1.0) - 1.0).abs() < 1e-6);
+        assert_eq!(OutputSpace::DisplayP3.encode(-1.0), 0.0);
+    }
+}
@@ -0,0 +1,260 @@
+use crate::camera::Camera;
+use crate::{consts::FRAC_PI_2, Float, Point3, Vec3};
+
+/// Radians turned per pixel of mouse motion.
+const ROTATE_SPEED: Float = 0.005;
+
+/// Distance panned per pixel of mouse motion, relative to the orbit distance.
+const PAN_SPEED: Float = 0.002;
+
+/// Factor the orbit distance is scaled by per zoom step.
+const ZOOM_STEP: Float = 0.9;
+
+/// Largest pitch, kept short of straight up or down where the view flips.
+const MAX_PITCH: Float = FRAC_PI_2 - 0.01;
+
+/// Unit viewing direction for a yaw around the y axis, from looking down -z,
+/// and a pitch above the horizon.
+fn direction(yaw: Float, pitch: Float) -> Vec3 {
+    Vec3::new(
+        pitch.cos() * yaw.sin(),
+        pitch.sin(),
+        -pitch.cos() * yaw.cos(),
+    )
+}
+
+/// Yaw and pitch of a viewing direction.
+fn angles(direction: &Vec3) -> (Float, Float) {
+    let d = direction.unit();
+    (
+        Float::atan2(d.x(), -d.z()),
+        d.y().clamp(-1.0, 1.0).asin().clamp(-MAX_PITCH, MAX_PITCH),
+    )
+}
+
+/// Mouse-driven camera circling a target point, for inspecting an object.
+/// Feed it the motion reported by the windowing layer and render through
+/// `apply`. The y axis is up.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OrbitController {
+    /// Point looked at and orbited around.
+    target: Point3,
+
+    /// Distance from the target.
+    distance: Float,
+
+    /// Angle around the y axis.
+    yaw: Float,
+
+    /// Angle above the horizon.
+    pitch: Float,
+
+    /// Whether the view changed since it was last checked.
+    moved: bool,
+}
+
+impl OrbitController {
+    /// Create a new controller starting from the camera's view.
+    pub fn new(camera: &Camera) -> Self {
+        let (yaw, pitch) = angles(&(camera.look_at - camera.look_from));
+        Self {
+            target: camera.look_at,
+            distance: (camera.look_at - camera.look_from).len(),
+            yaw,
+            pitch,
+            moved: false,
+        }
+    }
+
+    /// Circle around the target by a mouse motion of (dx, dy) pixels.
+    pub fn orbit(&mut self, dx: Float, dy: Float) {
+        self.yaw += dx * ROTATE_SPEED;
+        self.pitch = (self.pitch - dy * ROTATE_SPEED).clamp(-MAX_PITCH, MAX_PITCH);
+        self.moved = true;
+    }
+
+    /// Slide the target across the view by a mouse motion of (dx, dy) pixels.
+    pub fn pan(&mut self, dx: Float, dy: Float) {
+        let forward = direction(self.yaw, self.pitch);
+        let right = Vec3::cross(&forward, &Vec3::new(0.0, 1.0, 0.0)).unit();
+        let up = Vec3::cross(&right, &forward);
+        let scale = self.distance * PAN_SPEED;
+        self.target += scale * (-dx * right + dy * up);
+        self.moved = true;
+    }
+
+    /// Move toward the target by `steps` scroll steps, or away for negative steps.
+    pub fn zoom(&mut self, steps: Float) {
+        self.distance *= ZOOM_STEP.powf(steps);
+        self.moved = true;
+    }
+
+    /// Retrieve the point the camera looks from.
+    pub fn eye(&self) -> Point3 {
+        self.target - self.distance * direction(self.yaw, self.pitch)
+    }
+
+    /// Retrieve the point looked at.
+    pub fn target(&self) -> Point3 {
+        self.target
+    }
+
+    /// Determine whether the view changed since the last call, in which case
+    /// progressive accumulation should restart (see `TemporalAccumulator::reset`).
+    pub fn take_moved(&mut self) -> bool {
+        std::mem::take(&mut self.moved)
+    }
+
+    /// Create a copy of `camera` with the controller's view.
+    pub fn apply(&self, camera: &Camera) -> Camera {
+        camera.with_view(self.eye(), self.target, camera.vfov)
+    }
+}
+
+/// First-person camera moved with WASD-style keys and turned with the mouse,
+/// for exploring a scene from within. The y axis is up.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FlyController {
+    /// Point the camera looks from.
+    position: Point3,
+
+    /// Angle around the y axis.
+    yaw: Float,
+
+    /// Angle above the horizon.
+    pitch: Float,
+
+    /// Distance travelled per unit of time.
+    speed: Float,
+
+    /// Whether the view changed since it was last checked.
+    moved: bool,
+}
+
+impl FlyController {
+    /// Create a new controller starting from the camera's view, moving at
+    /// one unit per unit of time.
+    pub fn new(camera: &Camera) -> Self {
+        let (yaw, pitch) = angles(&(camera.look_at - camera.look_from));
+        Self {
+            position: camera.look_from,
+            yaw,
+            pitch,
+            speed: 1.0,
+            moved: false,
+        }
+    }
+
+    /// Set the distance travelled per unit of time.
+    pub fn with_speed(mut self, speed: Float) -> Self {
+        self.speed = speed;
+        self
+    }
+
+    /// Turn the view by a mouse motion of (dx, dy) pixels.
+    pub fn look(&mut self, dx: Float, dy: Float) {
+        self.yaw += dx * ROTATE_SPEED;
+        self.pitch = (self.pitch - dy * ROTATE_SPEED).clamp(-MAX_PITCH, MAX_PITCH);
+        self.moved = true;
+    }
+
+    /// Move for `dt` units of time along the held keys, each axis in [-1, 1]:
+    /// `forward` (W/S) along the view, `right` (D/A) sideways, and `up`
+    /// (E/Q) along the y axis.
+    pub fn fly(&mut self, forward: Float, right: Float, up: Float, dt: Float) {
+        let ahead = direction(self.yaw, self.pitch);
+        let side = Vec3::cross(&ahead, &Vec3::new(0.0, 1.0, 0.0)).unit();
+        let motion = forward * ahead + right * side + up * Vec3::new(0.0, 1.0, 0.0);
+        if !motion.almost_zero() {
+            self.position += self.speed * dt * motion;
+            self.moved = true;
+        }
+    }
+
+    /// Retrieve the point the camera looks from.
+    pub fn position(&self) -> Point3 {
+        self.position
+    }
+
+    /// Determine whether the view changed since the last call, in which case
+    /// progressive accumulation should restart (see `TemporalAccumulator::reset`).
+    pub fn take_moved(&mut self) -> bool {
+        std::mem::take(&mut self.moved)
+    }
+
+    /// Create a copy of `camera` with the controller's view.
+    pub fn apply(&self, camera: &Camera) -> Camera {
+        let look_at = self.position + direction(self.yaw, self.pitch);
+        camera.with_view(self.position, look_at, camera.vfov)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::consts::PI;
+
+    fn camera() -> Camera {
+        Camera::new(
+            1.0,
+            8,
+            60.0,
+            Point3::new(0.0, 0.0, 4.0),
+            Point3::new(0.0, 0.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            0.0,
+            1.0,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn controls_orbit() {
+        let camera = camera();
+        let mut orbit = OrbitController::new(&camera);
+        assert!(orbit.eye().almost_eq(&camera.look_from));
+        assert!(!orbit.take_moved());
+
+        // A quarter turn moves the eye to the side, at the same distance.
+        orbit.orbit(0.5 * PI / ROTATE_SPEED, 0.0);
+        assert!(orbit.eye().almost_eq(&Point3::new(-4.0, 0.0, 0.0)));
+        assert!(orbit.take_moved());
+        assert!(!orbit.take_moved());
+
+        // Pitch stops short of the pole.
+        orbit.orbit(0.0, -1e6);
+        assert!(orbit.eye().y() < 4.0);
+
+        orbit.zoom(1.0);
+        assert!(((orbit.eye() - orbit.target()).len() - 3.6).abs() < 1e-4);
+
+        orbit.pan(100.0, 0.0);
+        let moved = orbit.apply(&camera);
+        assert!(moved.look_at.almost_eq(&orbit.target()));
+        assert!(!orbit.target().almost_eq(&camera.look_at));
+    }
+
+    #[test]
+    fn controls_fly() {
+        let camera = camera();
+        let mut fly = FlyController::new(&camera).with_speed(2.0);
+
+        // Flying forward for half a unit of time moves one unit along the view.
+        fly.fly(1.0, 0.0, 0.0, 0.5);
+        assert!(fly.position().almost_eq(&Point3::new(0.0, 0.0, 3.0)));
+        assert!(fly.take_moved());
+
+        // Turning right by a quarter turn makes forward point along +x.
+        fly.look(0.5 * PI / ROTATE_SPEED, 0.0);
+        fly.fly(0.0, 0.0, 0.0, 1.0);
+        assert!(fly.take_moved());
+        fly.fly(1.0, 0.0, 1.0, 0.5);
+        assert!(fly.position().almost_eq(&Point3::new(1.0, 1.0, 3.0)));
+
+        let moved = fly.apply(&camera);
+        assert!(moved.look_from.almost_eq(&fly.position()));
+        assert!((moved.look_at - moved.look_from)
+            .unit()
+            .almost_eq(&Vec3::new(1.0, 0.0, 0.0)));
+    }
+}
@@ -0,0 +1,115 @@
+use std::fmt;
+
+use crate::{Color, Vec3};
+
+/// How serious a scene problem is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// Likely unintended, but renders.
+    Warning,
+
+    /// Produces black or NaN pixels.
+    Error,
+}
+
+/// Problem found in a scene before rendering.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// How serious the problem is.
+    pub severity: Severity,
+
+    /// Slash-separated names of the scene nodes leading to the problem, or
+    /// empty when it is not tied to a node.
+    pub path: String,
+
+    /// Description of the problem.
+    pub message: String,
+}
+
+impl Diagnostic {
+    /// Creates a new warning.
+    pub fn warning(message: &str) -> Self {
+        Self {
+            severity: Severity::Warning,
+            path: String::new(),
+            message: message.to_string(),
+        }
+    }
+
+    /// Creates a new error.
+    pub fn error(message: &str) -> Self {
+        Self {
+            severity: Severity::Error,
+            path: String::new(),
+            message: message.to_string(),
+        }
+    }
+
+    /// Sets the path of the scene node the problem was found in.
+    pub fn with_path(mut self, path: &str) -> Self {
+        self.path = path.to_string();
+        self
+    }
+
+    /// Determines whether the problem is an error.
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        if self.path.is_empty() {
+            write!(f, "{severity}: {}", self.message)
+        } else {
+            write!(f, "{severity}: {}: {}", self.path, self.message)
+        }
+    }
+}
+
+/// Determines whether every component of a vector is finite.
+pub fn is_finite(v: &Vec3) -> bool {
+    v.x().is_finite() && v.y().is_finite() && v.z().is_finite()
+}
+
+/// Determines whether any component of a vector is NaN.
+pub fn has_nan(v: &Vec3) -> bool {
+    v.x().is_nan() || v.y().is_nan() || v.z().is_nan()
+}
+
+/// Checks that a reflectance is finite and within [0, 1], since surfaces
+/// reflecting more light than they receive make paths gain energy.
+pub fn check_albedo(albedo: &Color) -> Option<Diagnostic> {
+    let channels = [albedo.r(), albedo.g(), albedo.b()];
+    if channels.iter().any(|c| !c.is_finite()) {
+        Some(Diagnostic::error(&format!("albedo {albedo} is not finite")))
+    } else if channels.iter().any(|c| !(0.0..=1.0).contains(c)) {
+        Some(Diagnostic::warning(&format!(
+            "albedo {albedo} is outside of [0, 1]"
+        )))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diagnostic_albedo() {
+        assert!(check_albedo(&Color::new(0.2, 0.5, 1.0)).is_none());
+        let bright = check_albedo(&Color::new(1.5, 0.5, 0.5)).unwrap();
+        assert_eq!(bright.severity, Severity::Warning);
+        assert!(check_albedo(&Color::new(f32::NAN, 0.5, 0.5))
+            .unwrap()
+            .is_error());
+
+        let diagnostic = Diagnostic::error("radius is 0").with_path("root/ball");
+        assert_eq!(diagnostic.to_string(), "error: root/ball: radius is 0");
+    }
+}
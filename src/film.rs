@@ -0,0 +1,343 @@
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+use crate::camera::CropWindow;
+use crate::{Color, Error};
+
+/// Leading bytes of a film checkpoint, with the format version.
+const CHECKPOINT_MAGIC: &[u8; 8] = b"RTFILM1\n";
+
+/// Samples accumulated at one pixel of a film.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FilmPixel {
+    /// Sum of the sample colors, each scaled by its filter weight.
+    pub weighted_sum: Color,
+
+    /// Sum of the filter weights.
+    pub weight_sum: f32,
+
+    /// Sum of the colors splatted onto the pixel, which are not weighted.
+    pub splat: Color,
+
+    /// Number of samples added, not counting splats.
+    pub samples: u32,
+}
+
+impl Default for FilmPixel {
+    /// A pixel without any samples.
+    fn default() -> Self {
+        Self {
+            weighted_sum: Color::new(0.0, 0.0, 0.0),
+            weight_sum: 0.0,
+            splat: Color::new(0.0, 0.0, 0.0),
+            samples: 0,
+        }
+    }
+}
+
+impl FilmPixel {
+    /// Final color of the pixel: the weighted mean of its samples plus its splats.
+    pub fn color(&self) -> Color {
+        // Filters with negative lobes can cancel out with very few samples.
+        let mean = if self.weight_sum.abs() < f32::EPSILON {
+            Color::new(0.0, 0.0, 0.0)
+        } else {
+            self.weighted_sum / self.weight_sum
+        };
+        mean + self.splat
+    }
+
+    /// Add the accumulations of another pixel.
+    fn merge(&mut self, other: &FilmPixel) {
+        self.weighted_sum += other.weighted_sum;
+        self.weight_sum += other.weight_sum;
+        self.splat += other.splat;
+        self.samples += other.samples;
+    }
+}
+
+/// High dynamic range buffer accumulating weighted radiance samples over a
+/// window of the image, addressed by image (row, col). Each row is locked
+/// separately, so threads rendering different rows can share one film, and
+/// splats landing anywhere only contend for their row. Resolve the film to
+/// get the image; merge films to combine partial renders.
+#[derive(Debug)]
+pub struct Film {
+    /// Pixels of the image covered by the film.
+    window: CropWindow,
+
+    /// Accumulated pixels, one locked row at a time.
+    rows: Vec<Mutex<Vec<FilmPixel>>>,
+}
+
+impl Film {
+    /// Create a new empty film covering a whole image of the given size.
+    pub fn new(width: u32, height: u32) -> Self {
+        Self::cropped(CropWindow::new(0, 0, width, height))
+    }
+
+    /// Create a new empty film covering only the pixels in `window`.
+    pub fn cropped(window: CropWindow) -> Self {
+        let row = vec![FilmPixel::default(); window.width as usize];
+        Self {
+            window,
+            rows: (0..window.height)
+                .map(|_| Mutex::new(row.clone()))
+                .collect(),
+        }
+    }
+
+    /// Retrieve the pixels of the image covered by the film.
+    pub fn window(&self) -> &CropWindow {
+        &self.window
+    }
+
+    /// Add a sample of the pixel at (row, col) with the given filter weight.
+    /// Samples of pixels outside the film's window are ignored.
+    pub fn add_sample(&self, row: u32, col: u32, color: Color, weight: f32) {
+        self.update(row, col, |pixel| {
+            pixel.weighted_sum += weight * color;
+            pixel.weight_sum += weight;
+            pixel.samples += 1;
+        });
+    }
+
+    /// Add a color to the pixel at (row, col) as is, regardless of the other
+    /// samples, for estimators that scatter contributions over the image.
+    /// Splats outside the film's window are ignored.
+    pub fn splat(&self, row: u32, col: u32, color: Color) {
+        self.update(row, col, |pixel| pixel.splat += color);
+    }
+
+    /// Retrieve the accumulated samples of the pixel at (row, col), or `None`
+    /// if it is outside the film's window.
+    pub fn pixel(&self, row: u32, col: u32) -> Option<FilmPixel> {
+        let mut found = None;
+        self.update(row, col, |pixel| found = Some(*pixel));
+        found
+    }
+
+    /// Add the accumulations of another film over the pixels both cover.
+    pub fn merge(&self, other: &Film) {
+        let window = other.window;
+        for (i, row) in other.rows.iter().enumerate() {
+            let row = lock(row).clone();
+            for (j, pixel) in row.iter().enumerate() {
+                let (r, c) = (window.row + i as u32, window.col + j as u32);
+                self.update(r, c, |mine| mine.merge(pixel));
+            }
+        }
+    }
+
+    /// Compute the colors of the film's pixels, row by row.
+    pub fn resolve(&self) -> Vec<Color> {
+        self.rows
+            .iter()
+            .flat_map(|row| lock(row).iter().map(FilmPixel::color).collect::<Vec<_>>())
+            .collect()
+    }
+
+    /// Number of samples added to each of the film's pixels, row by row,
+    /// e.g. to see where adaptive sampling stopped early.
+    pub fn sample_counts(&self) -> Vec<u32> {
+        self.rows
+            .iter()
+            .flat_map(|row| {
+                lock(row)
+                    .iter()
+                    .map(|pixel| pixel.samples)
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    /// Serialize the film's window and accumulated samples, e.g. to save an
+    /// interrupted render. Restore it with `from_checkpoint`, then merge it
+    /// into a later render of the same image to add up their samples.
+    pub fn to_checkpoint(&self) -> Vec<u8> {
+        let w = self.window;
+        let mut bytes = CHECKPOINT_MAGIC.to_vec();
+        for value in [w.col, w.row, w.width, w.height] {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        for row in &self.rows {
+            for pixel in lock(row).iter() {
+                let sums = [pixel.weighted_sum, pixel.splat];
+                for value in sums.iter().flat_map(|c| [c.r(), c.g(), c.b()]) {
+                    bytes.extend_from_slice(&value.to_le_bytes());
+                }
+                bytes.extend_from_slice(&pixel.weight_sum.to_le_bytes());
+                bytes.extend_from_slice(&pixel.samples.to_le_bytes());
+            }
+        }
+        bytes
+    }
+
+    /// Restore a film serialized by `to_checkpoint`.
+    pub fn from_checkpoint(bytes: &[u8]) -> Result<Self, Error> {
+        let body = bytes
+            .strip_prefix(CHECKPOINT_MAGIC)
+            .ok_or_else(|| Error::new_image("not a film checkpoint"))?;
+        let mut words = body
+            .chunks_exact(4)
+            .map(|word| [word[0], word[1], word[2], word[3]]);
+        let mut header = [0; 4];
+        for value in header.iter_mut() {
+            *value = u32::from_le_bytes(words.next().ok_or_else(truncated)?);
+        }
+        let [col, row, width, height] = header;
+
+        // The header is untrusted, so check its size before allocating the
+        // window. Windows without pixels must have no rows either.
+        let len = (width as usize)
+            .checked_mul(height as usize)
+            .and_then(|pixels| pixels.checked_mul(8))
+            .and_then(|words| words.checked_add(4))
+            .and_then(|words| words.checked_mul(4));
+        if len != Some(body.len()) || (width == 0 && height != 0) {
+            return Err(truncated());
+        }
+        let film = Self::cropped(CropWindow::new(col, row, width, height));
+        let mut next = || words.next().unwrap_or_default();
+        for pixels in &film.rows {
+            for pixel in lock(pixels).iter_mut() {
+                let mut float = || f32::from_le_bytes(next());
+                pixel.weighted_sum = Color::new(float(), float(), float());
+                pixel.splat = Color::new(float(), float(), float());
+                pixel.weight_sum = float();
+                pixel.samples = u32::from_le_bytes(next());
+            }
+        }
+        Ok(film)
+    }
+
+    /// Apply `f` to the pixel at (row, col), if the film covers it.
+    fn update<F: FnOnce(&mut FilmPixel)>(&self, row: u32, col: u32, f: F) {
+        if !self.window.contains(row, col) {
+            return;
+        }
+        let mut pixels = lock(&self.rows[(row - self.window.row) as usize]);
+        f(&mut pixels[(col - self.window.col) as usize]);
+    }
+}
+
+/// Error for a checkpoint that ends early or has trailing bytes.
+fn truncated() -> Error {
+    Error::new_image("film checkpoint size does not match its window")
+}
+
+/// Lock a row of pixels. A thread that panicked mid-update leaves at worst a
+/// partial sample behind, so poisoned rows are still used.
+fn lock(row: &Mutex<Vec<FilmPixel>>) -> MutexGuard<'_, Vec<FilmPixel>> {
+    row.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn film_accumulate() {
+        let film = Film::new(3, 2);
+        let red = Color::new(1.0, 0.0, 0.0);
+        let blue = Color::new(0.0, 0.0, 1.0);
+        film.add_sample(1, 2, red, 3.0);
+        film.add_sample(1, 2, blue, 1.0);
+        film.add_sample(2, 0, red, 1.0);
+
+        let pixel = film.pixel(1, 2).unwrap();
+        assert_eq!(pixel.samples, 2);
+        assert_eq!(pixel.color(), Color::new(0.75, 0.0, 0.25));
+        assert!(film.pixel(2, 0).is_none());
+
+        let image = film.resolve();
+        assert_eq!(image.len(), 6);
+        assert_eq!(image[5], Color::new(0.75, 0.0, 0.25));
+        assert!(image[..5].iter().all(|c| *c == Color::new(0.0, 0.0, 0.0)));
+
+        // Weights cancelling out leave the pixel black.
+        film.add_sample(0, 0, red, 1.0);
+        film.add_sample(0, 0, blue, -1.0);
+        assert_eq!(film.resolve()[0], Color::new(0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn film_splat_and_merge() {
+        let film = Film::new(4, 4);
+        let gray = Color::new(0.5, 0.5, 0.5);
+        film.add_sample(1, 1, gray, 1.0);
+        film.splat(1, 1, gray);
+        assert_eq!(film.pixel(1, 1).unwrap().color(), Color::new(1.0, 1.0, 1.0));
+
+        // A tile only covers its window, in image coordinates.
+        let tile = Film::cropped(CropWindow::new(1, 1, 2, 2));
+        tile.add_sample(0, 0, gray, 1.0);
+        tile.add_sample(2, 2, gray, 1.0);
+        tile.splat(1, 1, gray);
+        assert_eq!(tile.resolve().len(), 4);
+        assert!(tile.pixel(0, 0).is_none());
+
+        film.merge(&tile);
+        let pixel = film.pixel(1, 1).unwrap();
+        assert_eq!(pixel.samples, 1);
+        assert_eq!(pixel.splat, Color::new(1.0, 1.0, 1.0));
+        assert_eq!(film.pixel(2, 2).unwrap().color(), gray);
+        assert_eq!(film.pixel(0, 0).unwrap().samples, 0);
+    }
+
+    #[test]
+    fn film_checkpoint() {
+        let film = Film::cropped(CropWindow::new(1, 2, 3, 2));
+        film.add_sample(2, 1, Color::new(0.25, 0.5, 1.0), 0.5);
+        film.add_sample(3, 3, Color::new(2.0, 0.0, 0.0), 1.0);
+        film.splat(3, 2, Color::new(0.0, 0.125, 0.0));
+
+        let bytes = film.to_checkpoint();
+        let restored = Film::from_checkpoint(&bytes).unwrap();
+        assert_eq!(restored.window(), film.window());
+        assert_eq!(restored.resolve(), film.resolve());
+        assert_eq!(restored.sample_counts(), film.sample_counts());
+        assert_eq!(restored.pixel(2, 1), film.pixel(2, 1));
+
+        assert!(Film::from_checkpoint(b"RTFILM0\n").is_err());
+        assert!(Film::from_checkpoint(&bytes[..bytes.len() - 4]).is_err());
+        assert!(Film::from_checkpoint(&bytes[..10]).is_err());
+
+        // Corrupt headers are rejected before the window is allocated.
+        let header = |width: u32, height: u32| {
+            let mut bytes = CHECKPOINT_MAGIC.to_vec();
+            for value in [0, 0, width, height] {
+                bytes.extend(value.to_le_bytes());
+            }
+            bytes
+        };
+        assert!(Film::from_checkpoint(&header(u32::MAX, u32::MAX)).is_err());
+        assert!(Film::from_checkpoint(&header(0, u32::MAX)).is_err());
+        let mut body = header(2, 2);
+        body.extend([0; 4 * 8]);
+        assert!(Film::from_checkpoint(&body).is_err());
+        assert!(Film::from_checkpoint(&header(0, 0)).is_ok());
+    }
+
+    #[test]
+    fn film_threads() {
+        let film = Film::new(8, 2);
+        let white = Color::new(1.0, 1.0, 1.0);
+        std::thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| {
+                    for i in 0..100 {
+                        film.add_sample(i % 2, i % 8, white, 1.0);
+                    }
+                });
+            }
+        });
+
+        let total: u32 = (0..2)
+            .flat_map(|row| (0..8).map(move |col| (row, col)))
+            .map(|(row, col)| film.pixel(row, col).unwrap().samples)
+            .sum();
+        assert_eq!(total, 400);
+        assert_eq!(film.sample_counts().iter().sum::<u32>(), 400);
+        assert_eq!(film.pixel(1, 1).unwrap().color(), white);
+    }
+}
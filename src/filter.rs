@@ -0,0 +1,147 @@
+use crate::Float;
+
+/// Pixel reconstruction filter. Samples are spread over the filter's support
+/// around the pixel center and weighted by their offset from it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Filter {
+    /// Equal weight everywhere inside the support. A radius of 0.5 covers
+    /// exactly one pixel.
+    Box { radius: Float },
+
+    /// Weight falls off linearly to zero at the radius.
+    Tent { radius: Float },
+
+    /// Gaussian falloff with sharpness `alpha`, shifted to reach zero at the radius.
+    Gaussian { radius: Float, alpha: Float },
+
+    /// Mitchell–Netravali cubic with blur `b` and ringing `c`.
+    Mitchell { radius: Float, b: Float, c: Float },
+}
+
+impl Filter {
+    /// Box filter over a single pixel, i.e. plain sample averaging.
+    pub const PIXEL: Self = Self::Box { radius: 0.5 };
+
+    /// Gaussian filter with common defaults.
+    pub fn gaussian() -> Self {
+        Self::Gaussian {
+            radius: 1.5,
+            alpha: 2.0,
+        }
+    }
+
+    /// Mitchell–Netravali filter with the recommended `b = c = 1/3`.
+    pub fn mitchell() -> Self {
+        Self::Mitchell {
+            radius: 2.0,
+            b: 1.0 / 3.0,
+            c: 1.0 / 3.0,
+        }
+    }
+
+    /// Half-width of the filter support in pixels.
+    pub fn radius(&self) -> Float {
+        match *self {
+            Self::Box { radius }
+            | Self::Tent { radius }
+            | Self::Gaussian { radius, .. }
+            | Self::Mitchell { radius, .. } => radius,
+        }
+    }
+
+    /// Weight of a sample at offset `(x, y)` from the pixel center.
+    pub fn evaluate(&self, x: Float, y: Float) -> Float {
+        self.evaluate_1d(x) * self.evaluate_1d(y)
+    }
+
+    /// Weight along one axis. All filters are separable.
+    fn evaluate_1d(&self, x: Float) -> Float {
+        let x = x.abs();
+        match *self {
+            Self::Box { radius } => {
+                if x <= radius {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+            Self::Tent { radius } => Float::max(radius - x, 0.0),
+            Self::Gaussian { radius, alpha } => Float::max(
+                Float::exp(-alpha * x * x) - Float::exp(-alpha * radius * radius),
+                0.0,
+            ),
+            Self::Mitchell { radius, b, c } => {
+                // The cubic is defined over [-2, 2].
+                let x = 2.0 * x / radius;
+                let (x2, x3) = (x * x, x * x * x);
+                if x < 1.0 {
+                    ((12.0 - 9.0 * b - 6.0 * c) * x3
+                        + (-18.0 + 12.0 * b + 6.0 * c) * x2
+                        + (6.0 - 2.0 * b))
+                        / 6.0
+                } else if x < 2.0 {
+                    ((-b - 6.0 * c) * x3
+                        + (6.0 * b + 30.0 * c) * x2
+                        + (-12.0 * b - 48.0 * c) * x
+                        + (8.0 * b + 24.0 * c))
+                        / 6.0
+                } else {
+                    0.0
+                }
+            }
+        }
+    }
+}
+
+impl Default for Filter {
+    fn default() -> Self {
+        Self::PIXEL
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Filter;
+    use crate::Float;
+
+    /// Integrates a filter along one axis with the midpoint rule.
+    fn integral(filter: &Filter) -> Float {
+        let r = filter.radius();
+        let n = 10_000;
+        let dx = 2.0 * r / n as Float;
+        (0..n)
+            .map(|i| filter.evaluate_1d(-r + (i as Float + 0.5) * dx) * dx)
+            .sum()
+    }
+
+    #[test]
+    fn filter_box() {
+        let filter = Filter::PIXEL;
+        assert_eq!(filter.evaluate(0.0, 0.0), 1.0);
+        assert_eq!(filter.evaluate(0.49, -0.49), 1.0);
+        assert_eq!(filter.evaluate(0.51, 0.0), 0.0);
+    }
+
+    #[test]
+    fn filter_falloff() {
+        for filter in [
+            Filter::Tent { radius: 1.0 },
+            Filter::gaussian(),
+            Filter::mitchell(),
+        ] {
+            let r = filter.radius();
+            assert!(filter.evaluate(0.0, 0.0) > filter.evaluate(0.5 * r, 0.0));
+            assert!(filter.evaluate(r, 0.0).abs() < 1e-6);
+            assert_eq!(filter.evaluate(0.3, 0.1), filter.evaluate(-0.3, -0.1));
+        }
+    }
+
+    #[test]
+    fn filter_mitchell() {
+        // The Mitchell–Netravali cubic integrates to one over [-2, 2].
+        assert!((integral(&Filter::mitchell()) - 1.0).abs() < 1e-4);
+
+        // Its negative lobes sharpen edges.
+        assert!(Filter::mitchell().evaluate(1.5, 0.0) < 0.0);
+    }
+}
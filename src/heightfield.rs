@@ -0,0 +1,291 @@
+use std::sync::Arc;
+
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable};
+use crate::material::Material;
+use crate::triangle;
+use crate::{Float, Interval, Point3, Ray, Vec3};
+
+/// Terrain surface built from a regular 2-D grid of elevations over the XZ plane.
+/// Each grid cell is split into two triangles. Rays walk the grid cells they
+/// cross front to back (2-D DDA), so only a thin line of cells is tested.
+#[derive(Clone)]
+pub struct Heightfield {
+    /// Elevations in row-major order: `depth` rows of `width` samples along X.
+    heights: Vec<Float>,
+
+    /// Number of samples along the X axis.
+    width: usize,
+
+    /// Number of samples along the Z axis.
+    depth: usize,
+
+    /// World-space position of sample (0, 0) at elevation 0.
+    origin: Point3,
+
+    /// Spacing between neighbouring samples.
+    cell_size: Float,
+
+    /// Lowest elevation.
+    min_height: Float,
+
+    /// Highest elevation.
+    max_height: Float,
+
+    /// Surface material.
+    material: Arc<dyn Material>,
+}
+
+impl Heightfield {
+    /// Creates a new heightfield from `width * depth` elevations in row-major order.
+    pub fn new(
+        heights: Vec<Float>,
+        width: usize,
+        depth: usize,
+        origin: Point3,
+        cell_size: Float,
+        material: Arc<dyn Material>,
+    ) -> Self {
+        assert!(
+            width >= 2 && depth >= 2,
+            "heightfield needs at least 2x2 samples"
+        );
+        assert_eq!(
+            heights.len(),
+            width * depth,
+            "heightfield sample count mismatch"
+        );
+        assert!(cell_size > 0.0, "heightfield cell size must be positive");
+
+        let min_height = heights.iter().copied().fold(Float::INFINITY, Float::min);
+        let max_height = heights
+            .iter()
+            .copied()
+            .fold(Float::NEG_INFINITY, Float::max);
+
+        Self {
+            heights,
+            width,
+            depth,
+            origin,
+            cell_size,
+            min_height,
+            max_height,
+            material,
+        }
+    }
+
+    /// Creates a new heightfield by sampling `elevation(x, z)` at each grid point,
+    /// where `x` and `z` are offsets from `origin`.
+    pub fn from_fn<F>(
+        width: usize,
+        depth: usize,
+        origin: Point3,
+        cell_size: Float,
+        elevation: F,
+        material: Arc<dyn Material>,
+    ) -> Self
+    where
+        F: Fn(Float, Float) -> Float,
+    {
+        let heights = (0..depth)
+            .flat_map(|j| (0..width).map(move |i| (i, j)))
+            .map(|(i, j)| elevation(i as Float * cell_size, j as Float * cell_size))
+            .collect();
+
+        Self::new(heights, width, depth, origin, cell_size, material)
+    }
+
+    /// World-space vertex at grid sample `(i, j)`.
+    fn vertex(&self, i: usize, j: usize) -> Point3 {
+        self.origin
+            + Vec3::new(
+                i as Float * self.cell_size,
+                self.heights[j * self.width + i],
+                j as Float * self.cell_size,
+            )
+    }
+
+    /// Box bounding every cell.
+    fn bbox(&self) -> Aabb {
+        let lo = self.origin + Vec3::new(0.0, self.min_height, 0.0);
+        let hi = self.origin
+            + Vec3::new(
+                (self.width - 1) as Float * self.cell_size,
+                self.max_height,
+                (self.depth - 1) as Float * self.cell_size,
+            );
+        Aabb::new(&lo, &hi)
+    }
+
+    /// Intersects the two triangles of cell `(i, j)`. Returns the ray
+    /// parameter, the unnormalized normal, and which of the triangles was hit.
+    fn hit_cell(
+        &self,
+        ray: &Ray,
+        ray_t: &Interval,
+        i: usize,
+        j: usize,
+    ) -> Option<(Float, Vec3, usize)> {
+        let v00 = self.vertex(i, j);
+        let v10 = self.vertex(i + 1, j);
+        let v01 = self.vertex(i, j + 1);
+        let v11 = self.vertex(i + 1, j + 1);
+
+        [(v00, v11, v10), (v00, v01, v11)]
+            .iter()
+            .enumerate()
+            .filter_map(|(k, (a, b, c))| {
+                let (t, _, _) = triangle::intersect(ray, a, b, c)?;
+                ray_t
+                    .surrounds(t)
+                    .then(|| (t, Vec3::cross(&(b - a), &(c - a)), k))
+            })
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+    }
+}
+
+impl Hittable for Heightfield {
+    fn hit(&self, ray: &Ray, ray_t: &Interval) -> Option<HitRecord> {
+        let clipped = self.bbox().clip(ray, ray_t)?;
+        let (t_enter, t_exit) = (clipped.min(), clipped.max());
+
+        let (dx, dz) = (ray.direction().x(), ray.direction().z());
+        let entry = ray.at(t_enter);
+        let local_x = (entry.x() - self.origin.x()) / self.cell_size;
+        let local_z = (entry.z() - self.origin.z()) / self.cell_size;
+
+        let cells_x = self.width as i64 - 1;
+        let cells_z = self.depth as i64 - 1;
+        let mut i = (local_x.floor() as i64).clamp(0, cells_x - 1);
+        let mut j = (local_z.floor() as i64).clamp(0, cells_z - 1);
+
+        // Ray parameter at the next cell boundary along each axis, and the
+        // parameter step between boundaries.
+        let (step_i, mut next_x, delta_x) = if dx > 0.0 {
+            let boundary = self.origin.x() + (i + 1) as Float * self.cell_size;
+            (1, (boundary - ray.origin().x()) / dx, self.cell_size / dx)
+        } else if dx < 0.0 {
+            let boundary = self.origin.x() + i as Float * self.cell_size;
+            (-1, (boundary - ray.origin().x()) / dx, -self.cell_size / dx)
+        } else {
+            (0, Float::INFINITY, Float::INFINITY)
+        };
+        let (step_j, mut next_z, delta_z) = if dz > 0.0 {
+            let boundary = self.origin.z() + (j + 1) as Float * self.cell_size;
+            (1, (boundary - ray.origin().z()) / dz, self.cell_size / dz)
+        } else if dz < 0.0 {
+            let boundary = self.origin.z() + j as Float * self.cell_size;
+            (-1, (boundary - ray.origin().z()) / dz, -self.cell_size / dz)
+        } else {
+            (0, Float::INFINITY, Float::INFINITY)
+        };
+
+        loop {
+            if let Some((t, normal, k)) = self.hit_cell(ray, ray_t, i as usize, j as usize) {
+                let p = ray.at(t);
+                let normal = normal.unit();
+                let outward_normal = if normal.y() < 0.0 { -normal } else { normal };
+
+                let u = (p.x() - self.origin.x()) / (cells_x as Float * self.cell_size);
+                let v = (p.z() - self.origin.z()) / (cells_z as Float * self.cell_size);
+
+                // Triangles are numbered two per cell in row-major cell order.
+                let primitive = 2 * (j * cells_x + i) as usize + k;
+
+                let rec = HitRecord::new(&p, &outward_normal, t, ray, &*self.material)
+                    .with_uv(u, v)
+                    .with_primitive_id(primitive);
+                if !rec.is_masked() {
+                    return Some(rec);
+                }
+            }
+
+            // Step into the neighbouring cell whose boundary is closest.
+            if next_x < next_z {
+                if next_x > t_exit {
+                    return None;
+                }
+                i += step_i;
+                next_x += delta_x;
+            } else {
+                if next_z > t_exit {
+                    return None;
+                }
+                j += step_j;
+                next_z += delta_z;
+            }
+
+            if i < 0 || i >= cells_x || j < 0 || j >= cells_z {
+                return None;
+            }
+        }
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::Lambertian;
+    use crate::Color;
+
+    fn material() -> Arc<dyn Material> {
+        Arc::new(Lambertian::new(&Color::new(0.5, 0.5, 0.5)))
+    }
+
+    #[test]
+    fn heightfield_flat() {
+        let field = Heightfield::from_fn(
+            5,
+            5,
+            Point3::new(-2.0, 1.0, -2.0),
+            1.0,
+            |_, _| 0.0,
+            material(),
+        );
+        let bounds = Interval::new(0.001, Float::INFINITY);
+
+        let ray = Ray::new(Point3::new(0.3, 5.0, 0.7), Vec3::new(0.0, -1.0, 0.0));
+        let rec = field.hit(&ray, &bounds).unwrap();
+        assert!(rec.p.almost_eq(&Point3::new(0.3, 1.0, 0.7)));
+        assert!(rec.normal.almost_eq(&Vec3::new(0.0, 1.0, 0.0)));
+        assert_eq!(rec.primitive_id, Some(21));
+
+        // Rays outside the grid footprint miss.
+        let ray = Ray::new(Point3::new(3.0, 5.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+        assert!(field.hit(&ray, &bounds).is_none());
+    }
+
+    #[test]
+    fn heightfield_traversal() {
+        // A single tall ridge along the X = 6 column.
+        let field = Heightfield::from_fn(
+            10,
+            4,
+            Point3::new(0.0, 0.0, 0.0),
+            1.0,
+            |x, _| if (x - 6.0).abs() < 1e-9 { 2.0 } else { 0.0 },
+            material(),
+        );
+        let bounds = Interval::new(0.001, Float::INFINITY);
+
+        // A grazing ray travelling along +X passes over the low cells and hits the ridge.
+        let ray = Ray::new(Point3::new(0.5, 1.0, 1.5), Vec3::new(1.0, 0.0, 0.0));
+        let rec = field.hit(&ray, &bounds).unwrap();
+        assert!((rec.p.x() - 5.5).abs() < 1e-9);
+        assert!(rec.normal.x() < 0.0);
+
+        // The same ray travelling along -X from beyond the ridge hits its far side.
+        let ray = Ray::new(Point3::new(8.5, 1.0, 1.5), Vec3::new(-1.0, 0.0, 0.0));
+        let rec = field.hit(&ray, &bounds).unwrap();
+        assert!((rec.p.x() - 6.5).abs() < 1e-9);
+
+        // Above the ridge, the ray misses entirely.
+        let ray = Ray::new(Point3::new(0.5, 2.5, 1.5), Vec3::new(1.0, 0.0, 0.0));
+        assert!(field.hit(&ray, &bounds).is_none());
+    }
+}
@@ -1,4 +1,9 @@
-use crate::{material::Material, Interval, Point3, Ray, Vec3};
+use std::sync::Arc;
+
+use crate::diagnostic::Diagnostic;
+use crate::sphere::Sphere;
+use crate::triangle::Triangle;
+use crate::{aabb::Aabb, material::Material, Float, Interval, Point3, Ray, Vec3};
 
 /// Indicates a particular side of a closed polyhedron.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
@@ -19,19 +24,40 @@ pub struct HitRecord<'a> {
     /// The surface normal at `p`.
     pub normal: Vec3,
 
+    /// The normal of the underlying geometry at `p`, on the same side as
+    /// `normal`. Unlike `normal`, it is not bent by shading, so it decides
+    /// which side of the surface spawned rays leave from.
+    pub geometric_normal: Vec3,
+
     /// Reference to the object material.
     pub material: &'a dyn Material,
 
     /// Ray parameter on intersect.
-    t: f64,
+    t: Float,
 
     /// Orientation at which the intersection occurs.
     pub orientation: Orientation,
+
+    /// Surface coordinate along the first texture axis.
+    pub u: Float,
+
+    /// Surface coordinate along the second texture axis.
+    pub v: Float,
+
+    /// Index of the hit object in the outermost list or hierarchy containing it.
+    pub object_id: Option<usize>,
+
+    /// Index of the hit primitive within its object, e.g. a mesh triangle.
+    pub primitive_id: Option<usize>,
+
+    /// Bound on the distance between `p` and the true surface, for primitives
+    /// whose intersection error exceeds the rounding of `p` itself.
+    pub error: Float,
 }
 
 impl<'a> HitRecord<'a> {
     /// Creates a new hit record.
-    pub fn new(p: &Point3, normal: &Vec3, t: f64, ray: &Ray, material: &'a dyn Material) -> Self {
+    pub fn new(p: &Point3, normal: &Vec3, t: Float, ray: &Ray, material: &'a dyn Material) -> Self {
         // Enforce the normal and ray to be in the same hemisphere.
         let (normal, orientation) = if Vec3::dot(ray.direction(), normal) < 0.0 {
             (*normal, Orientation::Exterior)
@@ -42,22 +68,217 @@ impl<'a> HitRecord<'a> {
         Self {
             p: *p,
             normal,
+            geometric_normal: normal,
             material,
             t,
             orientation,
+            u: 0.0,
+            v: 0.0,
+            object_id: None,
+            primitive_id: None,
+            error: 0.0,
         }
     }
+
+    /// Retrieves the ray parameter at the intersection.
+    pub fn t(&self) -> Float {
+        self.t
+    }
+
+    /// Sets the surface coordinates of the hit.
+    pub fn with_uv(mut self, u: Float, v: Float) -> Self {
+        self.u = u;
+        self.v = v;
+        self
+    }
+
+    /// Sets the object ID of the hit.
+    pub fn with_object_id(mut self, id: usize) -> Self {
+        self.object_id = Some(id);
+        self
+    }
+
+    /// Sets the primitive ID of the hit.
+    pub fn with_primitive_id(mut self, id: usize) -> Self {
+        self.primitive_id = Some(id);
+        self
+    }
+
+    /// Sets the bound on the distance between the hit point and the surface.
+    pub fn with_error(mut self, error: Float) -> Self {
+        self.error = error;
+        self
+    }
+
+    /// Determines whether the material cuts the surface away at this hit.
+    pub fn is_masked(&self) -> bool {
+        self.material.is_masked(self)
+    }
+
+    /// Creates a ray leaving the hit towards `direction`. Its origin is
+    /// pushed off the surface, to the side `direction` points to, by just
+    /// enough to clear the rounding error in `p` and the hit's `error`, so
+    /// that the ray cannot hit the surface it left.
+    pub fn spawn_ray(&self, direction: Vec3) -> Ray {
+        let n = if Vec3::dot(&direction, &self.geometric_normal) < 0.0 {
+            -self.geometric_normal
+        } else {
+            self.geometric_normal
+        };
+        Ray::new(offset_origin(&(self.p + self.error * n), &n), direction)
+    }
+}
+
+/// Offsets a point along the unit normal `n` to clear the rounding error of a
+/// computed intersection, after Wächter and Binder, "A Fast and Robust Method
+/// for Avoiding Self-Intersection" (Ray Tracing Gems, 2019). Components far
+/// from the origin move by a fixed number of ulps, so the offset scales with
+/// their magnitude; components near the origin, where ulps vanish, move by a
+/// small fixed distance instead.
+pub fn offset_origin(p: &Point3, n: &Vec3) -> Point3 {
+    const ORIGIN: Float = 1.0 / 32.0;
+    const FLOAT_SCALE: Float = 1.0 / 65536.0;
+    const INT_SCALE: Float = 256.0;
+
+    let offset = |p: Float, n: Float| {
+        if p.abs() < ORIGIN {
+            return p + FLOAT_SCALE * n;
+        }
+        // Stepping the bit pattern moves by ulps; negative values step the
+        // other way, since their magnitude grows with the bits.
+        let ulps = (INT_SCALE * n) as i64;
+        let ulps = if p < 0.0 { -ulps } else { ulps };
+        Float::from_bits((p.to_bits() as i64).wrapping_add(ulps) as _)
+    };
+
+    Point3::new(
+        offset(p.x(), n.x()),
+        offset(p.y(), n.y()),
+        offset(p.z(), n.z()),
+    )
 }
 
 /// Specifies how rays intersect geometry.
-pub trait Hittable {
-    /// Produces a hit record when an intersection occurs.
+pub trait Hittable: Send + Sync {
+    /// Produces a hit record when an intersection occurs. Aggregates such as
+    /// lists and hierarchies also narrow `ray_t` to the ray's own valid range
+    /// (see `Ray::clip`), so rays carrying a range need no extra parameters.
     fn hit(&self, ray: &Ray, ray_t: &Interval) -> Option<HitRecord>;
+
+    /// Axis-aligned box bounding the object.
+    fn bounding_box(&self) -> Aabb;
+
+    /// Determines whether the ray hits anything within `ray_t`. Unlike `hit`,
+    /// this may stop at the first intersection found and skips building the
+    /// hit record, which makes it the cheaper query for shadow rays.
+    fn is_occluded(&self, ray: &Ray, ray_t: &Interval) -> bool {
+        self.hit(ray, ray_t).is_some()
+    }
+
+    /// Reports problems with the object's geometry and materials.
+    fn validate(&self) -> Vec<Diagnostic> {
+        Vec::new()
+    }
+}
+
+impl<T: Hittable + ?Sized> Hittable for Arc<T> {
+    fn hit(&self, ray: &Ray, ray_t: &Interval) -> Option<HitRecord> {
+        (**self).hit(ray, ray_t)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        (**self).bounding_box()
+    }
+
+    fn is_occluded(&self, ray: &Ray, ray_t: &Interval) -> bool {
+        (**self).is_occluded(ray, ray_t)
+    }
+
+    fn validate(&self) -> Vec<Diagnostic> {
+        (**self).validate()
+    }
+}
+
+impl<T: Hittable + ?Sized> Hittable for Box<T> {
+    fn hit(&self, ray: &Ray, ray_t: &Interval) -> Option<HitRecord> {
+        (**self).hit(ray, ray_t)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        (**self).bounding_box()
+    }
+
+    fn is_occluded(&self, ray: &Ray, ray_t: &Interval) -> bool {
+        (**self).is_occluded(ray, ray_t)
+    }
+
+    fn validate(&self) -> Vec<Diagnostic> {
+        (**self).validate()
+    }
 }
 
-/// List of objects that can be hit by rays.
+/// Closed set of the basic primitives, dispatched with a `match` rather than
+/// through a vtable so that the calls can be inlined, e.g. in a `Bvh` over
+/// mixed spheres and triangles. Other objects fall back to `Dynamic`.
+#[derive(Clone)]
+pub enum PrimitiveKind {
+    Sphere(Sphere),
+    Triangle(Triangle),
+    Dynamic(Arc<dyn Hittable>),
+}
+
+/// Applies `$f` to the object inside a `PrimitiveKind`, bound as `$o`.
+macro_rules! dispatch {
+    ($kind:expr, $o:ident => $f:expr) => {
+        match $kind {
+            PrimitiveKind::Sphere($o) => $f,
+            PrimitiveKind::Triangle($o) => $f,
+            PrimitiveKind::Dynamic($o) => $f,
+        }
+    };
+}
+
+impl Hittable for PrimitiveKind {
+    fn hit(&self, ray: &Ray, ray_t: &Interval) -> Option<HitRecord> {
+        dispatch!(self, o => o.hit(ray, ray_t))
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        dispatch!(self, o => o.bounding_box())
+    }
+
+    fn is_occluded(&self, ray: &Ray, ray_t: &Interval) -> bool {
+        dispatch!(self, o => o.is_occluded(ray, ray_t))
+    }
+
+    fn validate(&self) -> Vec<Diagnostic> {
+        dispatch!(self, o => o.validate())
+    }
+}
+
+impl From<Sphere> for PrimitiveKind {
+    fn from(sphere: Sphere) -> Self {
+        PrimitiveKind::Sphere(sphere)
+    }
+}
+
+impl From<Triangle> for PrimitiveKind {
+    fn from(triangle: Triangle) -> Self {
+        PrimitiveKind::Triangle(triangle)
+    }
+}
+
+impl From<Arc<dyn Hittable>> for PrimitiveKind {
+    fn from(object: Arc<dyn Hittable>) -> Self {
+        PrimitiveKind::Dynamic(object)
+    }
+}
+
+/// List of objects that can be hit by rays. Hits are tagged with the index of
+/// the object in the list as its object ID.
 pub struct HittableList<T: Hittable> {
     objects: Vec<T>,
+    bbox: Aabb,
 }
 
 impl<T: Hittable> HittableList<T> {
@@ -65,17 +286,21 @@ impl<T: Hittable> HittableList<T> {
     pub fn new() -> Self {
         Self {
             objects: Vec::new(),
+            bbox: Aabb::EMPTY,
         }
     }
 
-    /// Adds to hittable to the list.
+    /// Adds to hittable to the list. Its object ID is the number of objects
+    /// added before it.
     pub fn add(&mut self, object: T) {
+        self.bbox = Aabb::surrounding(&self.bbox, &object.bounding_box());
         self.objects.push(object);
     }
 
     /// Clears the list.
     pub fn clear(&mut self) {
         self.objects.clear();
+        self.bbox = Aabb::EMPTY;
     }
 }
 
@@ -87,16 +312,149 @@ impl<T: Hittable> Default for HittableList<T> {
 
 impl<T: Hittable> Hittable for HittableList<T> {
     fn hit(&self, ray: &Ray, ray_t: &Interval) -> Option<HitRecord> {
+        let ray_t = ray.clip(ray_t);
         self.objects
             .iter()
-            .fold((None, ray_t.max()), |(rec, t_max), object| {
+            .enumerate()
+            .fold((None, ray_t.max()), |(rec, t_max), (id, object)| {
                 if let Some(rec) = object.hit(ray, &Interval::new(ray_t.min(), t_max)) {
                     let t = rec.t;
-                    (Some(rec), t)
+                    (Some(rec.with_object_id(id)), t)
                 } else {
                     (rec, t_max)
                 }
             })
             .0
     }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+
+    fn is_occluded(&self, ray: &Ray, ray_t: &Interval) -> bool {
+        let ray_t = ray.clip(ray_t);
+        self.objects
+            .iter()
+            .any(|object| object.is_occluded(ray, &ray_t))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::Lambertian;
+    use crate::sphere::Sphere;
+    use crate::Color;
+
+    #[test]
+    fn hittable_offset_origin() {
+        // Near the origin, points move by a small fixed distance.
+        let n = Vec3::new(0.0, 1.0, 0.0);
+        let p = offset_origin(&Point3::new(0.0, 0.0, 0.0), &n);
+        assert!(p.y() > 0.0 && p.y() < 1e-4);
+        assert_eq!((p.x(), p.z()), (0.0, 0.0));
+
+        // Far from it, they move by a number of ulps, either way along each axis.
+        let far = Point3::new(-1e4, 1e4, 5.0);
+        let n = Vec3::new(1.0, -1.0, 0.0).unit();
+        let p = offset_origin(&far, &n);
+        assert!(p.x() > far.x() && p.y() < far.y() && p.z() == far.z());
+        assert!((p - far).len() < 1e4 * 1e-3);
+    }
+
+    #[test]
+    fn hittable_spawn_ray() {
+        // A sphere far from the origin, where rounding errors in hit points
+        // dwarf a fixed offset near the origin.
+        let center = Point3::new(1e4, -2e4, 3e4);
+        let material = Arc::new(Lambertian::new(&Color::new(0.5, 0.5, 0.5)));
+        let sphere = Sphere::new(center, 50.0, material);
+        let origin = center + Vec3::new(0.0, 0.0, 200.0);
+        let bounds = Interval::new(0.0, Float::INFINITY);
+
+        for _ in 0..500 {
+            let target = center + 45.0 * Vec3::random_unit();
+            let rec = sphere
+                .hit(&Ray::new(origin, target - origin), &bounds)
+                .unwrap();
+
+            // Rays leaving the surface never hit it again, and rays entering it
+            // reach the far side.
+            let outward = rec.normal + Vec3::random_unit();
+            assert!(!sphere.is_occluded(&rec.spawn_ray(outward), &bounds));
+            let inward = rec.spawn_ray(-rec.normal + 0.5 * Vec3::random_unit());
+            let exit = sphere.hit(&inward, &bounds).unwrap();
+            assert!((exit.p - rec.p).len() > 1.0);
+        }
+
+        // Blockers closer than any fixed bound still cast shadows.
+        let tiny = Sphere::new(
+            Point3::new(0.0, 3e-4, 0.0),
+            1e-4,
+            Arc::new(Lambertian::new(&Color::new(0.5, 0.5, 0.5))),
+        );
+        let ray = Ray::new(Point3::new(0.0, 1e-3, 0.0), Vec3::new(0.0, -1.0, 0.0));
+        let rec = tiny.hit(&ray, &bounds).unwrap();
+        let below = Ray::new(Point3::new(0.0, 1e-4, 0.0), Vec3::new(0.0, 1.0, 0.0));
+        assert!(tiny.is_occluded(&below, &bounds));
+        assert!(!tiny.is_occluded(&rec.spawn_ray(rec.normal), &bounds));
+    }
+
+    #[test]
+    fn hittable_spawn_ray_ground() {
+        // The classic ground sphere, whose hit points are only as precise as
+        // its center and radius, far coarser than the rounding of the points
+        // themselves.
+        let material = Arc::new(Lambertian::new(&Color::new(0.5, 0.5, 0.5)));
+        let ground = Sphere::new(Point3::new(0.0, -1000.0, 0.0), 1000.0, material);
+        let bounds = Interval::new(0.0, Float::INFINITY);
+
+        for _ in 0..5000 {
+            let origin = Point3::new(0.0, 2.0, 3.0) + Vec3::random_unit();
+            let disk = 50.0 * Vec3::random_on_unit_disk();
+            let target = Point3::new(disk.x(), -2.0, disk.y());
+            let rec = ground
+                .hit(&Ray::new(origin, target - origin), &bounds)
+                .unwrap();
+
+            let outward = rec.normal + Vec3::random_unit();
+            assert!(!ground.is_occluded(&rec.spawn_ray(outward), &bounds));
+            let inward = rec.spawn_ray(-rec.normal + 0.5 * Vec3::random_unit());
+            assert!(ground.hit(&inward, &bounds).unwrap().t() > 1.0);
+        }
+    }
+
+    #[test]
+    fn hittable_primitive_kind() {
+        let material = Arc::new(Lambertian::new(&Color::new(0.5, 0.5, 0.5)));
+        let sphere = Sphere::new(Point3::new(0.0, 0.0, -5.0), 1.0, material.clone());
+        let triangle = Triangle::new(
+            Point3::new(-2.0, -2.0, -8.0),
+            Point3::new(2.0, -2.0, -8.0),
+            Point3::new(0.0, 2.0, -8.0),
+            material,
+        );
+        let dynamic: Arc<dyn Hittable> = Arc::new(sphere.clone());
+
+        let mut kinds: HittableList<PrimitiveKind> = HittableList::new();
+        kinds.add(sphere.clone().into());
+        kinds.add(triangle.clone().into());
+        let mut boxed: HittableList<Box<dyn Hittable>> = HittableList::new();
+        boxed.add(Box::new(sphere));
+        boxed.add(Box::new(triangle));
+        assert_eq!(kinds.bounding_box(), boxed.bounding_box());
+
+        let bounds = Interval::new(0.001, Float::INFINITY);
+        for x in [-1.5, -0.5, 0.0, 0.5, 3.0] {
+            let ray = Ray::new(Point3::new(x, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0));
+            let a = kinds.hit(&ray, &bounds).map(|rec| (rec.t(), rec.object_id));
+            let b = boxed.hit(&ray, &bounds).map(|rec| (rec.t(), rec.object_id));
+            assert_eq!(a, b);
+            assert_eq!(kinds.is_occluded(&ray, &bounds), b.is_some());
+        }
+
+        let ray = Ray::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        let dynamic = PrimitiveKind::from(dynamic);
+        assert_eq!(dynamic.hit(&ray, &bounds).map(|rec| rec.t()), Some(4.0));
+    }
 }
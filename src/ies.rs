@@ -0,0 +1,246 @@
+use std::error::Error as StdError;
+use std::path::Path;
+
+use crate::trace;
+use crate::{Error, Float, Vec3};
+
+/// Photometric type code of type C goniometry, the one used for building
+/// and street fixtures.
+const TYPE_C: u32 = 1;
+
+/// Angular intensity distribution of a real light fixture, read from an
+/// IESNA LM-63 (`.ies`) photometric file. The fixture points down -y, and
+/// horizontal angles turn from +x towards +z, so the profile can shape a
+/// light by looking up directions in the light's own frame.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IesProfile {
+    /// Vertical angles in degrees from straight down, increasing.
+    vertical: Vec<Float>,
+
+    /// Horizontal angles in degrees, increasing.
+    horizontal: Vec<Float>,
+
+    /// Intensities in candelas, one row of vertical samples per horizontal angle.
+    candela: Vec<Vec<Float>>,
+
+    /// Largest intensity in candelas.
+    max: Float,
+}
+
+impl IesProfile {
+    /// Loads a photometric profile from an IES file.
+    pub fn load<P>(path: P) -> Result<Self, Box<dyn StdError>>
+    where
+        P: AsRef<Path>,
+    {
+        let text = std::fs::read_to_string(path)?;
+        Ok(Self::parse(&text)?)
+    }
+
+    /// Parses the text of an IES file. Only type C photometry is supported,
+    /// and lamp tilt data is skipped.
+    pub fn parse(text: &str) -> Result<Self, Error> {
+        trace::span!(DEBUG, "ies_parse", bytes = text.len());
+
+        // Keywords come first, up to the line giving the lamp tilt.
+        let mut lines = text.lines();
+        let tilt = lines
+            .find(|line| line.trim_start().starts_with("TILT="))
+            .ok_or_else(|| Error::new_light("IES data has no TILT line"))?;
+        let rest: Vec<&str> = lines.collect();
+        let mut tokens = rest
+            .iter()
+            .flat_map(|line| line.split(|c: char| c.is_ascii_whitespace() || c == ','))
+            .filter(|token| !token.is_empty());
+        let mut number = || -> Result<Float, Error> {
+            let token = tokens
+                .next()
+                .ok_or_else(|| Error::new_light("unexpected end of IES data"))?;
+            token
+                .parse()
+                .map_err(|_| Error::new_light(&format!("invalid IES number '{token}'")))
+        };
+
+        if tilt.trim().trim_start_matches("TILT=") == "INCLUDE" {
+            // Lamp-to-luminaire geometry, then pairs of angles and factors.
+            number()?;
+            let pairs = number()? as usize;
+            for _ in 0..2 * pairs {
+                number()?;
+            }
+        }
+
+        // Lamp count, lumens per lamp, multiplier, angle counts, photometric
+        // type, units and dimensions, then ballast factors and input watts.
+        let header: Vec<Float> = (0..13).map(|_| number()).collect::<Result<_, _>>()?;
+        let multiplier = header[2];
+        let (vertical_count, horizontal_count) = (header[3] as usize, header[4] as usize);
+        if header[5] as u32 != TYPE_C {
+            return Err(Error::new_light(&format!(
+                "unsupported IES photometric type {} (only type C is supported)",
+                header[5]
+            )));
+        }
+        if vertical_count == 0 || horizontal_count == 0 {
+            return Err(Error::new_light("IES data has no angles"));
+        }
+
+        let vertical: Vec<Float> = (0..vertical_count)
+            .map(|_| number())
+            .collect::<Result<_, _>>()?;
+        let horizontal: Vec<Float> = (0..horizontal_count)
+            .map(|_| number())
+            .collect::<Result<_, _>>()?;
+        let increasing = |angles: &[Float]| angles.windows(2).all(|w| w[0] < w[1]);
+        if !increasing(&vertical) || !increasing(&horizontal) {
+            return Err(Error::new_light("IES angles must be increasing"));
+        }
+
+        let mut candela = Vec::with_capacity(horizontal_count);
+        for _ in 0..horizontal_count {
+            let row: Vec<Float> = (0..vertical_count)
+                .map(|_| Ok(number()? * multiplier))
+                .collect::<Result<_, Error>>()?;
+            candela.push(row);
+        }
+        let max = candela.iter().flatten().fold(0.0, |m: Float, c| m.max(*c));
+
+        Ok(Self {
+            vertical,
+            horizontal,
+            candela,
+            max,
+        })
+    }
+
+    /// Retrieves the largest intensity in candelas.
+    pub fn max_intensity(&self) -> Float {
+        self.max
+    }
+
+    /// Intensity in candelas emitted along the direction `dir`, in the
+    /// fixture's frame. Directions outside of the measured angles are dark.
+    pub fn intensity(&self, dir: &Vec3) -> Float {
+        let d = dir.unit();
+        let theta = (-d.y()).clamp(-1.0, 1.0).acos().to_degrees();
+        let first = self.vertical[0];
+        let last = self.vertical[self.vertical.len() - 1];
+        if theta < first || theta > last {
+            return 0.0;
+        }
+
+        let phi = self.fold(Float::atan2(d.z(), d.x()).to_degrees().rem_euclid(360.0));
+        let (h, s) = locate(&self.horizontal, phi);
+        let (v, t) = locate(&self.vertical, theta);
+        let at = |i: usize, j: usize| {
+            let i = i.min(self.horizontal.len() - 1);
+            let j = j.min(self.vertical.len() - 1);
+            self.candela[i][j]
+        };
+
+        let near = (1.0 - t) * at(h, v) + t * at(h, v + 1);
+        let far = (1.0 - t) * at(h + 1, v) + t * at(h + 1, v + 1);
+        (1.0 - s) * near + s * far
+    }
+
+    /// Intensity along `dir` relative to the brightest direction, for
+    /// shaping a light whose power is set separately.
+    pub fn normalized(&self, dir: &Vec3) -> Float {
+        if self.max > 0.0 {
+            self.intensity(dir) / self.max
+        } else {
+            0.0
+        }
+    }
+
+    /// Maps a horizontal angle in [0, 360) into the measured range, using
+    /// the symmetry implied by the last horizontal angle.
+    fn fold(&self, phi: Float) -> Float {
+        let last = self.horizontal[self.horizontal.len() - 1];
+        let phi = if last <= 0.0 {
+            // Symmetric about the vertical axis.
+            0.0
+        } else if last <= 90.0 {
+            // Symmetric in each quadrant.
+            let half = if phi > 180.0 { 360.0 - phi } else { phi };
+            if half > 90.0 {
+                180.0 - half
+            } else {
+                half
+            }
+        } else if last <= 180.0 && phi > 180.0 {
+            // Symmetric about the 0-180 degree plane.
+            360.0 - phi
+        } else {
+            phi
+        };
+        phi.clamp(self.horizontal[0], last)
+    }
+}
+
+/// Finds the interval of the increasing `angles` containing `x`, returning
+/// its first index and the fraction of the way across it.
+fn locate(angles: &[Float], x: Float) -> (usize, Float) {
+    let i = angles.partition_point(|a| *a <= x).clamp(1, angles.len()) - 1;
+    if i + 1 >= angles.len() {
+        return (i, 0.0);
+    }
+    let t = (x - angles[i]) / (angles[i + 1] - angles[i]);
+    (i, t.clamp(0.0, 1.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SPOT: &str = "IESNA:LM-63-2002
+[TEST] spot
+[MANUFAC] none
+TILT=NONE
+1 1000 2 3 2 1 1 0 0 0
+1.0 1.0 50
+0 45 90
+0 90
+100 50 0
+80, 40, 0
+";
+
+    #[test]
+    fn ies_parse() {
+        let profile = IesProfile::parse(SPOT).unwrap();
+        assert_eq!(profile.max_intensity(), 200.0);
+
+        // Straight down is brightest, and the multiplier scales every value.
+        let down = Vec3::new(0.0, -1.0, 0.0);
+        assert!((profile.intensity(&down) - 200.0).abs() < 1e-3);
+        assert!((profile.normalized(&down) - 1.0).abs() < 1e-6);
+        let up = Vec3::new(0.0, 1.0, 0.0);
+        assert_eq!(profile.intensity(&up), 0.0);
+
+        // Halfway between measured angles is interpolated.
+        let x = Vec3::new(1.0, -1.0, 0.0);
+        assert!((profile.intensity(&x) - 100.0).abs() < 1e-2);
+        let z = Vec3::new(0.0, -1.0, 1.0);
+        assert!((profile.intensity(&z) - 80.0).abs() < 1e-2);
+
+        // Quadrant symmetry mirrors the measured quarter.
+        let back = Vec3::new(-1.0, -1.0, 0.0);
+        assert!((profile.intensity(&back) - 100.0).abs() < 1e-2);
+        let behind = Vec3::new(0.0, -1.0, -1.0);
+        assert!((profile.intensity(&behind) - 80.0).abs() < 1e-2);
+    }
+
+    #[test]
+    fn ies_tilt_and_errors() {
+        let tilted = SPOT.replace("TILT=NONE", "TILT=INCLUDE\n1\n2\n0 90\n1 1");
+        assert_eq!(
+            IesProfile::parse(&tilted).unwrap(),
+            IesProfile::parse(SPOT).unwrap()
+        );
+
+        assert!(IesProfile::parse("IESNA:LM-63-2002\n1 2 3").is_err());
+        assert!(IesProfile::parse(&SPOT.replace("80, 40, 0", "80, 40")).is_err());
+        assert!(IesProfile::parse(&SPOT.replace("1 1 0 0 0", "2 1 0 0 0")).is_err());
+        assert!(IesProfile::parse(&SPOT.replace("0 45 90", "0 90 45")).is_err());
+    }
+}
@@ -1,9 +1,75 @@
+use crate::colorspace::ColorManagement;
+use crate::netpbm::{self, Encoding};
 use crate::Color;
 use std::error::Error;
+#[cfg(any(feature = "png", feature = "jpeg"))]
 use std::fs::File;
+#[cfg(any(feature = "png", feature = "jpeg"))]
+use std::io::BufReader;
 use std::path::Path;
 
-use netpbmr::{ppm, EncodingType};
+/// Image of colors stored row by row from the upper-left corner.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImageBuffer {
+    /// Width in pixels.
+    width: u32,
+
+    /// Height in pixels.
+    height: u32,
+
+    /// Pixel colors in row-major order.
+    data: Vec<Color>,
+}
+
+impl ImageBuffer {
+    /// Creates a new image from row-major pixel data.
+    pub fn new(width: u32, height: u32, data: Vec<Color>) -> Self {
+        assert_eq!(
+            data.len(),
+            width as usize * height as usize,
+            "image needs one color per pixel"
+        );
+        Self {
+            width,
+            height,
+            data,
+        }
+    }
+
+    /// Retrieves the pixel dimensions.
+    pub fn dim(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// Retrieves the pixel colors in row-major order.
+    pub fn data(&self) -> &[Color] {
+        &self.data
+    }
+
+    /// Retrieves the color of the pixel at column `x` and row `y`.
+    pub fn get(&self, x: u32, y: u32) -> &Color {
+        &self.data[y as usize * self.width as usize + x as usize]
+    }
+}
+
+/// Writes colors into a caller-provided RGBA buffer with 8 bits per channel
+/// and opaque alpha, such as a browser canvas's pixel data. Performs gamma
+/// correction. The buffer must hold exactly 4 bytes per color.
+pub fn write_rgba8(data: &[Color], out: &mut [u8]) -> Result<(), crate::Error> {
+    if out.len() != 4 * data.len() {
+        return Err(crate::Error::new_image(&format!(
+            "RGBA buffer of {} bytes cannot hold {} pixels",
+            out.len(),
+            data.len()
+        )));
+    }
+
+    for (color, pixel) in data.iter().zip(out.chunks_exact_mut(4)) {
+        let [r, g, b] = color.gamma_correct().to_rgb24();
+        pixel.copy_from_slice(&[r, g, b, u8::MAX]);
+    }
+    Ok(())
+}
 
 /// Creates a new PPM file with the given color data.
 /// Performs gamma correction.
@@ -11,15 +77,225 @@ pub fn create_ppm<P>(path: P, data: &[Color], w: u32, h: u32) -> Result<(), Box<
 where
     P: AsRef<Path>,
 {
-    let file = File::create(path)?;
-    let mut encoder = ppm::Encoder::new(file);
+    let data: Vec<Color> = data.iter().map(Color::gamma_correct).collect();
+    std::fs::write(path, netpbm::encode_ppm(w, h, 255, Encoding::Raw, &data)?)?;
+    Ok(())
+}
 
-    let data: Vec<u8> = data
-        .iter()
-        .flat_map(|color| color.gamma_correct().to_rgb24())
-        .collect();
+/// Creates a new PPM file with the given working space color data, converted
+/// and encoded for the output space of `management` instead of gamma corrected.
+pub fn create_ppm_managed<P>(
+    path: P,
+    data: &[Color],
+    w: u32,
+    h: u32,
+    management: &ColorManagement,
+) -> Result<(), Box<dyn Error>>
+where
+    P: AsRef<Path>,
+{
+    let data: Vec<Color> = data.iter().map(|color| management.encode(color)).collect();
+    std::fs::write(path, netpbm::encode_ppm(w, h, 255, Encoding::Raw, &data)?)?;
+    Ok(())
+}
+
+/// Creates a new 16-bit PPM file with the given color data, for more precision
+/// than 8 bits per channel.
+/// Performs gamma correction.
+pub fn create_ppm16<P>(path: P, data: &[Color], w: u32, h: u32) -> Result<(), Box<dyn Error>>
+where
+    P: AsRef<Path>,
+{
+    let data: Vec<Color> = data.iter().map(Color::gamma_correct).collect();
+    std::fs::write(
+        path,
+        netpbm::encode_ppm(w, h, u16::MAX, Encoding::Raw, &data)?,
+    )?;
+    Ok(())
+}
+
+/// Creates a new 16-bit PGM file from single-channel values in [0, 1], such as
+/// a normalized depth or ambient occlusion pass. No gamma correction is applied.
+pub fn create_pgm<P>(path: P, data: &[f32], w: u32, h: u32) -> Result<(), Box<dyn Error>>
+where
+    P: AsRef<Path>,
+{
+    std::fs::write(
+        path,
+        netpbm::encode_pgm(w, h, u16::MAX, Encoding::Raw, data)?,
+    )?;
+    Ok(())
+}
+
+/// Creates a new PBM file from a mask, with set pixels shown black.
+pub fn create_pbm<P>(path: P, data: &[bool], w: u32, h: u32) -> Result<(), Box<dyn Error>>
+where
+    P: AsRef<Path>,
+{
+    std::fs::write(path, netpbm::encode_pbm(w, h, Encoding::Raw, data)?)?;
+    Ok(())
+}
 
-    encoder.write(EncodingType::Raw, w, h, 255, &data)?;
+/// Creates a new PFM file with the given linear color data, preserving values
+/// outside of [0, 1] for HDR workflows. No gamma correction is applied.
+pub fn create_pfm<P>(path: P, data: &[Color], w: u32, h: u32) -> Result<(), Box<dyn Error>>
+where
+    P: AsRef<Path>,
+{
+    std::fs::write(path, netpbm::encode_pfm(w, h, data)?)?;
+    Ok(())
+}
 
+/// Creates a new grayscale PFM file from single-channel values, such as a
+/// depth pass, preserving them unclamped.
+pub fn create_pfm_gray<P>(path: P, data: &[f32], w: u32, h: u32) -> Result<(), Box<dyn Error>>
+where
+    P: AsRef<Path>,
+{
+    std::fs::write(path, netpbm::encode_pfm_gray(w, h, data)?)?;
     Ok(())
 }
+
+/// Loads a PPM, PGM or PBM file, such as one written by `create_ppm`, undoing
+/// its gamma correction so that the colors are linear again.
+pub fn load_pnm<P>(path: P) -> Result<ImageBuffer, Box<dyn Error>>
+where
+    P: AsRef<Path>,
+{
+    let bytes = std::fs::read(path)?;
+    let image = netpbm::decode(&bytes)?;
+    let (width, height) = image.dim();
+    let data = image.data().iter().map(Color::linearize).collect();
+    Ok(ImageBuffer::new(width, height, data))
+}
+
+/// Loads an image by its file extension: PPM/PGM/PBM (`ppm`, `pgm`, `pbm`,
+/// `pnm`) and, with the respective features, PNG (`png`) and JPEG (`jpg`,
+/// `jpeg`).
+/// Colors are converted to linear.
+pub fn load<P>(path: P) -> Result<ImageBuffer, Box<dyn Error>>
+where
+    P: AsRef<Path>,
+{
+    let path = path.as_ref();
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(str::to_ascii_lowercase);
+
+    match extension.as_deref() {
+        Some("ppm" | "pgm" | "pbm" | "pnm") => load_pnm(path),
+        #[cfg(feature = "png")]
+        Some("png") => load_png(path),
+        #[cfg(feature = "jpeg")]
+        Some("jpg" | "jpeg") => load_jpeg(path),
+        _ => Err(Box::new(crate::Error::new_image(&format!(
+            "unsupported image file {}",
+            path.display()
+        )))),
+    }
+}
+
+/// Loads a PNG file, converting its sRGB colors to linear. Palettes and low bit
+/// depths are expanded, 16-bit samples are reduced to 8 bits, and alpha is
+/// ignored.
+#[cfg(feature = "png")]
+pub fn load_png<P>(path: P) -> Result<ImageBuffer, Box<dyn Error>>
+where
+    P: AsRef<Path>,
+{
+    let mut decoder = png::Decoder::new(BufReader::new(File::open(path)?));
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info()?;
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buf)?;
+
+    let channels = match info.color_type {
+        png::ColorType::Grayscale => 1,
+        png::ColorType::GrayscaleAlpha => 2,
+        png::ColorType::Rgb => 3,
+        png::ColorType::Rgba => 4,
+        png::ColorType::Indexed => {
+            return Err(Box::new(crate::Error::new_image(
+                "PNG palette was not expanded",
+            )))
+        }
+    };
+    Ok(from_srgb8(
+        info.width,
+        info.height,
+        channels,
+        &buf[..info.buffer_size()],
+    ))
+}
+
+/// Loads a grayscale or RGB JPEG file, converting its sRGB colors to linear.
+#[cfg(feature = "jpeg")]
+pub fn load_jpeg<P>(path: P) -> Result<ImageBuffer, Box<dyn Error>>
+where
+    P: AsRef<Path>,
+{
+    let mut decoder = jpeg_decoder::Decoder::new(BufReader::new(File::open(path)?));
+    let pixels = decoder.decode()?;
+    let info = decoder
+        .info()
+        .ok_or_else(|| crate::Error::new_image("JPEG has no image information"))?;
+
+    let channels = match info.pixel_format {
+        jpeg_decoder::PixelFormat::L8 => 1,
+        jpeg_decoder::PixelFormat::RGB24 => 3,
+        format => {
+            return Err(Box::new(crate::Error::new_image(&format!(
+                "unsupported JPEG pixel format {format:?}"
+            ))))
+        }
+    };
+    Ok(from_srgb8(
+        info.width as u32,
+        info.height as u32,
+        channels,
+        &pixels,
+    ))
+}
+
+/// Builds a linear image from interleaved 8-bit sRGB pixels of one (gray), two
+/// (gray and alpha), three (RGB), or four (RGBA) channels. Alpha is dropped.
+#[cfg(any(feature = "png", feature = "jpeg"))]
+fn from_srgb8(width: u32, height: u32, channels: usize, bytes: &[u8]) -> ImageBuffer {
+    let unit = |b: u8| b as f32 / 255.0;
+    let data = bytes
+        .chunks_exact(channels)
+        .map(|px| {
+            let color = if channels < 3 {
+                Color::new(unit(px[0]), unit(px[0]), unit(px[0]))
+            } else {
+                Color::new(unit(px[0]), unit(px[1]), unit(px[2]))
+            };
+            color.srgb_to_linear()
+        })
+        .collect();
+    ImageBuffer::new(width, height, data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn image_load_unsupported() {
+        assert!(load("texture.tga").is_err());
+    }
+
+    #[cfg(any(feature = "png", feature = "jpeg"))]
+    #[test]
+    fn image_from_srgb8() {
+        let image = from_srgb8(2, 1, 2, &[255, 0, 0, 255]);
+        assert_eq!(
+            image.data(),
+            [Color::new(1.0, 1.0, 1.0), Color::new(0.0, 0.0, 0.0)]
+        );
+
+        let image = from_srgb8(1, 1, 4, &[255, 0, 0, 7]);
+        assert_eq!(image.data(), [Color::new(1.0, 0.0, 0.0)]);
+    }
+}
@@ -0,0 +1,218 @@
+use std::sync::Arc;
+
+use crate::aabb::Aabb;
+use crate::bvh::Bvh;
+use crate::diagnostic::Diagnostic;
+use crate::hittable::{HitRecord, Hittable};
+use crate::transform::Transform;
+use crate::{Interval, Ray};
+
+/// Placement of shared geometry in the scene. Many instances may reference the
+/// same object, such as a mesh with its own hierarchy, without copying it.
+#[derive(Clone)]
+pub struct Instance {
+    /// Shared object-space geometry.
+    object: Arc<dyn Hittable>,
+
+    /// Object-to-world transformation.
+    transform: Transform,
+
+    /// World-space bounds of the transformed object.
+    bbox: Aabb,
+}
+
+impl Instance {
+    /// Creates a new instance of `object` placed by `transform`.
+    pub fn new(object: Arc<dyn Hittable>, transform: Transform) -> Self {
+        let bbox = transform.bounding_box(&object.bounding_box());
+        Self {
+            object,
+            transform,
+            bbox,
+        }
+    }
+
+    /// Retrieves the shared geometry.
+    pub fn object(&self) -> &Arc<dyn Hittable> {
+        &self.object
+    }
+
+    /// Retrieves the object-to-world transformation.
+    pub fn transform(&self) -> &Transform {
+        &self.transform
+    }
+}
+
+impl Hittable for Instance {
+    fn hit(&self, ray: &Ray, ray_t: &Interval) -> Option<HitRecord> {
+        // The object-space ray keeps the world-space parameterization, so the
+        // interval and the resulting t carry over unchanged.
+        let local = self.transform.ray_to_object(ray);
+        let mut rec = self.object.hit(&local, ray_t)?;
+
+        rec.p = self.transform.point(&rec.p);
+        rec.error = self
+            .transform
+            .vector(&(rec.error * rec.geometric_normal))
+            .len();
+        rec.normal = self.transform.normal(&rec.normal).unit();
+        rec.geometric_normal = self.transform.normal(&rec.geometric_normal).unit();
+        Some(rec)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+
+    fn is_occluded(&self, ray: &Ray, ray_t: &Interval) -> bool {
+        let local = self.transform.ray_to_object(ray);
+        self.object.is_occluded(&local, ray_t)
+    }
+
+    fn validate(&self) -> Vec<Diagnostic> {
+        self.object.validate()
+    }
+}
+
+/// Two-level acceleration structure: a top-level hierarchy over instances of
+/// bottom-level geometry that keeps its own hierarchy, such as meshes. The
+/// geometry is built once and shared, so moving, adding or swapping instances
+/// only rebuilds the cheap top level. Hits are tagged with the index of the
+/// instance, which stays the same across edits, as their object ID.
+pub struct Tlas {
+    /// Instances in insertion order.
+    instances: Vec<Instance>,
+
+    /// Top-level hierarchy over the instances.
+    bvh: Bvh<Instance>,
+}
+
+impl Tlas {
+    /// Builds the top level over the given instances.
+    pub fn new(instances: Vec<Instance>) -> Self {
+        let bvh = Bvh::new(instances.clone());
+        Self { instances, bvh }
+    }
+
+    /// Number of instances.
+    pub fn len(&self) -> usize {
+        self.instances.len()
+    }
+
+    /// Determines whether there are no instances.
+    pub fn is_empty(&self) -> bool {
+        self.instances.is_empty()
+    }
+
+    /// Retrieves the instances in insertion order, indexed by object ID.
+    pub fn instances(&self) -> &[Instance] {
+        &self.instances
+    }
+
+    /// Adds an instance and rebuilds the top level. Returns its object ID.
+    pub fn push(&mut self, instance: Instance) -> usize {
+        self.instances.push(instance);
+        self.rebuild();
+        self.instances.len() - 1
+    }
+
+    /// Moves the instance with object ID `id` and rebuilds the top level.
+    pub fn set_transform(&mut self, id: usize, transform: Transform) {
+        let object = self.instances[id].object.clone();
+        self.instances[id] = Instance::new(object, transform);
+        self.rebuild();
+    }
+
+    /// Rebuilds the top-level hierarchy, leaving the shared geometry untouched.
+    fn rebuild(&mut self) {
+        self.bvh = Bvh::new(self.instances.clone());
+    }
+}
+
+impl Hittable for Tlas {
+    fn hit(&self, ray: &Ray, ray_t: &Interval) -> Option<HitRecord> {
+        self.bvh.hit(ray, ray_t)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bvh.bounding_box()
+    }
+
+    fn is_occluded(&self, ray: &Ray, ray_t: &Interval) -> bool {
+        self.bvh.is_occluded(ray, ray_t)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::Lambertian;
+    use crate::sphere::Sphere;
+    use crate::{Color, Float, Point3, Vec3};
+
+    #[test]
+    fn instance_shared_geometry() {
+        let material = Arc::new(Lambertian::new(&Color::new(0.5, 0.5, 0.5)));
+        let unit: Arc<dyn Hittable> =
+            Arc::new(Sphere::new(Point3::new(0.0, 0.0, 0.0), 1.0, material));
+
+        let instances: Vec<Instance> = (0..10)
+            .map(|i| {
+                let offset = Vec3::new(3.0 * i as Float, 0.0, 0.0);
+                Instance::new(unit.clone(), Transform::translation(&offset))
+            })
+            .collect();
+        assert_eq!(Arc::strong_count(&unit), 11);
+
+        let bvh = Bvh::new(instances);
+        let bounds = Interval::new(0.001, Float::INFINITY);
+        let ray = Ray::new(Point3::new(9.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        let rec = bvh.hit(&ray, &bounds).unwrap();
+        assert_eq!(rec.t(), 4.0);
+        assert!(rec.p.almost_eq(&Point3::new(9.0, 0.0, 1.0)));
+        assert!(rec.normal.almost_eq(&Vec3::new(0.0, 0.0, 1.0)));
+    }
+
+    #[test]
+    fn instance_scaled() {
+        let material = Arc::new(Lambertian::new(&Color::new(0.5, 0.5, 0.5)));
+        let unit: Arc<dyn Hittable> =
+            Arc::new(Sphere::new(Point3::new(0.0, 0.0, 0.0), 1.0, material));
+        let instance = Instance::new(unit, Transform::scaling(&Vec3::new(2.0, 1.0, 1.0)));
+
+        let bbox = instance.bounding_box();
+        assert!(bbox.max().almost_eq(&Point3::new(2.0, 1.0, 1.0)));
+
+        let bounds = Interval::new(0.001, Float::INFINITY);
+        let ray = Ray::new(Point3::new(5.0, 0.0, 0.0), Vec3::new(-1.0, 0.0, 0.0));
+        let rec = instance.hit(&ray, &bounds).unwrap();
+        assert_eq!(rec.t(), 3.0);
+        assert!(rec.normal.almost_eq(&Vec3::new(1.0, 0.0, 0.0)));
+    }
+
+    #[test]
+    fn tlas_edit() {
+        let material = Arc::new(Lambertian::new(&Color::new(0.5, 0.5, 0.5)));
+        let unit: Arc<dyn Hittable> =
+            Arc::new(Sphere::new(Point3::new(0.0, 0.0, 0.0), 1.0, material));
+        let at = |x: Float| Transform::translation(&Vec3::new(x, 0.0, 0.0));
+
+        let mut tlas = Tlas::new(vec![Instance::new(unit.clone(), at(0.0))]);
+        let id = tlas.push(Instance::new(unit.clone(), at(3.0)));
+        assert_eq!((id, tlas.len()), (1, 2));
+
+        let bounds = Interval::new(0.001, Float::INFINITY);
+        let ray = Ray::new(Point3::new(10.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(tlas.hit(&ray, &bounds).is_none());
+
+        // Moving an instance keeps its ID and shares the same geometry.
+        tlas.set_transform(id, at(10.0));
+        let rec = tlas.hit(&ray, &bounds).unwrap();
+        assert_eq!(rec.object_id, Some(id));
+        assert!(Arc::ptr_eq(tlas.instances()[id].object(), &unit));
+        assert!(tlas
+            .bounding_box()
+            .max()
+            .almost_eq(&Point3::new(11.0, 1.0, 1.0)));
+    }
+}
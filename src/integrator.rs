@@ -0,0 +1,648 @@
+use std::cell::Cell;
+
+use crate::background::Background;
+use crate::bvh;
+use crate::hittable::{HitRecord, Hittable};
+use crate::light::PointLight;
+use crate::renderer::RenderSettings;
+use crate::variance::viridis;
+use crate::{Color, Float, Interval, Ray, Vec3};
+
+// Rays leaving surfaces are spawned off of them (see `HitRecord::spawn_ray`),
+// so any hit ahead of the origin is genuine.
+pub(crate) const INITIAL_T_BOUND: Interval = Interval::new(0.0, Float::INFINITY);
+
+thread_local! {
+    /// Scattering events followed by path tracers on this thread.
+    static BOUNCES: Cell<u64> = const { Cell::new(0) };
+}
+
+/// Returns the number of scattering events followed by `PathTracer` on this
+/// thread since the last call, and resets the count. Used to visualize path
+/// length.
+pub fn take_bounces() -> u64 {
+    BOUNCES.with(|bounces| bounces.replace(0))
+}
+
+/// Light transport algorithm estimating the color seen along a camera ray.
+pub trait Integrator: Send + Sync {
+    /// Estimates the color arriving along `ray` from `world`, seeing
+    /// `background` where rays escape it.
+    fn radiance(
+        &self,
+        ray: &Ray,
+        world: &dyn Hittable,
+        background: &dyn Background,
+        settings: &RenderSettings,
+    ) -> Color;
+}
+
+/// Unidirectional path tracer following scattered rays up to the maximum depth.
+/// The background's sun, if any, is also sampled directly at surfaces that
+/// can be evaluated, for low-noise sunlight and sharp shadows. So is an
+/// importance-sampled sky, with light and scattered samples combined by
+/// multiple importance sampling, and so are point lights, which scattered
+/// rays can never hit.
+#[derive(Debug, Clone, Default)]
+pub struct PathTracer {
+    /// Point lights illuminating the world.
+    lights: Vec<PointLight>,
+}
+
+impl PathTracer {
+    /// Create a new path tracer.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a point light illuminating the world.
+    pub fn with_light(mut self, light: PointLight) -> Self {
+        self.lights.push(light);
+        self
+    }
+
+    /// Determine the color of a ray with `depth` bounces left. `light_sampled`
+    /// holds the probability density of the ray's direction if lights were
+    /// already sampled directly at the last bounce.
+    fn ray_color(
+        &self,
+        ray: &Ray,
+        depth: u32,
+        light_sampled: Option<Float>,
+        world: &dyn Hittable,
+        background: &dyn Background,
+        settings: &RenderSettings,
+    ) -> Color {
+        if depth == 0 {
+            return Color::new(0.0, 0.0, 0.0);
+        }
+
+        if let Some(rec) = world.hit(ray, &INITIAL_T_BOUND) {
+            let Some((scattered, attenuation)) = rec.material.scatter(ray, &rec) else {
+                return Color::new(0.0, 0.0, 0.0);
+            };
+            BOUNCES.with(|bounces| bounces.set(bounces.get() + 1));
+            let direct = self.sample_lights(ray, &rec, world, background);
+            let channel = scattered.channel().or(ray.channel());
+            let scattered = Self::regularize(scattered, rec.material.roughness(), depth, settings);
+            let scattered = rec
+                .spawn_ray(*scattered.direction())
+                .with_time(ray.time())
+                .with_channel(channel);
+            let pdf = direct.map(|_| {
+                let direction = scattered.direction().unit();
+                rec.material.pdf(ray, &rec, &direction)
+            });
+            let indirect = self.ray_color(&scattered, depth - 1, pdf, world, background, settings);
+            return direct.unwrap_or(Color::new(0.0, 0.0, 0.0)) + attenuation * indirect;
+        }
+
+        match light_sampled {
+            Some(bsdf_pdf) => {
+                let light_pdf = background.sky_pdf(ray.origin(), &ray.direction().unit());
+                let weight = if light_pdf > 0.0 {
+                    power_heuristic(bsdf_pdf, light_pdf)
+                } else {
+                    1.0
+                };
+                background.sky(ray) * weight as f32
+            }
+            None => background.color(ray),
+        }
+    }
+
+    /// Estimate the light reaching a hit straight from the lights and the
+    /// background by sampling a direction within the sun's disk and one
+    /// towards the sky, or `None` when nothing can be sampled or the material
+    /// cannot be evaluated.
+    fn sample_lights(
+        &self,
+        ray: &Ray,
+        rec: &HitRecord,
+        world: &dyn Hittable,
+        background: &dyn Background,
+    ) -> Option<Color> {
+        let visible = |direction: &Vec3| {
+            let shadow = rec.spawn_ray(*direction).with_time(ray.time());
+            !world.is_occluded(&shadow, &INITIAL_T_BOUND)
+        };
+        let mut direct = None;
+
+        if let Some(sun) = background.sun() {
+            let direction = sun.sample();
+            let reflectance = rec.material.eval(ray, rec, &direction)?;
+            let mut light = Color::new(0.0, 0.0, 0.0);
+            if visible(&direction) {
+                light = reflectance * *sun.radiance() * sun.solid_angle() as f32;
+            }
+            direct = Some(light);
+        }
+
+        if let Some((direction, radiance, light_pdf)) = background.sample_sky(&rec.p) {
+            let reflectance = rec.material.eval(ray, rec, &direction)?;
+            let mut light = Color::new(0.0, 0.0, 0.0);
+            if visible(&direction) {
+                let bsdf_pdf = rec.material.pdf(ray, rec, &direction);
+                let weight = power_heuristic(light_pdf, bsdf_pdf) / light_pdf;
+                light = reflectance * radiance * weight as f32;
+            }
+            direct = Some(direct.unwrap_or(Color::new(0.0, 0.0, 0.0)) + light);
+        }
+
+        for light in &self.lights {
+            let Some((direction, distance, irradiance)) = light.illuminate(&rec.p) else {
+                continue;
+            };
+            let reflectance = rec.material.eval(ray, rec, &direction)?;
+            let shadow = rec.spawn_ray(direction).with_time(ray.time());
+            let mut light = Color::new(0.0, 0.0, 0.0);
+            if !world.is_occluded(&shadow, &Interval::new(0.0, distance)) {
+                light = reflectance * irradiance;
+            }
+            direct = Some(direct.unwrap_or(Color::new(0.0, 0.0, 0.0)) + light);
+        }
+
+        direct
+    }
+
+    /// Fuzz a ray scattered off a surface of the given roughness up to the
+    /// minimum roughness, unless it left a directly visible surface.
+    fn regularize(scattered: Ray, roughness: Float, depth: u32, settings: &RenderSettings) -> Ray {
+        match settings.min_roughness() {
+            Some(min) if depth < settings.max_depth && roughness < min => {
+                let fuzz = (min - roughness) * Vec3::random_unit();
+                Ray::new(*scattered.origin(), scattered.direction().unit() + fuzz)
+                    .with_time(scattered.time())
+            }
+            _ => scattered,
+        }
+    }
+}
+
+impl Integrator for PathTracer {
+    fn radiance(
+        &self,
+        ray: &Ray,
+        world: &dyn Hittable,
+        background: &dyn Background,
+        settings: &RenderSettings,
+    ) -> Color {
+        self.ray_color(ray, settings.max_depth, None, world, background, settings)
+    }
+}
+
+/// Power heuristic weight of a sample drawn with density `pdf` against
+/// another strategy with density `other`.
+fn power_heuristic(pdf: Float, other: Float) -> Float {
+    let (a, b) = (pdf * pdf, other * other);
+    if a + b > 0.0 {
+        a / (a + b)
+    } else {
+        0.0
+    }
+}
+
+/// Single-bounce lighting: surfaces are lit only by the background they see
+/// directly, without interreflection.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DirectLighting;
+
+impl DirectLighting {
+    /// Create a new direct lighting integrator.
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Integrator for DirectLighting {
+    fn radiance(
+        &self,
+        ray: &Ray,
+        world: &dyn Hittable,
+        background: &dyn Background,
+        _settings: &RenderSettings,
+    ) -> Color {
+        let Some(rec) = world.hit(ray, &INITIAL_T_BOUND) else {
+            return background.color(ray);
+        };
+        let Some((scattered, attenuation)) = rec.material.scatter(ray, &rec) else {
+            return Color::new(0.0, 0.0, 0.0);
+        };
+        let scattered = rec.spawn_ray(*scattered.direction()).with_time(ray.time());
+
+        if world.is_occluded(&scattered, &INITIAL_T_BOUND) {
+            Color::new(0.0, 0.0, 0.0)
+        } else {
+            attenuation * background.color(&scattered)
+        }
+    }
+}
+
+/// Ambient occlusion: white where a cosine-weighted ray from the surface
+/// escapes within `distance`, black where it is blocked. Misses are white.
+#[derive(Debug, Clone, Copy)]
+pub struct AmbientOcclusion {
+    /// Distance beyond which blockers are ignored.
+    distance: Float,
+}
+
+impl AmbientOcclusion {
+    /// Create a new ambient occlusion integrator.
+    pub fn new(distance: Float) -> Self {
+        Self { distance }
+    }
+}
+
+impl Integrator for AmbientOcclusion {
+    fn radiance(
+        &self,
+        ray: &Ray,
+        world: &dyn Hittable,
+        _background: &dyn Background,
+        _settings: &RenderSettings,
+    ) -> Color {
+        let Some(rec) = world.hit(ray, &INITIAL_T_BOUND) else {
+            return Color::new(1.0, 1.0, 1.0);
+        };
+
+        let mut direction = rec.normal + Vec3::random_unit();
+        if direction.almost_zero() {
+            direction = rec.normal;
+        }
+        let probe = rec.spawn_ray(direction).with_time(ray.time());
+        let reach = Interval::new(INITIAL_T_BOUND.min(), self.distance / direction.len());
+
+        if world.is_occluded(&probe, &reach) {
+            Color::new(0.0, 0.0, 0.0)
+        } else {
+            Color::new(1.0, 1.0, 1.0)
+        }
+    }
+}
+
+/// Debug view of the shading normal, mapped from [-1, 1] to [0, 1] per axis.
+/// Misses are black.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NormalDebug;
+
+impl NormalDebug {
+    /// Create a new normal debug integrator.
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Integrator for NormalDebug {
+    fn radiance(
+        &self,
+        ray: &Ray,
+        world: &dyn Hittable,
+        _background: &dyn Background,
+        _settings: &RenderSettings,
+    ) -> Color {
+        match world.hit(ray, &INITIAL_T_BOUND) {
+            Some(rec) => {
+                let n = 0.5 * (rec.normal + Vec3::new(1.0, 1.0, 1.0));
+                Color::new(n.x() as f32, n.y() as f32, n.z() as f32)
+            }
+            None => Color::new(0.0, 0.0, 0.0),
+        }
+    }
+}
+
+/// Debug view of the distance to the first hit, white at the camera fading
+/// to black at `max_distance`. Misses are black.
+#[derive(Debug, Clone, Copy)]
+pub struct DepthDebug {
+    /// Distance mapped to black.
+    max_distance: Float,
+}
+
+impl DepthDebug {
+    /// Create a new depth debug integrator.
+    pub fn new(max_distance: Float) -> Self {
+        Self { max_distance }
+    }
+}
+
+impl Integrator for DepthDebug {
+    fn radiance(
+        &self,
+        ray: &Ray,
+        world: &dyn Hittable,
+        _background: &dyn Background,
+        _settings: &RenderSettings,
+    ) -> Color {
+        match world.hit(ray, &INITIAL_T_BOUND) {
+            Some(rec) => {
+                let distance = rec.t() * ray.direction().len();
+                let c = (1.0 - distance / self.max_distance).clamp(0.0, 1.0) as f32;
+                Color::new(c, c, c)
+            }
+            None => Color::new(0.0, 0.0, 0.0),
+        }
+    }
+}
+
+/// Debug view of the surface UV coordinates as red and green. Misses are black.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct UvDebug;
+
+impl UvDebug {
+    /// Create a new UV debug integrator.
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Integrator for UvDebug {
+    fn radiance(
+        &self,
+        ray: &Ray,
+        world: &dyn Hittable,
+        _background: &dyn Background,
+        _settings: &RenderSettings,
+    ) -> Color {
+        match world.hit(ray, &INITIAL_T_BOUND) {
+            Some(rec) => Color::new(rec.u as f32, rec.v as f32, 0.0),
+            None => Color::new(0.0, 0.0, 0.0),
+        }
+    }
+}
+
+/// Debug view of the number of times a path scatters before it escapes, is
+/// absorbed, or reaches the maximum depth, as false color from none to the
+/// maximum depth.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BounceDebug;
+
+impl BounceDebug {
+    /// Create a new bounce count debug integrator.
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Integrator for BounceDebug {
+    fn radiance(
+        &self,
+        ray: &Ray,
+        world: &dyn Hittable,
+        _background: &dyn Background,
+        settings: &RenderSettings,
+    ) -> Color {
+        let mut ray = *ray;
+        let mut bounces = 0;
+        while bounces < settings.max_depth {
+            let Some(scattered) = world.hit(&ray, &INITIAL_T_BOUND).and_then(|rec| {
+                let (scattered, _) = rec.material.scatter(&ray, &rec)?;
+                Some(rec.spawn_ray(*scattered.direction()))
+            }) else {
+                break;
+            };
+            ray = scattered.with_time(ray.time());
+            bounces += 1;
+        }
+
+        viridis(bounces as f32 / settings.max_depth as f32)
+    }
+}
+
+/// Debug view of the number of hierarchy nodes tested to find the first hit,
+/// as false color from none to `max_tests`. Only worlds built on `Bvh`
+/// contribute to the count.
+#[derive(Debug, Clone, Copy)]
+pub struct TraversalDebug {
+    /// Node test count mapped to the top of the color scale.
+    max_tests: u64,
+}
+
+impl TraversalDebug {
+    /// Create a new traversal cost debug integrator.
+    pub fn new(max_tests: u64) -> Self {
+        Self { max_tests }
+    }
+}
+
+impl Integrator for TraversalDebug {
+    fn radiance(
+        &self,
+        ray: &Ray,
+        world: &dyn Hittable,
+        _background: &dyn Background,
+        _settings: &RenderSettings,
+    ) -> Color {
+        bvh::take_node_tests();
+        world.hit(ray, &INITIAL_T_BOUND);
+        let tests = bvh::take_node_tests();
+        viridis(tests as f32 / self.max_tests as f32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::background::{Environment, Gradient, Sun, Uniform};
+    use crate::bvh::Bvh;
+    use crate::consts;
+    use crate::hittable::HittableList;
+    use crate::ies::IesProfile;
+    use crate::image::ImageBuffer;
+    use crate::material::Lambertian;
+    use crate::scenes;
+    use crate::sphere::Sphere;
+    use crate::Point3;
+    use std::sync::Arc;
+
+    fn sphere() -> Sphere {
+        let material = Arc::new(Lambertian::new(&Color::new(0.5, 0.5, 0.5)));
+        Sphere::new(Point3::new(0.0, 0.0, -2.0), 1.0, material)
+    }
+
+    fn world() -> HittableList<Sphere> {
+        let mut world = HittableList::new();
+        world.add(sphere());
+        world
+    }
+
+    fn settings() -> RenderSettings {
+        RenderSettings::new(1, 4).unwrap()
+    }
+
+    fn radiance(integrator: &dyn Integrator, ray: &Ray, world: &dyn Hittable) -> Color {
+        integrator.radiance(ray, world, &Gradient::default(), &settings())
+    }
+
+    #[test]
+    fn integrator_debug() {
+        let world = world();
+        let hit = Ray::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        let miss = Ray::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0));
+
+        let normal = radiance(&NormalDebug::new(), &hit, &world);
+        assert!(normal.almost_eq(&Color::new(0.5, 0.5, 1.0)));
+        let depth = radiance(&DepthDebug::new(4.0), &hit, &world);
+        assert!(depth.almost_eq(&Color::new(0.75, 0.75, 0.75)));
+
+        let black = Color::new(0.0, 0.0, 0.0);
+        assert_eq!(radiance(&NormalDebug::new(), &miss, &world), black);
+        assert_eq!(radiance(&DepthDebug::new(4.0), &miss, &world), black);
+        assert_eq!(
+            radiance(&PathTracer::new(), &miss, &world),
+            Gradient::default().color(&miss)
+        );
+    }
+
+    #[test]
+    fn integrator_false_color() {
+        let world = world();
+        let hit = Ray::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        let miss = Ray::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0));
+
+        let uv = radiance(&UvDebug::new(), &hit, &world);
+        assert!((0.0..=1.0).contains(&uv.r()) && (0.0..=1.0).contains(&uv.g()));
+
+        let none = viridis(0.0);
+        assert_eq!(radiance(&BounceDebug::new(), &miss, &world), none);
+        assert_ne!(radiance(&BounceDebug::new(), &hit, &world), none);
+
+        // Lists have no hierarchy to traverse.
+        let traversal = TraversalDebug::new(8);
+        assert_eq!(radiance(&traversal, &hit, &world), none);
+        let bvh = Bvh::new(vec![sphere()]);
+        assert_ne!(radiance(&traversal, &hit, &bvh), none);
+    }
+
+    #[test]
+    fn integrator_ambient_occlusion() {
+        let world = world();
+        let hit = Ray::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0));
+
+        // A lone convex sphere never occludes itself.
+        let white = Color::new(1.0, 1.0, 1.0);
+        for _ in 0..20 {
+            assert_eq!(radiance(&AmbientOcclusion::new(10.0), &hit, &world), white);
+        }
+    }
+
+    #[test]
+    fn integrator_sun() {
+        let world = world();
+        let sun = Sun::new(
+            Vec3::new(0.0, 1.0, 0.0),
+            0.01,
+            Color::new(100.0, 100.0, 100.0),
+        );
+        let top = Ray::new(Point3::new(0.0, 3.0, -2.0), Vec3::new(0.0, -1.0, 0.0));
+
+        // The top of the sphere faces the sun, so every sample sees it fully,
+        // and paths escaping into the sun are not counted twice.
+        let expected = 0.5 / consts::PI * 100.0 * sun.solid_angle();
+        for _ in 0..20 {
+            let c = PathTracer::new().radiance(&top, &world, &sun, &settings());
+            assert!((c.r() as Float - expected).abs() < 1e-3 * expected);
+        }
+
+        // The bottom is in shadow.
+        let bottom = Ray::new(Point3::new(0.0, -3.0, -2.0), Vec3::new(0.0, 1.0, 0.0));
+        let c = PathTracer::new().radiance(&bottom, &world, &sun, &settings());
+        assert_eq!(c, Color::new(0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn integrator_point_light() {
+        let world = world();
+        let black = Uniform::new(Color::new(0.0, 0.0, 0.0));
+        let bulb = PointLight::new(Point3::new(0.0, 3.0, 0.0), Color::new(4.0, 4.0, 4.0));
+        let profile = "TILT=NONE\n1 1000 1 2 1 1 1 0 0 0\n1.0 1.0 50\n0 90\n0\n100 0\n";
+        let spot = bulb
+            .clone()
+            .with_profile(IesProfile::parse(profile).unwrap());
+        let render = |light: &PointLight, ray: &Ray| {
+            PathTracer::new()
+                .with_light(light.clone())
+                .radiance(ray, &world, &black, &settings())
+        };
+
+        // The front of the sphere sees the light 18 degrees off the beam axis,
+        // where the profile dims it linearly from full intensity straight down.
+        let front = Ray::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        let (direction, distance, _) = bulb.illuminate(&front.at(1.0)).unwrap();
+        let expected = 0.5 / consts::PI * direction.z() * 4.0 / (distance * distance);
+        let uniform = render(&bulb, &front).r() as Float;
+        assert!((uniform - expected).abs() < 1e-4 * expected);
+        let theta = Float::atan2(1.0, 3.0).to_degrees();
+        let shaped = render(&spot, &front).r() as Float;
+        assert!((shaped - expected * (1.0 - theta / 90.0)).abs() < 1e-4 * expected);
+
+        // The back faces away from the light.
+        let back = Ray::new(Point3::new(0.0, 0.0, -4.0), Vec3::new(0.0, 0.0, 1.0));
+        assert_eq!(render(&spot, &back), Color::new(0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn integrator_environment() {
+        let world = world();
+        let light = Color::new(2.0, 2.0, 2.0);
+        let env = Environment::new(ImageBuffer::new(4, 2, vec![light; 8]));
+        let hit = Ray::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0));
+
+        // A lone convex sphere sees the whole environment from every point,
+        // so it reflects its albedo times the light, however the samples
+        // are weighted.
+        let n = 2000;
+        let mut sum = 0.0;
+        for _ in 0..n {
+            sum += PathTracer::new()
+                .radiance(&hit, &world, &env, &settings())
+                .r();
+        }
+        assert!((sum / n as f32 - 1.0).abs() < 0.05);
+    }
+
+    #[test]
+    fn integrator_portals() {
+        let demo = scenes::cornell_box(8);
+        let floor = Point3::new(450.0, 0.0, 150.0);
+        let ray = Ray::new(demo.camera.look_from, floor - demo.camera.look_from);
+        let settings = RenderSettings::new(1, 2).unwrap();
+
+        // Sampling through the ceiling opening agrees with finding it by
+        // chance, with far less noise.
+        let stats = |background: &dyn Background, n: usize| {
+            let samples: Vec<f32> = (0..n)
+                .map(|_| {
+                    PathTracer::new()
+                        .radiance(&ray, &demo.world, background, &settings)
+                        .r()
+                })
+                .collect();
+            let mean = samples.iter().sum::<f32>() / n as f32;
+            let variance = samples.iter().map(|s| (s - mean).powi(2)).sum::<f32>() / n as f32;
+            (mean, variance)
+        };
+        let (portal_mean, portal_variance) = stats(demo.background.as_ref(), 500);
+        let uniform = Uniform::new(Color::new(15.0, 15.0, 15.0));
+        let (chance_mean, chance_variance) = stats(&uniform, 4000);
+        assert!(portal_mean > 0.0);
+        assert!((portal_mean - chance_mean).abs() < 0.5 * portal_mean);
+        assert!(portal_variance < 0.1 * chance_variance);
+    }
+
+    #[test]
+    fn integrator_regularize() {
+        let settings = RenderSettings::new(1, 4).unwrap().with_regularization(0.5);
+        let ray = Ray::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -2.0));
+
+        // Primary hits and rough surfaces are left alone.
+        assert_eq!(PathTracer::regularize(ray, 0.0, 4, &settings), ray);
+        assert_eq!(PathTracer::regularize(ray, 0.8, 1, &settings), ray);
+
+        // Mirror bounces are blurred by at most the missing roughness.
+        for _ in 0..100 {
+            let fuzzed = PathTracer::regularize(ray, 0.1, 1, &settings);
+            let offset = fuzzed.direction() - Vec3::new(0.0, 0.0, -1.0);
+            assert!(offset.len() <= 0.4 + 1e-5);
+        }
+    }
+}
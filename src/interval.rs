@@ -1,56 +1,89 @@
-use core::f64;
+use crate::Float;
 
 /// Defines an interval defined along [`min`, `max`].
 #[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
 pub struct Interval {
     /// Minimum of the interval.
-    min: f64,
+    min: Float,
 
     /// Maximum of the interval.
-    max: f64,
+    max: Float,
 }
 
 impl Interval {
-    pub const EMPTY: Self = Self::new(f64::INFINITY, f64::NEG_INFINITY);
-    pub const UNIVERSE: Self = Self::new(f64::NEG_INFINITY, f64::INFINITY);
+    pub const EMPTY: Self = Self::new(Float::INFINITY, Float::NEG_INFINITY);
+    pub const UNIVERSE: Self = Self::new(Float::NEG_INFINITY, Float::INFINITY);
 
     /// Creates a new interval.
-    pub const fn new(min: f64, max: f64) -> Self {
+    pub const fn new(min: Float, max: Float) -> Self {
         Self { min, max }
     }
 
     /// Retrieves the minimum of the interval.
-    pub fn min(&self) -> f64 {
+    pub fn min(&self) -> Float {
         self.min
     }
 
     /// Retrieves the maximum of the interval.
-    pub fn max(&self) -> f64 {
+    pub fn max(&self) -> Float {
         self.max
     }
 
+    /// Creates the smallest interval containing two intervals.
+    pub fn surrounding(a: &Self, b: &Self) -> Self {
+        Self::new(a.min.min(b.min), a.max.max(b.max))
+    }
+
+    /// Length of the interval, negative when it is empty.
+    pub fn size(&self) -> Float {
+        self.max - self.min
+    }
+
+    /// Checks whether the interval contains no values.
+    pub fn is_empty(&self) -> bool {
+        self.min > self.max
+    }
+
+    /// Creates a copy of the interval widened by `delta` in total, half on
+    /// each side. A negative `delta` shrinks it.
+    pub fn expand(&self, delta: Float) -> Self {
+        let padding = delta / 2.0;
+        Self::new(self.min - padding, self.max + padding)
+    }
+
+    /// Smallest interval containing both intervals, including any gap
+    /// between them (see `surrounding`).
+    pub fn union(&self, other: &Self) -> Self {
+        Self::surrounding(self, other)
+    }
+
+    /// Values contained in both intervals. Empty when they do not overlap.
+    pub fn intersection(&self, other: &Self) -> Self {
+        Self::new(self.min.max(other.min), self.max.min(other.max))
+    }
+
     /// Checks whether `x` is in `[min, max]` (bounds-inclusive).
-    pub fn contains(&self, x: f64) -> bool {
+    pub fn contains(&self, x: Float) -> bool {
         self.min <= x && x <= self.max
     }
 
     /// Checks whether `x` is in `(min, max)` (bounds-exclusive).
-    pub fn surrounds(&self, x: f64) -> bool {
+    pub fn surrounds(&self, x: Float) -> bool {
         self.min < x && x < self.max
     }
 
     /// Checks whether `x` is in `[min, max)` (lower bound-inclusive, upper bound-exclusive).
-    pub fn surrounds_or_min(&self, x: f64) -> bool {
+    pub fn surrounds_or_min(&self, x: Float) -> bool {
         self.min <= x && x < self.max
     }
 
     /// Checks whether `x` is in `(min, max]` (lower bound-exclusive, upper bound-inclusive).
-    pub fn surrounds_or_max(&self, x: f64) -> bool {
+    pub fn surrounds_or_max(&self, x: Float) -> bool {
         self.min < x && x <= self.max
     }
 
     /// Clamps `x` within the inclusive bounds of the interval.
-    pub fn clamp(&self, x: f64) -> f64 {
+    pub fn clamp(&self, x: Float) -> Float {
         if x < self.min {
             self.min
         } else if x > self.max {
@@ -104,6 +137,31 @@ mod tests {
         assert!(Interval::UNIVERSE.contains(1000000.0));
     }
 
+    #[test]
+    fn interval_algebra() {
+        let a = Interval::new(-2.0, 5.0);
+        let b = Interval::new(3.0, 8.0);
+        assert_eq!(a.size(), 7.0);
+        assert_eq!(a.expand(2.0), Interval::new(-3.0, 6.0));
+        assert_eq!(a.expand(-7.0).size(), 0.0);
+
+        assert_eq!(a.union(&b), Interval::new(-2.0, 8.0));
+        assert_eq!(Interval::surrounding(&b, &a), Interval::new(-2.0, 8.0));
+        assert_eq!(a.intersection(&b), Interval::new(3.0, 5.0));
+        assert_eq!(b.intersection(&a), Interval::new(3.0, 5.0));
+
+        // Disjoint intervals intersect in nothing, but their union spans the gap.
+        let c = Interval::new(10.0, 11.0);
+        assert!(a.intersection(&c).is_empty());
+        assert!(a.union(&c).contains(7.0));
+
+        // The empty interval is the identity of unions.
+        assert!(Interval::EMPTY.is_empty());
+        assert!(!Interval::UNIVERSE.is_empty());
+        assert_eq!(Interval::EMPTY.union(&a), a);
+        assert_eq!(Interval::UNIVERSE.intersection(&a), a);
+    }
+
     #[test]
     fn min_greater_than_max() {
         let int = Interval::new(10.0, 9.0);
@@ -1,19 +1,85 @@
+// Conversions and constants written for `f64` become redundant in single precision.
+#![cfg_attr(
+    feature = "f32",
+    allow(clippy::unnecessary_cast, clippy::excessive_precision)
+)]
+
+pub mod aabb;
 pub mod almost;
+pub mod animation;
+pub mod aov;
+pub mod arena;
+pub mod background;
+pub mod bvh;
 pub mod camera;
+pub mod cancel;
 pub mod color;
+pub mod colorspace;
+pub mod controls;
+pub mod diagnostic;
+pub mod film;
+pub mod filter;
+pub mod heightfield;
 pub mod hittable;
+pub mod ies;
 pub mod image;
+pub mod instance;
+pub mod integrator;
 pub mod interval;
+pub mod light;
 pub mod material;
+pub mod mesh;
+pub mod metropolis;
+pub mod netpbm;
+pub mod noise;
+pub mod onb;
+pub mod portal;
+pub mod procedural;
+pub mod profile;
+pub mod progressive;
 pub mod ray;
+pub mod registry;
+pub mod renderer;
+pub mod sampler;
+pub mod scene;
+pub mod scenes;
+pub mod sdf;
 pub mod sphere;
+pub mod stereo;
+pub mod stl;
+pub mod temporal;
+pub mod texture;
+pub mod tonemap;
+mod trace;
+pub mod transform;
+pub mod triangle;
 pub mod util;
+pub mod variance;
 pub mod vec3;
+pub mod volume;
+
+/// Scalar type of the geometry pipeline. The `f32` feature switches rendering
+/// to single precision to cut memory and cache traffic.
+#[cfg(not(feature = "f32"))]
+pub type Float = f64;
+
+/// Scalar type of the geometry pipeline. The `f32` feature switches rendering
+/// to single precision to cut memory and cache traffic.
+#[cfg(feature = "f32")]
+pub type Float = f32;
+
+/// Mathematical constants at `Float` precision.
+#[cfg(not(feature = "f32"))]
+pub use std::f64::consts;
+
+/// Mathematical constants at `Float` precision.
+#[cfg(feature = "f32")]
+pub use std::f32::consts;
 
 pub use color::Color;
 pub use interval::Interval;
 pub use ray::Ray;
-pub use vec3::{Point3, Vec3};
+pub use vec3::{Axis, Point3, Vec3};
 
 /// General raytracer error.
 #[derive(Debug, Clone)]
@@ -34,6 +100,48 @@ impl Error {
         }
     }
 
+    /// Create a new render error.
+    pub fn new_render(msg: &str) -> Self {
+        Self {
+            kind: ErrorKind::Render(msg.to_string()),
+        }
+    }
+
+    /// Create a new image error.
+    pub fn new_image(msg: &str) -> Self {
+        Self {
+            kind: ErrorKind::Image(msg.to_string()),
+        }
+    }
+
+    /// Create a new mesh error.
+    pub fn new_mesh(msg: &str) -> Self {
+        Self {
+            kind: ErrorKind::Mesh(msg.to_string()),
+        }
+    }
+
+    /// Create a new volume error.
+    pub fn new_volume(msg: &str) -> Self {
+        Self {
+            kind: ErrorKind::Volume(msg.to_string()),
+        }
+    }
+
+    /// Create a new light error.
+    pub fn new_light(msg: &str) -> Self {
+        Self {
+            kind: ErrorKind::Light(msg.to_string()),
+        }
+    }
+
+    /// Create a new color error.
+    pub fn new_color(msg: &str) -> Self {
+        Self {
+            kind: ErrorKind::Color(msg.to_string()),
+        }
+    }
+
     /// Retrieves the error kind.
     pub fn kind(&self) -> &ErrorKind {
         &self.kind
@@ -45,12 +153,36 @@ impl Error {
 pub enum ErrorKind {
     /// Specifies an error generated by the camera.
     Camera(String),
+
+    /// Specifies an error generated by invalid render settings.
+    Render(String),
+
+    /// Specifies an error generated while decoding an image.
+    Image(String),
+
+    /// Specifies an error generated while building or loading a mesh.
+    Mesh(String),
+
+    /// Specifies an error generated while building or loading a volume.
+    Volume(String),
+
+    /// Specifies an error generated while loading a light profile.
+    Light(String),
+
+    /// Specifies an error generated while parsing a color.
+    Color(String),
 }
 
 impl std::error::Error for Error {
     fn description(&self) -> &str {
         match self.kind {
             ErrorKind::Camera(_) => "camera error",
+            ErrorKind::Render(_) => "render error",
+            ErrorKind::Image(_) => "image error",
+            ErrorKind::Mesh(_) => "mesh error",
+            ErrorKind::Volume(_) => "volume error",
+            ErrorKind::Light(_) => "light error",
+            ErrorKind::Color(_) => "color error",
         }
     }
 }
@@ -59,6 +191,12 @@ impl std::fmt::Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self.kind {
             ErrorKind::Camera(ref s) => write!(f, "{}", s),
+            ErrorKind::Render(ref s) => write!(f, "{}", s),
+            ErrorKind::Image(ref s) => write!(f, "{}", s),
+            ErrorKind::Mesh(ref s) => write!(f, "{}", s),
+            ErrorKind::Volume(ref s) => write!(f, "{}", s),
+            ErrorKind::Light(ref s) => write!(f, "{}", s),
+            ErrorKind::Color(ref s) => write!(f, "{}", s),
         }
     }
 }
@@ -0,0 +1,115 @@
+use crate::ies::IesProfile;
+use crate::transform::Transform;
+use crate::{Color, Float, Point3, Vec3};
+
+/// Light emitting from a single point, such as a bulb or a spot. Its
+/// intensity is uniform unless an IES profile shapes it into the angular
+/// distribution of a real fixture.
+#[derive(Debug, Clone)]
+pub struct PointLight {
+    /// Position of the light.
+    position: Point3,
+
+    /// Radiant intensity along the brightest direction.
+    intensity: Color,
+
+    /// Photometric profile shaping the intensity, if any.
+    profile: Option<IesProfile>,
+
+    /// Maps directions from world space into the fixture's frame.
+    to_fixture: Transform,
+}
+
+impl PointLight {
+    /// Creates a new light radiating `intensity` uniformly from `position`.
+    pub fn new(position: Point3, intensity: Color) -> Self {
+        Self {
+            position,
+            intensity,
+            profile: None,
+            to_fixture: Transform::IDENTITY,
+        }
+    }
+
+    /// Shapes the light by a photometric profile, scaled so that its
+    /// brightest direction emits the light's intensity.
+    pub fn with_profile(mut self, profile: IesProfile) -> Self {
+        self.profile = Some(profile);
+        self
+    }
+
+    /// Orients the fixture, which points down -y, with a rotation into world
+    /// space.
+    pub fn with_orientation(mut self, orientation: &Transform) -> Self {
+        self.to_fixture = orientation.inverse();
+        self
+    }
+
+    /// Retrieves the position of the light.
+    pub fn position(&self) -> &Point3 {
+        &self.position
+    }
+
+    /// Radiant intensity emitted along the world-space direction `dir`.
+    pub fn intensity(&self, dir: &Vec3) -> Color {
+        match &self.profile {
+            Some(profile) => {
+                self.intensity * profile.normalized(&self.to_fixture.vector(dir)) as f32
+            }
+            None => self.intensity,
+        }
+    }
+
+    /// Determines the light arriving at `p`: the unit direction towards the
+    /// light, its distance and the irradiance it delivers perpendicular to
+    /// that direction. `None` if `p` is at the light.
+    pub fn illuminate(&self, p: &Point3) -> Option<(Vec3, Float, Color)> {
+        let offset = self.position - p;
+        let distance = offset.len();
+        if distance <= 0.0 {
+            return None;
+        }
+        let direction = offset / distance;
+        let irradiance = self.intensity(&-direction) / (distance * distance) as f32;
+        Some((direction, distance, irradiance))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SPOT: &str = "IESNA:LM-63-2002
+TILT=NONE
+1 1000 1 2 1 1 1 0 0 0
+1.0 1.0 50
+0 90
+0
+100 0
+";
+
+    #[test]
+    fn light_profile() {
+        let bulb = PointLight::new(Point3::new(0.0, 2.0, 0.0), Color::new(4.0, 4.0, 4.0));
+        let (direction, distance, irradiance) =
+            bulb.illuminate(&Point3::new(0.0, 0.0, 0.0)).unwrap();
+        assert_eq!(direction, Vec3::new(0.0, 1.0, 0.0));
+        assert_eq!(distance, 2.0);
+        assert_eq!(irradiance, Color::new(1.0, 1.0, 1.0));
+        assert!(bulb.illuminate(bulb.position()).is_none());
+
+        // The profile dims the light away from straight down, and rotating the
+        // fixture turns the beam.
+        let spot = bulb.with_profile(IesProfile::parse(SPOT).unwrap());
+        let down = Vec3::new(0.0, -1.0, 0.0);
+        let aside = Vec3::new(1.0, -1.0, 0.0);
+        assert_eq!(spot.intensity(&down), Color::new(4.0, 4.0, 4.0));
+        assert!((spot.intensity(&aside).r() - 2.0).abs() < 1e-3);
+        assert_eq!(spot.intensity(&-down), Color::new(0.0, 0.0, 0.0));
+
+        let rotation = Transform::rotation(&Vec3::new(0.0, 0.0, 1.0), 90.0);
+        let turned = spot.with_orientation(&rotation);
+        assert!((turned.intensity(&Vec3::new(1.0, 0.0, 0.0)).r() - 4.0).abs() < 1e-3);
+        assert!(turned.intensity(&down).r() < 1e-3);
+    }
+}
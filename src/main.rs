@@ -1,6 +1,9 @@
 use raytracer::camera::Camera;
+use raytracer::cancel::CancelToken;
 use raytracer::hittable::HittableList;
 use raytracer::material::{Dielectric, Lambertian, Metallic};
+use raytracer::progressive::Progressive;
+use raytracer::renderer::{RenderSettings, Renderer};
 use raytracer::sphere::Sphere;
 use raytracer::Color;
 use raytracer::Point3;
@@ -9,6 +12,11 @@ use std::error::Error;
 use std::sync::Arc;
 
 fn main() -> Result<(), Box<dyn Error>> {
+    // With --save-on-interrupt, Ctrl-C stops the render at the next tile and
+    // saves what was rendered so far instead of discarding it. Natively only,
+    // as there is no Ctrl-C to catch on the web.
+    let save_on_interrupt = std::env::args().any(|arg| arg == "--save-on-interrupt");
+
     // World setup.
     let mut world = HittableList::new();
 
@@ -31,8 +39,6 @@ fn main() -> Result<(), Box<dyn Error>> {
     // Camera setup.
     let aspect_ratio = 16.0 / 9.0;
     let image_width = 400;
-    let samples_per_pixel = 100;
-    let max_depth = 50;
 
     let vfov = 20.0;
     let look_from = Point3::new(-2.0, 2.0, 1.0);
@@ -45,8 +51,6 @@ fn main() -> Result<(), Box<dyn Error>> {
     let camera = Camera::new(
         aspect_ratio,
         image_width,
-        samples_per_pixel,
-        max_depth,
         vfov,
         look_from,
         look_at,
@@ -56,17 +60,53 @@ fn main() -> Result<(), Box<dyn Error>> {
     )?;
 
     // Renderer setup.
+    let samples_per_pixel = 100;
+    let max_depth = 50;
+
+    let renderer = Renderer::new(RenderSettings::new(samples_per_pixel, max_depth)?);
+    let (image_width, image_height) = camera.dim();
+
     use std::time::Instant;
     let now = Instant::now();
 
-    let data = camera.render(&world);
+    if !save_on_interrupt {
+        let data = renderer.render(&camera, &world);
+
+        let elapsed = now.elapsed();
+        println!("Rendering: {:.2?}", elapsed);
+
+        // Save the rendered image.
+        image::create_ppm("sample.ppm", &data, image_width, image_height)?;
+        return Ok(());
+    }
+
+    let token = CancelToken::new();
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let handler = token.clone();
+        ctrlc::set_handler(move || handler.cancel())?;
+    }
+    let renderer = renderer.with_cancel(token.clone());
+
+    // A single pass takes the same samples as a plain render, one tile at a time.
+    let film = renderer.render_progressive_film(&camera, &world, &Progressive::new(1)?, |_, _| {});
 
     let elapsed = now.elapsed();
     println!("Rendering: {:.2?}", elapsed);
 
-    // Save the rendered image.
-    let (image_width, image_height) = camera.dim();
-    image::create_ppm("sample.ppm", &data, image_width, image_height)?;
+    if token.is_cancelled() {
+        // Completed tiles are normalized by their samples; the rest are black.
+        image::create_ppm(
+            "sample.partial.ppm",
+            &film.resolve(),
+            image_width,
+            image_height,
+        )?;
+        std::fs::write("sample.checkpoint", film.to_checkpoint())?;
+        println!("Interrupted: saved sample.partial.ppm and sample.checkpoint");
+    } else {
+        image::create_ppm("sample.ppm", &film.resolve(), image_width, image_height)?;
+    }
 
     Ok(())
 }
@@ -1,42 +1,110 @@
+use crate::color::RGB_WAVELENGTHS;
+use crate::diagnostic::{self, Diagnostic};
 use crate::hittable::{HitRecord, Orientation};
-use crate::{util::random, Color, Ray, Vec3};
+use crate::onb::Onb;
+use crate::texture::{SolidColor, Texture};
+use crate::{consts::PI, util::random, Color, Float, Ray, Vec3};
+use std::sync::Arc;
 
 /// Specifies how rays scatter off of geometry.
-pub trait Material {
+pub trait Material: Send + Sync {
     /// Determines the reflected ray and color produced by a particular hit.
     #[allow(unused)]
     fn scatter(&self, ray: &Ray, rec: &HitRecord) -> Option<(Ray, Color)> {
         None
     }
+
+    /// Approximate roughness in `[0, 1]`, from mirror-like at 0 to diffuse at 1.
+    /// Used to regularize near-specular paths.
+    fn roughness(&self) -> Float {
+        1.0
+    }
+
+    /// Evaluates the fraction of light arriving from the unit direction
+    /// `direction` that is reflected back along `ray`, weighted by the cosine
+    /// at the surface. Materials that can be evaluated this way are lit by
+    /// directly sampling lights; the rest only find lights by scattering.
+    #[allow(unused)]
+    fn eval(&self, ray: &Ray, rec: &HitRecord, direction: &Vec3) -> Option<Color> {
+        None
+    }
+
+    /// Probability density per solid angle of `scatter` choosing the unit
+    /// direction `direction`, for weighting scattered rays against light
+    /// samples. Zero for materials that cannot be evaluated.
+    #[allow(unused)]
+    fn pdf(&self, ray: &Ray, rec: &HitRecord, direction: &Vec3) -> Float {
+        0.0
+    }
+
+    /// Determines whether the surface is cut away at a particular hit.
+    /// Primitives consult this while intersecting and skip masked hits, so
+    /// rays pass through the holes as if nothing were there.
+    #[allow(unused)]
+    fn is_masked(&self, rec: &HitRecord) -> bool {
+        false
+    }
+
+    /// Reports problems with the material's parameters.
+    fn validate(&self) -> Vec<Diagnostic> {
+        Vec::new()
+    }
 }
 
 /// Lambertian diffuse material.
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub struct Lambertian {
-    /// Fractional reflectance color.
-    albedo: Color,
+    /// Fractional reflectance texture.
+    albedo: Arc<dyn Texture>,
 }
 
 impl Lambertian {
     /// Create a Lambertian material. Rays will always scatter.
     pub fn new(albedo: &Color) -> Self {
-        Self { albedo: *albedo }
+        Self {
+            albedo: Arc::new(SolidColor::new(albedo)),
+        }
+    }
+
+    /// Create a Lambertian material whose albedo varies by texture.
+    pub fn textured(albedo: Arc<dyn Texture>) -> Self {
+        Self { albedo }
     }
 }
 
 impl Material for Lambertian {
     #[allow(unused)]
     fn scatter(&self, ray: &Ray, rec: &HitRecord) -> Option<(Ray, Color)> {
+        let albedo = self.albedo.value(rec.u, rec.v, &rec.p, &rec.normal);
+
         // Generate the reflected ray in the unit circle from the surface normal.
         let scatter_direction = rec.normal + Vec3::random_unit();
 
         // Use the surface normal if the generated ray is degenerate.
         if !scatter_direction.almost_zero() {
-            Some((Ray::new(rec.p, scatter_direction), self.albedo))
+            Some((Ray::new(rec.p, scatter_direction), albedo))
         } else {
-            Some((Ray::new(rec.p, rec.normal), self.albedo))
+            Some((Ray::new(rec.p, rec.normal), albedo))
         }
     }
+
+    #[allow(unused)]
+    fn eval(&self, ray: &Ray, rec: &HitRecord, direction: &Vec3) -> Option<Color> {
+        let albedo = self.albedo.value(rec.u, rec.v, &rec.p, &rec.normal);
+        let cosine = Vec3::dot(&rec.normal, direction).max(0.0);
+        Some(albedo * (cosine / PI) as f32)
+    }
+
+    #[allow(unused)]
+    fn pdf(&self, ray: &Ray, rec: &HitRecord, direction: &Vec3) -> Float {
+        Vec3::dot(&rec.normal, direction).max(0.0) / PI
+    }
+
+    fn validate(&self) -> Vec<Diagnostic> {
+        // Only uniform albedos can be checked without sampling the texture.
+        let albedo = self.albedo.constant();
+        albedo.iter().filter_map(diagnostic::check_albedo).collect()
+    }
 }
 
 /// Lambertian probabilistic diffuse material.
@@ -46,12 +114,12 @@ pub struct LambertianRandom {
     albedo: Color,
 
     /// Probability of scattering.
-    p: f64,
+    p: Float,
 }
 
 impl LambertianRandom {
     /// Create a Lambertian material. Rays will scatter with probability `p`.
-    pub fn new(albedo: &Color, p: f64, is_attenuated: bool) -> Self {
+    pub fn new(albedo: &Color, p: Float, is_attenuated: bool) -> Self {
         assert!((0.0..=1.0).contains(&p));
 
         let albedo = if is_attenuated {
@@ -86,23 +154,82 @@ impl Material for LambertianRandom {
     }
 }
 
-/// Metallic material.
+/// Isotropic phase function for participating media. Rays scatter uniformly
+/// in all directions.
 #[derive(Debug, Clone)]
+pub struct Isotropic {
+    /// Single-scattering albedo.
+    albedo: Color,
+}
+
+impl Isotropic {
+    /// Create an isotropic phase function.
+    pub fn new(albedo: &Color) -> Self {
+        Self { albedo: *albedo }
+    }
+}
+
+impl Material for Isotropic {
+    #[allow(unused)]
+    fn scatter(&self, ray: &Ray, rec: &HitRecord) -> Option<(Ray, Color)> {
+        Some((Ray::new(rec.p, Vec3::random_unit()), self.albedo))
+    }
+
+    #[allow(unused)]
+    fn eval(&self, ray: &Ray, rec: &HitRecord, direction: &Vec3) -> Option<Color> {
+        Some(self.albedo / (4.0 * PI) as f32)
+    }
+
+    #[allow(unused)]
+    fn pdf(&self, ray: &Ray, rec: &HitRecord, direction: &Vec3) -> Float {
+        1.0 / (4.0 * PI)
+    }
+
+    fn validate(&self) -> Vec<Diagnostic> {
+        diagnostic::check_albedo(&self.albedo).into_iter().collect()
+    }
+}
+
+/// Metallic material.
+#[derive(Clone)]
 pub struct Metallic {
     /// Fractional reflectance color.
     albedo: Color,
 
     /// Fuzz radius. Specifies a sphere around a perfect reflected ray
     /// in which the actual reflected ray can be generated.
-    fuzz: f64,
+    fuzz: Float,
+
+    /// Texture varying the fuzz radius over the surface, if any.
+    fuzz_texture: Option<Arc<dyn Texture>>,
 }
 
 impl Metallic {
     // Creates a new metallic material.
-    pub fn new(albedo: &Color, fuzz: f64) -> Self {
+    pub fn new(albedo: &Color, fuzz: Float) -> Self {
         Metallic {
             albedo: *albedo,
-            fuzz: f64::max(fuzz, 1.0),
+            fuzz: Float::max(fuzz, 1.0),
+            fuzz_texture: None,
+        }
+    }
+
+    /// Varies the fuzz radius over the surface by a texture, e.g. noise for
+    /// worn or smudged patches. The mean of its channels, clamped to
+    /// `[0, 1]`, replaces the constant fuzz.
+    pub fn with_fuzz_texture(mut self, fuzz: Arc<dyn Texture>) -> Self {
+        self.fuzz_texture = Some(fuzz);
+        self
+    }
+
+    /// Fuzz radius at a hit.
+    fn fuzz(&self, rec: &HitRecord) -> Float {
+        match &self.fuzz_texture {
+            Some(texture) => {
+                let fuzz = texture.value(rec.u, rec.v, &rec.p, &rec.normal);
+                (((fuzz.r() + fuzz.g() + fuzz.b()) / 3.0) as Float).clamp(0.0, 1.0)
+            }
+            None => self.fuzz,
         }
     }
 }
@@ -112,7 +239,7 @@ impl Material for Metallic {
         let reflected = Vec3::reflect(ray.direction(), &rec.normal);
 
         // Fuzz the reflected ray within a fuzz sphere.
-        let reflected = reflected.unit() + (self.fuzz * Vec3::random_unit());
+        let reflected = reflected.unit() + (self.fuzz(rec) * Vec3::random_unit());
 
         let scattered = Ray::new(rec.p, reflected);
 
@@ -123,41 +250,400 @@ impl Material for Metallic {
             None
         }
     }
+
+    fn roughness(&self) -> Float {
+        // Textured fuzz may vanish anywhere, so report the smoothest it can be.
+        match self.fuzz_texture {
+            Some(_) => 0.0,
+            None => self.fuzz.min(1.0),
+        }
+    }
+
+    fn validate(&self) -> Vec<Diagnostic> {
+        diagnostic::check_albedo(&self.albedo).into_iter().collect()
+    }
+}
+
+/// Conductor material with a complex refractive index per RGB channel.
+#[derive(Debug, Clone)]
+pub struct Conductor {
+    /// Real part of the refractive index, sampled at red, green, and blue wavelengths.
+    eta: [Float; 3],
+
+    /// Imaginary part of the refractive index (absorption coefficient).
+    k: [Float; 3],
+
+    /// Fuzz radius. Specifies a sphere around a perfect reflected ray
+    /// in which the actual reflected ray can be generated.
+    fuzz: Float,
+}
+
+impl Conductor {
+    /// Creates a new conductor from the complex refractive index `eta + ik`.
+    pub fn new(eta: [Float; 3], k: [Float; 3], fuzz: Float) -> Self {
+        Self {
+            eta,
+            k,
+            fuzz: fuzz.clamp(0.0, 1.0),
+        }
+    }
+
+    /// Creates a gold conductor.
+    pub fn gold(fuzz: Float) -> Self {
+        Self::new([0.143, 0.374, 1.442], [3.983, 2.385, 1.603], fuzz)
+    }
+
+    /// Creates a copper conductor.
+    pub fn copper(fuzz: Float) -> Self {
+        Self::new([0.200, 0.924, 1.102], [3.912, 2.452, 2.142], fuzz)
+    }
+
+    /// Creates an aluminum conductor.
+    pub fn aluminum(fuzz: Float) -> Self {
+        Self::new([1.657, 0.880, 0.521], [9.224, 6.270, 4.837], fuzz)
+    }
+
+    /// Creates a silver conductor.
+    pub fn silver(fuzz: Float) -> Self {
+        Self::new([0.155, 0.117, 0.138], [4.828, 3.122, 2.147], fuzz)
+    }
+
+    /// Compute the unpolarized Fresnel reflectance of a conductor.
+    /// `cosine` should be the dot of a vector and a surface normal, both normalized.
+    pub fn reflectance_fresnel(cosine: Float, eta: Float, k: Float) -> Float {
+        let cos2 = cosine * cosine;
+        let sin2 = 1.0 - cos2;
+        let eta2 = eta * eta;
+        let k2 = k * k;
+
+        let t0 = eta2 - k2 - sin2;
+        let a2b2 = Float::sqrt(t0 * t0 + 4.0 * eta2 * k2);
+        let t1 = a2b2 + cos2;
+        let a = Float::sqrt(0.5 * (a2b2 + t0));
+        let t2 = 2.0 * cosine * a;
+        let rs = (t1 - t2) / (t1 + t2);
+
+        let t3 = cos2 * a2b2 + sin2 * sin2;
+        let t4 = t2 * sin2;
+        let rp = rs * (t3 - t4) / (t3 + t4);
+
+        0.5 * (rp + rs)
+    }
+
+    /// Reflectance color at the given incident cosine.
+    pub fn reflectance(&self, cosine: Float) -> Color {
+        let channel = |i: usize| Self::reflectance_fresnel(cosine, self.eta[i], self.k[i]) as f32;
+        Color::new(channel(0), channel(1), channel(2))
+    }
+}
+
+impl Material for Conductor {
+    fn scatter(&self, ray: &Ray, rec: &HitRecord) -> Option<(Ray, Color)> {
+        let unit_direction = ray.direction().unit();
+        let cos_theta = Float::min(Vec3::dot(&-unit_direction, &rec.normal), 1.0);
+
+        let reflected = Vec3::reflect(&unit_direction, &rec.normal);
+
+        // Fuzz the reflected ray within a fuzz sphere.
+        let reflected = reflected + (self.fuzz * Vec3::random_unit());
+
+        let scattered = Ray::new(rec.p, reflected);
+
+        // If the scattered ray would return back to the surface, just absorb it.
+        if Vec3::dot(scattered.direction(), &rec.normal) > 0.0 {
+            Some((scattered, self.reflectance(cos_theta)))
+        } else {
+            None
+        }
+    }
+
+    fn roughness(&self) -> Float {
+        self.fuzz
+    }
+}
+
+/// Thin translucent sheet material. Light is diffusely reflected or
+/// diffusely transmitted to the opposite side of the surface.
+#[derive(Debug, Clone)]
+pub struct Translucent {
+    /// Tint of light scattered at the front (exterior) side.
+    front: Color,
+
+    /// Tint of light scattered at the back (interior) side.
+    back: Color,
+
+    /// Probability of transmitting through the sheet rather than reflecting.
+    transmission: Float,
+}
+
+impl Translucent {
+    /// Creates a new translucent material. `transmission` is clamped to `[0, 1]`.
+    pub fn new(front: &Color, back: &Color, transmission: Float) -> Self {
+        Self {
+            front: *front,
+            back: *back,
+            transmission: transmission.clamp(0.0, 1.0),
+        }
+    }
+}
+
+impl Material for Translucent {
+    #[allow(unused)]
+    fn scatter(&self, ray: &Ray, rec: &HitRecord) -> Option<(Ray, Color)> {
+        let tint = if rec.orientation == Orientation::Exterior {
+            self.front
+        } else {
+            self.back
+        };
+
+        // Transmitted light scatters into the hemisphere behind the surface.
+        let normal = if random::gen_unit() < self.transmission {
+            -rec.normal
+        } else {
+            rec.normal
+        };
+
+        let scatter_direction = normal + Vec3::random_unit();
+
+        // Use the surface normal if the generated ray is degenerate.
+        if !scatter_direction.almost_zero() {
+            Some((Ray::new(rec.p, scatter_direction), tint))
+        } else {
+            Some((Ray::new(rec.p, normal), tint))
+        }
+    }
+}
+
+/// Thin-film coated dielectric. Reflectance is computed per RGB wavelength
+/// from interference between the two film interfaces, producing iridescence.
+/// With a substrate index of 1 the film behaves as a free-standing soap bubble.
+#[derive(Debug, Clone)]
+pub struct ThinFilm {
+    /// Film thickness in nanometers.
+    thickness: Float,
+
+    /// Refractive index of the film.
+    film_index: Float,
+
+    /// Refractive index of the coated substrate.
+    substrate_index: Float,
+}
+
+impl ThinFilm {
+    /// Creates a new thin-film material.
+    pub fn new(thickness: Float, film_index: Float, substrate_index: Float) -> Self {
+        Self {
+            thickness,
+            film_index,
+            substrate_index,
+        }
+    }
+
+    /// Creates a free-standing film, such as a soap bubble.
+    pub fn bubble(thickness: Float, film_index: Float) -> Self {
+        Self::new(thickness, film_index, 1.0)
+    }
+
+    /// Compute the unpolarized reflectance of a film of index `n1` and thickness
+    /// `thickness` (nanometers) between media of index `n0` (incident) and `n2`,
+    /// at the given wavelength (nanometers). `cosine` is the incident cosine.
+    pub fn reflectance_airy(
+        cosine: Float,
+        wavelength: Float,
+        thickness: Float,
+        n0: Float,
+        n1: Float,
+        n2: Float,
+    ) -> Float {
+        let sin0 = Float::sqrt(Float::max(0.0, 1.0 - cosine * cosine));
+
+        // Snell's law through each layer. Total internal reflection reflects everything.
+        let sin1 = n0 / n1 * sin0;
+        let sin2 = n0 / n2 * sin0;
+        if sin1 >= 1.0 || sin2 >= 1.0 {
+            return 1.0;
+        }
+        let cos1 = Float::sqrt(1.0 - sin1 * sin1);
+        let cos2 = Float::sqrt(1.0 - sin2 * sin2);
+
+        // Fresnel amplitude coefficients for both polarizations.
+        let rs =
+            |ni: Float, ci: Float, nt: Float, ct: Float| (ni * ci - nt * ct) / (ni * ci + nt * ct);
+        let rp =
+            |ni: Float, ci: Float, nt: Float, ct: Float| (nt * ci - ni * ct) / (nt * ci + ni * ct);
+
+        // Phase difference accumulated by a round trip through the film.
+        let delta = 4.0 * crate::consts::PI * n1 * thickness * cos1 / wavelength;
+        let cos_delta = Float::cos(delta);
+
+        let airy = |r01: Float, r12: Float| {
+            let num = r01 * r01 + r12 * r12 + 2.0 * r01 * r12 * cos_delta;
+            let den = 1.0 + r01 * r01 * r12 * r12 + 2.0 * r01 * r12 * cos_delta;
+            num / den
+        };
+
+        let r_s = airy(rs(n0, cosine, n1, cos1), rs(n1, cos1, n2, cos2));
+        let r_p = airy(rp(n0, cosine, n1, cos1), rp(n1, cos1, n2, cos2));
+
+        0.5 * (r_s + r_p)
+    }
+}
+
+impl Material for ThinFilm {
+    fn scatter(&self, ray: &Ray, rec: &HitRecord) -> Option<(Ray, Color)> {
+        let (n0, n2) = if rec.orientation == Orientation::Exterior {
+            (1.0, self.substrate_index)
+        } else {
+            (self.substrate_index, 1.0)
+        };
+
+        let unit_direction = ray.direction().unit();
+        let cos_theta = Float::min(Vec3::dot(&-unit_direction, &rec.normal), 1.0);
+
+        let channel = |i: usize| {
+            let lambda = RGB_WAVELENGTHS[i];
+            Self::reflectance_airy(cos_theta, lambda, self.thickness, n0, self.film_index, n2)
+        };
+        let reflectance = Color::new(channel(0) as f32, channel(1) as f32, channel(2) as f32);
+
+        // Choose between reflection and transmission by the average reflectance,
+        // then reweight each channel by its own probability.
+        let p = (reflectance.r() + reflectance.g() + reflectance.b()) as Float / 3.0;
+
+        if p > 0.0 && random::gen_unit() < p {
+            let direction = Vec3::reflect(&unit_direction, &rec.normal);
+            Some((Ray::new(rec.p, direction), reflectance / p as f32))
+        } else {
+            let direction = Vec3::refract(&unit_direction, &rec.normal, n0 / n2);
+            let transmittance = Color::new(1.0, 1.0, 1.0) - reflectance;
+            Some((Ray::new(rec.p, direction), transmittance / (1.0 - p) as f32))
+        }
+    }
+
+    fn roughness(&self) -> Float {
+        0.0
+    }
+}
+
+/// Wavelength dependence of a refractive index.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Dispersion {
+    /// Cauchy's equation `n = a + b / λ²`, with λ in micrometers.
+    Cauchy { a: Float, b: Float },
+
+    /// Sellmeier equation `n² = 1 + Σ bᵢλ² / (λ² - cᵢ)`, with λ in micrometers
+    /// and `cᵢ` in square micrometers.
+    Sellmeier { b: [Float; 3], c: [Float; 3] },
+}
+
+impl Dispersion {
+    /// Schott N-BK7 borosilicate crown glass.
+    pub const BK7: Self = Self::Sellmeier {
+        b: [1.03961212, 0.231792344, 1.01046945],
+        c: [0.00600069867, 0.0200179144, 103.560653],
+    };
+
+    /// Diamond.
+    pub const DIAMOND: Self = Self::Sellmeier {
+        b: [4.3356, 0.3306, 0.0],
+        c: [0.01124, 0.030625, 0.0],
+    };
+
+    /// Wavelength of the sodium D line in nanometers, the conventional
+    /// reference for quoting a single refractive index.
+    pub const SODIUM_D_NM: Float = 589.3;
+
+    /// Refractive index at the given wavelength in nanometers.
+    pub fn refractive_index(&self, wavelength: Float) -> Float {
+        let l = wavelength * 1e-3;
+        let l2 = l * l;
+        match *self {
+            Self::Cauchy { a, b } => a + b / l2,
+            Self::Sellmeier { b, c } => {
+                let n2 = 1.0 + (0..3).map(|i| b[i] * l2 / (l2 - c[i])).sum::<Float>();
+                Float::sqrt(n2)
+            }
+        }
+    }
 }
 
 /// Dielectric material.
 #[derive(Debug, Clone)]
 pub struct Dielectric {
     /// Refractive index in a vacuum.
-    refractive_index: f64,
+    refractive_index: Float,
+
+    /// Optional wavelength dependence of the refractive index.
+    dispersion: Option<Dispersion>,
 }
 
 impl Dielectric {
     /// Creates a new dielectric material.
-    pub fn new(refractive_index: f64) -> Self {
-        Self { refractive_index }
+    pub fn new(refractive_index: Float) -> Self {
+        Self {
+            refractive_index,
+            dispersion: None,
+        }
+    }
+
+    /// Creates a new dispersive dielectric material. The first dispersive
+    /// event on a path picks a random color channel, which the path then
+    /// carries alone and refracts at its own wavelength at every interface,
+    /// so white light splits into its components.
+    pub fn dispersive(dispersion: Dispersion) -> Self {
+        Self {
+            refractive_index: dispersion.refractive_index(Dispersion::SODIUM_D_NM),
+            dispersion: Some(dispersion),
+        }
     }
 
     /// Compute reflectance using Schlick approximation.
     /// `cosine` should be the dot of a vector and a surface normal, both normalized.
-    pub fn reflectance_schlick(cosine: f64, refractive_index: f64) -> f64 {
+    pub fn reflectance_schlick(cosine: Float, refractive_index: Float) -> Float {
         let r0 = (1.0 - refractive_index) / (1.0 + refractive_index);
         let r0 = r0 * r0;
-        r0 + (1.0 - r0) * f64::powi(1.0 - cosine, 5)
+        r0 + (1.0 - r0) * Float::powi(1.0 - cosine, 5)
+    }
+
+    /// Picks the refractive index, channel weight and carried channel for a
+    /// scattering event on a path carrying `channel`.
+    fn sample_index(&self, channel: Option<usize>) -> (Float, Color, Option<usize>) {
+        let Some(dispersion) = self.dispersion else {
+            return (self.refractive_index, Color::new(1.0, 1.0, 1.0), channel);
+        };
+
+        // Paths pick a channel at their first dispersive event and carry only
+        // it from then on, weighted once by the inverse of its selection
+        // probability.
+        let (channel, weight) = match channel {
+            Some(channel) => (channel, 1.0),
+            None => (usize::min((random::gen_unit() * 3.0) as usize, 2), 3.0),
+        };
+        let mut attenuation = Color::new(0.0, 0.0, 0.0);
+        attenuation[channel] = weight;
+
+        let wavelength = RGB_WAVELENGTHS[channel];
+        (
+            dispersion.refractive_index(wavelength),
+            attenuation,
+            Some(channel),
+        )
     }
 }
 
 impl Material for Dielectric {
     fn scatter(&self, ray: &Ray, rec: &HitRecord) -> Option<(Ray, Color)> {
+        let (refractive_index, attenuation, channel) = self.sample_index(ray.channel());
+
         let ri = if rec.orientation == Orientation::Exterior {
-            1.0 / self.refractive_index
+            1.0 / refractive_index
         } else {
-            self.refractive_index
+            refractive_index
         };
 
         let unit_direction = ray.direction().unit();
-        let cos_theta = f64::min(Vec3::dot(&-unit_direction, &rec.normal), 1.0);
-        let sin_theta = f64::sqrt(1.0 - cos_theta * cos_theta);
+        let cos_theta = Float::min(Vec3::dot(&-unit_direction, &rec.normal), 1.0);
+        let sin_theta = Float::sqrt(1.0 - cos_theta * cos_theta);
 
         let total_internal_reflection = ri * sin_theta > 1.0;
 
@@ -170,10 +656,253 @@ impl Material for Dielectric {
             Vec3::refract(&unit_direction, &rec.normal, ri)
         };
 
+        let scattered = Ray::new(rec.p, direction).with_channel(channel);
+        Some((scattered, attenuation))
+    }
+
+    fn roughness(&self) -> Float {
+        0.0
+    }
+}
+
+/// Rough dielectric material. Reflection and refraction occur about microfacet
+/// normals drawn from a GGX distribution, giving frosted or ground glass.
+#[derive(Debug, Clone)]
+pub struct RoughDielectric {
+    /// Refractive index in a vacuum.
+    refractive_index: Float,
+
+    /// GGX width parameter, the square of the perceptual roughness.
+    alpha: Float,
+}
+
+impl RoughDielectric {
+    /// Creates a new rough dielectric material. `roughness` is clamped to `[0, 1]`.
+    pub fn new(refractive_index: Float, roughness: Float) -> Self {
+        let roughness = roughness.clamp(0.0, 1.0);
+        Self {
+            refractive_index,
+            alpha: roughness * roughness,
+        }
+    }
+
+    /// Samples a microfacet normal around `normal` proportionally to `D(m) cos(m)`.
+    fn sample_microfacet(&self, normal: &Vec3) -> Vec3 {
+        let r1 = random::gen_unit();
+        let r2 = random::gen_unit();
+
+        let tan_theta = self.alpha * Float::sqrt(r1 / (1.0 - r1));
+        let cos_theta = 1.0 / Float::sqrt(1.0 + tan_theta * tan_theta);
+        let sin_theta = tan_theta * cos_theta;
+        let phi = 2.0 * crate::consts::PI * r2;
+
+        let local = Vec3::new(
+            sin_theta * Float::cos(phi),
+            sin_theta * Float::sin(phi),
+            cos_theta,
+        );
+        Onb::from_normal(normal).to_world(&local)
+    }
+
+    /// Smith masking function for the GGX distribution. `cosine` is the cosine
+    /// between a direction and the macrosurface normal.
+    pub fn smith_g1(cosine: Float, alpha: Float) -> Float {
+        let cos2 = cosine * cosine;
+        if cos2 <= 0.0 {
+            return 0.0;
+        }
+        let tan2 = (1.0 - cos2) / cos2;
+        2.0 / (1.0 + Float::sqrt(1.0 + alpha * alpha * tan2))
+    }
+}
+
+impl Material for RoughDielectric {
+    fn scatter(&self, ray: &Ray, rec: &HitRecord) -> Option<(Ray, Color)> {
+        let ri = if rec.orientation == Orientation::Exterior {
+            1.0 / self.refractive_index
+        } else {
+            self.refractive_index
+        };
+
+        let unit_direction = ray.direction().unit();
+        let m = self.sample_microfacet(&rec.normal);
+
+        let cos_theta = Float::min(Vec3::dot(&-unit_direction, &m), 1.0);
+        if cos_theta <= 0.0 {
+            return None;
+        }
+        let sin_theta = Float::sqrt(1.0 - cos_theta * cos_theta);
+
+        let total_internal_reflection = ri * sin_theta > 1.0;
+
+        let schlick = Dielectric::reflectance_schlick(cos_theta, ri);
+        let reflect_schlick = schlick > random::gen_unit();
+
+        let (direction, is_reflection) = if total_internal_reflection || reflect_schlick {
+            (Vec3::reflect(&unit_direction, &m), true)
+        } else {
+            (Vec3::refract(&unit_direction, &m, ri), false)
+        };
+
+        // Reject directions that end up on the wrong side of the macrosurface.
+        let cos_out = Vec3::dot(&direction.unit(), &rec.normal);
+        if (is_reflection && cos_out <= 0.0) || (!is_reflection && cos_out >= 0.0) {
+            return None;
+        }
+
+        // Weight of sampling microfacets by D(m) cos(m) is G |wo.m| / (|wo.n| |m.n|).
+        let cos_in = Vec3::dot(&-unit_direction, &rec.normal);
+        let cos_m = Vec3::dot(&m, &rec.normal);
+        let g = Self::smith_g1(cos_in, self.alpha) * Self::smith_g1(cos_out.abs(), self.alpha);
+        let weight = g * cos_theta / (cos_in * cos_m);
+
         let scattered = Ray::new(rec.p, direction);
-        let attenuation = Color::new(1.0, 1.0, 1.0);
+        let attenuation = Color::new(1.0, 1.0, 1.0) * weight as f32;
         Some((scattered, attenuation))
     }
+
+    fn roughness(&self) -> Float {
+        self.alpha.sqrt()
+    }
+}
+
+/// Alpha-cutout wrapper around another material. Hits where the luminance of
+/// the alpha texture is below the threshold are cut away, so leaves, fences
+/// and decals can be drawn on simple quads.
+#[derive(Clone)]
+pub struct Masked {
+    /// Material of the uncut surface.
+    material: Arc<dyn Material>,
+
+    /// Opacity texture.
+    alpha: Arc<dyn Texture>,
+
+    /// Smallest opacity kept.
+    threshold: f32,
+}
+
+impl Masked {
+    /// Creates a new masked material, keeping hits with an alpha of at least 0.5.
+    pub fn new(material: Arc<dyn Material>, alpha: Arc<dyn Texture>) -> Self {
+        Self {
+            material,
+            alpha,
+            threshold: 0.5,
+        }
+    }
+
+    /// Sets the smallest opacity kept.
+    pub fn with_threshold(mut self, threshold: f32) -> Self {
+        self.threshold = threshold;
+        self
+    }
+}
+
+impl Material for Masked {
+    fn scatter(&self, ray: &Ray, rec: &HitRecord) -> Option<(Ray, Color)> {
+        self.material.scatter(ray, rec)
+    }
+
+    fn roughness(&self) -> Float {
+        self.material.roughness()
+    }
+
+    fn eval(&self, ray: &Ray, rec: &HitRecord, direction: &Vec3) -> Option<Color> {
+        self.material.eval(ray, rec, direction)
+    }
+
+    fn pdf(&self, ray: &Ray, rec: &HitRecord, direction: &Vec3) -> Float {
+        self.material.pdf(ray, rec, direction)
+    }
+
+    fn is_masked(&self, rec: &HitRecord) -> bool {
+        let alpha = self.alpha.value(rec.u, rec.v, &rec.p, &rec.normal);
+        alpha.luminance() < self.threshold || self.material.is_masked(rec)
+    }
+
+    fn validate(&self) -> Vec<Diagnostic> {
+        self.material.validate()
+    }
+}
+
+/// Treatment of the back faces of a surface, i.e. hits on its interior side.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Sidedness {
+    /// Both faces are shaded alike.
+    #[default]
+    TwoSided,
+
+    /// Back faces are hit but reflect nothing, so they appear black.
+    Black,
+
+    /// Back faces are culled at intersection, so rays pass through them.
+    Culled,
+}
+
+/// Wrapper around another material controlling how its back faces are
+/// treated. Surfaces are otherwise shaded from both sides alike, e.g. for
+/// panels meant to be seen or to let light through from one side only.
+#[derive(Clone)]
+pub struct Sided {
+    /// Material of the front face.
+    material: Arc<dyn Material>,
+
+    /// Treatment of the back face.
+    sidedness: Sidedness,
+}
+
+impl Sided {
+    /// Creates a new material with the given back face treatment.
+    pub fn new(material: Arc<dyn Material>, sidedness: Sidedness) -> Self {
+        Self {
+            material,
+            sidedness,
+        }
+    }
+
+    /// Retrieves the treatment of the back face.
+    pub fn sidedness(&self) -> Sidedness {
+        self.sidedness
+    }
+
+    /// Determines whether a hit is on a back face that reflects nothing.
+    fn is_black(&self, rec: &HitRecord) -> bool {
+        self.sidedness == Sidedness::Black && rec.orientation == Orientation::Interior
+    }
+}
+
+impl Material for Sided {
+    fn scatter(&self, ray: &Ray, rec: &HitRecord) -> Option<(Ray, Color)> {
+        if self.is_black(rec) {
+            return None;
+        }
+        self.material.scatter(ray, rec)
+    }
+
+    fn roughness(&self) -> Float {
+        self.material.roughness()
+    }
+
+    fn eval(&self, ray: &Ray, rec: &HitRecord, direction: &Vec3) -> Option<Color> {
+        if self.is_black(rec) {
+            return Some(Color::new(0.0, 0.0, 0.0));
+        }
+        self.material.eval(ray, rec, direction)
+    }
+
+    fn pdf(&self, ray: &Ray, rec: &HitRecord, direction: &Vec3) -> Float {
+        self.material.pdf(ray, rec, direction)
+    }
+
+    fn is_masked(&self, rec: &HitRecord) -> bool {
+        let culled =
+            self.sidedness == Sidedness::Culled && rec.orientation == Orientation::Interior;
+        culled || self.material.is_masked(rec)
+    }
+
+    fn validate(&self) -> Vec<Diagnostic> {
+        self.material.validate()
+    }
 }
 
 /// Normal map with Lambertian scattering.
@@ -206,3 +935,344 @@ impl Material for NormalMap {
         }
     }
 }
+
+/// Closed set of the common materials, dispatched with a `match` rather than
+/// through a vtable so that the calls can be inlined. Materials outside the
+/// set, such as wrappers like `Masked` and `Sided`, fall back to `Dynamic`.
+#[derive(Clone)]
+pub enum MaterialKind {
+    Lambertian(Lambertian),
+    LambertianRandom(LambertianRandom),
+    Isotropic(Isotropic),
+    Metallic(Metallic),
+    Conductor(Conductor),
+    Translucent(Translucent),
+    ThinFilm(ThinFilm),
+    Dielectric(Dielectric),
+    RoughDielectric(RoughDielectric),
+    Dynamic(Arc<dyn Material>),
+}
+
+/// Applies `$f` to the material inside a `MaterialKind`, bound as `$m`.
+macro_rules! dispatch {
+    ($kind:expr, $m:ident => $f:expr) => {
+        match $kind {
+            MaterialKind::Lambertian($m) => $f,
+            MaterialKind::LambertianRandom($m) => $f,
+            MaterialKind::Isotropic($m) => $f,
+            MaterialKind::Metallic($m) => $f,
+            MaterialKind::Conductor($m) => $f,
+            MaterialKind::Translucent($m) => $f,
+            MaterialKind::ThinFilm($m) => $f,
+            MaterialKind::Dielectric($m) => $f,
+            MaterialKind::RoughDielectric($m) => $f,
+            MaterialKind::Dynamic($m) => $f,
+        }
+    };
+}
+
+impl Material for MaterialKind {
+    fn scatter(&self, ray: &Ray, rec: &HitRecord) -> Option<(Ray, Color)> {
+        dispatch!(self, m => m.scatter(ray, rec))
+    }
+
+    fn roughness(&self) -> Float {
+        dispatch!(self, m => m.roughness())
+    }
+
+    fn eval(&self, ray: &Ray, rec: &HitRecord, direction: &Vec3) -> Option<Color> {
+        dispatch!(self, m => m.eval(ray, rec, direction))
+    }
+
+    fn pdf(&self, ray: &Ray, rec: &HitRecord, direction: &Vec3) -> Float {
+        dispatch!(self, m => m.pdf(ray, rec, direction))
+    }
+
+    fn is_masked(&self, rec: &HitRecord) -> bool {
+        dispatch!(self, m => m.is_masked(rec))
+    }
+
+    fn validate(&self) -> Vec<Diagnostic> {
+        dispatch!(self, m => m.validate())
+    }
+}
+
+/// Implements `From` for each concrete variant of `MaterialKind`.
+macro_rules! material_kind_from {
+    ($($variant:ident),*) => {
+        $(
+            impl From<$variant> for MaterialKind {
+                fn from(material: $variant) -> Self {
+                    MaterialKind::$variant(material)
+                }
+            }
+        )*
+    };
+}
+
+material_kind_from!(
+    Lambertian,
+    LambertianRandom,
+    Isotropic,
+    Metallic,
+    Conductor,
+    Translucent,
+    ThinFilm,
+    Dielectric,
+    RoughDielectric
+);
+
+impl From<Arc<dyn Material>> for MaterialKind {
+    fn from(material: Arc<dyn Material>) -> Self {
+        MaterialKind::Dynamic(material)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::almost::AlmostPartialEq;
+    use crate::hittable::Hittable;
+    use crate::sphere::Sphere;
+    use crate::triangle::Triangle;
+    use crate::{Interval, Point3};
+
+    /// Opaque below the z = 0 plane, transparent above.
+    struct LowerHalf;
+
+    impl Texture for LowerHalf {
+        #[allow(unused)]
+        fn value(&self, u: Float, v: Float, p: &Point3, normal: &Vec3) -> Color {
+            if p.z() < 0.0 {
+                Color::new(1.0, 1.0, 1.0)
+            } else {
+                Color::new(0.0, 0.0, 0.0)
+            }
+        }
+    }
+
+    #[test]
+    fn metallic_fuzz_texture() {
+        let metal =
+            Metallic::new(&Color::new(0.8, 0.8, 0.8), 0.0).with_fuzz_texture(Arc::new(LowerHalf));
+        let normal = Vec3::new(0.0, 1.0, 0.0);
+        let direction = Vec3::new(1.0, -1.0, 0.0);
+        let mirrored = Vec3::reflect(&direction, &normal).unit();
+        assert_eq!(metal.roughness(), 0.0);
+
+        // Polished where the texture is black, fully fuzzed where it is white.
+        let mut fuzzed = 0;
+        for _ in 0..50 {
+            for z in [1.0, -1.0] {
+                let p = Point3::new(0.0, 0.0, z);
+                let ray = Ray::new(p - direction, direction);
+                let rec = HitRecord::new(&p, &normal, 1.0, &ray, &metal);
+                let Some((scattered, _)) = metal.scatter(&ray, &rec) else {
+                    continue;
+                };
+                let sharp = scattered.direction().almost_eq(&mirrored);
+                assert!(z < 0.0 || sharp);
+                fuzzed += (!sharp) as usize;
+            }
+        }
+        assert!(fuzzed > 0);
+    }
+
+    #[test]
+    fn masked_cutout() {
+        let gray: Arc<dyn Material> = Arc::new(Lambertian::new(&Color::new(0.5, 0.5, 0.5)));
+        let faint = Arc::new(SolidColor::new(&Color::new(0.2, 0.2, 0.2)));
+        let bounds = Interval::new(0.001, Float::INFINITY);
+        let triangle = |material: Masked| {
+            Triangle::new(
+                Point3::new(0.0, 0.0, 0.0),
+                Point3::new(1.0, 0.0, 0.0),
+                Point3::new(0.0, 1.0, 0.0),
+                Arc::new(material),
+            )
+        };
+
+        // A triangle with alpha below the threshold neither hits nor occludes.
+        let tri = triangle(Masked::new(gray.clone(), faint.clone()));
+        let ray = Ray::new(Point3::new(0.25, 0.25, 1.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(tri.hit(&ray, &bounds).is_none());
+        assert!(!tri.is_occluded(&ray, &bounds));
+
+        // Lowering the threshold keeps it.
+        let tri = triangle(Masked::new(gray.clone(), faint).with_threshold(0.1));
+        assert!(tri.hit(&ray, &bounds).is_some());
+        assert!(tri.is_occluded(&ray, &bounds));
+
+        // Rays pass through the cut-away front of a sphere to reach its back.
+        let material = Arc::new(Masked::new(gray, Arc::new(LowerHalf)));
+        let sphere = Sphere::new(Point3::new(0.0, 0.0, 0.0), 1.0, material);
+        let ray = Ray::new(Point3::new(0.0, 0.0, 2.0), Vec3::new(0.0, 0.0, -1.0));
+        assert_eq!(sphere.hit(&ray, &bounds).unwrap().t(), 3.0);
+        assert!(sphere.is_occluded(&ray, &bounds));
+        assert!(!sphere.is_occluded(&ray, &Interval::new(0.001, 2.5)));
+    }
+
+    #[test]
+    fn sided_back_faces() {
+        let gray: Arc<dyn Material> = Arc::new(Lambertian::new(&Color::new(0.5, 0.5, 0.5)));
+        let bounds = Interval::new(0.001, Float::INFINITY);
+        let triangle = |sidedness| {
+            Triangle::new(
+                Point3::new(0.0, 0.0, 0.0),
+                Point3::new(1.0, 0.0, 0.0),
+                Point3::new(0.0, 1.0, 0.0),
+                Arc::new(Sided::new(gray.clone(), sidedness)),
+            )
+        };
+        let front = Ray::new(Point3::new(0.25, 0.25, 1.0), Vec3::new(0.0, 0.0, -1.0));
+        let back = Ray::new(Point3::new(0.25, 0.25, -1.0), Vec3::new(0.0, 0.0, 1.0));
+        let up = Vec3::new(0.0, 0.0, 1.0);
+
+        // Two-sided surfaces shade both faces.
+        let tri = triangle(Sidedness::TwoSided);
+        let rec = tri.hit(&back, &bounds).unwrap();
+        assert!(rec.material.scatter(&back, &rec).is_some());
+
+        // Black back faces are still hit, but reflect nothing.
+        let tri = triangle(Sidedness::Black);
+        let rec = tri.hit(&front, &bounds).unwrap();
+        assert!(rec.material.scatter(&front, &rec).is_some());
+        let rec = tri.hit(&back, &bounds).unwrap();
+        assert!(rec.material.scatter(&back, &rec).is_none());
+        assert_eq!(
+            rec.material.eval(&back, &rec, &-up),
+            Some(Color::new(0.0, 0.0, 0.0))
+        );
+
+        // Culled back faces are not hit at all.
+        let tri = triangle(Sidedness::Culled);
+        assert!(tri.hit(&front, &bounds).is_some());
+        assert!(tri.hit(&back, &bounds).is_none());
+        assert!(!tri.is_occluded(&back, &bounds));
+    }
+
+    #[test]
+    fn material_kind_dispatch() {
+        let glass = Dielectric::new(1.5);
+        let kind = MaterialKind::from(glass.clone());
+        assert!(matches!(kind, MaterialKind::Dielectric(_)));
+        assert_eq!(kind.roughness(), glass.roughness());
+
+        // Materials outside of the set keep their behavior behind `Dynamic`.
+        let masked: Arc<dyn Material> = Arc::new(Masked::new(
+            Arc::new(Lambertian::new(&Color::new(0.5, 0.5, 0.5))),
+            Arc::new(LowerHalf),
+        ));
+        let kind = MaterialKind::from(masked);
+        let sphere = Sphere::new(Point3::new(0.0, 0.0, 0.0), 1.0, Arc::new(kind));
+        let bounds = Interval::new(0.001, Float::INFINITY);
+        let down = Ray::new(Point3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        let rec = sphere.hit(&down, &bounds).unwrap();
+        assert!(rec.p.z() < 0.0);
+
+        let diffuse = MaterialKind::from(Lambertian::new(&Color::new(0.5, 0.5, 0.5)));
+        let up = Vec3::new(0.0, 0.0, 1.0);
+        assert_eq!(
+            diffuse.eval(&down, &rec, &up),
+            Lambertian::new(&Color::new(0.5, 0.5, 0.5)).eval(&down, &rec, &up)
+        );
+    }
+
+    #[test]
+    fn conductor_fresnel() {
+        // A perfect conductor with no absorption and unit index reflects nothing at normal incidence.
+        assert!(Conductor::reflectance_fresnel(1.0, 1.0, 0.0).almost_zero());
+
+        // Reflectance approaches 1 at grazing angles.
+        assert!(Conductor::reflectance_fresnel(1e-6, 0.2, 3.9) > 0.99);
+
+        // At normal incidence, the conductor equation reduces to ((n-1)^2 + k^2) / ((n+1)^2 + k^2).
+        let (n, k) = (0.2, 3.9);
+        let expected = ((n - 1.0) * (n - 1.0) + k * k) / ((n + 1.0) * (n + 1.0) + k * k);
+        assert!((Conductor::reflectance_fresnel(1.0, n, k) - expected).almost_zero());
+    }
+
+    #[test]
+    fn conductor_presets() {
+        // Gold and copper reflect red more strongly than blue.
+        let gold = Conductor::gold(0.0).reflectance(1.0);
+        assert!(gold.r() > gold.b());
+        let copper = Conductor::copper(0.0).reflectance(1.0);
+        assert!(copper.r() > copper.g());
+
+        // Silver and aluminum are close to neutral and highly reflective.
+        let silver = Conductor::silver(0.0).reflectance(1.0);
+        assert!(silver.r() > 0.9 && silver.g() > 0.9 && silver.b() > 0.85);
+        let aluminum = Conductor::aluminum(0.0).reflectance(1.0);
+        assert!(aluminum.r() > 0.85 && aluminum.b() > 0.85);
+    }
+
+    #[test]
+    fn thin_film_airy() {
+        // A film of zero thickness reduces to a single interface between the outer media.
+        let (n0, n2) = (1.0, 1.5);
+        let expected = ((n0 - n2) / (n0 + n2)) * ((n0 - n2) / (n0 + n2));
+        let r = ThinFilm::reflectance_airy(1.0, 550.0, 0.0, n0, 1.33, n2);
+        assert!((r - expected).almost_zero());
+
+        // A quarter-wave film with the geometric-mean index is a perfect antireflection coating.
+        let n1 = Float::sqrt(n0 * n2);
+        let r = ThinFilm::reflectance_airy(1.0, 550.0, 550.0 / (4.0 * n1), n0, n1, n2);
+        assert!(r.almost_zero());
+
+        // Interference makes the reflectance wavelength dependent.
+        let r_red = ThinFilm::reflectance_airy(1.0, 650.0, 250.0, 1.0, 1.33, 1.0);
+        let r_blue = ThinFilm::reflectance_airy(1.0, 450.0, 250.0, 1.0, 1.33, 1.0);
+        assert!((r_red - r_blue).abs() > 1e-3);
+    }
+
+    #[test]
+    fn rough_dielectric_masking() {
+        // No masking at normal incidence or for a perfectly smooth surface.
+        assert!((RoughDielectric::smith_g1(1.0, 0.5) - 1.0).abs() < 1e-12);
+        assert!((RoughDielectric::smith_g1(0.3, 0.0) - 1.0).abs() < 1e-12);
+
+        // Masking increases towards grazing angles and with roughness.
+        assert!(RoughDielectric::smith_g1(0.2, 0.5) < RoughDielectric::smith_g1(0.8, 0.5));
+        assert!(RoughDielectric::smith_g1(0.5, 0.9) < RoughDielectric::smith_g1(0.5, 0.1));
+        assert_eq!(RoughDielectric::smith_g1(0.0, 0.5), 0.0);
+    }
+
+    #[test]
+    fn dispersion_index() {
+        // Reference index of N-BK7 at the helium d line.
+        assert!((Dispersion::BK7.refractive_index(587.6) - 1.5168).abs() < 1e-4);
+        assert!(
+            (Dispersion::DIAMOND.refractive_index(Dispersion::SODIUM_D_NM) - 2.417).abs() < 1e-2
+        );
+
+        // Normal dispersion: shorter wavelengths refract more strongly.
+        let cauchy = Dispersion::Cauchy { a: 1.5, b: 0.004 };
+        assert!(cauchy.refractive_index(450.0) > cauchy.refractive_index(650.0));
+        assert!(Dispersion::BK7.refractive_index(450.0) > Dispersion::BK7.refractive_index(650.0));
+    }
+
+    #[test]
+    fn dispersion_channel() {
+        let glass = Dielectric::dispersive(Dispersion::BK7);
+        let ray = Ray::new(Point3::new(0.0, 0.0, 1.0), Vec3::new(0.3, 0.0, -1.0));
+        let normal = Vec3::new(0.0, 0.0, 1.0);
+        let rec = HitRecord::new(&Point3::new(0.0, 0.0, 0.0), &normal, 1.0, &ray, &glass);
+
+        for _ in 0..20 {
+            // The first interface picks a channel and weights it.
+            let (entered, attenuation) = glass.scatter(&ray, &rec).unwrap();
+            let channel = entered.channel().unwrap();
+            assert_eq!(attenuation[channel], 3.0);
+            assert_eq!(attenuation.r() + attenuation.g() + attenuation.b(), 3.0);
+
+            // Later interfaces keep refracting the same channel.
+            let inside = Ray::new(rec.p, Vec3::new(0.0, 0.0, 1.0)).with_channel(Some(channel));
+            let back = HitRecord::new(&rec.p, &normal, 1.0, &inside, &glass);
+            let (left, attenuation) = glass.scatter(&inside, &back).unwrap();
+            assert_eq!(left.channel(), Some(channel));
+            assert_eq!(attenuation[channel], 1.0);
+            assert_eq!(attenuation.r() + attenuation.g() + attenuation.b(), 1.0);
+        }
+    }
+}
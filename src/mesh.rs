@@ -0,0 +1,312 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::aabb::Aabb;
+use crate::bvh::Bvh;
+use crate::hittable::{HitRecord, Hittable};
+use crate::material::Material;
+use crate::texture::Texture;
+use crate::trace;
+use crate::triangle::Triangle;
+use crate::{Float, Interval, Point3, Ray, Vec3};
+
+/// Normal interpolation mode for mesh surfaces.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shading {
+    /// Each triangle uses its face normal.
+    Flat,
+
+    /// Vertex normals are averaged from adjacent faces and interpolated across triangles.
+    Smooth,
+}
+
+/// Triangle mesh with its own bounding volume hierarchy. Hits are tagged with
+/// the index of the input triangle as the primitive ID.
+pub struct TriangleMesh {
+    /// Hierarchy over the mesh triangles.
+    bvh: Bvh<Triangle>,
+
+    /// Input triangle index of each triangle given to the hierarchy.
+    faces: Vec<usize>,
+}
+
+impl TriangleMesh {
+    /// Creates a new mesh from shared vertex positions and triangles given as
+    /// triples of vertex indices. Degenerate (zero-area) triangles are dropped.
+    pub fn new(vertices: &[Point3], indices: &[[usize; 3]], material: Arc<dyn Material>) -> Self {
+        Self::build(vertices, None, indices, material)
+    }
+
+    /// Creates a new mesh with explicit per-vertex shading normals, indexed
+    /// the same as `vertices`.
+    pub fn with_normals(
+        vertices: &[Point3],
+        normals: &[Vec3],
+        indices: &[[usize; 3]],
+        material: Arc<dyn Material>,
+    ) -> Self {
+        assert_eq!(
+            vertices.len(),
+            normals.len(),
+            "mesh needs one normal per vertex"
+        );
+        Self::build(vertices, Some(normals), indices, material)
+    }
+
+    /// Creates a new mesh with the given shading mode.
+    pub fn with_shading(
+        vertices: &[Point3],
+        indices: &[[usize; 3]],
+        material: Arc<dyn Material>,
+        shading: Shading,
+    ) -> Self {
+        match shading {
+            Shading::Flat => Self::new(vertices, indices, material),
+            Shading::Smooth => {
+                let normals = Self::vertex_normals(vertices, indices);
+                Self::with_normals(vertices, &normals, indices, material)
+            }
+        }
+    }
+
+    /// Computes area-weighted vertex normals from the adjacent faces. Vertices
+    /// at identical positions share a normal even when they are not shared by
+    /// index, so unwelded meshes (e.g. from STL) still shade smoothly.
+    pub fn vertex_normals(vertices: &[Point3], indices: &[[usize; 3]]) -> Vec<Vec3> {
+        let key = |p: &Point3| [p.x().to_bits(), p.y().to_bits(), p.z().to_bits()];
+
+        // The cross product's length is twice the face area, which weights the sum.
+        let mut sums: HashMap<_, Vec3> = HashMap::new();
+        for &[a, b, c] in indices {
+            let n = Vec3::cross(&(vertices[b] - vertices[a]), &(vertices[c] - vertices[a]));
+            for i in [a, b, c] {
+                let sum = sums
+                    .entry(key(&vertices[i]))
+                    .or_insert(Vec3::new(0.0, 0.0, 0.0));
+                *sum += n;
+            }
+        }
+
+        // Zero normals make the triangle fall back to its face normal.
+        vertices
+            .iter()
+            .map(|p| match sums.get(&key(p)) {
+                Some(n) if !n.almost_zero() => n.unit(),
+                _ => Vec3::new(0.0, 0.0, 0.0),
+            })
+            .collect()
+    }
+
+    /// Creates a new smooth-shaded mesh with displaced detail. The triangles are
+    /// subdivided `levels` times, then every vertex is moved along its normal
+    /// by `scale` times the luminance of `height`. Meshes have no surface
+    /// coordinates, so the height is looked up by position, which suits solid
+    /// textures such as noise.
+    pub fn displaced(
+        vertices: &[Point3],
+        indices: &[[usize; 3]],
+        material: Arc<dyn Material>,
+        height: &dyn Texture,
+        scale: Float,
+        levels: u32,
+    ) -> Self {
+        trace::span!(DEBUG, "mesh_displace", faces = indices.len(), levels);
+
+        let (mut vertices, mut indices) = (vertices.to_vec(), indices.to_vec());
+        for _ in 0..levels {
+            (vertices, indices) = Self::subdivide(&vertices, &indices);
+        }
+
+        let normals = Self::vertex_normals(&vertices, &indices);
+        let displaced: Vec<Point3> = vertices
+            .iter()
+            .zip(&normals)
+            .map(|(p, n)| *p + scale * height.value(0.0, 0.0, p, n).luminance() as Float * *n)
+            .collect();
+
+        Self::with_shading(&displaced, &indices, material, Shading::Smooth)
+    }
+
+    /// Splits every triangle into four at its edge midpoints. Triangles that
+    /// share an edge by index share its midpoint, so the result stays welded.
+    pub fn subdivide(
+        vertices: &[Point3],
+        indices: &[[usize; 3]],
+    ) -> (Vec<Point3>, Vec<[usize; 3]>) {
+        let mut vertices = vertices.to_vec();
+        let mut midpoints: HashMap<(usize, usize), usize> = HashMap::new();
+        let mut midpoint = |a: usize, b: usize, vertices: &mut Vec<Point3>| {
+            *midpoints.entry((a.min(b), a.max(b))).or_insert_with(|| {
+                vertices.push(0.5 * (vertices[a] + vertices[b]));
+                vertices.len() - 1
+            })
+        };
+
+        let mut subdivided = Vec::with_capacity(4 * indices.len());
+        for &[a, b, c] in indices {
+            let ab = midpoint(a, b, &mut vertices);
+            let bc = midpoint(b, c, &mut vertices);
+            let ca = midpoint(c, a, &mut vertices);
+            subdivided.extend([[a, ab, ca], [ab, b, bc], [ca, bc, c], [ab, bc, ca]]);
+        }
+
+        (vertices, subdivided)
+    }
+
+    /// Builds the triangles and their hierarchy, dropping degenerate triangles.
+    fn build(
+        vertices: &[Point3],
+        normals: Option<&[Vec3]>,
+        indices: &[[usize; 3]],
+        material: Arc<dyn Material>,
+    ) -> Self {
+        trace::span!(DEBUG, "mesh_build", faces = indices.len());
+
+        let (faces, triangles): (Vec<usize>, Vec<Triangle>) = indices
+            .iter()
+            .enumerate()
+            .map(|(face, &[a, b, c])| {
+                let triangle =
+                    Triangle::new(vertices[a], vertices[b], vertices[c], material.clone());
+                let triangle = match normals {
+                    Some(n) => triangle.with_normals(n[a], n[b], n[c]),
+                    None => triangle,
+                };
+                (face, triangle)
+            })
+            .filter(|(_, triangle)| !triangle.face_normal().almost_zero())
+            .unzip();
+        trace::event!(
+            DEBUG,
+            dropped = indices.len() - faces.len(),
+            "dropped degenerate triangles"
+        );
+
+        Self {
+            bvh: Bvh::new(triangles),
+            faces,
+        }
+    }
+
+    /// Number of triangles in the mesh.
+    pub fn len(&self) -> usize {
+        self.bvh.len()
+    }
+
+    /// Determines whether the mesh has no triangles.
+    pub fn is_empty(&self) -> bool {
+        self.bvh.is_empty()
+    }
+
+    /// Retrieves the mesh triangles.
+    pub fn triangles(&self) -> &[Triangle] {
+        self.bvh.objects()
+    }
+}
+
+impl Hittable for TriangleMesh {
+    fn hit(&self, ray: &Ray, ray_t: &Interval) -> Option<HitRecord> {
+        self.bvh
+            .hit_indexed(ray, ray_t)
+            .map(|(rec, id)| rec.with_primitive_id(self.faces[id]))
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bvh.bounding_box()
+    }
+
+    fn is_occluded(&self, ray: &Ray, ray_t: &Interval) -> bool {
+        self.bvh.is_occluded(ray, ray_t)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::Lambertian;
+    use crate::texture::SolidColor;
+    use crate::Color;
+
+    #[test]
+    fn mesh_quad() {
+        let vertices = [
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(1.0, 0.0, 0.0),
+            Point3::new(1.0, 1.0, 0.0),
+            Point3::new(0.0, 1.0, 0.0),
+        ];
+        let indices = [[0, 0, 1], [0, 1, 2], [0, 2, 3]];
+        let material = Arc::new(Lambertian::new(&Color::new(0.5, 0.5, 0.5)));
+        let mesh = TriangleMesh::new(&vertices, &indices, material);
+
+        // The degenerate triangle is dropped, but primitive IDs still follow the input.
+        assert_eq!(mesh.len(), 2);
+
+        let bounds = Interval::new(0.001, Float::INFINITY);
+        for (x, y, id) in [(0.2, 0.7, 2), (0.7, 0.2, 1)] {
+            let ray = Ray::new(Point3::new(x, y, 1.0), Vec3::new(0.0, 0.0, -1.0));
+            let rec = mesh.hit(&ray, &bounds).unwrap();
+            assert_eq!(rec.t(), 1.0);
+            assert_eq!(rec.primitive_id, Some(id));
+        }
+
+        let ray = Ray::new(Point3::new(1.5, 0.5, 1.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(mesh.hit(&ray, &bounds).is_none());
+    }
+
+    #[test]
+    fn mesh_smooth_normals() {
+        // Unwelded corner of a cube: three faces meeting at the origin, each
+        // with its own copy of the shared vertex.
+        let vertices = [
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(0.0, 1.0, 0.0),
+            Point3::new(1.0, 0.0, 0.0),
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(0.0, 0.0, 1.0),
+            Point3::new(0.0, 1.0, 0.0),
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(1.0, 0.0, 0.0),
+            Point3::new(0.0, 0.0, 1.0),
+        ];
+        let indices = [[0, 1, 2], [3, 4, 5], [6, 7, 8]];
+        let normals = TriangleMesh::vertex_normals(&vertices, &indices);
+
+        let corner = -Vec3::new(1.0, 1.0, 1.0).unit();
+        for i in [0, 3, 6] {
+            assert!(normals[i].almost_eq(&corner));
+        }
+        assert!(normals[1].almost_eq(&-Vec3::new(1.0, 0.0, 1.0).unit()));
+
+        let material = Arc::new(Lambertian::new(&Color::new(0.5, 0.5, 0.5)));
+        let mesh = TriangleMesh::with_shading(&vertices, &indices, material, Shading::Smooth);
+        assert_eq!(mesh.len(), 3);
+        assert!(mesh.triangles().iter().all(|t| t.normals().is_some()));
+    }
+
+    #[test]
+    fn mesh_displaced() {
+        let vertices = [
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(1.0, 0.0, 0.0),
+            Point3::new(1.0, 1.0, 0.0),
+            Point3::new(0.0, 1.0, 0.0),
+        ];
+        let indices = [[0, 1, 2], [0, 2, 3]];
+
+        // The diagonal is shared, so its midpoint is too.
+        let (subdivided, faces) = TriangleMesh::subdivide(&vertices, &indices);
+        assert_eq!((subdivided.len(), faces.len()), (9, 8));
+
+        // A uniform height lifts the whole quad along its normal.
+        let material = Arc::new(Lambertian::new(&Color::new(0.5, 0.5, 0.5)));
+        let height = SolidColor::new(&Color::new(1.0, 1.0, 1.0));
+        let mesh = TriangleMesh::displaced(&vertices, &indices, material, &height, 0.25, 2);
+        assert_eq!(mesh.len(), 32);
+
+        let bounds = Interval::new(0.001, Float::INFINITY);
+        let ray = Ray::new(Point3::new(0.3, 0.6, 1.0), Vec3::new(0.0, 0.0, -1.0));
+        let rec = mesh.hit(&ray, &bounds).unwrap();
+        assert!((rec.t() - 0.75).abs() < 1e-5);
+    }
+}
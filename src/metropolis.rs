@@ -0,0 +1,255 @@
+use crate::camera::Camera;
+use crate::film::Film;
+use crate::hittable::Hittable;
+use crate::renderer::Renderer;
+use crate::trace;
+use crate::{util::random, Color, Error, Float};
+
+/// Smallest and largest perturbation of a primary sample in a small step.
+const SMALL_STEP_MIN: Float = 1.0 / 1024.0;
+const SMALL_STEP_MAX: Float = 1.0 / 64.0;
+
+/// Primary sample space Metropolis light transport (Kelemen et al. 2002).
+///
+/// Every random number drawn while tracing a path is a coordinate of a point
+/// in the unit hypercube. A Markov chain wanders through this space, mostly by
+/// slightly perturbing the current point, so once a path carrying a lot of
+/// light is found, similar paths are explored around it. This suits scenes
+/// whose light arrives through hard to find paths, such as caustics seen
+/// through glass or light leaking through a gap. Paths are traced by the
+/// renderer's integrator, so its settings and background apply.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Metropolis {
+    /// Probability of proposing a fresh, independent path instead of a
+    /// perturbation of the current one.
+    large_step: Float,
+
+    /// Number of independent paths used to estimate the overall image
+    /// brightness and to pick the chain's starting path.
+    bootstrap: u32,
+}
+
+impl Metropolis {
+    /// Create a new Metropolis renderer.
+    pub fn new(large_step: Float, bootstrap: u32) -> Result<Self, Error> {
+        if !(0.0..=1.0).contains(&large_step) {
+            return Err(Error::new_render(&format!(
+                "large_step must be within [0, 1] (given {large_step})"
+            )));
+        }
+        if bootstrap == 0 {
+            return Err(Error::new_render(&format!(
+                "bootstrap must be greater than 0 (given {bootstrap})"
+            )));
+        }
+
+        Ok(Self {
+            large_step,
+            bootstrap,
+        })
+    }
+
+    /// Render the image seen by `camera`, taking on average the renderer's
+    /// samples per pixel. Samples land anywhere in the image and count towards
+    /// the pixel containing them, so the crop window and reconstruction
+    /// filter are ignored. If the renderer is cancelled, the image so far is
+    /// returned, normalized as if complete.
+    pub fn render<T: Hittable>(
+        &self,
+        renderer: &Renderer,
+        camera: &Camera,
+        world: &T,
+    ) -> Vec<Color> {
+        let (width, height) = camera.dim();
+        let pixels = (width * height) as usize;
+        let mutations = pixels as u64 * renderer.settings().samples_per_pixel as u64;
+        trace::span!(INFO, "render_metropolis", width, height, mutations);
+
+        // Trace the path given by a point in primary sample space.
+        let sample = |samples: &mut Vec<Float>| -> PathSample {
+            random::replay(samples, || {
+                let x = random::gen_unit() * width as Float;
+                let y = random::gen_unit() * height as Float;
+                let (col, row) = ((x as u32).min(width - 1), (y as u32).min(height - 1));
+                let (dx, dy) = (x - col as Float - 0.5, y - row as Float - 0.5);
+                let color = match camera.get_ray_vignetted(row, col, dx, dy) {
+                    Some(ray) => renderer.trace_sample(&ray, world),
+                    None => Color::new(0.0, 0.0, 0.0),
+                };
+                PathSample {
+                    pixel: (row, col),
+                    color,
+                    weight: color.luminance().max(0.0) as Float,
+                }
+            })
+        };
+
+        // Estimate the mean path weight, and pick a starting path in
+        // proportion to its weight.
+        let mut candidates = Vec::with_capacity(self.bootstrap as usize);
+        let mut total = 0.0;
+        for _ in 0..self.bootstrap {
+            let mut samples = Vec::new();
+            let path = sample(&mut samples);
+            total += path.weight;
+            candidates.push((samples, total));
+        }
+        let film = Film::new(width, height);
+        if total <= 0.0 {
+            return film.resolve();
+        }
+        let mean = total / self.bootstrap as Float;
+        let pick = random::gen_unit() * total;
+        let start = candidates.partition_point(|(_, cumulative)| *cumulative <= pick);
+        let (mut current_samples, _) = candidates.swap_remove(start.min(candidates.len() - 1));
+        let mut current = sample(&mut current_samples);
+
+        // Each path splats its color over its weight; the scale turns those
+        // into per-pixel averages.
+        let scale = (mean * pixels as Float / mutations as Float) as f32;
+        let splat = |path: &PathSample, weight: Float| {
+            if weight > 0.0 && path.weight > 0.0 {
+                let (row, col) = path.pixel;
+                film.splat(
+                    row,
+                    col,
+                    path.color * (scale * (weight / path.weight) as f32),
+                );
+            }
+        };
+
+        for _ in 0..mutations {
+            if renderer.is_cancelled() {
+                break;
+            }
+
+            let mut proposed_samples = if random::gen_unit() < self.large_step {
+                Vec::new()
+            } else {
+                current_samples.iter().map(|&u| Self::perturb(u)).collect()
+            };
+            let proposed = sample(&mut proposed_samples);
+
+            // Splat both paths by their expected share, which keeps rejected
+            // proposals from being wasted.
+            let accept = if current.weight > 0.0 {
+                Float::min(1.0, proposed.weight / current.weight)
+            } else {
+                1.0
+            };
+            splat(&proposed, accept);
+            splat(&current, 1.0 - accept);
+
+            if random::gen_unit() < accept {
+                current = proposed;
+                current_samples = proposed_samples;
+            }
+        }
+
+        film.resolve()
+    }
+
+    /// Move a primary sample by a small random amount with an exponential
+    /// distribution, wrapping around the unit interval.
+    fn perturb(u: Float) -> Float {
+        let delta = SMALL_STEP_MAX
+            * Float::exp(-Float::ln(SMALL_STEP_MAX / SMALL_STEP_MIN) * random::gen_unit());
+        let u = if random::gen_unit() < 0.5 {
+            u + delta
+        } else {
+            u - delta
+        };
+        u - u.floor()
+    }
+}
+
+impl Default for Metropolis {
+    /// Large steps 30% of the time, with 10000 bootstrap paths.
+    fn default() -> Self {
+        Self {
+            large_step: 0.3,
+            bootstrap: 10_000,
+        }
+    }
+}
+
+/// Result of tracing one path.
+struct PathSample {
+    /// Row and column of the pixel the path passes through.
+    pixel: (u32, u32),
+
+    /// Color carried by the path.
+    color: Color,
+
+    /// Scalar contribution the chain samples in proportion to.
+    weight: Float,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::background::Uniform;
+    use crate::hittable::HittableList;
+    use crate::renderer::RenderSettings;
+    use crate::sphere::Sphere;
+    use crate::{Point3, Vec3};
+    use std::sync::Arc;
+
+    fn small_camera() -> Camera {
+        Camera::new(
+            2.0,
+            8,
+            90.0,
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(0.0, 0.0, -1.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            0.0,
+            1.0,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn metropolis_settings() {
+        assert!(Metropolis::new(1.5, 10).is_err());
+        assert!(Metropolis::new(0.5, 0).is_err());
+        assert_eq!(Metropolis::new(0.3, 10_000).unwrap(), Metropolis::default());
+
+        for _ in 0..100 {
+            let u = Metropolis::perturb(0.999);
+            assert!((0.0..1.0).contains(&u));
+        }
+    }
+
+    #[test]
+    fn metropolis_uniform() {
+        // Under a uniform background every path weighs the same, so every
+        // proposal is accepted and the image averages to the background.
+        let world: HittableList<Sphere> = HittableList::new();
+        let gray = Color::new(0.5, 0.5, 0.5);
+        let renderer = Renderer::new(RenderSettings::new(64, 4).unwrap())
+            .with_background(Arc::new(Uniform::new(gray)));
+
+        let data = Metropolis::new(0.5, 100)
+            .unwrap()
+            .render(&renderer, &small_camera(), &world);
+        let mean = data.iter().map(|c| c.g()).sum::<f32>() / data.len() as f32;
+        assert!((mean - 0.5).abs() < 1e-3);
+        assert!(data.iter().all(|c| c.g() > 0.0));
+    }
+
+    #[test]
+    fn random_replay() {
+        let mut samples = vec![0.25, 0.75];
+        let drawn = random::replay(&mut samples, || {
+            [
+                random::gen_unit(),
+                random::gen_range(-1.0, 1.0),
+                random::gen_unit(),
+            ]
+        });
+        assert_eq!(&drawn[..2], &[0.25, 0.5]);
+        assert_eq!(samples.len(), 3);
+        assert_eq!(samples[2], drawn[2]);
+    }
+}
@@ -0,0 +1,591 @@
+use crate::image::ImageBuffer;
+use crate::{Color, Error};
+
+/// Longest line of a plain raster, as recommended by the netpbm formats.
+const MAX_LINE: usize = 70;
+
+/// Encoding of the raster of a netpbm image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    /// Binary samples (P4, P5, P6), compact and fast to read.
+    Raw,
+
+    /// Whitespace-separated decimal samples (P1, P2, P3), readable as text.
+    Plain,
+}
+
+/// Decodes a PPM (P3/P6), PGM (P2/P5) or PBM (P1/P4) image. Samples are
+/// scaled to [0, 1] by the maximum value without any gamma conversion, set
+/// bitmap pixels are black, and gray images are expanded to RGB. Raw samples
+/// above 255 take two bytes, most significant first.
+pub fn decode(bytes: &[u8]) -> Result<ImageBuffer, Error> {
+    let mut reader = Reader { bytes, pos: 0 };
+
+    let magic = reader.token()?;
+    let (channels, raw, bitmap) = match magic {
+        b"P1" => (1, false, true),
+        b"P2" => (1, false, false),
+        b"P3" => (3, false, false),
+        b"P4" => (1, true, true),
+        b"P5" => (1, true, false),
+        b"P6" => (3, true, false),
+        _ => {
+            return Err(Error::new_image(&format!(
+                "unsupported netpbm format {}",
+                String::from_utf8_lossy(magic)
+            )))
+        }
+    };
+
+    let width = reader.number()?;
+    let height = reader.number()?;
+    let maxval = if bitmap { 1 } else { reader.number()? };
+    if width == 0 || height == 0 {
+        return Err(Error::new_image("netpbm image must not be empty"));
+    }
+    if maxval == 0 || maxval > u16::MAX as u32 {
+        return Err(Error::new_image(&format!(
+            "netpbm maxval must be in [1, 65535] (given {maxval})"
+        )));
+    }
+
+    // The dimensions come from the file, so sizes must not overflow.
+    let too_large = || Error::new_image(&format!("netpbm image of {width}x{height} is too large"));
+    let count = (width as usize)
+        .checked_mul(height as usize)
+        .and_then(|n| n.checked_mul(channels))
+        .ok_or_else(too_large)?;
+    // Bitmaps store black as 1, the opposite of gray levels.
+    let samples: Vec<u32> = match (raw, bitmap) {
+        (true, true) => {
+            // Rows are packed from the most significant bit of whole bytes.
+            let stride = (width as usize).div_ceil(8);
+            let len = stride.checked_mul(height as usize).ok_or_else(too_large)?;
+            reader
+                .raster(len)?
+                .chunks_exact(stride)
+                .flat_map(|row| (0..width as usize).map(move |i| row[i / 8] >> (7 - i % 8) & 1))
+                .map(|bit| 1 - bit as u32)
+                .collect()
+        }
+        (true, false) => {
+            let size = if maxval > 255 { 2 } else { 1 };
+            reader
+                .raster(count.checked_mul(size).ok_or_else(too_large)?)?
+                .chunks_exact(size)
+                .map(|s| s.iter().fold(0, |acc, &b| (acc << 8) | b as u32))
+                .collect()
+        }
+        (false, true) => (0..count)
+            .map(|_| reader.bit().map(|bit| 1 - bit))
+            .collect::<Result<_, _>>()?,
+        (false, false) => (0..count)
+            .map(|_| reader.number())
+            .collect::<Result<_, _>>()?,
+    };
+
+    if let Some(sample) = samples.iter().find(|&&s| s > maxval) {
+        return Err(Error::new_image(&format!(
+            "netpbm sample {sample} exceeds maxval {maxval}"
+        )));
+    }
+
+    let scale = |s: u32| s as f32 / maxval as f32;
+    let data = samples
+        .chunks_exact(channels)
+        .map(|px| match *px {
+            [y] => Color::new(scale(y), scale(y), scale(y)),
+            [r, g, b] => Color::new(scale(r), scale(g), scale(b)),
+            _ => unreachable!("pixels have one or three channels"),
+        })
+        .collect();
+
+    Ok(ImageBuffer::new(width, height, data))
+}
+
+/// Encodes colors as a PPM (P6 raw or P3 plain) image with samples in
+/// [0, `maxval`]. Colors are written as given, so apply any gamma correction
+/// beforehand. Raw samples take one byte for a `maxval` up to 255 and
+/// otherwise two bytes, most significant first.
+pub fn encode_ppm(
+    width: u32,
+    height: u32,
+    maxval: u16,
+    encoding: Encoding,
+    data: &[Color],
+) -> Result<Vec<u8>, Error> {
+    check_size(width, height, data.len())?;
+    check_maxval(maxval)?;
+
+    let magic = match encoding {
+        Encoding::Raw => "P6",
+        Encoding::Plain => "P3",
+    };
+    let mut raster = Raster::new(&format!("{magic}\n{width} {height}\n{maxval}\n"), encoding);
+    for row in data.chunks_exact(width as usize) {
+        for color in row {
+            let channels = match maxval {
+                255 => color.to_rgb24().map(u16::from),
+                u16::MAX => color.to_rgb48(),
+                _ => [color.r(), color.g(), color.b()].map(|c| quantize(c, maxval)),
+            };
+            for sample in channels {
+                raster.push(sample, maxval);
+            }
+        }
+        raster.end_row();
+    }
+    Ok(raster.bytes)
+}
+
+/// Encodes single-channel values in [0, 1] as a PGM (P5 raw or P2 plain)
+/// image with samples in [0, `maxval`], e.g. for depth or ambient occlusion
+/// passes. Values are written as given, without gamma correction.
+pub fn encode_pgm(
+    width: u32,
+    height: u32,
+    maxval: u16,
+    encoding: Encoding,
+    data: &[f32],
+) -> Result<Vec<u8>, Error> {
+    check_size(width, height, data.len())?;
+    check_maxval(maxval)?;
+
+    let magic = match encoding {
+        Encoding::Raw => "P5",
+        Encoding::Plain => "P2",
+    };
+    let mut raster = Raster::new(&format!("{magic}\n{width} {height}\n{maxval}\n"), encoding);
+    for row in data.chunks_exact(width as usize) {
+        for &value in row {
+            raster.push(quantize(value, maxval), maxval);
+        }
+        raster.end_row();
+    }
+    Ok(raster.bytes)
+}
+
+/// Encodes a bitmap as a PBM (P4 raw or P1 plain) image, e.g. for mask
+/// passes. Set pixels are written as 1, which netpbm displays as black. Raw
+/// rows are packed into bytes from the most significant bit and padded to a
+/// whole byte.
+pub fn encode_pbm(
+    width: u32,
+    height: u32,
+    encoding: Encoding,
+    data: &[bool],
+) -> Result<Vec<u8>, Error> {
+    check_size(width, height, data.len())?;
+
+    let magic = match encoding {
+        Encoding::Raw => "P4",
+        Encoding::Plain => "P1",
+    };
+    let mut raster = Raster::new(&format!("{magic}\n{width} {height}\n"), encoding);
+    for row in data.chunks_exact(width as usize) {
+        match encoding {
+            Encoding::Raw => {
+                for bits in row.chunks(8) {
+                    let byte = bits
+                        .iter()
+                        .enumerate()
+                        .fold(0u8, |acc, (i, &set)| acc | ((set as u8) << (7 - i)));
+                    raster.bytes.push(byte);
+                }
+            }
+            Encoding::Plain => {
+                for &set in row {
+                    raster.push(set as u16, 1);
+                }
+                raster.end_row();
+            }
+        }
+    }
+    Ok(raster.bytes)
+}
+
+/// Checks that an image of `width` by `height` pixels has pixels, and `len`
+/// values for them.
+fn check_size(width: u32, height: u32, len: usize) -> Result<(), Error> {
+    if width == 0 || height == 0 {
+        return Err(Error::new_image("netpbm image must not be empty"));
+    }
+    if (width as usize).checked_mul(height as usize) != Some(len) {
+        return Err(Error::new_image(&format!(
+            "netpbm image of {width}x{height} pixels needs one value per pixel (given {len})"
+        )));
+    }
+    Ok(())
+}
+
+/// Checks that samples can be scaled to `maxval`.
+fn check_maxval(maxval: u16) -> Result<(), Error> {
+    if maxval == 0 {
+        return Err(Error::new_image(
+            "netpbm maxval must be in [1, 65535] (given 0)",
+        ));
+    }
+    Ok(())
+}
+
+/// Scales a value in [0, 1] to the nearest sample in [0, `maxval`].
+fn quantize(value: f32, maxval: u16) -> u16 {
+    let value = value.clamp(0.0, 1.0) as f64;
+    (value * maxval as f64).round() as u16
+}
+
+/// Encoded image written sample by sample after its header.
+struct Raster {
+    /// Encoded file contents.
+    bytes: Vec<u8>,
+
+    /// Encoding of the samples.
+    encoding: Encoding,
+
+    /// Length of the current line of a plain raster.
+    line: usize,
+}
+
+impl Raster {
+    /// Starts an image with the given header.
+    fn new(header: &str, encoding: Encoding) -> Self {
+        Self {
+            bytes: header.as_bytes().to_vec(),
+            encoding,
+            line: 0,
+        }
+    }
+
+    /// Appends a sample. Raw samples take two bytes (most significant first)
+    /// when `maxval` exceeds 255; plain samples are separated by spaces and
+    /// wrapped to lines of at most `MAX_LINE` characters.
+    fn push(&mut self, sample: u16, maxval: u16) {
+        match self.encoding {
+            Encoding::Raw if maxval > 255 => self.bytes.extend(sample.to_be_bytes()),
+            Encoding::Raw => self.bytes.push(sample as u8),
+            Encoding::Plain => {
+                let token = sample.to_string();
+                if self.line > 0 {
+                    if self.line + 1 + token.len() > MAX_LINE {
+                        self.bytes.push(b'\n');
+                        self.line = 0;
+                    } else {
+                        self.bytes.push(b' ');
+                        self.line += 1;
+                    }
+                }
+                self.bytes.extend(token.as_bytes());
+                self.line += token.len();
+            }
+        }
+    }
+
+    /// Ends a row of pixels, which plain rasters finish with a line break.
+    fn end_row(&mut self) {
+        if self.encoding == Encoding::Plain && self.line > 0 {
+            self.bytes.push(b'\n');
+            self.line = 0;
+        }
+    }
+}
+
+/// Encodes linear colors as a little-endian color PFM image. PFM stores
+/// 32-bit floats with rows from the bottom up, so HDR values survive unclamped.
+pub fn encode_pfm(width: u32, height: u32, data: &[Color]) -> Result<Vec<u8>, Error> {
+    check_size(width, height, data.len())?;
+
+    // A negative scale marks the samples as little-endian.
+    let mut bytes = format!("PF\n{width} {height}\n-1.0\n").into_bytes();
+    for row in data.chunks_exact(width as usize).rev() {
+        for color in row {
+            for channel in [color.r(), color.g(), color.b()] {
+                bytes.extend(channel.to_le_bytes());
+            }
+        }
+    }
+    Ok(bytes)
+}
+
+/// Encodes single-channel values, such as a depth pass, as a little-endian
+/// grayscale PFM image.
+pub fn encode_pfm_gray(width: u32, height: u32, data: &[f32]) -> Result<Vec<u8>, Error> {
+    check_size(width, height, data.len())?;
+
+    let mut bytes = format!("Pf\n{width} {height}\n-1.0\n").into_bytes();
+    for row in data.chunks_exact(width as usize).rev() {
+        for value in row {
+            bytes.extend(value.to_le_bytes());
+        }
+    }
+    Ok(bytes)
+}
+
+/// Cursor over the whitespace-separated tokens of a netpbm header or plain raster.
+struct Reader<'a> {
+    /// Encoded file contents.
+    bytes: &'a [u8],
+
+    /// Position of the next unread byte.
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    /// Reads the next token, skipping whitespace and `#` comments.
+    fn token(&mut self) -> Result<&'a [u8], Error> {
+        self.skip()?;
+        let start = self.pos;
+        while self
+            .bytes
+            .get(self.pos)
+            .is_some_and(|b| !b.is_ascii_whitespace() && *b != b'#')
+        {
+            self.pos += 1;
+        }
+        Ok(&self.bytes[start..self.pos])
+    }
+
+    /// Skips whitespace and `#` comments up to the next token.
+    fn skip(&mut self) -> Result<(), Error> {
+        loop {
+            match self.bytes.get(self.pos) {
+                Some(b) if b.is_ascii_whitespace() => self.pos += 1,
+                Some(b'#') => {
+                    while self.bytes.get(self.pos).is_some_and(|&b| b != b'\n') {
+                        self.pos += 1;
+                    }
+                }
+                Some(_) => break,
+                None => return Err(Error::new_image("unexpected end of netpbm data")),
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads the raster of a raw image, which follows the header after a
+    /// single whitespace byte.
+    fn raster(&mut self, len: usize) -> Result<&'a [u8], Error> {
+        let start = self.pos + 1;
+        let end = start
+            .checked_add(len)
+            .filter(|&end| end <= self.bytes.len())
+            .ok_or_else(|| Error::new_image("netpbm raster is truncated"))?;
+        self.pos = end;
+        Ok(&self.bytes[start..end])
+    }
+
+    /// Reads the next digit of a plain bitmap, which need not be separated
+    /// by whitespace.
+    fn bit(&mut self) -> Result<u32, Error> {
+        self.skip()?;
+        let digit = self.bytes[self.pos];
+        self.pos += 1;
+        match digit {
+            b'0' => Ok(0),
+            b'1' => Ok(1),
+            _ => Err(Error::new_image(&format!(
+                "expected a bit in netpbm data, found {}",
+                digit as char
+            ))),
+        }
+    }
+
+    /// Reads the next token as a decimal number.
+    fn number(&mut self) -> Result<u32, Error> {
+        let token = self.token()?;
+        std::str::from_utf8(token)
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .ok_or_else(|| {
+                Error::new_image(&format!(
+                    "expected a number in netpbm data, found {}",
+                    String::from_utf8_lossy(token)
+                ))
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        decode, encode_pbm, encode_pfm, encode_pfm_gray, encode_pgm, encode_ppm, Encoding,
+    };
+    use crate::almost::{AlmostPartialEq, Tolerance};
+    use crate::Color;
+
+    #[test]
+    fn netpbm_decode_plain() {
+        let ppm = b"P3\n# comment\n2 1\n255\n255 0 0  0 51 255\n";
+        let image = decode(ppm).unwrap();
+        assert_eq!(image.dim(), (2, 1));
+        assert_eq!(
+            image.data(),
+            [Color::new(1.0, 0.0, 0.0), Color::new(0.0, 0.2, 1.0)]
+        );
+
+        let pgm = b"P2 1 2 4 0 2";
+        let image = decode(pgm).unwrap();
+        assert_eq!(image.get(0, 1), &Color::new(0.5, 0.5, 0.5));
+    }
+
+    #[test]
+    fn netpbm_decode_raw() {
+        let mut ppm = b"P6 1 1 255\n".to_vec();
+        ppm.extend([255, 0, 51]);
+        let image = decode(&ppm).unwrap();
+        assert_eq!(image.data(), [Color::new(1.0, 0.0, 0.2)]);
+
+        // Wide samples are big-endian.
+        let mut pgm = b"P5 2 1 65535\n".to_vec();
+        pgm.extend([0xff, 0xff, 0x00, 0x00]);
+        let image = decode(&pgm).unwrap();
+        assert_eq!(
+            image.data(),
+            [Color::new(1.0, 1.0, 1.0), Color::new(0.0, 0.0, 0.0)]
+        );
+    }
+
+    #[test]
+    fn netpbm_encode_ppm() {
+        let data = [Color::new(1.0, 0.5, 0.0), Color::new(0.0, 0.2, 1.0)];
+
+        let bytes = encode_ppm(2, 1, 255, Encoding::Raw, &data).unwrap();
+        assert_eq!(bytes, b"P6\n2 1\n255\n\xfe\x7f\x00\x00\x33\xfe");
+
+        // Wide samples take two bytes each and decode back to the same colors.
+        let bytes = encode_ppm(2, 1, u16::MAX, Encoding::Raw, &data).unwrap();
+        assert_eq!(bytes.len(), b"P6\n2 1\n65535\n".len() + 2 * 3 * 2);
+        let image = decode(&bytes).unwrap();
+        for (a, b) in image.data().iter().zip(data.iter()) {
+            assert!((*a - *b).luminance().abs() < 1e-4);
+        }
+
+        let bytes = encode_ppm(2, 1, 1000, Encoding::Raw, &data).unwrap();
+        assert_eq!(&bytes[bytes.len() - 6..], [0, 0, 0, 200, 3, 232]);
+    }
+
+    #[test]
+    fn netpbm_encode_single_channel() {
+        let bytes = encode_pgm(3, 1, 255, Encoding::Raw, &[0.0, 0.5, 2.0]).unwrap();
+        assert_eq!(bytes, b"P5\n3 1\n255\n\x00\x80\xff");
+        let image = decode(&encode_pgm(1, 1, u16::MAX, Encoding::Raw, &[0.25]).unwrap()).unwrap();
+        assert!((image.get(0, 0).r() - 0.25).abs() < 1e-4);
+
+        // Rows are padded to whole bytes.
+        let mask = [
+            true, false, true, false, false, false, false, false, true, //
+            false, false, false, false, false, false, false, false, false,
+        ];
+        let bytes = encode_pbm(9, 2, Encoding::Raw, &mask).unwrap();
+        assert_eq!(bytes, b"P4\n9 2\n\xa0\x80\x00\x00");
+    }
+
+    #[test]
+    fn netpbm_plain() {
+        let data = [Color::new(1.0, 0.5, 0.0), Color::new(0.0, 0.2, 1.0)];
+        let bytes = encode_ppm(1, 2, 255, Encoding::Plain, &data).unwrap();
+        assert_eq!(bytes, b"P3\n1 2\n255\n254 127 0\n0 51 254\n");
+
+        let bytes = encode_pgm(2, 1, 1000, Encoding::Plain, &[0.0, 0.2]).unwrap();
+        assert_eq!(bytes, b"P2\n2 1\n1000\n0 200\n");
+
+        // Long rows wrap at 70 characters.
+        let bytes = encode_pgm(40, 1, 255, Encoding::Plain, &[1.0; 40]).unwrap();
+        let text = String::from_utf8(bytes).unwrap();
+        assert!(text.lines().all(|line| line.len() <= 70));
+        assert_eq!(text.split_ascii_whitespace().count(), 4 + 40);
+
+        let bytes = encode_pbm(3, 1, Encoding::Plain, &[true, false, true]).unwrap();
+        assert_eq!(bytes, b"P1\n3 1\n1 0 1\n");
+    }
+
+    #[test]
+    fn netpbm_round_trip() {
+        let colors = [0.0, 0.25, 0.5, 1.0].map(|v| Color::new(v, 1.0 - v, 0.5));
+        let values = [0.0, 0.25, 0.5, 1.0];
+        let quantized = Tolerance::new(1e-2, 0.0);
+        for encoding in [Encoding::Raw, Encoding::Plain] {
+            for maxval in [255, u16::MAX] {
+                let image = decode(&encode_ppm(2, 2, maxval, encoding, &colors).unwrap()).unwrap();
+                for (a, b) in image.data().iter().zip(&colors) {
+                    assert!(a.almost_eq_with(b, &quantized));
+                }
+
+                let image = decode(&encode_pgm(4, 1, maxval, encoding, &values).unwrap()).unwrap();
+                for (color, value) in image.data().iter().zip(values) {
+                    assert!(color.g().almost_eq_with(value, &quantized));
+                }
+            }
+
+            // Set pixels are black.
+            let image = decode(&encode_pbm(2, 1, encoding, &[true, false]).unwrap()).unwrap();
+            assert_eq!(
+                image.data(),
+                [Color::new(0.0, 0.0, 0.0), Color::new(1.0, 1.0, 1.0)]
+            );
+        }
+
+        // Plain bitmaps need no whitespace between pixels.
+        let image = decode(b"P1 3 1 011").unwrap();
+        assert_eq!(image.get(0, 0), &Color::new(1.0, 1.0, 1.0));
+        assert_eq!(image.get(2, 0), &Color::new(0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn netpbm_encode_pfm() {
+        let top = Color::new(1.5, 0.0, -0.25);
+        let bottom = Color::new(0.0, 100.0, 0.5);
+        let bytes = encode_pfm(1, 2, &[top, bottom]).unwrap();
+
+        let header = b"PF\n1 2\n-1.0\n";
+        assert_eq!(&bytes[..header.len()], header);
+        assert_eq!(bytes.len(), header.len() + 2 * 3 * 4);
+
+        // The bottom row comes first.
+        let first = f32::from_le_bytes(
+            bytes[header.len() + 4..header.len() + 8]
+                .try_into()
+                .unwrap(),
+        );
+        assert_eq!(first, 100.0);
+        let last = f32::from_le_bytes(bytes[bytes.len() - 4..].try_into().unwrap());
+        assert_eq!(last, -0.25);
+    }
+
+    #[test]
+    fn netpbm_encode_pfm_gray() {
+        let bytes = encode_pfm_gray(2, 2, &[1.0, 2.0, 3.5, f32::INFINITY]).unwrap();
+
+        let header = b"Pf\n2 2\n-1.0\n";
+        assert_eq!(&bytes[..header.len()], header);
+        assert_eq!(bytes.len(), header.len() + 4 * 4);
+
+        // The bottom row comes first.
+        let first = f32::from_le_bytes(bytes[header.len()..header.len() + 4].try_into().unwrap());
+        assert_eq!(first, 3.5);
+        let last = f32::from_le_bytes(bytes[bytes.len() - 4..].try_into().unwrap());
+        assert_eq!(last, 2.0);
+    }
+
+    #[test]
+    fn netpbm_decode_invalid() {
+        assert!(decode(b"P7 1 1 255\n").is_err());
+        assert!(decode(b"P6 2 2 255\n\x00\x00").is_err());
+        assert!(decode(b"P3 1 1 100 0 0 200").is_err());
+        assert!(decode(b"P3 1 1").is_err());
+
+        // Oversized headers fail instead of overflowing the raster size.
+        assert!(decode(b"P3\n4294967295 4294967295\n255\n1 2 3\n").is_err());
+        assert!(decode(b"P6\n4294967295 4294967295\n65535\n\x00").is_err());
+        assert!(decode(b"P4\n4294967295 4294967295\n\x00").is_err());
+
+        // Encoders reject what the decoder would.
+        assert!(encode_ppm(0, 1, 255, Encoding::Raw, &[]).is_err());
+        assert!(encode_ppm(1, 1, 0, Encoding::Raw, &[Color::new(0.0, 0.0, 0.0)]).is_err());
+        assert!(encode_pgm(2, 1, 255, Encoding::Plain, &[0.0]).is_err());
+        assert!(encode_pbm(0, 0, Encoding::Plain, &[]).is_err());
+        assert!(encode_pfm(0, 3, &[]).is_err());
+        assert!(encode_pfm_gray(3, 0, &[]).is_err());
+        assert!(decode(b"P1 2 1 0 2").is_err());
+        assert!(decode(b"P4 9 1\n\x00").is_err());
+    }
+}
@@ -0,0 +1,150 @@
+use crate::{Float, Point3};
+
+/// Lattice value noise driven by an integer hash. Produces smooth,
+/// band-limited pseudo-random values in `[0, 1]` without any lookup tables.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ValueNoise {
+    /// Seed mixed into every lattice hash.
+    seed: u32,
+}
+
+impl ValueNoise {
+    /// Creates a new value noise generator.
+    pub fn new(seed: u32) -> Self {
+        Self { seed }
+    }
+
+    /// Hashes integer lattice coordinates to a 32-bit value.
+    pub fn hash(&self, x: i64, y: i64, z: i64) -> u32 {
+        let mut h = self.seed
+            ^ (x as u32).wrapping_mul(0x8da6b343)
+            ^ (y as u32).wrapping_mul(0xd8163841)
+            ^ (z as u32).wrapping_mul(0xcb1ab31f);
+
+        // Avalanche the bits so neighbouring cells are uncorrelated.
+        h ^= h >> 16;
+        h = h.wrapping_mul(0x7feb352d);
+        h ^= h >> 15;
+        h = h.wrapping_mul(0x846ca68b);
+        h ^= h >> 16;
+        h
+    }
+
+    /// Random value in `[0, 1]` at a lattice point.
+    fn lattice(&self, x: i64, y: i64, z: i64) -> Float {
+        self.hash(x, y, z) as Float / u32::MAX as Float
+    }
+
+    /// Samples the noise at `p`. Values are smoothly interpolated between lattice points.
+    pub fn noise(&self, p: &Point3) -> Float {
+        let (fx, fy, fz) = (p.x().floor(), p.y().floor(), p.z().floor());
+        let (x, y, z) = (fx as i64, fy as i64, fz as i64);
+
+        // Smoothstep the fractional offsets to hide the lattice.
+        let smooth = |t: Float| t * t * (3.0 - 2.0 * t);
+        let u = smooth(p.x() - fx);
+        let v = smooth(p.y() - fy);
+        let w = smooth(p.z() - fz);
+
+        let lerp = |a: Float, b: Float, t: Float| a + (b - a) * t;
+
+        let x00 = lerp(self.lattice(x, y, z), self.lattice(x + 1, y, z), u);
+        let x10 = lerp(self.lattice(x, y + 1, z), self.lattice(x + 1, y + 1, z), u);
+        let x01 = lerp(self.lattice(x, y, z + 1), self.lattice(x + 1, y, z + 1), u);
+        let x11 = lerp(
+            self.lattice(x, y + 1, z + 1),
+            self.lattice(x + 1, y + 1, z + 1),
+            u,
+        );
+
+        lerp(lerp(x00, x10, v), lerp(x01, x11, v), w)
+    }
+
+    /// Fractal sum of `octaves` noise layers, each at double the frequency and
+    /// half the amplitude of the previous. Normalized to `[0, 1]`.
+    pub fn fbm(&self, p: &Point3, octaves: u32) -> Float {
+        let mut sum = 0.0;
+        let mut norm = 0.0;
+        let mut amplitude = 1.0;
+        let mut p = *p;
+
+        for _ in 0..octaves.max(1) {
+            sum += amplitude * self.noise(&p);
+            norm += amplitude;
+            amplitude *= 0.5;
+            p *= 2.0;
+        }
+
+        sum / norm
+    }
+
+    /// Fractal sum of the absolute deviation of `octaves` noise layers from their
+    /// midpoint. Creases at the zero crossings give a turbulent look. Normalized to `[0, 1]`.
+    pub fn turbulence(&self, p: &Point3, octaves: u32) -> Float {
+        let mut sum = 0.0;
+        let mut norm = 0.0;
+        let mut amplitude = 1.0;
+        let mut p = *p;
+
+        for _ in 0..octaves.max(1) {
+            sum += amplitude * Float::abs(2.0 * self.noise(&p) - 1.0);
+            norm += amplitude;
+            amplitude *= 0.5;
+            p *= 2.0;
+        }
+
+        sum / norm
+    }
+}
+
+impl Default for ValueNoise {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ValueNoise;
+    use crate::{Float, Point3};
+
+    #[test]
+    fn noise_deterministic() {
+        let a = ValueNoise::new(7);
+        let b = ValueNoise::new(7);
+        let c = ValueNoise::new(8);
+        let p = Point3::new(1.3, -2.7, 0.5);
+
+        assert_eq!(a.noise(&p), b.noise(&p));
+        assert_ne!(a.noise(&p), c.noise(&p));
+    }
+
+    #[test]
+    fn noise_range() {
+        let noise = ValueNoise::new(1);
+        for i in 0..1000 {
+            let t = i as Float * 0.173;
+            let p = Point3::new(t, -0.5 * t, 3.0 * t);
+            let n = noise.noise(&p);
+            assert!((0.0..=1.0).contains(&n));
+            let f = noise.fbm(&p, 4);
+            assert!((0.0..=1.0).contains(&f));
+            let t = noise.turbulence(&p, 4);
+            assert!((0.0..=1.0).contains(&t));
+        }
+    }
+
+    #[test]
+    fn noise_continuous() {
+        let noise = ValueNoise::new(3);
+
+        // Lattice points take on the hashed value exactly.
+        let lattice = noise.hash(2, 5, -1) as Float / u32::MAX as Float;
+        assert!((noise.noise(&Point3::new(2.0, 5.0, -1.0)) - lattice).abs() < 1e-12);
+
+        // Nearby points produce nearby values.
+        let p = Point3::new(0.999999, 0.5, 0.5);
+        let q = Point3::new(1.000001, 0.5, 0.5);
+        assert!((noise.noise(&p) - noise.noise(&q)).abs() < 1e-4);
+    }
+}
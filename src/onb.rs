@@ -0,0 +1,118 @@
+use crate::util::random;
+use crate::{Float, Vec3};
+
+/// Orthonormal basis with `w` along a given direction, used to move
+/// directions between a local frame (`w` up) and world space.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Onb {
+    /// First tangent.
+    u: Vec3,
+
+    /// Second tangent.
+    v: Vec3,
+
+    /// Basis normal.
+    w: Vec3,
+}
+
+impl Onb {
+    /// Builds a right-handed basis around the unit vector `normal` with the
+    /// branchless method of Duff et al., which is continuous everywhere except
+    /// across the `z = 0` plane and has no singularity.
+    pub fn from_normal(normal: &Vec3) -> Self {
+        let sign = Float::copysign(1.0, normal.z());
+        let a = -1.0 / (sign + normal.z());
+        let b = normal.x() * normal.y() * a;
+        let u = Vec3::new(
+            1.0 + sign * normal.x() * normal.x() * a,
+            sign * b,
+            -sign * normal.x(),
+        );
+        let v = Vec3::new(b, sign + normal.y() * normal.y() * a, -normal.y());
+
+        Self { u, v, w: *normal }
+    }
+
+    /// Retrieves the first tangent.
+    pub fn u(&self) -> &Vec3 {
+        &self.u
+    }
+
+    /// Retrieves the second tangent.
+    pub fn v(&self) -> &Vec3 {
+        &self.v
+    }
+
+    /// Retrieves the basis normal.
+    pub fn w(&self) -> &Vec3 {
+        &self.w
+    }
+
+    /// Maps a direction from the local frame to world space.
+    pub fn to_world(&self, local: &Vec3) -> Vec3 {
+        local.x() * self.u + local.y() * self.v + local.z() * self.w
+    }
+
+    /// Maps a direction from world space to the local frame.
+    pub fn to_local(&self, world: &Vec3) -> Vec3 {
+        Vec3::new(
+            Vec3::dot(world, &self.u),
+            Vec3::dot(world, &self.v),
+            Vec3::dot(world, &self.w),
+        )
+    }
+
+    /// Samples a unit direction on the hemisphere around `w` with probability
+    /// proportional to the cosine to `w`.
+    pub fn random_cosine_direction(&self) -> Vec3 {
+        let r1 = random::gen_unit();
+        let r2 = random::gen_unit();
+
+        let phi = 2.0 * crate::consts::PI * r1;
+        let r = Float::sqrt(r2);
+        let local = Vec3::new(
+            r * Float::cos(phi),
+            r * Float::sin(phi),
+            Float::sqrt(1.0 - r2),
+        );
+        self.to_world(&local)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Onb;
+    use crate::Vec3;
+
+    #[test]
+    fn onb_orthonormal() {
+        for n in [
+            Vec3::new(0.0, 0.0, 1.0),
+            Vec3::new(0.0, 0.0, -1.0),
+            Vec3::new(1.0, 2.0, -3.0).unit(),
+            Vec3::new(-0.3, 0.9, 0.1).unit(),
+        ] {
+            let onb = Onb::from_normal(&n);
+            assert!(Vec3::dot(onb.u(), onb.v()).abs() < 1e-6);
+            assert!(Vec3::dot(onb.u(), onb.w()).abs() < 1e-6);
+            assert!(Vec3::dot(onb.v(), onb.w()).abs() < 1e-6);
+            assert!((onb.u().len() - 1.0).abs() < 1e-6);
+            assert!((onb.v().len() - 1.0).abs() < 1e-6);
+            assert!((Vec3::cross(onb.u(), onb.v()) - n).len() < 1e-6);
+
+            let d = Vec3::new(0.2, -0.4, 0.7);
+            assert!((onb.to_local(&onb.to_world(&d)) - d).len() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn onb_cosine_direction() {
+        let n = Vec3::new(1.0, 1.0, 0.0).unit();
+        let onb = Onb::from_normal(&n);
+        for _ in 0..100 {
+            let d = onb.random_cosine_direction();
+            assert!((d.len() - 1.0).abs() < 1e-6);
+            assert!(Vec3::dot(&d, &n) >= 0.0);
+        }
+    }
+}
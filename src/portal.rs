@@ -0,0 +1,196 @@
+use std::sync::Arc;
+
+use crate::background::{Background, Sun};
+use crate::{util::random, Color, Float, Point3, Ray, Vec3};
+
+/// Parallelogram marking a window or other opening through which an interior
+/// sees the background. Portals are not geometry: rays pass through them, and
+/// they only guide where light samples are sent.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Portal {
+    /// Corner the edges start from.
+    corner: Point3,
+
+    /// First edge.
+    u: Vec3,
+
+    /// Second edge.
+    v: Vec3,
+
+    /// Normal scaled by the inverse area, for finding coordinates along the edges.
+    w: Vec3,
+
+    /// Unit normal.
+    normal: Vec3,
+
+    /// Area of the opening.
+    area: Float,
+}
+
+impl Portal {
+    /// Create a new portal spanning the edges `u` and `v` from `corner`.
+    pub fn new(corner: Point3, u: Vec3, v: Vec3) -> Self {
+        let n = Vec3::cross(&u, &v);
+        Self {
+            corner,
+            u,
+            v,
+            w: n / Vec3::dot(&n, &n),
+            normal: n.unit(),
+            area: n.len(),
+        }
+    }
+
+    /// Retrieve the area of the opening.
+    pub fn area(&self) -> Float {
+        self.area
+    }
+
+    /// Sample a unit direction from `origin` towards a point chosen uniformly
+    /// on the opening.
+    fn sample(&self, origin: &Point3) -> Vec3 {
+        let p = self.corner + random::gen_unit() * self.u + random::gen_unit() * self.v;
+        (p - *origin).unit()
+    }
+
+    /// Probability density per solid angle of `sample` choosing the unit
+    /// direction `dir` from `origin`, or zero if it misses the opening.
+    fn pdf(&self, origin: &Point3, dir: &Vec3) -> Float {
+        let cosine = Vec3::dot(&self.normal, dir);
+        if cosine.abs() < 1e-8 || self.area <= 0.0 {
+            return 0.0;
+        }
+
+        let t = Vec3::dot(&self.normal, &(self.corner - *origin)) / cosine;
+        if t <= 0.0 {
+            return 0.0;
+        }
+
+        let p = *origin + t * *dir - self.corner;
+        let alpha = Vec3::dot(&self.w, &Vec3::cross(&p, &self.v));
+        let beta = Vec3::dot(&self.w, &Vec3::cross(&self.u, &p));
+        if !(0.0..=1.0).contains(&alpha) || !(0.0..=1.0).contains(&beta) {
+            return 0.0;
+        }
+        t * t / (self.area * cosine.abs())
+    }
+}
+
+/// Background lighting an interior through portals. Sky samples are sent
+/// through the openings instead of over the whole sphere, most of which is
+/// blocked by walls, so rooms lit only through their windows converge
+/// without the usual speckled noise. Works with any background; its sun, if
+/// any, is still sampled directly.
+#[derive(Clone)]
+pub struct Portals {
+    /// Background seen through the openings.
+    background: Arc<dyn Background>,
+
+    /// Openings onto the background.
+    portals: Vec<Portal>,
+}
+
+impl Portals {
+    /// Create a new background without any portal, which behaves like
+    /// `background` until portals are added.
+    pub fn new(background: Arc<dyn Background>) -> Self {
+        Self {
+            background,
+            portals: Vec::new(),
+        }
+    }
+
+    /// Add an opening onto the background.
+    pub fn with_portal(mut self, portal: Portal) -> Self {
+        self.portals.push(portal);
+        self
+    }
+
+    /// Retrieve the openings onto the background.
+    pub fn portals(&self) -> &[Portal] {
+        &self.portals
+    }
+}
+
+impl Background for Portals {
+    fn color(&self, ray: &Ray) -> Color {
+        self.background.color(ray)
+    }
+
+    fn sky(&self, ray: &Ray) -> Color {
+        self.background.sky(ray)
+    }
+
+    fn sun(&self) -> Option<&Sun> {
+        self.background.sun()
+    }
+
+    fn sample_sky(&self, origin: &Point3) -> Option<(Vec3, Color, Float)> {
+        if self.portals.is_empty() {
+            return self.background.sample_sky(origin);
+        }
+
+        let i = ((random::gen_unit() * self.portals.len() as Float) as usize)
+            .min(self.portals.len() - 1);
+        let dir = self.portals[i].sample(origin);
+
+        // Openings may overlap as seen from the origin, so the density
+        // accounts for every one of them.
+        let pdf = self.sky_pdf(origin, &dir);
+        if pdf <= 0.0 {
+            return None;
+        }
+        Some((dir, self.background.sky(&Ray::new(*origin, dir)), pdf))
+    }
+
+    fn sky_pdf(&self, origin: &Point3, dir: &Vec3) -> Float {
+        if self.portals.is_empty() {
+            return self.background.sky_pdf(origin, dir);
+        }
+
+        let sum: Float = self.portals.iter().map(|p| p.pdf(origin, dir)).sum();
+        sum / self.portals.len() as Float
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::background::Uniform;
+
+    #[test]
+    fn portal_sampling() {
+        // A 2x2 opening 10 units overhead covers about 4 / 100 steradians.
+        let portal = Portal::new(
+            Point3::new(-1.0, 10.0, -1.0),
+            Vec3::new(2.0, 0.0, 0.0),
+            Vec3::new(0.0, 0.0, 2.0),
+        );
+        let sky = Color::new(3.0, 3.0, 3.0);
+        let portals = Portals::new(Arc::new(Uniform::new(sky))).with_portal(portal);
+        assert_eq!(portals.portals()[0].area(), 4.0);
+
+        let origin = Point3::new(0.0, 0.0, 0.0);
+        let n = 1000;
+        let mut solid_angle = 0.0;
+        for _ in 0..n {
+            let (dir, radiance, pdf) = portals.sample_sky(&origin).unwrap();
+            assert!(dir.y() > 0.99);
+            assert_eq!(radiance, sky);
+            solid_angle += 1.0 / pdf;
+        }
+        assert!((solid_angle / n as Float - 0.04).abs() < 0.002);
+
+        // Directions missing the opening are never sampled.
+        let side = Vec3::new(1.0, 0.0, 0.0);
+        assert_eq!(portals.sky_pdf(&origin, &side), 0.0);
+        assert_eq!(
+            portals.sky_pdf(&Point3::new(0.0, 20.0, 0.0), &Vec3::new(0.0, 1.0, 0.0)),
+            0.0
+        );
+
+        // Without portals, the background is sampled as usual.
+        let open = Portals::new(Arc::new(Uniform::new(sky)));
+        assert!(open.sample_sky(&origin).is_none());
+    }
+}
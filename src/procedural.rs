@@ -0,0 +1,142 @@
+use std::sync::Arc;
+
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable};
+use crate::material::Material;
+use crate::{Float, Interval, Ray, Vec3};
+
+/// Intersection reported by a procedural primitive.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SurfaceHit {
+    /// Ray parameter at the intersection.
+    pub t: Float,
+
+    /// Outward surface normal at the intersection. Need not be normalized.
+    pub normal: Vec3,
+
+    /// Surface coordinate along the first texture axis.
+    pub u: Float,
+
+    /// Surface coordinate along the second texture axis.
+    pub v: Float,
+}
+
+impl SurfaceHit {
+    /// Creates a new intersection with zero surface coordinates.
+    pub fn new(t: Float, normal: Vec3) -> Self {
+        Self {
+            t,
+            normal,
+            u: 0.0,
+            v: 0.0,
+        }
+    }
+
+    /// Sets the surface coordinates of the hit.
+    pub fn with_uv(mut self, u: Float, v: Float) -> Self {
+        self.u = u;
+        self.v = v;
+        self
+    }
+}
+
+/// User-defined primitive given by its bounding box and an intersection
+/// closure, for adding geometry without writing a `Hittable` by hand. The
+/// closure receives the ray and the interval of acceptable ray parameters,
+/// and returns the closest intersection within it, if any. Rays that miss the
+/// bounding box never reach the closure.
+#[derive(Clone)]
+pub struct AnyHittable<F>
+where
+    F: Fn(&Ray, &Interval) -> Option<SurfaceHit> + Send + Sync,
+{
+    /// Intersection routine.
+    intersect: F,
+
+    /// Box containing the primitive.
+    bbox: Aabb,
+
+    /// Surface material.
+    material: Arc<dyn Material>,
+}
+
+impl<F> AnyHittable<F>
+where
+    F: Fn(&Ray, &Interval) -> Option<SurfaceHit> + Send + Sync,
+{
+    /// Creates a new procedural primitive.
+    pub fn new(bbox: Aabb, intersect: F, material: Arc<dyn Material>) -> Self {
+        Self {
+            intersect,
+            bbox,
+            material,
+        }
+    }
+}
+
+impl<F> Hittable for AnyHittable<F>
+where
+    F: Fn(&Ray, &Interval) -> Option<SurfaceHit> + Send + Sync,
+{
+    fn hit(&self, ray: &Ray, ray_t: &Interval) -> Option<HitRecord> {
+        if !self.bbox.hit(ray, ray_t) {
+            return None;
+        }
+
+        // Guard against closures reporting hits outside the interval. Only the
+        // closest hit is reported, so masked hits leave nothing behind them.
+        let hit = (self.intersect)(ray, ray_t).filter(|hit| ray_t.surrounds(hit.t))?;
+        let p = ray.at(hit.t);
+        let rec = HitRecord::new(&p, &hit.normal.unit(), hit.t, ray, &*self.material)
+            .with_uv(hit.u, hit.v);
+        (!rec.is_masked()).then_some(rec)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::Lambertian;
+    use crate::{Color, Point3};
+
+    /// Unit disk in the z = 0 plane, facing +z.
+    fn disk() -> AnyHittable<impl Fn(&Ray, &Interval) -> Option<SurfaceHit> + Send + Sync> {
+        let bbox = Aabb::new(
+            &Point3::new(-1.0, -1.0, -1e-4),
+            &Point3::new(1.0, 1.0, 1e-4),
+        );
+        let material = Arc::new(Lambertian::new(&Color::new(0.5, 0.5, 0.5)));
+        AnyHittable::new(
+            bbox,
+            |ray: &Ray, _: &Interval| {
+                let t = -ray.origin().z() / ray.direction().z();
+                let p = ray.at(t);
+                (p.x() * p.x() + p.y() * p.y() <= 1.0)
+                    .then(|| SurfaceHit::new(t, Vec3::new(0.0, 0.0, 2.0)).with_uv(p.x(), p.y()))
+            },
+            material,
+        )
+    }
+
+    #[test]
+    fn procedural_disk() {
+        let disk = disk();
+        let bounds = Interval::new(0.001, Float::INFINITY);
+
+        let ray = Ray::new(Point3::new(0.5, 0.0, 2.0), Vec3::new(0.0, 0.0, -1.0));
+        let rec = disk.hit(&ray, &bounds).unwrap();
+        assert_eq!(rec.t(), 2.0);
+        assert_eq!(rec.normal, Vec3::new(0.0, 0.0, 1.0));
+        assert_eq!((rec.u, rec.v), (0.5, 0.0));
+
+        // Misses, and hits behind the ray, are rejected.
+        let outside = Ray::new(Point3::new(0.9, 0.9, 2.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(disk.hit(&outside, &bounds).is_none());
+        let away = Ray::new(Point3::new(0.5, 0.0, 2.0), Vec3::new(0.0, 0.0, 1.0));
+        assert!(disk.hit(&away, &bounds).is_none());
+    }
+}
@@ -0,0 +1,255 @@
+use std::cell::{Cell, RefCell};
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable};
+use crate::{Interval, Ray};
+
+/// Part of the work of producing an image, timed separately when profiling.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Stage {
+    /// Assembling a scene, e.g. flattening a scene graph.
+    SceneBuild,
+
+    /// Constructing bounding volume hierarchies.
+    BvhBuild,
+
+    /// Intersecting camera rays with the world.
+    PrimaryRays,
+
+    /// Intersecting scattered rays with the world.
+    SecondaryRays,
+
+    /// Testing the visibility of lights.
+    ShadowRays,
+
+    /// Everything else spent estimating samples: scattering, evaluating
+    /// materials and textures, sampling lights.
+    Shading,
+}
+
+impl Stage {
+    /// Every stage, in pipeline order.
+    pub const ALL: [Stage; 6] = [
+        Stage::SceneBuild,
+        Stage::BvhBuild,
+        Stage::PrimaryRays,
+        Stage::SecondaryRays,
+        Stage::ShadowRays,
+        Stage::Shading,
+    ];
+
+    /// Human-readable name of the stage.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Stage::SceneBuild => "scene build",
+            Stage::BvhBuild => "bvh build",
+            Stage::PrimaryRays => "primary rays",
+            Stage::SecondaryRays => "secondary rays",
+            Stage::ShadowRays => "shadow rays",
+            Stage::Shading => "shading",
+        }
+    }
+}
+
+/// Time spent in each stage. Stages nest, e.g. rays are traced while
+/// shading; each stage only counts the time not spent in the stages nested
+/// within it, so the times add up to the total.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Profile {
+    times: [Duration; Stage::ALL.len()],
+}
+
+impl Profile {
+    /// Retrieve the time spent in `stage`.
+    pub fn get(&self, stage: Stage) -> Duration {
+        self.times[stage as usize]
+    }
+
+    /// Total time spent in every stage.
+    pub fn total(&self) -> Duration {
+        self.times.iter().sum()
+    }
+
+    /// Add the times of another profile.
+    pub fn merge(&mut self, other: &Profile) {
+        for (time, other) in self.times.iter_mut().zip(other.times) {
+            *time += other;
+        }
+    }
+
+    /// Add time to a stage.
+    fn add(&mut self, stage: Stage, time: Duration) {
+        self.times[stage as usize] += time;
+    }
+}
+
+impl fmt::Display for Profile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for stage in Stage::ALL {
+            writeln!(f, "{}: {:.2?}", stage.name(), self.get(stage))?;
+        }
+        write!(f, "total: {:.2?}", self.total())
+    }
+}
+
+/// Times recorded on one thread, and the stages it is in, innermost last
+/// with the time it was entered or resumed.
+#[derive(Default)]
+struct Recorder {
+    profile: Profile,
+    stack: Vec<(Stage, Instant)>,
+}
+
+thread_local! {
+    /// Whether stages are timed on this thread.
+    static ENABLED: Cell<bool> = const { Cell::new(false) };
+
+    /// Stage times recorded on this thread.
+    static RECORDER: RefCell<Recorder> = RefCell::new(Recorder::default());
+}
+
+/// Enable or disable timing stages on this thread. Disabled by default, as
+/// reading the clock around every ray slows renders down.
+pub fn set_enabled(enabled: bool) {
+    ENABLED.with(|cell| cell.set(enabled));
+}
+
+/// Determines whether stages are timed on this thread.
+pub fn is_enabled() -> bool {
+    ENABLED.with(Cell::get)
+}
+
+/// Returns the stage times recorded on this thread since the last call, and
+/// resets them. Stages still in progress are counted up to now.
+pub fn take_profile() -> Profile {
+    RECORDER.with(|recorder| {
+        let mut recorder = recorder.borrow_mut();
+        if let Some((stage, start)) = recorder.stack.last_mut() {
+            let (stage, elapsed) = (*stage, start.elapsed());
+            *start = Instant::now();
+            recorder.profile.add(stage, elapsed);
+        }
+        std::mem::take(&mut recorder.profile)
+    })
+}
+
+/// Guard timing a stage on this thread until dropped (see `scope`).
+pub(crate) struct Scope {
+    active: bool,
+}
+
+/// Times `stage` for the rest of the enclosing scope, pausing the stage it
+/// is nested in. Does nothing unless profiling is enabled.
+pub(crate) fn scope(stage: Stage) -> Scope {
+    if !is_enabled() {
+        return Scope { active: false };
+    }
+    RECORDER.with(|recorder| {
+        let mut recorder = recorder.borrow_mut();
+        let now = Instant::now();
+        if let Some(&(outer, start)) = recorder.stack.last() {
+            recorder.profile.add(outer, now - start);
+        }
+        recorder.stack.push((stage, now));
+    });
+    Scope { active: true }
+}
+
+impl Drop for Scope {
+    fn drop(&mut self) {
+        if !self.active {
+            return;
+        }
+        RECORDER.with(|recorder| {
+            let mut recorder = recorder.borrow_mut();
+            let now = Instant::now();
+            if let Some((stage, start)) = recorder.stack.pop() {
+                recorder.profile.add(stage, now - start);
+            }
+            if let Some((_, start)) = recorder.stack.last_mut() {
+                *start = now;
+            }
+        });
+    }
+}
+
+/// World timing the rays traced through it for one sample: the first hit
+/// query is the primary ray, later ones are secondary rays, and occlusion
+/// queries are shadow rays.
+pub(crate) struct Profiled<'a> {
+    world: &'a dyn Hittable,
+    traced: AtomicBool,
+}
+
+impl<'a> Profiled<'a> {
+    /// Wrap `world` for a new sample.
+    pub(crate) fn new(world: &'a dyn Hittable) -> Self {
+        Self {
+            world,
+            traced: AtomicBool::new(false),
+        }
+    }
+}
+
+impl Hittable for Profiled<'_> {
+    fn hit(&self, ray: &Ray, ray_t: &Interval) -> Option<HitRecord> {
+        let stage = if self.traced.swap(true, Ordering::Relaxed) {
+            Stage::SecondaryRays
+        } else {
+            Stage::PrimaryRays
+        };
+        let _profile = scope(stage);
+        self.world.hit(ray, ray_t)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.world.bounding_box()
+    }
+
+    fn is_occluded(&self, ray: &Ray, ray_t: &Interval) -> bool {
+        let _profile = scope(Stage::ShadowRays);
+        self.world.is_occluded(ray, ray_t)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn profile_nesting() {
+        set_enabled(true);
+        take_profile();
+        {
+            let _shading = scope(Stage::Shading);
+            std::thread::sleep(Duration::from_millis(5));
+            {
+                let _shadow = scope(Stage::ShadowRays);
+                std::thread::sleep(Duration::from_millis(10));
+            }
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        let profile = take_profile();
+
+        // The shadow rays are not counted as shading.
+        let (shading, shadow) = (profile.get(Stage::Shading), profile.get(Stage::ShadowRays));
+        assert!(shading >= Duration::from_millis(10) && shading < profile.total());
+        assert!(shadow >= Duration::from_millis(10));
+        assert_eq!(profile.total(), shading + shadow);
+        assert_eq!(profile.get(Stage::PrimaryRays), Duration::ZERO);
+        assert_eq!(take_profile(), Profile::default());
+
+        let mut merged = profile;
+        merged.merge(&profile);
+        assert_eq!(merged.total(), 2 * profile.total());
+        assert!(merged.to_string().contains("shadow rays: "));
+
+        // Nothing is recorded while disabled.
+        set_enabled(false);
+        drop(scope(Stage::Shading));
+        assert_eq!(take_profile(), Profile::default());
+    }
+}
@@ -0,0 +1,241 @@
+use crate::camera::CropWindow;
+use crate::Error;
+
+/// Importance of each pixel of an image, to render regions of interest
+/// first (see `Progressive::with_priority`). Pixels have a weight of 0 unless
+/// raised; a weight of 1 gets twice the samples of the rest in early passes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PriorityMap {
+    /// Image width in pixels.
+    width: u32,
+
+    /// Image height in pixels.
+    height: u32,
+
+    /// Weight of each pixel, row by row.
+    weights: Vec<f32>,
+}
+
+impl PriorityMap {
+    /// Create a new map of an image of the given size without priorities.
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            weights: vec![0.0; (width * height) as usize],
+        }
+    }
+
+    /// Create a new map from a weight mask, row by row. Weights must be
+    /// non-negative and finite.
+    pub fn from_weights(width: u32, height: u32, weights: Vec<f32>) -> Result<Self, Error> {
+        if weights.len() != (width * height) as usize {
+            return Err(Error::new_render(&format!(
+                "priority mask has {} weights for a {width}x{height} image",
+                weights.len()
+            )));
+        }
+        if let Some(weight) = weights.iter().find(|w| !(w.is_finite() && **w >= 0.0)) {
+            return Err(Error::new_render(&format!(
+                "priority weights must be non-negative and finite (given {weight})"
+            )));
+        }
+        Ok(Self {
+            width,
+            height,
+            weights,
+        })
+    }
+
+    /// Raise the weight of the pixels in `window` to at least `weight`.
+    /// Negative and non-finite weights are ignored.
+    pub fn with_region(mut self, window: CropWindow, weight: f32) -> Self {
+        if !(weight.is_finite() && weight >= 0.0) {
+            return self;
+        }
+        let rows = window.row..window.row.saturating_add(window.height).min(self.height);
+        let cols = window.col..window.col.saturating_add(window.width).min(self.width);
+        for row in rows {
+            for col in cols.clone() {
+                let w = &mut self.weights[(row * self.width + col) as usize];
+                *w = w.max(weight);
+            }
+        }
+        self
+    }
+
+    /// Retrieve the image dimensions.
+    pub fn dim(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// Retrieve the weight of the pixel at (row, col), which is 0 outside of
+    /// the image.
+    pub fn weight(&self, row: u32, col: u32) -> f32 {
+        if row < self.height && col < self.width {
+            self.weights[(row * self.width + col) as usize]
+        } else {
+            0.0
+        }
+    }
+
+    /// Highest weight of the pixels in `window`.
+    pub fn max_in(&self, window: &CropWindow) -> f32 {
+        let rows = window.row..window.row.saturating_add(window.height).min(self.height);
+        let cols = window.col..window.col.saturating_add(window.width).min(self.width);
+        rows.flat_map(|row| cols.clone().map(move |col| (row, col)))
+            .map(|(row, col)| self.weight(row, col))
+            .fold(0.0, f32::max)
+    }
+}
+
+/// Schedule of a progressive render: the image is rendered in square tiles
+/// over several passes, each adding samples to every tile, so a usable
+/// image is available after the first pass. With priorities, tiles covering
+/// regions of interest are rendered first in each pass and take their
+/// samples earlier, sharper sooner at the expense of the rest.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Progressive {
+    /// Number of passes over the image.
+    passes: u32,
+
+    /// Side of the square tiles in pixels.
+    tile_size: u32,
+
+    /// Importance of each pixel.
+    priority: Option<PriorityMap>,
+}
+
+impl Progressive {
+    /// Create a new schedule spreading each pixel's samples over `passes`
+    /// passes of 16x16 tiles.
+    pub fn new(passes: u32) -> Result<Self, Error> {
+        if passes == 0 {
+            return Err(Error::new_render(&format!(
+                "passes must be greater than 0 (given {passes})"
+            )));
+        }
+        Ok(Self {
+            passes,
+            tile_size: 16,
+            priority: None,
+        })
+    }
+
+    /// Set the side of the tiles in pixels, at least 1.
+    pub fn with_tile_size(mut self, tile_size: u32) -> Self {
+        self.tile_size = tile_size.max(1);
+        self
+    }
+
+    /// Set the importance of each pixel. Tiles get the highest weight among
+    /// their pixels.
+    pub fn with_priority(mut self, priority: PriorityMap) -> Self {
+        self.priority = Some(priority);
+        self
+    }
+
+    /// Retrieve the number of passes.
+    pub fn passes(&self) -> u32 {
+        self.passes
+    }
+
+    /// Retrieve the side of the tiles in pixels.
+    pub fn tile_size(&self) -> u32 {
+        self.tile_size
+    }
+
+    /// Retrieve the importance of each pixel, if any.
+    pub fn priority(&self) -> Option<&PriorityMap> {
+        self.priority.as_ref()
+    }
+
+    /// Tiles covering `window` along with their weights, in the order they
+    /// are rendered: highest weight first, then top to bottom and left to
+    /// right.
+    pub fn tiles(&self, window: &CropWindow) -> Vec<(CropWindow, f32)> {
+        let size = self.tile_size;
+        let mut tiles: Vec<(CropWindow, f32)> = (0..window.height.div_ceil(size))
+            .flat_map(|i| (0..window.width.div_ceil(size)).map(move |j| (i * size, j * size)))
+            .map(|(row, col)| {
+                let tile = CropWindow::new(
+                    window.col + col,
+                    window.row + row,
+                    size.min(window.width - col),
+                    size.min(window.height - row),
+                );
+                let weight = self.priority.as_ref().map_or(0.0, |p| p.max_in(&tile));
+                (tile, weight)
+            })
+            .collect();
+        tiles.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+        tiles
+    }
+
+    /// Number of samples that pixels of a tile with the given weight have
+    /// once `pass` passes (counting from 1) are complete, out of
+    /// `samples_per_pixel`. Weighted tiles get ahead by a factor of one plus
+    /// their weight, and every tile is complete after the last pass. With
+    /// few samples per pixel, unweighted tiles may have none early on.
+    pub fn samples_after(&self, pass: u32, weight: f32, samples_per_pixel: u32) -> u32 {
+        if pass >= self.passes {
+            return samples_per_pixel;
+        }
+        let fraction = (pass as f32 / self.passes as f32 * (1.0 + weight)).min(1.0);
+        (fraction * samples_per_pixel as f32).floor() as u32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn progressive_priority_map() {
+        assert!(PriorityMap::from_weights(2, 2, vec![0.0; 3]).is_err());
+        assert!(PriorityMap::from_weights(2, 1, vec![0.0, -1.0]).is_err());
+        let mask = PriorityMap::from_weights(2, 1, vec![0.0, 0.5]).unwrap();
+        assert_eq!(mask.weight(0, 1), 0.5);
+
+        // Regions are clipped to the image and only raise weights.
+        let map = PriorityMap::new(4, 4)
+            .with_region(CropWindow::new(2, 2, 8, 8), 2.0)
+            .with_region(CropWindow::new(0, 0, 3, 3), 1.0);
+        assert_eq!(map.weight(3, 3), 2.0);
+        assert_eq!(map.weight(2, 2), 2.0);
+        assert_eq!(map.weight(0, 0), 1.0);
+        assert_eq!(map.weight(0, 3), 0.0);
+        assert_eq!(map.weight(9, 9), 0.0);
+        assert_eq!(map.max_in(&CropWindow::new(0, 0, 2, 2)), 1.0);
+        let far = CropWindow::new(u32::MAX, 3, u32::MAX, 1);
+        assert_eq!(map.with_region(far, 5.0).max_in(&far), 0.0);
+    }
+
+    #[test]
+    fn progressive_schedule() {
+        assert!(Progressive::new(0).is_err());
+
+        // A 5x3 window in 2x2 tiles, with the bottom right corner first.
+        let priority = PriorityMap::new(8, 8).with_region(CropWindow::new(5, 3, 1, 1), 1.0);
+        let schedule = Progressive::new(4)
+            .unwrap()
+            .with_tile_size(2)
+            .with_priority(priority);
+        let tiles = schedule.tiles(&CropWindow::new(1, 1, 5, 3));
+        assert_eq!(tiles.len(), 6);
+        assert_eq!(tiles[0], (CropWindow::new(5, 3, 1, 1), 1.0));
+        assert_eq!(tiles[1], (CropWindow::new(1, 1, 2, 2), 0.0));
+        let area: u32 = tiles.iter().map(|(t, _)| t.width * t.height).sum();
+        assert_eq!(area, 15);
+
+        // Weighted tiles finish halfway through; the rest catch up by the end.
+        let counts: Vec<_> = (1..=4)
+            .map(|p| schedule.samples_after(p, 1.0, 16))
+            .collect();
+        assert_eq!(counts, [8, 16, 16, 16]);
+        let counts: Vec<_> = (1..=4)
+            .map(|p| schedule.samples_after(p, 0.0, 16))
+            .collect();
+        assert_eq!(counts, [4, 8, 12, 16]);
+    }
+}
@@ -1,16 +1,50 @@
-use crate::{Point3, Vec3};
+use crate::{Float, Interval, Point3, Vec3};
 
 /// 3-D ray.
 #[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
 pub struct Ray {
     origin: Point3,
     direction: Vec3,
+
+    /// Parameter values along which the ray is valid.
+    t_range: Interval,
+
+    /// Time at which the ray is cast.
+    time: Float,
+
+    /// Color channel the ray's path carries alone, once a dispersive material
+    /// has picked one to refract at its own wavelength.
+    channel: Option<usize>,
 }
 
 impl Ray {
-    /// Creates a new ray.
+    /// Creates a new ray, valid for all parameter values and cast at time 0.
     pub fn new(origin: Point3, direction: Vec3) -> Self {
-        Self { origin, direction }
+        Self {
+            origin,
+            direction,
+            t_range: Interval::UNIVERSE,
+            time: 0.0,
+            channel: None,
+        }
+    }
+
+    /// Restricts the ray to the given parameter values.
+    pub fn with_t_range(mut self, t_range: Interval) -> Self {
+        self.t_range = t_range;
+        self
+    }
+
+    /// Sets the time at which the ray is cast.
+    pub fn with_time(mut self, time: Float) -> Self {
+        self.time = time;
+        self
+    }
+
+    /// Sets the color channel the ray's path carries alone, if any.
+    pub fn with_channel(mut self, channel: Option<usize>) -> Self {
+        self.channel = channel;
+        self
     }
 
     /// Retrieves the ray's origin.
@@ -23,8 +57,45 @@ impl Ray {
         &self.direction
     }
 
+    /// Creates a new ray from a glam origin and direction of any supported
+    /// precision, e.g. `glam::DVec3` or `glam::Vec3A`.
+    #[cfg(feature = "glam")]
+    pub fn from_glam<V: Into<Vec3>>(origin: V, direction: V) -> Self {
+        Self::new(origin.into(), direction.into())
+    }
+
+    /// Retrieves the parameter values along which the ray is valid.
+    pub fn t_range(&self) -> &Interval {
+        &self.t_range
+    }
+
+    /// Retrieves the smallest valid parameter value.
+    pub fn t_min(&self) -> Float {
+        self.t_range.min()
+    }
+
+    /// Retrieves the largest valid parameter value.
+    pub fn t_max(&self) -> Float {
+        self.t_range.max()
+    }
+
+    /// Retrieves the time at which the ray is cast.
+    pub fn time(&self) -> Float {
+        self.time
+    }
+
+    /// Retrieves the color channel the ray's path carries alone, if any.
+    pub fn channel(&self) -> Option<usize> {
+        self.channel
+    }
+
+    /// Narrows `ray_t` to the parameter values along which the ray is valid.
+    pub fn clip(&self, ray_t: &Interval) -> Interval {
+        ray_t.intersection(&self.t_range)
+    }
+
     /// Determines the vector for the ray at a given parameter value.
-    pub fn at(&self, t: f64) -> Point3 {
+    pub fn at(&self, t: Float) -> Point3 {
         self.origin + t * self.direction
     }
 }
@@ -50,4 +121,35 @@ mod test {
         let point = ray.at(20.0);
         assert_eq!([point[0], point[1], point[2]], [81.0, 102.0, 123.0]);
     }
+
+    #[test]
+    fn ray_range_and_time() {
+        let ray = Ray::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        assert_eq!(*ray.t_range(), Interval::UNIVERSE);
+        assert_eq!(ray.time(), 0.0);
+        assert_eq!(ray.channel(), None);
+
+        let ray = ray
+            .with_t_range(Interval::new(1.0, 5.0))
+            .with_time(0.25)
+            .with_channel(Some(2));
+        assert_eq!((ray.t_min(), ray.t_max()), (1.0, 5.0));
+        assert_eq!(ray.time(), 0.25);
+        assert_eq!(ray.channel(), Some(2));
+        assert_eq!(
+            ray.clip(&Interval::new(0.001, 3.0)),
+            Interval::new(1.0, 3.0)
+        );
+    }
+
+    #[cfg(feature = "glam")]
+    #[test]
+    fn ray_glam() {
+        let ray = Ray::from_glam(
+            glam::Vec3A::new(1.0, 2.0, 3.0),
+            glam::Vec3A::new(0.0, 0.0, -1.0),
+        );
+        assert_eq!(*ray.origin(), Point3::new(1.0, 2.0, 3.0));
+        assert_eq!(*ray.direction(), Vec3::new(0.0, 0.0, -1.0));
+    }
 }
@@ -0,0 +1,102 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::material::Material;
+use crate::texture::Texture;
+
+/// Materials and textures shared by name. Objects look up their material when
+/// they are constructed, so many objects can share a few materials without
+/// passing handles around, and scene descriptions can refer to them by name.
+#[derive(Clone, Default)]
+pub struct Registry {
+    /// Materials by name.
+    materials: HashMap<String, Arc<dyn Material>>,
+
+    /// Textures by name.
+    textures: HashMap<String, Arc<dyn Texture>>,
+}
+
+impl Registry {
+    /// Creates a new empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a material under `name`, returning the material it replaces.
+    pub fn insert_material(
+        &mut self,
+        name: &str,
+        material: Arc<dyn Material>,
+    ) -> Option<Arc<dyn Material>> {
+        self.materials.insert(name.to_string(), material)
+    }
+
+    /// Registers a texture under `name`, returning the texture it replaces.
+    pub fn insert_texture(
+        &mut self,
+        name: &str,
+        texture: Arc<dyn Texture>,
+    ) -> Option<Arc<dyn Texture>> {
+        self.textures.insert(name.to_string(), texture)
+    }
+
+    /// Registers a material under `name`.
+    pub fn with_material(mut self, name: &str, material: Arc<dyn Material>) -> Self {
+        self.insert_material(name, material);
+        self
+    }
+
+    /// Registers a texture under `name`.
+    pub fn with_texture(mut self, name: &str, texture: Arc<dyn Texture>) -> Self {
+        self.insert_texture(name, texture);
+        self
+    }
+
+    /// Retrieves a shared handle to the material named `name`.
+    pub fn material(&self, name: &str) -> Option<Arc<dyn Material>> {
+        self.materials.get(name).cloned()
+    }
+
+    /// Retrieves a shared handle to the texture named `name`.
+    pub fn texture(&self, name: &str) -> Option<Arc<dyn Texture>> {
+        self.textures.get(name).cloned()
+    }
+
+    /// Retrieves the names of the registered materials, in no particular order.
+    pub fn material_names(&self) -> impl Iterator<Item = &str> {
+        self.materials.keys().map(String::as_str)
+    }
+
+    /// Retrieves the names of the registered textures, in no particular order.
+    pub fn texture_names(&self) -> impl Iterator<Item = &str> {
+        self.textures.keys().map(String::as_str)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::Lambertian;
+    use crate::texture::SolidColor;
+    use crate::Color;
+
+    #[test]
+    fn registry_lookup() {
+        let gray = Color::new(0.5, 0.5, 0.5);
+        let mut registry = Registry::new().with_texture("gray", Arc::new(SolidColor::new(&gray)));
+        let albedo = registry.texture("gray").unwrap();
+        registry.insert_material("matte", Arc::new(Lambertian::textured(albedo)));
+        assert!(registry.material("glossy").is_none());
+
+        // Lookups share the registered material.
+        let a = registry.material("matte").unwrap();
+        let b = registry.material("matte").unwrap();
+        assert!(Arc::ptr_eq(&a, &b));
+
+        // Replacing a material hands back the old one.
+        let replaced = registry.insert_material("matte", Arc::new(Lambertian::new(&gray)));
+        assert!(Arc::ptr_eq(&replaced.unwrap(), &a));
+        assert_eq!(registry.material_names().collect::<Vec<_>>(), ["matte"]);
+        assert_eq!(registry.texture_names().count(), 1);
+    }
+}
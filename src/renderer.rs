@@ -0,0 +1,1310 @@
+use crate::aov::{self, Coverage};
+use crate::background::{Background, Gradient};
+use crate::bvh;
+use crate::camera::{Camera, CropWindow};
+use crate::cancel::CancelToken;
+use crate::film::Film;
+use crate::filter::Filter;
+use crate::image;
+use crate::integrator::{self, Integrator, PathTracer};
+use crate::profile::{self, Profile, Profiled, Stage};
+use crate::progressive::Progressive;
+use crate::sampler::Sampler;
+use crate::stereo::Stereo;
+use crate::trace;
+use crate::util::random;
+use crate::variance::SampleStats;
+use crate::{hittable::Hittable, Color, Error, Float, Point3, Ray};
+use std::ops::Range;
+use std::sync::Arc;
+
+/// Downsampling factors of the previews shown by `Renderer::render_preview`,
+/// coarsest first.
+pub const PREVIEW_SCALES: [u32; 3] = [8, 4, 2];
+
+/// Completed row of a streamed render.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Scanline {
+    /// Row index in the image.
+    pub row: u32,
+
+    /// Pixel colors from left to right.
+    pub data: Vec<Color>,
+}
+
+/// Per-pixel cost of a render, averaged over each pixel's samples. Map the
+/// passes to false color with `variance::heatmap` to find slow pixels.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CostPasses {
+    /// Scattering events followed per sample by the path tracer.
+    pub bounces: Vec<f32>,
+
+    /// Hierarchy nodes tested per sample, by every ray traced. Only worlds
+    /// built on `Bvh` contribute.
+    pub node_tests: Vec<f32>,
+}
+
+/// Time spent rendering an image, broken down by stage (see `profile`).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RenderProfile {
+    /// Times over the whole image.
+    pub total: Profile,
+
+    /// Side of the square tiles, if timed per tile.
+    pub tile_size: Option<u32>,
+
+    /// Times of each tile, row by row. Tiles on the right and bottom edges
+    /// may be partial.
+    pub tiles: Vec<Profile>,
+}
+
+/// Criterion ending a pixel's sampling early once its estimate has converged,
+/// to spend samples where the image is noisy.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AdaptiveSampling {
+    /// Samples taken before testing for convergence.
+    pub min_samples: u32,
+
+    /// Standard error of the pixel's mean luminance, relative to the mean,
+    /// below which the pixel has converged.
+    pub max_error: f32,
+}
+
+impl AdaptiveSampling {
+    /// Determines whether a pixel with the given sample statistics has converged.
+    pub fn converged(&self, stats: &SampleStats) -> bool {
+        stats.count() >= self.min_samples.max(2)
+            && stats.standard_error() <= self.max_error * stats.mean().abs()
+    }
+}
+
+/// Integration settings, independent of any camera or scene.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RenderSettings {
+    /// Number of samples to be taken per pixel region.
+    pub samples_per_pixel: u32,
+
+    /// Maximum number of ray bounces.
+    pub max_depth: u32,
+
+    /// Pixel reconstruction filter.
+    filter: Filter,
+
+    /// Placement of the samples within each pixel's filter support.
+    sampler: Sampler,
+
+    /// Largest channel value allowed for a single sample.
+    sample_clamp: Option<f32>,
+
+    /// Minimum roughness of materials hit after the first bounce.
+    min_roughness: Option<Float>,
+
+    /// Criterion ending pixels early, taking up to `samples_per_pixel`.
+    adaptive: Option<AdaptiveSampling>,
+
+    /// Seed from which every sample's random stream is derived.
+    seed: Option<u64>,
+}
+
+impl RenderSettings {
+    /// Create new render settings.
+    pub fn new(samples_per_pixel: u32, max_depth: u32) -> Result<Self, Error> {
+        if samples_per_pixel == 0 {
+            return Err(Error::new_render(&format!(
+                "samples_per_pixel must be greater than 0 (given {samples_per_pixel})"
+            )));
+        }
+        if max_depth == 0 {
+            return Err(Error::new_render(&format!(
+                "max_depth must be greater than 0 (given {max_depth})"
+            )));
+        }
+
+        Ok(Self {
+            samples_per_pixel,
+            max_depth,
+            filter: Filter::default(),
+            sampler: Sampler::default(),
+            sample_clamp: None,
+            min_roughness: None,
+            adaptive: None,
+            seed: None,
+        })
+    }
+
+    /// Set the pixel reconstruction filter.
+    pub fn with_filter(mut self, filter: Filter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    /// Set the placement of the samples within each pixel's filter support.
+    pub fn with_sampler(mut self, sampler: Sampler) -> Self {
+        self.sampler = sampler;
+        self
+    }
+
+    /// Clamp every sample so that no channel exceeds `max`, trading a little
+    /// energy for the removal of rare, very bright fireflies.
+    pub fn with_sample_clamp(mut self, max: f32) -> Self {
+        self.sample_clamp = Some(max);
+        self
+    }
+
+    /// Blur near-specular scattering after the first bounce so that it is at
+    /// least `min_roughness` rough, suppressing noisy caustic paths. Directly
+    /// visible surfaces keep their look.
+    pub fn with_regularization(mut self, min_roughness: Float) -> Self {
+        self.min_roughness = Some(min_roughness.clamp(0.0, 1.0));
+        self
+    }
+
+    /// Stop sampling pixels once their estimate has converged, taking at
+    /// least `min_samples` and at most `samples_per_pixel` samples each.
+    pub fn with_adaptive_sampling(mut self, min_samples: u32, max_error: f32) -> Self {
+        self.adaptive = Some(AdaptiveSampling {
+            min_samples,
+            max_error,
+        });
+        self
+    }
+
+    /// Make renders deterministic: each sample draws its random numbers from
+    /// a stream derived from `seed` and the sample's pixel and index (see
+    /// `random::sample_seed`), so the image does not depend on how rows are
+    /// split between threads, streamed or cropped. Use a different seed per
+    /// frame to avoid fixed noise in animations, as
+    /// `Timeline::render_sequence` does.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Retrieve the pixel reconstruction filter.
+    pub fn filter(&self) -> &Filter {
+        &self.filter
+    }
+
+    /// Retrieve the sample placement.
+    pub fn sampler(&self) -> &Sampler {
+        &self.sampler
+    }
+
+    /// Retrieve the minimum roughness after the first bounce, if regularizing.
+    pub fn min_roughness(&self) -> Option<Float> {
+        self.min_roughness
+    }
+
+    /// Retrieve the seed of the samples' random streams, if deterministic.
+    pub fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+
+    /// Retrieve the convergence criterion, if sampling adaptively.
+    pub fn adaptive(&self) -> Option<&AdaptiveSampling> {
+        self.adaptive.as_ref()
+    }
+}
+
+/// Renders images of a world through a camera with the given settings.
+#[derive(Clone)]
+pub struct Renderer {
+    /// Integration settings.
+    settings: RenderSettings,
+
+    /// Light transport algorithm.
+    integrator: Arc<dyn Integrator>,
+
+    /// Radiance seen by rays that escape the world.
+    background: Arc<dyn Background>,
+
+    /// Token checked before each pixel to abort the render.
+    cancel: Option<CancelToken>,
+}
+
+impl Renderer {
+    /// Create a new renderer using a path tracer under a sky gradient.
+    pub fn new(settings: RenderSettings) -> Self {
+        Self {
+            settings,
+            integrator: Arc::new(PathTracer::new()),
+            background: Arc::new(Gradient::default()),
+            cancel: None,
+        }
+    }
+
+    /// Set the light transport algorithm.
+    pub fn with_integrator(mut self, integrator: Arc<dyn Integrator>) -> Self {
+        self.integrator = integrator;
+        self
+    }
+
+    /// Set the radiance seen by rays that escape the world.
+    pub fn with_background(mut self, background: Arc<dyn Background>) -> Self {
+        self.background = background;
+        self
+    }
+
+    /// Observe a cancellation token. Once it is cancelled, renders stop tracing
+    /// and return the partial image, with untraced pixels left black.
+    pub fn with_cancel(mut self, token: CancelToken) -> Self {
+        self.cancel = Some(token);
+        self
+    }
+
+    /// Set the integration settings.
+    pub fn with_settings(mut self, settings: RenderSettings) -> Self {
+        self.settings = settings;
+        self
+    }
+
+    /// Retrieve the render settings.
+    pub fn settings(&self) -> &RenderSettings {
+        &self.settings
+    }
+
+    /// Determines whether the render was cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.as_ref().is_some_and(CancelToken::is_cancelled)
+    }
+
+    /// Render the image seen by `camera` given a world of hittable objects,
+    /// resolving the film of `render_film`. With a crop window, pixels
+    /// outside of it are left black, as are pixels not reached before the
+    /// render is cancelled.
+    pub fn render<T: Hittable>(&self, camera: &Camera, world: &T) -> Vec<Color> {
+        self.render_film(camera, world).resolve()
+    }
+
+    /// Render the samples seen by `camera` into a film covering the whole
+    /// image, without resolving them, e.g. to merge with other renders.
+    /// Pixels outside of the crop window or not reached before the render is
+    /// cancelled have no samples.
+    pub fn render_film<T: Hittable>(&self, camera: &Camera, world: &T) -> Film {
+        let (width, height) = camera.dim();
+        trace::span!(
+            INFO,
+            "render",
+            width,
+            height,
+            samples = self.settings.samples_per_pixel
+        );
+        let film = Film::new(width, height);
+        let window = CropWindow::new(0, 0, width, height);
+        self.map_pixels(camera, window, (), |row, col| {
+            self.render_pixel(camera, row, col, world, &film);
+        });
+        film
+    }
+
+    /// Render a stereo frame: the images seen by the left and right eyes of
+    /// the rig around `camera`, arranged by its layout (see `Stereo::dim`).
+    pub fn render_stereo<T: Hittable>(
+        &self,
+        stereo: &Stereo,
+        camera: &Camera,
+        world: &T,
+    ) -> Vec<Color> {
+        trace::span!(INFO, "render_stereo");
+        let (left, right) = stereo.eyes(camera);
+        let (width, _) = camera.dim();
+        stereo.pack(
+            &self.render(&left, world),
+            &self.render(&right, world),
+            width,
+        )
+    }
+
+    /// Render the image like `render` into a caller-provided RGBA buffer with
+    /// 8 bits per channel, such as a browser canvas's pixel data (see
+    /// `image::write_rgba8`).
+    pub fn render_rgba8<T: Hittable>(
+        &self,
+        camera: &Camera,
+        world: &T,
+        out: &mut [u8],
+    ) -> Result<(), Error> {
+        let (width, height) = camera.dim();
+        if out.len() != 4 * (width * height) as usize {
+            return Err(Error::new_render(&format!(
+                "RGBA buffer of {} bytes does not fit a {width}x{height} image",
+                out.len()
+            )));
+        }
+        image::write_rgba8(&self.render(camera, world), out)
+    }
+
+    /// Render the image like `render`, calling `on_row` after each row
+    /// completes with the row's index and the whole image buffer so far.
+    /// Rows below the completed one are still black, which lets progress
+    /// displays and incremental writers work on the partial image.
+    pub fn render_with_progress<T, F>(
+        &self,
+        camera: &Camera,
+        world: &T,
+        mut on_row: F,
+    ) -> Vec<Color>
+    where
+        T: Hittable,
+        F: FnMut(u32, &[Color]),
+    {
+        let (width, height) = camera.dim();
+        trace::span!(
+            INFO,
+            "render_with_progress",
+            width,
+            height,
+            samples = self.settings.samples_per_pixel
+        );
+        let width = width as usize;
+        let mut data = vec![Color::new(0.0, 0.0, 0.0); width * height as usize];
+
+        for line in self.scanlines(camera, world) {
+            let start = line.row as usize * width;
+            data[start..start + width].copy_from_slice(&line.data);
+            on_row(line.row, &data);
+        }
+
+        data
+    }
+
+    /// Render the image one row at a time, from top to bottom, yielding each
+    /// row as soon as it completes so that it can be streamed to disk or a
+    /// display without buffering the whole image. Rows are rendered as in
+    /// `render`. The iterator ends early once the render is cancelled.
+    pub fn scanlines<'a, T: Hittable>(
+        &'a self,
+        camera: &'a Camera,
+        world: &'a T,
+    ) -> impl Iterator<Item = Scanline> + 'a {
+        let (width, height) = camera.dim();
+        (0..height)
+            .take_while(|_| !self.is_cancelled())
+            .map(move |row| {
+                let film = Film::cropped(CropWindow::new(0, row, width, 1));
+                self.render_row(camera, row, world, &film);
+                Scanline {
+                    row,
+                    data: film.resolve(),
+                }
+            })
+    }
+
+    /// Render the row at `row` into `film`, skipping pixels outside the crop
+    /// window.
+    fn render_row<T: Hittable>(&self, camera: &Camera, row: u32, world: &T, film: &Film) {
+        let (width, _) = camera.dim();
+        self.map_pixels(camera, CropWindow::new(0, row, width, 1), (), |row, col| {
+            self.render_pixel(camera, row, col, world, film);
+        });
+    }
+
+    /// Render the image progressively following `schedule`, calling
+    /// `on_pass` after each pass completes with the pass number, counting
+    /// from 1, and the image so far. Within a pass, tiles are rendered in the
+    /// schedule's order, so regions of interest show up first and take their
+    /// samples in earlier passes. The final image has the same samples as
+    /// with `render`. Once the render is cancelled, it stops at the next tile
+    /// boundary and returns the image so far without calling `on_pass`.
+    pub fn render_progressive<T, F>(
+        &self,
+        camera: &Camera,
+        world: &T,
+        schedule: &Progressive,
+        on_pass: F,
+    ) -> Vec<Color>
+    where
+        T: Hittable,
+        F: FnMut(u32, &[Color]),
+    {
+        self.render_progressive_film(camera, world, schedule, on_pass)
+            .resolve()
+    }
+
+    /// Render the samples of `render_progressive` into a film covering the
+    /// whole image, without resolving them. A cancelled render leaves a
+    /// film with every completed tile, which can be saved as a checkpoint
+    /// (see `Film::to_checkpoint`).
+    pub fn render_progressive_film<T, F>(
+        &self,
+        camera: &Camera,
+        world: &T,
+        schedule: &Progressive,
+        mut on_pass: F,
+    ) -> Film
+    where
+        T: Hittable,
+        F: FnMut(u32, &[Color]),
+    {
+        let (width, height) = camera.dim();
+        trace::span!(
+            INFO,
+            "render_progressive",
+            width,
+            height,
+            samples = self.settings.samples_per_pixel,
+            passes = schedule.passes()
+        );
+        let film = Film::new(width, height);
+        let mut stats = vec![SampleStats::new(); (width * height) as usize];
+        let tiles = schedule.tiles(&camera.full_or_crop());
+
+        for pass in 1..=schedule.passes() {
+            trace::span!(DEBUG, "pass", pass);
+            for (tile, weight) in &tiles {
+                if self.is_cancelled() {
+                    return film;
+                }
+                let end = schedule.samples_after(pass, *weight, self.settings.samples_per_pixel);
+                for row in tile.row..tile.row + tile.height {
+                    for col in tile.col..tile.col + tile.width {
+                        let stats = &mut stats[(row * width + col) as usize];
+                        if self.settings.adaptive.is_some_and(|a| a.converged(stats)) {
+                            continue;
+                        }
+                        let indices = stats.count()..end;
+                        self.render_samples(camera, row, col, world, &film, indices, stats);
+                    }
+                }
+            }
+            on_pass(pass, &film.resolve());
+        }
+
+        film
+    }
+
+    /// Render the image after a series of quick previews at increasing
+    /// resolutions (see `PREVIEW_SCALES` and `render_downsampled`), so the
+    /// composition shows up early even for expensive scenes. `on_image` is
+    /// called with each preview's downsampling factor and the preview, then
+    /// with 1 and the final image, which is rendered as in `render`. Once the
+    /// render is cancelled, no more images are reported.
+    pub fn render_preview<T, F>(&self, camera: &Camera, world: &T, mut on_image: F) -> Vec<Color>
+    where
+        T: Hittable,
+        F: FnMut(u32, &[Color]),
+    {
+        for scale in PREVIEW_SCALES {
+            let preview = self.render_downsampled(camera, world, scale);
+            if self.is_cancelled() {
+                return preview;
+            }
+            on_image(scale, &preview);
+        }
+
+        let data = self.render(camera, world);
+        if !self.is_cancelled() {
+            on_image(1, &data);
+        }
+        data
+    }
+
+    /// Render a cheap preview at `1/scale` of the resolution: a single ray
+    /// through the center of each block of `scale`x`scale` pixels, whose
+    /// color fills the block, so the preview has the size of the full image.
+    /// Blocks are clipped to the image's edges, and pixels outside of the
+    /// crop window are black.
+    pub fn render_downsampled<T: Hittable>(
+        &self,
+        camera: &Camera,
+        world: &T,
+        scale: u32,
+    ) -> Vec<Color> {
+        let (width, height) = camera.dim();
+        let scale = scale.max(1);
+        trace::span!(DEBUG, "render_downsampled", width, height, scale);
+        let mut data = vec![Color::new(0.0, 0.0, 0.0); (width * height) as usize];
+
+        for row in (0..height).step_by(scale as usize) {
+            if self.is_cancelled() {
+                break;
+            }
+            for col in (0..width).step_by(scale as usize) {
+                let (rows, cols) = (scale.min(height - row), scale.min(width - col));
+                let dx = (cols - 1) as Float / 2.0;
+                let dy = (rows - 1) as Float / 2.0;
+                let color = match camera.get_ray_vignetted(row, col, dx, dy) {
+                    Some(ray) => self.trace_sample(&ray, world),
+                    None => Color::new(0.0, 0.0, 0.0),
+                };
+                for r in row..row + rows {
+                    for c in col..col + cols {
+                        if camera.crop().is_none_or(|crop| crop.contains(r, c)) {
+                            data[(r * width + c) as usize] = color;
+                        }
+                    }
+                }
+            }
+        }
+
+        data
+    }
+
+    /// Render the image along with the luminance variance of each pixel's
+    /// samples, to show where noise concentrates (see `variance::heatmap`).
+    /// With a crop window, pixels outside of it are black with zero variance.
+    pub fn render_with_variance<T: Hittable>(
+        &self,
+        camera: &Camera,
+        world: &T,
+    ) -> (Vec<Color>, Vec<f32>) {
+        let (width, height) = camera.dim();
+        trace::span!(
+            INFO,
+            "render_with_variance",
+            width,
+            height,
+            samples = self.settings.samples_per_pixel
+        );
+        let film = Film::new(width, height);
+        let window = CropWindow::new(0, 0, width, height);
+        let variance = self.map_pixels(camera, window, 0.0, |row, col| {
+            self.render_pixel(camera, row, col, world, &film).variance()
+        });
+
+        (film.resolve(), variance)
+    }
+
+    /// Render the image along with the number of samples taken at each pixel,
+    /// to inspect where adaptive sampling stopped early (see
+    /// `RenderSettings::with_adaptive_sampling`) and tune its criterion.
+    /// Pixels outside of the crop window have no samples.
+    pub fn render_with_sample_counts<T: Hittable>(
+        &self,
+        camera: &Camera,
+        world: &T,
+    ) -> (Vec<Color>, Vec<u32>) {
+        let film = self.render_film(camera, world);
+        (film.resolve(), film.sample_counts())
+    }
+
+    /// Render the image along with the average cost of each pixel's samples:
+    /// their bounce count and hierarchy traversal work. With a crop window,
+    /// pixels outside of it are black with zero cost.
+    pub fn render_with_cost<T: Hittable>(
+        &self,
+        camera: &Camera,
+        world: &T,
+    ) -> (Vec<Color>, CostPasses) {
+        let (width, height) = camera.dim();
+        trace::span!(
+            INFO,
+            "render_with_cost",
+            width,
+            height,
+            samples = self.settings.samples_per_pixel
+        );
+        let film = Film::new(width, height);
+        let samples = self.settings.samples_per_pixel as f32;
+        let window = CropWindow::new(0, 0, width, height);
+        let (bounces, node_tests) = self
+            .map_pixels(camera, window, (0.0, 0.0), |row, col| {
+                integrator::take_bounces();
+                bvh::take_node_tests();
+                self.render_pixel(camera, row, col, world, &film);
+                (
+                    integrator::take_bounces() as f32 / samples,
+                    bvh::take_node_tests() as f32 / samples,
+                )
+            })
+            .into_iter()
+            .unzip();
+
+        (
+            film.resolve(),
+            CostPasses {
+                bounces,
+                node_tests,
+            },
+        )
+    }
+
+    /// Render the image along with the time spent in each stage of the
+    /// render, over the whole image and, given a `tile_size`, per square tile
+    /// to find slow regions. Profiling is enabled on this thread for the
+    /// render, and times recorded before it are discarded. With a crop
+    /// window, tiles outside of it are not timed.
+    pub fn render_with_profile<T: Hittable>(
+        &self,
+        camera: &Camera,
+        world: &T,
+        tile_size: Option<u32>,
+    ) -> (Vec<Color>, RenderProfile) {
+        let (width, height) = camera.dim();
+        trace::span!(
+            INFO,
+            "render_with_profile",
+            width,
+            height,
+            samples = self.settings.samples_per_pixel
+        );
+        let film = Film::new(width, height);
+        let tile_size = tile_size.map(|size| size.max(1));
+        let tiles_across = tile_size.map_or(0, |size| width.div_ceil(size));
+        let mut result = RenderProfile {
+            total: Profile::default(),
+            tile_size,
+            tiles: match tile_size {
+                Some(size) => {
+                    vec![Profile::default(); (tiles_across * height.div_ceil(size)) as usize]
+                }
+                None => Vec::new(),
+            },
+        };
+
+        let enabled = profile::is_enabled();
+        profile::set_enabled(true);
+        profile::take_profile();
+        let window = CropWindow::new(0, 0, width, height);
+        self.map_pixels(camera, window, (), |row, col| {
+            self.render_pixel(camera, row, col, world, &film);
+            let pixel = profile::take_profile();
+            result.total.merge(&pixel);
+            if let Some(size) = tile_size {
+                result.tiles[((row / size) * tiles_across + col / size) as usize].merge(&pixel);
+            }
+        });
+        profile::set_enabled(enabled);
+
+        (film.resolve(), result)
+    }
+
+    /// Render only the crop window, producing an image of the window's size
+    /// (see `Camera::crop_dim`). Without a crop window, renders the whole image.
+    pub fn render_cropped<T: Hittable>(&self, camera: &Camera, world: &T) -> Vec<Color> {
+        let crop = camera.full_or_crop();
+        trace::span!(
+            INFO,
+            "render_cropped",
+            width = crop.width,
+            height = crop.height,
+            samples = self.settings.samples_per_pixel
+        );
+        let film = Film::cropped(crop);
+        self.map_pixels(camera, crop, (), |row, col| {
+            self.render_pixel(camera, row, col, world, &film);
+        });
+        film.resolve()
+    }
+
+    /// Render an object ID pass: for every pixel, the objects hit by its
+    /// primary rays and the fraction of samples each covers, keeping the
+    /// `ranks` largest. Object IDs are those assigned by the world. Pixels
+    /// outside of the crop window or not reached before the render is
+    /// cancelled cover no objects.
+    pub fn render_object_ids<T: Hittable>(
+        &self,
+        camera: &Camera,
+        world: &T,
+        ranks: usize,
+    ) -> Vec<Vec<Coverage>> {
+        trace::span!(INFO, "render_object_ids", ranks);
+        let (width, height) = camera.dim();
+        let window = CropWindow::new(0, 0, width, height);
+        self.map_pixels(camera, window, Vec::new(), |row, col| {
+            let samples: Vec<Option<usize>> = (0..self.settings.samples_per_pixel)
+                .map(|index| {
+                    let (dx, dy) = self.sample_offset(row, col, index);
+                    let ray = camera.get_ray(row, col, dx, dy);
+                    world
+                        .hit(&ray, &integrator::INITIAL_T_BOUND)
+                        .and_then(|rec| rec.object_id)
+                })
+                .collect();
+            aov::coverage(&samples, ranks)
+        })
+    }
+
+    /// Render a depth pass: for every pixel, the camera-space depth of the
+    /// first hit of the ray through its center, i.e. its distance from the
+    /// camera along the viewing direction, or infinity on a miss. Use
+    /// `aov::normalize_depth` to map it between near and far planes, and
+    /// `image::create_pfm_gray` to write it out.
+    pub fn render_depth<T: Hittable>(&self, camera: &Camera, world: &T) -> Vec<f32> {
+        trace::span!(INFO, "render_depth");
+        self.render_positions(camera, world)
+            .iter()
+            .map(|p| p.map_or(f32::INFINITY, |p| camera.depth(&p) as f32))
+            .collect()
+    }
+
+    /// Render a position pass: for every pixel, the point in worldspace where
+    /// the ray through its center first hits the world, or `None` on a miss
+    /// and for pixels outside of the crop window or not reached before the
+    /// render is cancelled.
+    pub fn render_positions<T: Hittable>(&self, camera: &Camera, world: &T) -> Vec<Option<Point3>> {
+        trace::span!(INFO, "render_positions");
+        let (width, height) = camera.dim();
+        let window = CropWindow::new(0, 0, width, height);
+        self.map_pixels(camera, window, None, |row, col| {
+            let ray = camera.get_ray(row, col, 0.0, 0.0);
+            world
+                .hit(&ray, &integrator::INITIAL_T_BOUND)
+                .map(|rec| rec.p)
+        })
+    }
+
+    /// Call `render` on the pixels of `window` row by row, collecting its
+    /// results. Pixels outside of the camera's crop window, and every pixel
+    /// once the render is cancelled, are skipped and get `skipped` instead.
+    fn map_pixels<U, F>(
+        &self,
+        camera: &Camera,
+        window: CropWindow,
+        skipped: U,
+        mut render: F,
+    ) -> Vec<U>
+    where
+        U: Clone,
+        F: FnMut(u32, u32) -> U,
+    {
+        let mut data = Vec::with_capacity(window.width as usize * window.height as usize);
+        for row in window.row..window.row + window.height {
+            trace::span!(TRACE, "row", row);
+            for col in window.col..window.col + window.width {
+                if self.is_cancelled() || camera.crop().is_some_and(|crop| !crop.contains(row, col))
+                {
+                    data.push(skipped.clone());
+                } else {
+                    data.push(render(row, col));
+                }
+            }
+        }
+        data
+    }
+
+    /// Add the samples of the pixel located at (row, col) to `film`, weighted
+    /// by the reconstruction filter at each sample's offset from the pixel
+    /// center, returning the unweighted statistics of the samples.
+    fn render_pixel<T: Hittable>(
+        &self,
+        camera: &Camera,
+        row: u32,
+        col: u32,
+        world: &T,
+        film: &Film,
+    ) -> SampleStats {
+        let mut stats = SampleStats::new();
+        let indices = 0..self.settings.samples_per_pixel;
+        self.render_samples(camera, row, col, world, film, indices, &mut stats);
+        stats
+    }
+
+    /// Add the samples with the given indices of the pixel located at
+    /// (row, col) to `film` like `render_pixel`, accumulating their
+    /// statistics into `stats`, which also decide when adaptive sampling
+    /// stops.
+    #[allow(clippy::too_many_arguments)]
+    fn render_samples<T: Hittable>(
+        &self,
+        camera: &Camera,
+        row: u32,
+        col: u32,
+        world: &T,
+        film: &Film,
+        indices: Range<u32>,
+        stats: &mut SampleStats,
+    ) {
+        for index in indices {
+            let (dx, dy) = self.sample_offset(row, col, index);
+            let weight = self.settings.filter.evaluate(dx, dy) as f32;
+            let sample = match camera.get_ray_vignetted(row, col, dx, dy) {
+                Some(ray) => self.trace_sample(&ray, world),
+                None => Color::new(0.0, 0.0, 0.0),
+            };
+            stats.push(&sample);
+            film.add_sample(row, col, sample, weight);
+
+            if self.settings.adaptive.is_some_and(|a| a.converged(stats)) {
+                break;
+            }
+        }
+    }
+
+    /// Estimate the clamped color arriving along a camera ray. When
+    /// profiling, its rays and shading are timed.
+    pub(crate) fn trace_sample(&self, ray: &Ray, world: &dyn Hittable) -> Color {
+        let radiance = if profile::is_enabled() {
+            let _profile = profile::scope(Stage::Shading);
+            let world = Profiled::new(world);
+            self.integrator
+                .radiance(ray, &world, self.background.as_ref(), &self.settings)
+        } else {
+            self.integrator
+                .radiance(ray, world, self.background.as_ref(), &self.settings)
+        };
+        self.clamp_sample(radiance)
+    }
+
+    /// Offset of the `index`th sample of the pixel at (row, col) from the
+    /// pixel center, in pixels, spread over the filter support by the sampler.
+    /// Starts the sample: with a seed, this thread's generator is reseeded
+    /// for it first.
+    fn sample_offset(&self, row: u32, col: u32, index: u32) -> (Float, Float) {
+        if let Some(seed) = self.settings.seed {
+            random::seed(random::sample_seed(seed, col, row, index));
+        }
+        let r = self.settings.filter.radius();
+        let (u, v) = self.settings.sampler.sample_2d(row, col, index);
+        (r * (2.0 * u - 1.0), r * (2.0 * v - 1.0))
+    }
+
+    /// Scale a sample down so that no channel exceeds the sample clamp.
+    fn clamp_sample(&self, color: Color) -> Color {
+        match self.settings.sample_clamp {
+            Some(max) => {
+                let peak = color.r().max(color.g()).max(color.b());
+                if peak > max {
+                    color * (max / peak)
+                } else {
+                    color
+                }
+            }
+            None => color,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::background::Uniform;
+    use crate::bvh::Bvh;
+    use crate::camera::CropWindow;
+    use crate::hittable::HittableList;
+    use crate::integrator::NormalDebug;
+    use crate::material::Lambertian;
+    use crate::progressive::PriorityMap;
+    use crate::sphere::Sphere;
+    use crate::Vec3;
+    use std::time::Duration;
+
+    fn small_camera() -> Camera {
+        Camera::new(
+            2.0,
+            8,
+            90.0,
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(0.0, 0.0, -1.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            0.0,
+            1.0,
+        )
+        .unwrap()
+    }
+
+    fn renderer() -> Renderer {
+        Renderer::new(RenderSettings::new(1, 1).unwrap())
+    }
+
+    #[test]
+    fn renderer_settings() {
+        assert!(RenderSettings::new(0, 1).is_err());
+        assert!(RenderSettings::new(1, 0).is_err());
+
+        let settings = RenderSettings::new(4, 8)
+            .unwrap()
+            .with_filter(Filter::gaussian())
+            .with_sampler(Sampler::SOBOL);
+        assert_eq!(settings.filter(), &Filter::gaussian());
+        assert_eq!(settings.sampler(), &Sampler::SOBOL);
+        assert_eq!(Renderer::new(settings).settings(), &settings);
+
+        // Offsets stay within the filter support.
+        let renderer = Renderer::new(settings);
+        for index in 0..16 {
+            let (dx, dy) = renderer.sample_offset(2, 3, index);
+            assert!(dx.abs() <= 1.5 && dy.abs() <= 1.5);
+        }
+    }
+
+    #[test]
+    fn renderer_rgba8() {
+        let world: HittableList<Sphere> = HittableList::new();
+        let red = Color::new(1.0, 0.0, 0.0);
+        let renderer = renderer().with_background(Arc::new(Uniform::new(red)));
+        let mut out = vec![0; 4 * 8 * 4];
+        renderer
+            .render_rgba8(&small_camera(), &world, &mut out)
+            .unwrap();
+        let [r, g, b] = red.to_rgb24();
+        assert!(out.chunks_exact(4).all(|pixel| pixel == [r, g, b, 255]));
+
+        assert!(renderer
+            .render_rgba8(&small_camera(), &world, &mut out[..12])
+            .is_err());
+    }
+
+    #[test]
+    fn renderer_depth() {
+        let mut world: HittableList<Sphere> = HittableList::new();
+        let renderer = renderer();
+        let depth = renderer.render_depth(&small_camera(), &world);
+        assert!(depth.iter().all(|d| *d == f32::INFINITY));
+
+        // A large sphere is nearly flat one unit in front of the camera.
+        let gray = Arc::new(Lambertian::new(&Color::new(0.5, 0.5, 0.5)));
+        world.add(Sphere::new(Point3::new(0.0, 0.0, -101.0), 100.0, gray));
+        let depth = renderer.render_depth(&small_camera(), &world);
+        assert_eq!(depth.len(), 32);
+        assert!(depth.iter().all(|d| (1.0..1.02).contains(d)));
+    }
+
+    #[test]
+    fn renderer_stereo() {
+        let world: HittableList<Sphere> = HittableList::new();
+        let stereo = Stereo::new(0.1, 2.0).unwrap();
+        let camera = small_camera();
+        let frame = renderer().render_stereo(&stereo, &camera, &world);
+        let (width, height) = stereo.dim(&camera);
+        assert_eq!(frame.len(), (width * height) as usize);
+    }
+
+    #[test]
+    fn renderer_cost() {
+        let gray = Arc::new(Lambertian::new(&Color::new(0.5, 0.5, 0.5)));
+        let sphere = Sphere::new(Point3::new(0.0, 0.0, -101.0), 100.0, gray);
+        let world = Bvh::new(vec![sphere]);
+        let (data, cost) = renderer().render_with_cost(&small_camera(), &world);
+        assert_eq!(data.len(), 32);
+        assert_eq!(cost.bounces.len(), 32);
+
+        // Every camera ray hits the sphere and scatters once, which is as
+        // deep as paths go, after testing the hierarchy's only node.
+        assert!(cost.bounces.iter().all(|b| *b == 1.0));
+        assert!(cost.node_tests.iter().all(|n| *n == 1.0));
+    }
+
+    #[test]
+    fn renderer_profile() {
+        let gray = Arc::new(Lambertian::new(&Color::new(0.5, 0.5, 0.5)));
+        let world = Bvh::new(vec![Sphere::new(
+            Point3::new(0.0, 0.0, -101.0),
+            100.0,
+            gray,
+        )]);
+        let camera = small_camera();
+        let renderer = Renderer::new(RenderSettings::new(2, 3).unwrap().with_seed(7));
+
+        // Profiling does not change the image.
+        let (data, timings) = renderer.render_with_profile(&camera, &world, Some(3));
+        assert_eq!(data, renderer.render(&camera, &world));
+        assert!(!profile::is_enabled());
+
+        // Rays bounce off the sphere, and every 8x4 pixel lands in one of the
+        // 3x2 tiles.
+        let total = timings.total;
+        assert!(total.get(Stage::PrimaryRays) > Duration::ZERO);
+        assert!(total.get(Stage::SecondaryRays) > Duration::ZERO);
+        assert!(total.get(Stage::Shading) > Duration::ZERO);
+        assert_eq!(total.get(Stage::BvhBuild), Duration::ZERO);
+        assert_eq!(timings.tiles.len(), 6);
+        let mut tiles = Profile::default();
+        timings.tiles.iter().for_each(|tile| tiles.merge(tile));
+        assert_eq!(tiles, total);
+
+        let (_, untiled) = renderer.render_with_profile(&camera, &world, None);
+        assert!(untiled.tiles.is_empty());
+    }
+
+    #[test]
+    fn renderer_progressive() {
+        let world: HittableList<Sphere> = HittableList::new();
+        let camera = small_camera();
+        let blue = Color::new(0.2, 0.4, 0.6);
+        let renderer = Renderer::new(RenderSettings::new(2, 1).unwrap().with_seed(3))
+            .with_background(Arc::new(Uniform::new(blue)));
+        let priority = PriorityMap::new(8, 4).with_region(CropWindow::new(4, 0, 4, 4), 1.0);
+        let schedule = Progressive::new(4)
+            .unwrap()
+            .with_tile_size(4)
+            .with_priority(priority);
+
+        let mut passes = Vec::new();
+        let data = renderer.render_progressive(&camera, &world, &schedule, |pass, image| {
+            passes.push(pass);
+            assert_eq!(image.len(), 32);
+        });
+        assert_eq!(passes, [1, 2, 3, 4]);
+        assert_eq!(data, renderer.render(&camera, &world));
+
+        // After the first pass, only the region of interest has samples.
+        let cancel = CancelToken::new();
+        let cancelled = renderer.with_cancel(cancel.clone());
+        let partial = cancelled.render_progressive(&camera, &world, &schedule, |_, _| {
+            cancel.cancel();
+        });
+        for (i, color) in partial.iter().enumerate() {
+            let expected = if i % 8 >= 4 {
+                blue
+            } else {
+                Color::new(0.0, 0.0, 0.0)
+            };
+            assert!(color.almost_eq(&expected));
+        }
+    }
+
+    #[test]
+    fn renderer_preview() {
+        let world: HittableList<Sphere> = HittableList::new();
+        let camera = small_camera();
+        let blue = Color::new(0.2, 0.4, 0.6);
+        let uniform = renderer().with_background(Arc::new(Uniform::new(blue)));
+
+        let mut scales = Vec::new();
+        let data = uniform.render_preview(&camera, &world, |scale, image| {
+            scales.push(scale);
+            assert_eq!(image.len(), 32);
+            assert!(image.iter().all(|c| c.almost_eq(&blue)));
+        });
+        assert_eq!(scales, [8, 4, 2, 1]);
+        assert_eq!(data.len(), 32);
+
+        // Each 2x2 block of a preview has the color of its center.
+        let mut world: HittableList<Sphere> = HittableList::new();
+        let gray = Arc::new(Lambertian::new(&Color::new(0.5, 0.5, 0.5)));
+        world.add(Sphere::new(Point3::new(0.0, 0.0, -2.0), 1.5, gray));
+        let normals = renderer().with_integrator(Arc::new(NormalDebug::new()));
+        let preview = normals.render_downsampled(&camera, &world, 2);
+        for row in (0..4).step_by(2) {
+            for col in (0..8).step_by(2) {
+                let block = [(0, 0), (0, 1), (1, 0), (1, 1)]
+                    .map(|(r, c)| preview[((row + r) * 8 + col + c) as usize]);
+                assert!(block.iter().all(|c| *c == block[0]));
+            }
+        }
+        assert!(preview[3 * 8 + 3] != preview[0]);
+
+        // Nothing is reported once cancelled.
+        let token = CancelToken::new();
+        token.cancel();
+        let cancelled = uniform.with_cancel(token);
+        cancelled.render_preview(&camera, &world, |_, _| panic!("cancelled render reported"));
+    }
+
+    #[test]
+    fn renderer_film() {
+        let world: HittableList<Sphere> = HittableList::new();
+        let red = Color::new(1.0, 0.0, 0.0);
+        let renderer = Renderer::new(RenderSettings::new(4, 1).unwrap())
+            .with_background(Arc::new(Uniform::new(red)));
+        let camera = small_camera()
+            .with_crop(CropWindow::new(2, 1, 3, 2))
+            .unwrap();
+
+        // Only pixels in the crop window collect samples.
+        let film = renderer.render_film(&camera, &world);
+        assert_eq!(film.pixel(1, 2).unwrap().samples, 4);
+        assert_eq!(film.pixel(0, 0).unwrap().samples, 0);
+
+        // Merging a second render doubles the samples, but not the colors.
+        film.merge(&renderer.render_film(&camera, &world));
+        assert_eq!(film.pixel(2, 4).unwrap().samples, 8);
+        assert_eq!(film.resolve(), renderer.render(&camera, &world));
+    }
+
+    #[test]
+    fn renderer_adaptive() {
+        // Background pixels have no noise and converge at once. Light bounces
+        // off the diffuse sphere in the middle either to the sky or onto the
+        // ground, so it takes more samples.
+        let gray = Arc::new(Lambertian::new(&Color::new(0.5, 0.5, 0.5)));
+        let mut world = HittableList::new();
+        world.add(Sphere::new(Point3::new(0.0, 0.0, -2.0), 1.0, gray.clone()));
+        world.add(Sphere::new(Point3::new(0.0, -101.0, -2.0), 100.0, gray));
+        let settings = RenderSettings::new(64, 4)
+            .unwrap()
+            .with_adaptive_sampling(4, 0.01)
+            .with_seed(1);
+        let white = Color::new(1.0, 1.0, 1.0);
+        let adaptive = Renderer::new(settings).with_background(Arc::new(Uniform::new(white)));
+        assert_eq!(adaptive.settings().adaptive().unwrap().min_samples, 4);
+
+        let (data, counts) = adaptive.render_with_sample_counts(&small_camera(), &world);
+        assert_eq!(counts.len(), data.len());
+        assert_eq!(counts[0], 4);
+        assert!(counts[2 * 8 + 4] > 4);
+        assert!(counts.iter().all(|n| (4..=64).contains(n)));
+
+        // Without adaptive sampling, every pixel takes all its samples.
+        let (_, counts) = renderer().render_with_sample_counts(&small_camera(), &world);
+        assert!(counts.iter().all(|n| *n == 1));
+    }
+
+    #[test]
+    fn renderer_seed() {
+        let gray = Arc::new(Lambertian::new(&Color::new(0.5, 0.5, 0.5)));
+        let mut world = HittableList::new();
+        world.add(Sphere::new(Point3::new(0.0, 0.0, -2.0), 1.0, gray));
+        let settings = RenderSettings::new(4, 4).unwrap().with_seed(7);
+        assert_eq!(settings.seed(), Some(7));
+        let renderer = Renderer::new(settings);
+        let camera = small_camera();
+
+        // Pixels come out the same however the image is rendered.
+        let full = renderer.render(&camera, &world);
+        assert_eq!(renderer.render(&camera, &world), full);
+        let rows: Vec<Color> = renderer
+            .scanlines(&camera, &world)
+            .flat_map(|line| line.data)
+            .collect();
+        assert_eq!(rows, full);
+        let window = camera
+            .clone()
+            .with_crop(CropWindow::new(3, 2, 2, 2))
+            .unwrap();
+        let cropped = renderer.render_cropped(&window, &world);
+        assert_eq!(cropped, [full[19], full[20], full[27], full[28]]);
+
+        let reseeded = Renderer::new(settings.with_seed(8)).render(&camera, &world);
+        assert_ne!(reseeded, full);
+    }
+
+    #[test]
+    fn renderer_integrator() {
+        let world: HittableList<Sphere> = HittableList::new();
+        let renderer = renderer().with_integrator(Arc::new(NormalDebug::new()));
+        let data = renderer.render(&small_camera(), &world);
+        assert!(data.iter().all(|c| *c == Color::new(0.0, 0.0, 0.0)));
+    }
+
+    #[test]
+    fn renderer_background() {
+        let world: HittableList<Sphere> = HittableList::new();
+        let red = Color::new(1.0, 0.0, 0.0);
+        let renderer = renderer().with_background(Arc::new(Uniform::new(red)));
+        let data = renderer.render(&small_camera(), &world);
+        assert!(data.iter().all(|c| *c == red));
+    }
+
+    #[test]
+    fn renderer_sample_clamp() {
+        let renderer = Renderer::new(RenderSettings::new(1, 1).unwrap().with_sample_clamp(2.0));
+        let clamped = renderer.clamp_sample(Color::new(8.0, 4.0, 1.0));
+        assert_eq!(clamped, Color::new(2.0, 1.0, 0.25));
+        assert_eq!(
+            renderer.clamp_sample(Color::new(1.0, 0.5, 0.0)),
+            Color::new(1.0, 0.5, 0.0)
+        );
+    }
+
+    #[test]
+    fn renderer_variance() {
+        let world: HittableList<Sphere> = HittableList::new();
+        let camera = small_camera()
+            .with_crop(CropWindow::new(0, 0, 4, 4))
+            .unwrap();
+
+        let (data, variance) = renderer().render_with_variance(&camera, &world);
+        assert_eq!(data.len(), variance.len());
+        assert!(variance.iter().all(|v| *v >= 0.0 && v.is_finite()));
+        assert_eq!(variance[7], 0.0);
+    }
+
+    #[test]
+    fn renderer_cancel() {
+        let world: HittableList<Sphere> = HittableList::new();
+        let black = Color::new(0.0, 0.0, 0.0);
+        let camera = small_camera();
+        let token = CancelToken::new();
+        let renderer = renderer().with_cancel(token.clone());
+
+        assert!(renderer.render(&camera, &world).iter().all(|c| *c != black));
+
+        token.cancel();
+        assert!(renderer.is_cancelled());
+        let data = renderer.render(&camera, &world);
+        assert_eq!(data.len(), 8 * 4);
+        assert!(data.iter().all(|c| *c == black));
+        assert!(renderer
+            .render_object_ids(&camera, &world, 1)
+            .iter()
+            .all(Vec::is_empty));
+        assert!(renderer
+            .render_positions(&camera, &world)
+            .iter()
+            .all(Option::is_none));
+    }
+
+    #[test]
+    fn renderer_scanlines() {
+        let world: HittableList<Sphere> = HittableList::new();
+        let camera = small_camera();
+
+        let rows: Vec<Scanline> = renderer().scanlines(&camera, &world).collect();
+        assert_eq!(rows.len(), 4);
+        for (i, line) in rows.iter().enumerate() {
+            assert_eq!(line.row, i as u32);
+            assert_eq!(line.data.len(), 8);
+        }
+
+        // Streaming stops once cancelled.
+        let token = CancelToken::new();
+        let renderer = renderer().with_cancel(token.clone());
+        let mut lines = renderer.scanlines(&camera, &world);
+        assert!(lines.next().is_some());
+        token.cancel();
+        assert!(lines.next().is_none());
+    }
+
+    #[test]
+    fn renderer_progress() {
+        let world: HittableList<Sphere> = HittableList::new();
+        let black = Color::new(0.0, 0.0, 0.0);
+        let camera = small_camera();
+
+        let mut rows = Vec::new();
+        let data = renderer().render_with_progress(&camera, &world, |row, partial| {
+            assert_eq!(partial.len(), 8 * 4);
+            let done = (row as usize + 1) * 8;
+            assert!(partial[..done].iter().all(|c| *c != black));
+            assert!(partial[done..].iter().all(|c| *c == black));
+            rows.push(row);
+        });
+
+        assert_eq!(rows, [0, 1, 2, 3]);
+        assert_eq!(data.len(), 8 * 4);
+    }
+
+    #[test]
+    fn renderer_crop() {
+        let world: HittableList<Sphere> = HittableList::new();
+        let black = Color::new(0.0, 0.0, 0.0);
+        let camera = small_camera()
+            .with_crop(CropWindow::new(2, 1, 3, 2))
+            .unwrap();
+
+        let full = renderer().render(&camera, &world);
+        assert_eq!(full.len(), 8 * 4);
+        for row in 0..4 {
+            for col in 0..8 {
+                let inside = (1..3).contains(&row) && (2..5).contains(&col);
+                assert_eq!(full[(row * 8 + col) as usize] != black, inside);
+            }
+        }
+
+        let cropped = renderer().render_cropped(&camera, &world);
+        assert_eq!(cropped.len(), 6);
+        assert!(cropped.iter().all(|c| *c != black));
+
+        // Passes are only rendered within the window too.
+        let mut wall = HittableList::new();
+        wall.add(Sphere::new(
+            Point3::new(0.0, 0.0, -100.0),
+            90.0,
+            Arc::new(Lambertian::new(&Color::new(0.5, 0.5, 0.5))),
+        ));
+        let positions = renderer().render_positions(&camera, &wall);
+        let ids = renderer().render_object_ids(&camera, &wall, 1);
+        let (_, variance) = renderer().render_with_variance(&camera, &wall);
+        assert_eq!((positions.len(), ids.len(), variance.len()), (32, 32, 32));
+        for row in 0..4 {
+            for col in 0..8 {
+                let inside = (1..3).contains(&row) && (2..5).contains(&col);
+                let i = (row * 8 + col) as usize;
+                assert_eq!(positions[i].is_some(), inside);
+                assert_eq!(ids[i].is_empty(), !inside);
+            }
+        }
+    }
+}
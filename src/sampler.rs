@@ -0,0 +1,112 @@
+use crate::{util::random, Float};
+
+/// Source of the 2D points that place each pixel's samples.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Sampler {
+    /// Independent uniform random points (white noise).
+    #[default]
+    Independent,
+
+    /// Owen-scrambled Sobol points (Burley, "Practical Hash-based Owen
+    /// Scrambling", 2020). Each pixel's samples are well stratified, and
+    /// every pixel is scrambled differently, so that residual noise at low
+    /// sample counts is fine-grained, with little low-frequency blotching.
+    /// Varying `seed` between frames changes the pattern.
+    Sobol { seed: u32 },
+}
+
+impl Sampler {
+    /// Owen-scrambled Sobol sampler with a zero seed.
+    pub const SOBOL: Self = Self::Sobol { seed: 0 };
+
+    /// Point in `[0, 1)²` for the `index`th sample of the pixel at (row, col).
+    pub fn sample_2d(&self, row: u32, col: u32, index: u32) -> (Float, Float) {
+        match *self {
+            Self::Independent => (random::gen_unit(), random::gen_unit()),
+            Self::Sobol { seed } => {
+                let pixel_seed = hash(hash(seed ^ row) ^ col);
+
+                // Shuffle the order of the points, then scramble each dimension.
+                let index = nested_uniform_scramble(index, pixel_seed);
+                let x = nested_uniform_scramble(index.reverse_bits(), hash(pixel_seed ^ 1));
+                let y = nested_uniform_scramble(sobol_1(index), hash(pixel_seed ^ 2));
+                (to_unit(x), to_unit(y))
+            }
+        }
+    }
+}
+
+/// Second dimension of the Sobol sequence. The first is the bit-reversed index.
+fn sobol_1(mut index: u32) -> u32 {
+    let mut result = 0;
+    let mut v = 1 << 31;
+    while index != 0 {
+        if index & 1 == 1 {
+            result ^= v;
+        }
+        index >>= 1;
+        v ^= v >> 1;
+    }
+    result
+}
+
+/// Owen scrambling of the bits of `x`, from most to least significant, where
+/// each bit is flipped based on a hash of the bits above it.
+fn nested_uniform_scramble(x: u32, seed: u32) -> u32 {
+    laine_karras_permutation(x.reverse_bits(), seed).reverse_bits()
+}
+
+/// Hash in which each bit depends only on the bits below it (Laine and Karras).
+fn laine_karras_permutation(mut x: u32, seed: u32) -> u32 {
+    x = x.wrapping_add(seed);
+    x ^= x.wrapping_mul(0x6c50_b47c);
+    x ^= x.wrapping_mul(0xb82f_1e52);
+    x ^= x.wrapping_mul(0xc7af_e638);
+    x ^= x.wrapping_mul(0x8d22_f6e6);
+    x
+}
+
+/// Integer hash with good avalanche (lowbias32).
+fn hash(mut x: u32) -> u32 {
+    x ^= x >> 16;
+    x = x.wrapping_mul(0x7feb_352d);
+    x ^= x >> 15;
+    x = x.wrapping_mul(0x846c_a68b);
+    x ^= x >> 16;
+    x
+}
+
+/// Map the bits of a fixed-point fraction to `[0, 1)`.
+fn to_unit(x: u32) -> Float {
+    // Keep only the bits that fit the mantissa, so that rounding never reaches 1.
+    (x >> 8) as Float / (1u32 << 24) as Float
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sampler_sobol_stratified() {
+        for (row, col) in [(0, 0), (3, 7), (100, 2)] {
+            // Any 16 consecutive points from the start cover a 4x4 grid once.
+            let mut cells = [false; 16];
+            for i in 0..16 {
+                let (x, y) = Sampler::SOBOL.sample_2d(row, col, i);
+                assert!((0.0..1.0).contains(&x) && (0.0..1.0).contains(&y));
+                let cell = (4.0 * y) as usize * 4 + (4.0 * x) as usize;
+                assert!(!cells[cell]);
+                cells[cell] = true;
+            }
+        }
+
+        // Neighbouring pixels and seeds see different points.
+        let first = |sampler: Sampler, col| sampler.sample_2d(0, col, 0);
+        assert_ne!(first(Sampler::SOBOL, 0), first(Sampler::SOBOL, 1));
+        assert_ne!(
+            first(Sampler::SOBOL, 0),
+            first(Sampler::Sobol { seed: 1 }, 0)
+        );
+        assert_eq!(first(Sampler::SOBOL, 0), first(Sampler::SOBOL, 0));
+    }
+}
@@ -0,0 +1,299 @@
+use std::sync::Arc;
+
+use crate::camera::Camera;
+use crate::diagnostic::{self, Diagnostic};
+use crate::hittable::{Hittable, Orientation};
+use crate::instance::{Instance, Tlas};
+use crate::integrator;
+use crate::profile::{self, Stage};
+use crate::transform::Transform;
+use crate::Ray;
+
+/// Named node of a scene graph. Each node places its objects and children
+/// relative to its parent, so moving or hiding a node affects the whole group
+/// beneath it.
+#[derive(Clone)]
+pub struct SceneNode {
+    /// Name used to look the node up.
+    name: String,
+
+    /// Transformation from this node's space to its parent's.
+    transform: Transform,
+
+    /// Objects attached to this node, in node space.
+    objects: Vec<Arc<dyn Hittable>>,
+
+    /// Child nodes.
+    children: Vec<SceneNode>,
+
+    /// Whether the node and its descendants are part of the built world.
+    visible: bool,
+}
+
+impl SceneNode {
+    /// Creates a new empty, visible node with the identity transform.
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            transform: Transform::IDENTITY,
+            objects: Vec::new(),
+            children: Vec::new(),
+            visible: true,
+        }
+    }
+
+    /// Sets the node's local transformation.
+    pub fn with_transform(mut self, transform: Transform) -> Self {
+        self.transform = transform;
+        self
+    }
+
+    /// Attaches an object to the node.
+    pub fn with_object(mut self, object: Arc<dyn Hittable>) -> Self {
+        self.objects.push(object);
+        self
+    }
+
+    /// Adds a child node.
+    pub fn with_child(mut self, child: SceneNode) -> Self {
+        self.children.push(child);
+        self
+    }
+
+    /// Retrieves the node's name.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Retrieves the node's local transformation.
+    pub fn transform(&self) -> &Transform {
+        &self.transform
+    }
+
+    /// Replaces the node's local transformation.
+    pub fn set_transform(&mut self, transform: Transform) {
+        self.transform = transform;
+    }
+
+    /// Determines whether the node is visible.
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    /// Shows or hides the node along with its descendants.
+    pub fn set_visible(&mut self, visible: bool) {
+        self.visible = visible;
+    }
+
+    /// Retrieves the objects attached to the node.
+    pub fn objects(&self) -> &[Arc<dyn Hittable>] {
+        &self.objects
+    }
+
+    /// Retrieves the child nodes.
+    pub fn children(&self) -> &[SceneNode] {
+        &self.children
+    }
+
+    /// Attaches an object to the node.
+    pub fn add_object(&mut self, object: Arc<dyn Hittable>) {
+        self.objects.push(object);
+    }
+
+    /// Adds a child node.
+    pub fn add_child(&mut self, child: SceneNode) {
+        self.children.push(child);
+    }
+
+    /// Finds the first node named `name` in this subtree, depth first.
+    pub fn find(&self, name: &str) -> Option<&SceneNode> {
+        if self.name == name {
+            return Some(self);
+        }
+        self.children.iter().find_map(|child| child.find(name))
+    }
+
+    /// Finds the first node named `name` in this subtree, depth first.
+    pub fn find_mut(&mut self, name: &str) -> Option<&mut SceneNode> {
+        if self.name == name {
+            return Some(self);
+        }
+        self.children
+            .iter_mut()
+            .find_map(|child| child.find_mut(name))
+    }
+
+    /// Instances every object of the visible subtree in world space, treating
+    /// this node as the root.
+    pub fn flatten(&self) -> Vec<Instance> {
+        let mut instances = Vec::new();
+        self.flatten_into(&Transform::IDENTITY, &mut instances);
+        instances
+    }
+
+    /// Flattens the visible subtree into a two-level hierarchy ready for
+    /// rendering. The objects' own hierarchies are shared, not rebuilt.
+    pub fn build(&self) -> Tlas {
+        let _profile = profile::scope(Stage::SceneBuild);
+        Tlas::new(self.flatten())
+    }
+
+    /// Reports problems with the visible subtree, treating this node as the
+    /// root, and with the camera viewing it: invalid camera settings,
+    /// non-finite transforms or bounds, degenerate geometry, materials out of
+    /// range, and a camera placed inside a closed object.
+    pub fn validate(&self, camera: &Camera) -> Vec<Diagnostic> {
+        let mut diagnostics = camera.validate();
+        self.validate_into(&Transform::IDENTITY, "", &mut diagnostics);
+
+        // A camera inside a closed object sees only its interior.
+        if !diagnostics.iter().any(Diagnostic::is_error) {
+            let view = Ray::new(camera.look_from, camera.look_at - camera.look_from);
+            let world = self.build();
+            if let Some(rec) = world.hit(&view, &integrator::INITIAL_T_BOUND) {
+                if rec.orientation == Orientation::Interior {
+                    diagnostics.push(Diagnostic::warning(
+                        "camera is inside geometry and sees its back faces",
+                    ));
+                }
+            }
+        }
+
+        diagnostics
+    }
+
+    /// Appends the problems of the visible subtree, whose parent is placed in
+    /// world space by `parent` and found at `path`.
+    fn validate_into(&self, parent: &Transform, path: &str, diagnostics: &mut Vec<Diagnostic>) {
+        if !self.visible {
+            return;
+        }
+
+        let path = if path.is_empty() {
+            self.name.clone()
+        } else {
+            format!("{path}/{}", self.name)
+        };
+        let world = self.transform.then(parent);
+        for object in &self.objects {
+            diagnostics.extend(object.validate().into_iter().map(|d| d.with_path(&path)));
+
+            let bbox = Instance::new(object.clone(), world).bounding_box();
+            if diagnostic::has_nan(bbox.min()) || diagnostic::has_nan(bbox.max()) {
+                let message =
+                    "object bounds in world space are NaN, e.g. from a degenerate transform";
+                diagnostics.push(Diagnostic::error(message).with_path(&path));
+            }
+        }
+        for child in &self.children {
+            child.validate_into(&world, &path, diagnostics);
+        }
+    }
+
+    /// Appends the instances of the visible subtree, whose parent is placed
+    /// in world space by `parent`.
+    fn flatten_into(&self, parent: &Transform, instances: &mut Vec<Instance>) {
+        if !self.visible {
+            return;
+        }
+
+        let world = self.transform.then(parent);
+        for object in &self.objects {
+            instances.push(Instance::new(object.clone(), world));
+        }
+        for child in &self.children {
+            child.flatten_into(&world, instances);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::Lambertian;
+    use crate::sphere::Sphere;
+    use crate::{Color, Float, Interval, Point3, Vec3};
+
+    fn ball() -> Arc<dyn Hittable> {
+        let material = Arc::new(Lambertian::new(&Color::new(0.5, 0.5, 0.5)));
+        Arc::new(Sphere::new(Point3::new(0.0, 0.0, 0.0), 0.5, material))
+    }
+
+    fn table() -> SceneNode {
+        SceneNode::new("table")
+            .with_transform(Transform::translation(&Vec3::new(0.0, 0.0, -2.0)))
+            .with_child(
+                SceneNode::new("cup")
+                    .with_transform(Transform::translation(&Vec3::new(0.0, 0.0, -1.0)))
+                    .with_object(ball()),
+            )
+    }
+
+    #[test]
+    fn scene_flatten() {
+        let root = SceneNode::new("root")
+            .with_object(ball())
+            .with_child(table());
+        assert_eq!(root.flatten().len(), 2);
+        assert_eq!(root.find("cup").map(SceneNode::name), Some("cup"));
+        assert!(root.find("lamp").is_none());
+
+        // The cup sits at z = -3 after both translations.
+        let ray = Ray::new(Point3::new(0.0, 0.0, 10.0), Vec3::new(0.0, 0.0, -1.0));
+        let bounds = Interval::new(0.001, Float::INFINITY);
+        let world = SceneNode::new("root").with_child(table()).build();
+        let rec = world.hit(&ray, &bounds).unwrap();
+        assert!((rec.t() - 12.5).abs() < 1e-4);
+    }
+
+    #[test]
+    fn scene_toggle() {
+        let mut root = SceneNode::new("root").with_child(table());
+
+        root.find_mut("table").unwrap().set_visible(false);
+        assert!(root.flatten().is_empty());
+
+        let table = root.find_mut("table").unwrap();
+        table.set_visible(true);
+        table.set_transform(Transform::translation(&Vec3::new(0.0, 5.0, 0.0)));
+        let ray = Ray::new(Point3::new(0.0, 5.0, 10.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(root
+            .build()
+            .hit(&ray, &Interval::new(0.001, Float::INFINITY))
+            .is_some());
+    }
+
+    #[test]
+    fn scene_validate() {
+        let camera = |look_from: Point3| {
+            Camera::new(
+                1.0,
+                4,
+                90.0,
+                look_from,
+                Point3::new(0.0, 0.0, -3.0),
+                Vec3::new(0.0, 1.0, 0.0),
+                0.0,
+                1.0,
+            )
+            .unwrap()
+        };
+        let outside = camera(Point3::new(0.0, 0.0, 10.0));
+        assert!(table().validate(&outside).is_empty());
+
+        // Problems are reported with the path of their node.
+        let bright = Arc::new(Lambertian::new(&Color::new(1.5, 0.5, 0.5)));
+        let point = Arc::new(Sphere::new(Point3::new(0.0, 0.0, 0.0), 0.0, bright));
+        let root = SceneNode::new("root").with_child(table().with_object(point));
+        let diagnostics = root.validate(&outside);
+        assert_eq!(diagnostics.len(), 2);
+        assert!(diagnostics.iter().all(|d| d.path == "root/table"));
+        assert_eq!(diagnostics.iter().filter(|d| d.is_error()).count(), 1);
+
+        // Cameras inside objects or with a degenerate basis are caught.
+        let inside = camera(Point3::new(0.0, 0.0, -2.9));
+        assert_eq!(table().validate(&inside).len(), 1);
+        let degenerate = camera(Point3::new(0.0, 0.0, -3.0));
+        assert!(table().validate(&degenerate)[0].is_error());
+    }
+}
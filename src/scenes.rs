@@ -0,0 +1,285 @@
+use std::sync::Arc;
+
+use crate::background::{Background, Gradient, Uniform};
+use crate::bvh::Bvh;
+use crate::camera::Camera;
+use crate::hittable::Hittable;
+use crate::instance::Instance;
+use crate::material::{Dielectric, Lambertian, Material, Metallic};
+use crate::mesh::TriangleMesh;
+use crate::portal::{Portal, Portals};
+use crate::profile::{self, Stage};
+use crate::sphere::Sphere;
+use crate::transform::Transform;
+use crate::{util::random, Axis, Color, Float, Point3, Vec3};
+
+/// Ready-to-render scene, for demos and benchmarks.
+pub struct Demo {
+    /// Objects of the scene.
+    pub world: Bvh<Arc<dyn Hittable>>,
+
+    /// Camera framing the scene.
+    pub camera: Camera,
+
+    /// Background lighting the scene.
+    pub background: Arc<dyn Background>,
+}
+
+/// Final scene of "Ray Tracing in One Weekend": three large spheres of glass,
+/// diffuse and metal on a ground of small random spheres. Each cell of the
+/// 22x22 grid gets a small sphere with probability `density` (1 for the
+/// original). Reseeds this thread's generator with `seed`, so the same seed
+/// and density always give the same scene.
+pub fn random_spheres(seed: u64, density: Float, image_width: u32) -> Demo {
+    let _profile = profile::scope(Stage::SceneBuild);
+    random::seed(seed);
+
+    let mut objects: Vec<Arc<dyn Hittable>> = Vec::new();
+    let ground = Arc::new(Lambertian::new(&Color::new(0.5, 0.5, 0.5)));
+    objects.push(Arc::new(Sphere::new(
+        Point3::new(0.0, -1000.0, 0.0),
+        1000.0,
+        ground,
+    )));
+
+    for a in -11..11 {
+        for b in -11..11 {
+            if random::gen_unit() >= density {
+                continue;
+            }
+
+            let choose_material = random::gen_unit();
+            let center = Point3::new(
+                a as Float + 0.9 * random::gen_unit(),
+                0.2,
+                b as Float + 0.9 * random::gen_unit(),
+            );
+            if (center - Point3::new(4.0, 0.2, 0.0)).len() <= 0.9 {
+                continue;
+            }
+
+            let material: Arc<dyn Material> = if choose_material < 0.8 {
+                let albedo = random_color(0.0, 1.0) * random_color(0.0, 1.0);
+                Arc::new(Lambertian::new(&albedo))
+            } else if choose_material < 0.95 {
+                let albedo = random_color(0.5, 1.0);
+                Arc::new(Metallic::new(&albedo, random::gen_range(0.0, 0.5)))
+            } else {
+                Arc::new(Dielectric::new(1.5))
+            };
+            objects.push(Arc::new(Sphere::new(center, 0.2, material)));
+        }
+    }
+
+    let glass = Arc::new(Dielectric::new(1.5));
+    let diffuse = Arc::new(Lambertian::new(&Color::new(0.4, 0.2, 0.1)));
+    let metal = Arc::new(Metallic::new(&Color::new(0.7, 0.6, 0.5), 0.0));
+    objects.push(Arc::new(Sphere::new(
+        Point3::new(0.0, 1.0, 0.0),
+        1.0,
+        glass,
+    )));
+    objects.push(Arc::new(Sphere::new(
+        Point3::new(-4.0, 1.0, 0.0),
+        1.0,
+        diffuse,
+    )));
+    objects.push(Arc::new(Sphere::new(
+        Point3::new(4.0, 1.0, 0.0),
+        1.0,
+        metal,
+    )));
+
+    let camera = Camera::new(
+        16.0 / 9.0,
+        image_width,
+        20.0,
+        Point3::new(13.0, 2.0, 3.0),
+        Point3::new(0.0, 0.0, 0.0),
+        Vec3::new(0.0, 1.0, 0.0),
+        0.6,
+        10.0,
+    )
+    .expect("scene camera settings are valid");
+
+    Demo {
+        world: Bvh::new(objects),
+        camera,
+        background: Arc::new(Gradient::default()),
+    }
+}
+
+/// Cornell box: a 555-unit room with a red left wall, a green right wall and
+/// two white blocks. Without area lights, the light is a hole in the ceiling
+/// open to a white background, marked as a portal. The camera looks in through the missing front
+/// wall from a black tunnel, so no other light gets in.
+pub fn cornell_box(image_width: u32) -> Demo {
+    let _profile = profile::scope(Stage::SceneBuild);
+    let red: Arc<dyn Material> = Arc::new(Lambertian::new(&Color::new(0.65, 0.05, 0.05)));
+    let white: Arc<dyn Material> = Arc::new(Lambertian::new(&Color::new(0.73, 0.73, 0.73)));
+    let green: Arc<dyn Material> = Arc::new(Lambertian::new(&Color::new(0.12, 0.45, 0.15)));
+    let black: Arc<dyn Material> = Arc::new(Lambertian::new(&Color::new(0.0, 0.0, 0.0)));
+
+    let p = Point3::new;
+    let (s, near) = (555.0, -810.0);
+    let mut objects: Vec<Arc<dyn Hittable>> = vec![
+        // Room.
+        rect(p(s, 0.0, 0.0), p(s, s, s), &green),
+        rect(p(0.0, 0.0, 0.0), p(0.0, s, s), &red),
+        rect(p(0.0, 0.0, 0.0), p(s, 0.0, s), &white),
+        rect(p(0.0, 0.0, s), p(s, s, s), &white),
+        // Ceiling around the light opening, from x = 213 to 343 and z = 227 to 332.
+        rect(p(0.0, s, 0.0), p(213.0, s, s), &white),
+        rect(p(343.0, s, 0.0), p(s, s, s), &white),
+        rect(p(213.0, s, 0.0), p(343.0, s, 227.0), &white),
+        rect(p(213.0, s, 332.0), p(343.0, s, s), &white),
+        // Tunnel around the camera.
+        rect(p(s, 0.0, near), p(s, s, 0.0), &black),
+        rect(p(0.0, 0.0, near), p(0.0, s, 0.0), &black),
+        rect(p(0.0, 0.0, near), p(s, 0.0, 0.0), &black),
+        rect(p(0.0, s, near), p(s, s, 0.0), &black),
+        rect(p(0.0, 0.0, near), p(s, s, near), &black),
+    ];
+
+    let tall = Arc::new(cuboid(&Vec3::new(165.0, 330.0, 165.0), &white));
+    let tall_placement = Transform::rotation(&Vec3::new(0.0, 1.0, 0.0), 15.0)
+        .then(&Transform::translation(&Vec3::new(265.0, 0.0, 295.0)));
+    objects.push(Arc::new(Instance::new(tall, tall_placement)));
+
+    let short = Arc::new(cuboid(&Vec3::new(165.0, 165.0, 165.0), &white));
+    let short_placement = Transform::rotation(&Vec3::new(0.0, 1.0, 0.0), -18.0)
+        .then(&Transform::translation(&Vec3::new(130.0, 0.0, 65.0)));
+    objects.push(Arc::new(Instance::new(short, short_placement)));
+
+    let opening = Portal::new(
+        p(213.0, s, 227.0),
+        Vec3::new(130.0, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, 105.0),
+    );
+    let sky =
+        Portals::new(Arc::new(Uniform::new(Color::new(15.0, 15.0, 15.0)))).with_portal(opening);
+
+    let camera = Camera::new(
+        1.0,
+        image_width,
+        40.0,
+        Point3::new(278.0, 278.0, -800.0),
+        Point3::new(278.0, 278.0, 0.0),
+        Vec3::new(0.0, 1.0, 0.0),
+        0.0,
+        10.0,
+    )
+    .expect("scene camera settings are valid");
+
+    Demo {
+        world: Bvh::new(objects),
+        camera,
+        background: Arc::new(sky),
+    }
+}
+
+/// Random color with each channel uniform in `[min, max]`.
+fn random_color(min: Float, max: Float) -> Color {
+    Color::new(
+        random::gen_range(min, max) as f32,
+        random::gen_range(min, max) as f32,
+        random::gen_range(min, max) as f32,
+    )
+}
+
+/// Axis-aligned rectangle spanning from `min` to `max`, which share one coordinate.
+fn rect(min: Point3, max: Point3, material: &Arc<dyn Material>) -> Arc<dyn Hittable> {
+    let flat = Axis::ALL
+        .into_iter()
+        .find(|&axis| min[axis] == max[axis])
+        .unwrap_or(Axis::Z);
+    let (i, j) = (flat.next(), flat.next().next());
+    let corner = |a: Float, b: Float| {
+        let mut p = min;
+        p[i] = a;
+        p[j] = b;
+        p
+    };
+    let vertices = [
+        corner(min[i], min[j]),
+        corner(max[i], min[j]),
+        corner(max[i], max[j]),
+        corner(min[i], max[j]),
+    ];
+    Arc::new(TriangleMesh::new(
+        &vertices,
+        &[[0, 1, 2], [0, 2, 3]],
+        material.clone(),
+    ))
+}
+
+/// Box spanning from the origin to `size`.
+fn cuboid(size: &Vec3, material: &Arc<dyn Material>) -> TriangleMesh {
+    let vertices: Vec<Point3> = (0..8)
+        .map(|i| {
+            Point3::new(
+                if i & 1 == 1 { size.x() } else { 0.0 },
+                if i & 2 == 2 { size.y() } else { 0.0 },
+                if i & 4 == 4 { size.z() } else { 0.0 },
+            )
+        })
+        .collect();
+    let faces = [
+        [0, 2, 3, 1],
+        [4, 5, 7, 6],
+        [0, 1, 5, 4],
+        [2, 6, 7, 3],
+        [0, 4, 6, 2],
+        [1, 3, 7, 5],
+    ];
+    let indices: Vec<[usize; 3]> = faces
+        .iter()
+        .flat_map(|[a, b, c, d]| [[*a, *b, *c], [*a, *c, *d]])
+        .collect();
+    TriangleMesh::new(&vertices, &indices, material.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Interval, Ray};
+
+    #[test]
+    fn scenes_random_spheres() {
+        // The ground and the three large spheres are always there.
+        assert_eq!(random_spheres(1, 0.0, 16).world.len(), 4);
+
+        let a = random_spheres(7, 0.5, 16);
+        let b = random_spheres(7, 0.5, 16);
+        assert_eq!(a.world.len(), b.world.len());
+        assert!(a.world.len() > 100 && a.world.len() < 400);
+        assert!(random_spheres(7, 1.0, 16).world.len() > a.world.len());
+    }
+
+    #[test]
+    fn scenes_cornell_box() {
+        let demo = cornell_box(8);
+        let bounds = Interval::new(0.001, Float::INFINITY);
+
+        // The camera sees into the room, and light only enters through the
+        // ceiling opening.
+        let view = Ray::new(demo.camera.look_from, Vec3::new(0.0, 0.0, 1.0));
+        assert!(demo.world.hit(&view, &bounds).unwrap().p.z() > 0.0);
+        let up = Vec3::new(0.0, 1.0, 0.0);
+        assert!(demo
+            .world
+            .hit(&Ray::new(Point3::new(278.0, 500.0, 280.0), up), &bounds)
+            .is_none());
+        for origin in [
+            Point3::new(100.0, 500.0, 100.0),
+            Point3::new(278.0, 278.0, -500.0),
+        ] {
+            for direction in [up, -up, Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0)] {
+                assert!(demo
+                    .world
+                    .hit(&Ray::new(origin, direction), &bounds)
+                    .is_some());
+            }
+        }
+    }
+}
@@ -0,0 +1,305 @@
+use std::sync::Arc;
+
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable};
+use crate::material::Material;
+use crate::{Float, Interval, Point3, Ray, Vec3};
+
+/// Hittable surface defined as the zero set of a signed distance function,
+/// intersected by sphere tracing. The distance function must never
+/// overestimate the distance to the surface.
+#[derive(Clone)]
+pub struct Sdf<F>
+where
+    F: Fn(&Point3) -> Float + Send + Sync,
+{
+    /// Signed distance function. Negative inside the surface.
+    distance: F,
+
+    /// Surface material.
+    material: Arc<dyn Material>,
+
+    /// Maximum number of marching steps per ray.
+    max_steps: u32,
+
+    /// Distance below which the surface is considered hit.
+    epsilon: Float,
+
+    /// Farthest distance along a ray that is marched.
+    max_distance: Float,
+
+    /// Box known to contain the surface.
+    bbox: Aabb,
+}
+
+impl<F> Sdf<F>
+where
+    F: Fn(&Point3) -> Float + Send + Sync,
+{
+    /// Creates a new signed distance field surface.
+    pub fn new(distance: F, material: Arc<dyn Material>) -> Self {
+        Self {
+            distance,
+            material,
+            max_steps: 256,
+            epsilon: 1e-5,
+            max_distance: 1e3,
+            bbox: Aabb::UNIVERSE,
+        }
+    }
+
+    /// Sets a box known to contain the surface. Rays outside of it are not
+    /// marched, and acceleration structures can cull the surface. Unbounded by default.
+    pub fn with_bounds(mut self, bbox: Aabb) -> Self {
+        self.bbox = bbox;
+        self
+    }
+
+    /// Sets the maximum number of marching steps per ray.
+    pub fn with_max_steps(mut self, max_steps: u32) -> Self {
+        self.max_steps = max_steps;
+        self
+    }
+
+    /// Sets the distance below which the surface is considered hit.
+    pub fn with_epsilon(mut self, epsilon: Float) -> Self {
+        self.epsilon = epsilon;
+        self
+    }
+
+    /// Sets the farthest distance along a ray that is marched.
+    pub fn with_max_distance(mut self, max_distance: Float) -> Self {
+        self.max_distance = max_distance;
+        self
+    }
+
+    /// Estimates the outward surface normal from the gradient of the distance
+    /// function using central differences.
+    fn normal(&self, p: &Point3) -> Vec3 {
+        let h = self.epsilon;
+        let dx = Vec3::new(h, 0.0, 0.0);
+        let dy = Vec3::new(0.0, h, 0.0);
+        let dz = Vec3::new(0.0, 0.0, h);
+
+        let gradient = Vec3::new(
+            (self.distance)(&(p + dx)) - (self.distance)(&(p - dx)),
+            (self.distance)(&(p + dy)) - (self.distance)(&(p - dy)),
+            (self.distance)(&(p + dz)) - (self.distance)(&(p - dz)),
+        );
+
+        gradient.unit()
+    }
+}
+
+impl<F> Hittable for Sdf<F>
+where
+    F: Fn(&Point3) -> Float + Send + Sync,
+{
+    fn hit(&self, ray: &Ray, ray_t: &Interval) -> Option<HitRecord> {
+        // March in world units, then convert back to the ray's parameterization.
+        let clipped = self.bbox.clip(ray, ray_t)?;
+
+        let dir_len = ray.direction().len();
+        let t_max = Float::min(clipped.max(), self.max_distance / dir_len);
+
+        // Rays spawned from a surface start within `epsilon` of it. Hits only
+        // count once the ray has left that shell, or it would find the surface
+        // it leaves at t = 0 instead of the next one along the way.
+        let mut escaped = false;
+        let mut t = clipped.min();
+        for _ in 0..self.max_steps {
+            if t > t_max {
+                return None;
+            }
+
+            let p = ray.at(t);
+
+            // Rays that start inside the surface march on the absolute distance.
+            let d = Float::abs((self.distance)(&p));
+            if d >= self.epsilon {
+                escaped = true;
+            } else if escaped {
+                if !ray_t.surrounds(t) {
+                    return None;
+                }
+                let outward_normal = self.normal(&p);
+                return Some(HitRecord::new(&p, &outward_normal, t, ray, &*self.material));
+            }
+
+            t += d.max(self.epsilon) / dir_len;
+        }
+
+        None
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+}
+
+/// Distance to a sphere.
+pub fn sphere(p: &Point3, center: &Point3, radius: Float) -> Float {
+    (p - center).len() - radius
+}
+
+/// Distance to an axis-aligned box with the given half extents.
+pub fn cuboid(p: &Point3, center: &Point3, half_extents: &Vec3) -> Float {
+    let q = p - center;
+    let q = Vec3::new(
+        q.x().abs() - half_extents.x(),
+        q.y().abs() - half_extents.y(),
+        q.z().abs() - half_extents.z(),
+    );
+
+    let outside = Vec3::new(q.x().max(0.0), q.y().max(0.0), q.z().max(0.0)).len();
+    let inside = Float::min(q.x().max(q.y()).max(q.z()), 0.0);
+    outside + inside
+}
+
+/// Distance to a torus lying in the XZ plane. `major` is the ring radius and
+/// `minor` is the tube radius.
+pub fn torus(p: &Point3, center: &Point3, major: Float, minor: Float) -> Float {
+    let q = p - center;
+    let ring = Float::sqrt(q.x() * q.x() + q.z() * q.z()) - major;
+    Float::sqrt(ring * ring + q.y() * q.y()) - minor
+}
+
+/// Distance to a capsule, the set of points within `radius` of the segment `ab`.
+pub fn capsule(p: &Point3, a: &Point3, b: &Point3, radius: Float) -> Float {
+    let pa = p - a;
+    let ba = b - a;
+    let h = (Vec3::dot(&pa, &ba) / ba.len_sqr()).clamp(0.0, 1.0);
+    (pa - h * ba).len() - radius
+}
+
+/// Distance to the plane through `point` with unit normal `normal`.
+pub fn plane(p: &Point3, point: &Point3, normal: &Vec3) -> Float {
+    Vec3::dot(&(p - point), normal)
+}
+
+/// Union of two distance fields.
+pub fn union(d1: Float, d2: Float) -> Float {
+    Float::min(d1, d2)
+}
+
+/// Intersection of two distance fields.
+pub fn intersection(d1: Float, d2: Float) -> Float {
+    Float::max(d1, d2)
+}
+
+/// Subtracts the second distance field from the first.
+pub fn subtraction(d1: Float, d2: Float) -> Float {
+    Float::max(d1, -d2)
+}
+
+/// Union of two distance fields, blended over a distance of `k`.
+pub fn smooth_union(d1: Float, d2: Float, k: Float) -> Float {
+    let h = (0.5 + 0.5 * (d2 - d1) / k).clamp(0.0, 1.0);
+    lerp(d2, d1, h) - k * h * (1.0 - h)
+}
+
+/// Intersection of two distance fields, blended over a distance of `k`.
+pub fn smooth_intersection(d1: Float, d2: Float, k: Float) -> Float {
+    let h = (0.5 - 0.5 * (d2 - d1) / k).clamp(0.0, 1.0);
+    lerp(d2, d1, h) + k * h * (1.0 - h)
+}
+
+/// Subtracts the second distance field from the first, blended over a distance of `k`.
+pub fn smooth_subtraction(d1: Float, d2: Float, k: Float) -> Float {
+    smooth_intersection(d1, -d2, k)
+}
+
+/// Linearly interpolates from `a` to `b`.
+fn lerp(a: Float, b: Float, t: Float) -> Float {
+    a + (b - a) * t
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::Lambertian;
+    use crate::sphere::Sphere;
+    use crate::Color;
+
+    #[test]
+    fn sdf_matches_sphere() {
+        let material = Arc::new(Lambertian::new(&Color::new(0.5, 0.5, 0.5)));
+        let center = Point3::new(0.0, 0.0, -3.0);
+        let sdf = Sdf::new(move |p: &Point3| sphere(p, &center, 1.0), material.clone());
+        let analytic = Sphere::new(center, 1.0, material);
+
+        let ray = Ray::new(Point3::new(0.0, 0.2, 0.0), Vec3::new(0.1, 0.0, -2.0));
+        let bounds = Interval::new(0.001, Float::INFINITY);
+
+        let a = sdf.hit(&ray, &bounds).unwrap();
+        let b = analytic.hit(&ray, &bounds).unwrap();
+        assert!((a.p - b.p).len() < 1e-4);
+        assert!((a.normal - b.normal).len() < 1e-3);
+
+        let miss = Ray::new(Point3::new(0.0, 2.0, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(sdf.hit(&miss, &bounds).is_none());
+    }
+
+    #[test]
+    fn sdf_spawned_rays() {
+        let material = Arc::new(Lambertian::new(&Color::new(0.5, 0.5, 0.5)));
+        let center = Point3::new(0.0, 0.0, -3.0);
+        let sdf = Sdf::new(move |p: &Point3| sphere(p, &center, 1.0), material.clone());
+        let bounds = Interval::new(0.0, Float::INFINITY);
+
+        // A ray continuing into the surface finds its far side, and one
+        // spawned back from there finds the near side again.
+        let ray = Ray::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        let entry = sdf.hit(&ray, &bounds).unwrap();
+        let exit = sdf
+            .hit(&entry.spawn_ray(*ray.direction()), &bounds)
+            .unwrap();
+        assert!((exit.p.z() + 4.0).abs() < 1e-4);
+        let back = sdf.hit(&exit.spawn_ray(-ray.direction()), &bounds).unwrap();
+        assert!((back.p.z() + 2.0).abs() < 1e-4);
+        assert!(sdf
+            .hit(&exit.spawn_ray(*ray.direction()), &bounds)
+            .is_none());
+
+        // In a concave union, rays leaving one lobe find the other.
+        let (a, b) = (Point3::new(-1.5, 0.0, -3.0), Point3::new(1.5, 0.0, -3.0));
+        let lobes = Sdf::new(
+            move |p: &Point3| union(sphere(p, &a, 1.0), sphere(p, &b, 1.0)),
+            material,
+        );
+        let ray = Ray::new(Point3::new(0.0, 0.0, -3.0), Vec3::new(-1.0, 0.0, 0.0));
+        let rec = lobes.hit(&ray, &bounds).unwrap();
+        let across = lobes
+            .hit(&rec.spawn_ray(-ray.direction()), &bounds)
+            .unwrap();
+        assert!((across.p.x() - 0.5).abs() < 1e-4);
+    }
+
+    #[test]
+    fn sdf_primitives() {
+        let origin = Point3::new(0.0, 0.0, 0.0);
+        let p = Point3::new(2.0, 0.0, 0.0);
+
+        assert!((sphere(&p, &origin, 1.0) - 1.0).abs() < 1e-12);
+        assert!((cuboid(&p, &origin, &Vec3::new(1.0, 1.0, 1.0)) - 1.0).abs() < 1e-12);
+        assert!((cuboid(&origin, &origin, &Vec3::new(1.0, 2.0, 3.0)) + 1.0).abs() < 1e-12);
+        assert!((torus(&p, &origin, 1.5, 0.25) - 0.25).abs() < 1e-12);
+        assert!((capsule(&p, &origin, &Point3::new(0.0, 1.0, 0.0), 0.5) - 1.5).abs() < 1e-12);
+        assert!((plane(&p, &origin, &Vec3::new(1.0, 0.0, 0.0)) - 2.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn sdf_operators() {
+        assert_eq!(union(1.0, 2.0), 1.0);
+        assert_eq!(intersection(1.0, 2.0), 2.0);
+        assert_eq!(subtraction(1.0, 2.0), 1.0);
+
+        // Smooth operators agree with the sharp ones far from the blend region
+        // and never exceed them within it.
+        assert!((smooth_union(1.0, 5.0, 0.5) - 1.0).abs() < 1e-12);
+        assert!(smooth_union(1.0, 1.1, 0.5) < 1.0);
+        assert!((smooth_intersection(1.0, 5.0, 0.5) - 5.0).abs() < 1e-12);
+        assert!(smooth_intersection(1.0, 1.1, 0.5) > 1.1);
+        assert!((smooth_subtraction(1.0, -5.0, 0.5) - 5.0).abs() < 1e-12);
+    }
+}
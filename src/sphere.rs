@@ -1,57 +1,109 @@
 use std::sync::Arc;
 
+use crate::aabb::Aabb;
+use crate::diagnostic::{self, Diagnostic};
 use crate::hittable::{HitRecord, Hittable};
 use crate::material::Material;
-use crate::{Interval, Point3, Ray, Vec3};
+use crate::{Float, Interval, Point3, Ray, Vec3};
 
 /// Sphere object in world space and material.
 #[derive(Clone)]
 pub struct Sphere {
     center: Point3,
-    radius: f64,
+    radius: Float,
     material: Arc<dyn Material>,
 }
 
 impl Sphere {
     /// Creates a new sphere.
-    pub fn new(center: Point3, radius: f64, material: Arc<dyn Material>) -> Self {
+    pub fn new(center: Point3, radius: Float, material: Arc<dyn Material>) -> Self {
         Self {
             center,
             radius,
             material,
         }
     }
-}
 
-impl Hittable for Sphere {
-    fn hit(&self, ray: &Ray, ray_t: &Interval) -> Option<HitRecord> {
+    /// Computes surface coordinates from a point on the unit sphere. `u` is the
+    /// angle around the Y axis from X = -1, and `v` is the angle from Y = -1 to Y = +1,
+    /// both normalized to `[0, 1]`.
+    pub(crate) fn uv(p: &Point3) -> (Float, Float) {
+        let theta = Float::acos((-p.y()).clamp(-1.0, 1.0));
+        let phi = Float::atan2(-p.z(), p.x()) + crate::consts::PI;
+
+        (phi / (2.0 * crate::consts::PI), theta / crate::consts::PI)
+    }
+
+    /// Finds the ray parameters where the ray enters and leaves the sphere
+    /// at `center` with `radius`.
+    pub(crate) fn roots(center: &Point3, radius: Float, ray: &Ray) -> Option<[Float; 2]> {
         // Use discriminant to determine number of intersections
-        let oc = ray.origin() - self.center;
+        let oc = ray.origin() - center;
         let a = ray.direction().len_sqr();
         let half_b = Vec3::dot(&oc, ray.direction());
-        let c = oc.len_sqr() - self.radius * self.radius;
+        let c = oc.len_sqr() - radius * radius;
 
         let discriminant = half_b * half_b - a * c;
         if discriminant < 0.0 {
             return None;
         }
 
-        let sqrtd = f64::sqrt(discriminant);
+        let sqrtd = Float::sqrt(discriminant);
+        Some([(-half_b - sqrtd) / a, (-half_b + sqrtd) / a])
+    }
+
+    /// Builds the hit record where `ray` meets the sphere at `center` with
+    /// `radius` at `t`. The hit point is projected back onto the surface, but
+    /// it is only known to within the rounding of the sphere's own coordinates,
+    /// which dwarfs the rounding of `p` when the sphere is large or far away,
+    /// so the record carries that bound as its error.
+    pub(crate) fn record<'a>(
+        center: &Point3,
+        radius: Float,
+        ray: &Ray,
+        t: Float,
+        material: &'a dyn Material,
+    ) -> HitRecord<'a> {
+        let outward_normal = (ray.at(t) - center).unit();
+        let p = center + radius * outward_normal;
+        let (u, v) = Self::uv(&outward_normal);
+        let error = 8.0 * Float::EPSILON * (center.len() + radius);
 
-        // Take the first root where there is a hit
-        let mut root = (-half_b - sqrtd) / a;
-        if !ray_t.surrounds(root) {
-            root = (-half_b + sqrtd) / a;
-            if !ray_t.surrounds(root) {
-                return None;
-            }
-        }
+        HitRecord::new(&p, &outward_normal, t, ray, material)
+            .with_uv(u, v)
+            .with_error(error)
+    }
+}
 
-        // Compute the normal, i.e. the reflected ray
-        let t = root;
-        let p = ray.at(root);
-        let outward_normal = (p - self.center) / self.radius;
+impl Hittable for Sphere {
+    fn hit(&self, ray: &Ray, ray_t: &Interval) -> Option<HitRecord> {
+        // Take the first root where there is a hit that the material keeps.
+        Self::roots(&self.center, self.radius, ray)?
+            .into_iter()
+            .filter(|&t| ray_t.surrounds(t))
+            .map(|t| Self::record(&self.center, self.radius, ray, t, &*self.material))
+            .find(|rec| !rec.is_masked())
+    }
+
+    fn validate(&self) -> Vec<Diagnostic> {
+        let mut diagnostics = self.material.validate();
+        if !diagnostic::is_finite(&self.center) {
+            diagnostics.push(Diagnostic::error(&format!(
+                "sphere center {} is not finite",
+                self.center
+            )));
+        }
+        if !(self.radius > 0.0 && self.radius.is_finite()) {
+            diagnostics.push(Diagnostic::error(&format!(
+                "sphere radius must be positive and finite (given {})",
+                self.radius
+            )));
+        }
+        diagnostics
+    }
 
-        Some(HitRecord::new(&p, &outward_normal, t, ray, &*self.material))
+    fn bounding_box(&self) -> Aabb {
+        let r = Vec3::new(self.radius, self.radius, self.radius);
+        Aabb::new(&(self.center - r), &(self.center + r))
     }
 }
@@ -0,0 +1,218 @@
+use crate::camera::{Camera, Eye, Lens, Ods};
+use crate::{Color, Error, Float, Vec3};
+
+/// Arrangement of the two eyes' images in a stereo frame.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum StereoLayout {
+    /// Left eye on the left half, right eye on the right half.
+    #[default]
+    SideBySide,
+
+    /// Left eye on the top half, right eye on the bottom half.
+    TopBottom,
+
+    /// Red/cyan anaglyph, for viewing with colored glasses: the left eye's
+    /// red channel with the right eye's green and blue channels. Saturated
+    /// colors reach only one eye, so they may shimmer.
+    Anaglyph,
+}
+
+/// Stereo rig deriving a pair of eye cameras from a center camera, for 3D
+/// displays. The eyes look in parallel and shift their images toward each
+/// other (see `Lens::shift`), so objects at the convergence distance appear
+/// on the screen plane without the keystone distortion of toed-in eyes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Stereo {
+    /// Distance between the eyes.
+    interocular: Float,
+
+    /// Distance from the eyes to the plane seen without parallax.
+    convergence: Float,
+
+    /// Arrangement of the images in the frame.
+    layout: StereoLayout,
+
+    /// Whether the eyes see 360° panoramas instead of perspective images.
+    omnidirectional: bool,
+}
+
+impl Stereo {
+    /// Create a new side-by-side stereo rig.
+    pub fn new(interocular: Float, convergence: Float) -> Result<Self, Error> {
+        if !(interocular >= 0.0 && interocular.is_finite()) {
+            return Err(Error::new_camera(&format!(
+                "interocular distance must be non-negative and finite (given {interocular})"
+            )));
+        }
+        if convergence.is_nan() || convergence <= 0.0 {
+            return Err(Error::new_camera(&format!(
+                "convergence distance must be greater than 0 (given {convergence})"
+            )));
+        }
+
+        Ok(Self {
+            interocular,
+            convergence,
+            layout: StereoLayout::default(),
+            omnidirectional: false,
+        })
+    }
+
+    /// Create a new rig rendering omni-directional stereo panoramas (see
+    /// `Ods`), stacked top-bottom as VR players expect. Give the eyes'
+    /// camera an aspect ratio of 2. The eyes converge at infinity.
+    pub fn omnidirectional(interocular: Float) -> Result<Self, Error> {
+        let rig = Self::new(interocular, Float::INFINITY)?;
+        Ok(Self {
+            layout: StereoLayout::TopBottom,
+            omnidirectional: true,
+            ..rig
+        })
+    }
+
+    /// Set the arrangement of the images in the frame.
+    pub fn with_layout(mut self, layout: StereoLayout) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    /// Retrieve the arrangement of the images in the frame.
+    pub fn layout(&self) -> StereoLayout {
+        self.layout
+    }
+
+    /// Create the left and right eye cameras, each offset from `camera` by
+    /// half the interocular distance and keeping its other settings.
+    pub fn eyes(&self, camera: &Camera) -> (Camera, Camera) {
+        if self.omnidirectional {
+            let eye = |eye| camera.clone().with_ods(Ods::new(eye, self.interocular));
+            return (eye(Eye::Left), eye(Eye::Right));
+        }
+
+        let forward = (camera.look_at - camera.look_from).unit();
+        let right = Vec3::cross(&forward, &camera.vup).unit();
+        let half = 0.5 * self.interocular;
+
+        // Shift each image by the eye's offset as seen at the convergence
+        // distance, in units of half the image height.
+        let half_height = (0.5 * camera.vfov).to_radians().tan();
+        let shift = half / (self.convergence * half_height);
+
+        let eye = |side: Float| {
+            let offset = side * half * right;
+            let moved = camera.with_view(
+                camera.look_from + offset,
+                camera.look_at + offset,
+                camera.vfov,
+            );
+            let lens = *camera.lens();
+            let [x, y] = lens.shift;
+            moved.with_lens(Lens {
+                shift: [x - side * shift, y],
+                ..lens
+            })
+        };
+        (eye(-1.0), eye(1.0))
+    }
+
+    /// Pixel dimensions of the stereo frame for a camera's image size.
+    pub fn dim(&self, camera: &Camera) -> (u32, u32) {
+        let (width, height) = camera.dim();
+        match self.layout {
+            StereoLayout::SideBySide => (2 * width, height),
+            StereoLayout::TopBottom => (width, 2 * height),
+            StereoLayout::Anaglyph => (width, height),
+        }
+    }
+
+    /// Arrange the left and right images, each `width` by `height`, into a
+    /// stereo frame.
+    pub fn pack(&self, left: &[Color], right: &[Color], width: u32) -> Vec<Color> {
+        match self.layout {
+            StereoLayout::SideBySide => left
+                .chunks(width as usize)
+                .zip(right.chunks(width as usize))
+                .flat_map(|(l, r)| l.iter().chain(r))
+                .copied()
+                .collect(),
+            StereoLayout::TopBottom => left.iter().chain(right).copied().collect(),
+            StereoLayout::Anaglyph => left
+                .iter()
+                .zip(right)
+                .map(|(l, r)| Color::new(l.r(), r.g(), r.b()))
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Point3;
+
+    fn camera() -> Camera {
+        Camera::new(
+            1.0,
+            8,
+            90.0,
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(0.0, 0.0, -1.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            0.0,
+            1.0,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn stereo_eyes() {
+        let stereo = Stereo::new(0.2, 4.0).unwrap();
+        let (left, right) = stereo.eyes(&camera());
+        assert!(left.look_from.almost_eq(&Point3::new(-0.1, 0.0, 0.0)));
+        assert!(right.look_from.almost_eq(&Point3::new(0.1, 0.0, 0.0)));
+
+        // A point at the convergence distance lands on the same pixel in both
+        // eyes; nearer points are seen apart.
+        let far = Point3::new(0.0, 0.0, -4.0);
+        let (l, r) = (left.project(&far).unwrap(), right.project(&far).unwrap());
+        assert!((l.0 - r.0).abs() < 1e-4 && (l.1 - r.1).abs() < 1e-4);
+        let near = Point3::new(0.0, 0.0, -1.0);
+        let (l, r) = (left.project(&near).unwrap(), right.project(&near).unwrap());
+        assert!(l.0 > r.0 + 0.5);
+
+        assert!(Stereo::new(0.2, 0.0).is_err());
+        assert!(Stereo::new(-1.0, 1.0).is_err());
+    }
+
+    #[test]
+    fn stereo_omnidirectional() {
+        let stereo = Stereo::omnidirectional(0.064).unwrap();
+        assert_eq!(stereo.layout(), StereoLayout::TopBottom);
+
+        let (left, right) = stereo.eyes(&camera());
+        assert_eq!(left.ods().unwrap().eye, Eye::Left);
+        assert_eq!(right.ods().unwrap().interpupillary, 0.064);
+        assert!(left.look_from.almost_eq(&camera().look_from));
+    }
+
+    #[test]
+    fn stereo_pack() {
+        let (l, r) = (Color::new(1.0, 0.0, 0.0), Color::new(0.0, 0.0, 1.0));
+        let (left, right) = (vec![l; 4], vec![r; 4]);
+
+        let side = Stereo::new(0.1, 1.0).unwrap();
+        assert_eq!(side.pack(&left, &right, 2), [l, l, r, r, l, l, r, r]);
+        assert_eq!(side.dim(&camera()), (16, 8));
+
+        let stacked = side.with_layout(StereoLayout::TopBottom);
+        assert_eq!(stacked.pack(&left, &right, 2), [l, l, l, l, r, r, r, r]);
+        assert_eq!(stacked.dim(&camera()), (8, 16));
+
+        let anaglyph = side.with_layout(StereoLayout::Anaglyph);
+        let left = [Color::new(0.2, 0.4, 0.6); 2];
+        let right = [Color::new(0.8, 0.5, 0.3); 2];
+        let merged = Color::new(0.2, 0.5, 0.3);
+        assert_eq!(anaglyph.pack(&left, &right, 2), [merged, merged]);
+        assert_eq!(anaglyph.dim(&camera()), (8, 8));
+    }
+}
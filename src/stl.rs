@@ -0,0 +1,198 @@
+use std::error::Error as StdError;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::material::Material;
+use crate::mesh::{Shading, TriangleMesh};
+use crate::trace;
+use crate::{Error, Float, Point3};
+
+/// Size of the binary STL header in bytes.
+const HEADER_SIZE: usize = 80;
+
+/// Size of a binary STL triangle record in bytes.
+const TRIANGLE_SIZE: usize = 50;
+
+/// Loads a binary or ASCII STL file into a triangle mesh.
+pub fn load<P>(
+    path: P,
+    material: Arc<dyn Material>,
+    shading: Shading,
+) -> Result<TriangleMesh, Box<dyn StdError>>
+where
+    P: AsRef<Path>,
+{
+    let bytes = std::fs::read(path)?;
+    Ok(parse(&bytes, material, shading)?)
+}
+
+/// Parses binary or ASCII STL data into a triangle mesh.
+/// The stored facet normals are ignored in favour of the vertex winding.
+/// STL has no vertex normals, so smooth shading derives them from the faces.
+pub fn parse(
+    bytes: &[u8],
+    material: Arc<dyn Material>,
+    shading: Shading,
+) -> Result<TriangleMesh, Error> {
+    trace::span!(DEBUG, "stl_parse", bytes = bytes.len());
+
+    let vertices = if is_binary(bytes) {
+        parse_binary(bytes)?
+    } else if bytes.trim_ascii_start().starts_with(b"solid") {
+        parse_ascii(bytes)?
+    } else {
+        return Err(Error::new_mesh("unrecognized STL data"));
+    };
+
+    let indices: Vec<[usize; 3]> = (0..vertices.len() / 3)
+        .map(|i| [3 * i, 3 * i + 1, 3 * i + 2])
+        .collect();
+
+    Ok(TriangleMesh::with_shading(
+        &vertices, &indices, material, shading,
+    ))
+}
+
+/// Determines whether the data is a binary STL. Binary files may also begin
+/// with "solid", so the size implied by the triangle count is checked instead.
+fn is_binary(bytes: &[u8]) -> bool {
+    if bytes.len() < HEADER_SIZE + 4 {
+        return false;
+    }
+    // The count comes from the file, so the implied size must not overflow
+    // where `usize` is 32 bits wide.
+    let count = u32::from_le_bytes([bytes[80], bytes[81], bytes[82], bytes[83]]) as usize;
+    count
+        .checked_mul(TRIANGLE_SIZE)
+        .and_then(|n| n.checked_add(HEADER_SIZE + 4))
+        == Some(bytes.len())
+}
+
+/// Reads the vertices of a binary STL, three per triangle.
+fn parse_binary(bytes: &[u8]) -> Result<Vec<Point3>, Error> {
+    let read_f32 = |offset: usize| {
+        f32::from_le_bytes([
+            bytes[offset],
+            bytes[offset + 1],
+            bytes[offset + 2],
+            bytes[offset + 3],
+        ]) as Float
+    };
+
+    let count = (bytes.len() - HEADER_SIZE - 4) / TRIANGLE_SIZE;
+    let mut vertices = Vec::with_capacity(3 * count);
+    for i in 0..count {
+        // Skip the 12-byte facet normal.
+        let record = HEADER_SIZE + 4 + i * TRIANGLE_SIZE + 12;
+        for v in 0..3 {
+            let offset = record + 12 * v;
+            vertices.push(Point3::new(
+                read_f32(offset),
+                read_f32(offset + 4),
+                read_f32(offset + 8),
+            ));
+        }
+    }
+
+    Ok(vertices)
+}
+
+/// Reads the vertices of an ASCII STL, three per facet.
+fn parse_ascii(bytes: &[u8]) -> Result<Vec<Point3>, Error> {
+    let text = std::str::from_utf8(bytes)
+        .map_err(|_| Error::new_mesh("ASCII STL data is not valid UTF-8"))?;
+
+    let mut vertices = Vec::new();
+    let mut tokens = text.split_ascii_whitespace();
+    while let Some(token) = tokens.next() {
+        if token != "vertex" {
+            continue;
+        }
+
+        let mut coord = || -> Result<Float, Error> {
+            let token = tokens
+                .next()
+                .ok_or_else(|| Error::new_mesh("unexpected end of STL vertex"))?;
+            token
+                .parse()
+                .map_err(|_| Error::new_mesh(&format!("invalid STL coordinate '{token}'")))
+        };
+        vertices.push(Point3::new(coord()?, coord()?, coord()?));
+    }
+
+    if vertices.len() % 3 != 0 {
+        return Err(Error::new_mesh(&format!(
+            "STL facets must have 3 vertices (found {} vertices)",
+            vertices.len()
+        )));
+    }
+
+    Ok(vertices)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::Lambertian;
+    use crate::Color;
+
+    fn material() -> Arc<dyn Material> {
+        Arc::new(Lambertian::new(&Color::new(0.5, 0.5, 0.5)))
+    }
+
+    #[test]
+    fn stl_ascii() {
+        let data = b"solid test
+  facet normal 0 0 1
+    outer loop
+      vertex 0 0 0
+      vertex 1 0 0
+      vertex 0 1 0
+    endloop
+  endfacet
+  facet normal 0 0 1
+    outer loop
+      vertex 1 0 0
+      vertex 1 1 0
+      vertex 0 1.0e0 0
+    endloop
+  endfacet
+endsolid test
+";
+        let mesh = parse(data, material(), Shading::Flat).unwrap();
+        assert_eq!(mesh.len(), 2);
+
+        let truncated = b"solid test\n facet normal 0 0 1\n outer loop\n vertex 0 0 0\n vertex 1 0";
+        assert!(parse(truncated, material(), Shading::Flat).is_err());
+        let invalid = b"solid test\n vertex 0 0 x\n";
+        assert!(parse(invalid, material(), Shading::Flat).is_err());
+    }
+
+    #[test]
+    fn stl_binary() {
+        // Binary files may start with "solid" too.
+        let mut data = b"solid binary".to_vec();
+        data.resize(HEADER_SIZE, 0);
+        data.extend_from_slice(&1u32.to_le_bytes());
+        for value in [
+            0.0f32, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0,
+        ] {
+            data.extend_from_slice(&value.to_le_bytes());
+        }
+        data.extend_from_slice(&0u16.to_le_bytes());
+
+        let mesh = parse(&data, material(), Shading::Flat).unwrap();
+        assert_eq!(mesh.len(), 1);
+        assert_eq!(
+            mesh.triangles()[0].vertices()[1],
+            Point3::new(1.0, 0.0, 0.0)
+        );
+
+        assert!(parse(b"not an stl", material(), Shading::Flat).is_err());
+
+        // A count too large for the data is not binary, however large.
+        data[HEADER_SIZE..HEADER_SIZE + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(!is_binary(&data));
+        assert!(parse(&data, material(), Shading::Flat).is_err());
+    }
+}
@@ -0,0 +1,210 @@
+use crate::camera::Camera;
+use crate::{Color, Float, Point3};
+
+/// Previous frame kept for reprojection.
+#[derive(Debug, Clone)]
+struct History {
+    /// Camera the frame was seen through.
+    camera: Camera,
+
+    /// Accumulated colors.
+    color: Vec<Color>,
+
+    /// Position pass of the frame.
+    positions: Vec<Option<Point3>>,
+
+    /// Number of frames accumulated into each pixel.
+    frames: Vec<u32>,
+}
+
+/// Accumulates noisy frames over time for interactive previews. Each new
+/// frame is blended with the previous accumulation, warped to follow the
+/// camera motion: every pixel's first hit is projected into the previous
+/// camera to find where the same surface was seen. History is discarded where
+/// the surface was hidden before (disocclusion) and for pixels that miss
+/// the world. Surfaces are assumed static.
+#[derive(Debug, Clone)]
+pub struct TemporalAccumulator {
+    /// Smallest weight given to the newest frame, bounding how long stale
+    /// history lingers.
+    min_weight: f32,
+
+    /// Largest distance between the reprojected and the current hit, relative
+    /// to the distance from the camera, for the history to be reused.
+    tolerance: Float,
+
+    /// Previous frame, if any.
+    history: Option<History>,
+}
+
+impl TemporalAccumulator {
+    /// Create a new accumulator, giving each new frame at least `min_weight`
+    /// of the result.
+    pub fn new(min_weight: f32) -> Self {
+        Self {
+            min_weight: min_weight.clamp(0.0, 1.0),
+            tolerance: 0.02,
+            history: None,
+        }
+    }
+
+    /// Set the largest distance between the reprojected and the current hit,
+    /// relative to the distance from the camera, for the history to be reused.
+    pub fn with_tolerance(mut self, tolerance: Float) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// Forget the accumulated history, e.g. after a cut or a scene edit.
+    pub fn reset(&mut self) {
+        self.history = None;
+    }
+
+    /// Blend a new `frame` seen through `camera`, along with its position
+    /// pass (see `Renderer::render_positions`), into the accumulation and
+    /// return the result. The first frame, or one of a different size, is
+    /// returned as is.
+    pub fn accumulate(
+        &mut self,
+        camera: &Camera,
+        frame: &[Color],
+        positions: &[Option<Point3>],
+    ) -> Vec<Color> {
+        let (width, height) = camera.dim();
+        assert_eq!(frame.len(), (width * height) as usize);
+        assert_eq!(positions.len(), frame.len());
+
+        let history = self
+            .history
+            .take()
+            .filter(|history| history.camera.dim() == camera.dim());
+        let mut color = Vec::with_capacity(frame.len());
+        let mut frames = Vec::with_capacity(frame.len());
+
+        for (sample, position) in frame.iter().zip(positions) {
+            let previous = history.as_ref().zip(*position).and_then(|(history, p)| {
+                let index = self.reproject(history, camera, &p)?;
+                Some((history.color[index], history.frames[index]))
+            });
+
+            match previous {
+                Some((previous, count)) => {
+                    let weight = Float::max(1.0 / (count + 1) as Float, self.min_weight as Float);
+                    let weight = weight as f32;
+                    color.push((1.0 - weight) * previous + weight * *sample);
+                    frames.push(count + 1);
+                }
+                None => {
+                    color.push(*sample);
+                    frames.push(1);
+                }
+            }
+        }
+
+        self.history = Some(History {
+            camera: camera.clone(),
+            color: color.clone(),
+            positions: positions.to_vec(),
+            frames,
+        });
+        color
+    }
+
+    /// Find the pixel of the previous frame that saw the point `p`, if it
+    /// saw the same surface there.
+    fn reproject(&self, history: &History, camera: &Camera, p: &Point3) -> Option<usize> {
+        let (width, height) = history.camera.dim();
+        let (x, y) = history.camera.project(p)?;
+        let (col, row) = (x.round(), y.round());
+        if col < 0.0 || row < 0.0 || col >= width as Float || row >= height as Float {
+            return None;
+        }
+
+        let index = row as usize * width as usize + col as usize;
+        let seen = history.positions[index]?;
+        let distance = (*p - camera.look_from).len();
+        ((seen - *p).len() <= self.tolerance * distance).then_some(index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Vec3;
+
+    fn camera(x: Float) -> Camera {
+        Camera::new(
+            1.0,
+            8,
+            90.0,
+            Point3::new(x, 0.0, 0.0),
+            Point3::new(x, 0.0, -1.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            0.0,
+            1.0,
+        )
+        .unwrap()
+    }
+
+    /// Position pass of a wall at z = -1 seen through `camera`.
+    fn wall(camera: &Camera) -> Vec<Option<Point3>> {
+        let (width, height) = camera.dim();
+        (0..height)
+            .flat_map(|row| (0..width).map(move |col| (row, col)))
+            .map(|(row, col)| Some(camera.get_ray(row, col, 0.0, 0.0).at(1.0)))
+            .collect()
+    }
+
+    #[test]
+    fn temporal_static() {
+        let camera = camera(0.0);
+        let mut accumulator = TemporalAccumulator::new(0.0);
+        let positions = wall(&camera);
+
+        // A still camera averages its frames.
+        let black = vec![Color::new(0.0, 0.0, 0.0); 64];
+        let white = vec![Color::new(1.0, 1.0, 1.0); 64];
+        accumulator.accumulate(&camera, &black, &positions);
+        let mean = accumulator.accumulate(&camera, &white, &positions);
+        assert!(mean.iter().all(|c| c.almost_eq(&Color::new(0.5, 0.5, 0.5))));
+
+        // History is dropped on a reset and for misses.
+        accumulator.reset();
+        let fresh = accumulator.accumulate(&camera, &white, &positions);
+        assert_eq!(fresh, white);
+        let misses = vec![None; 64];
+        assert_eq!(accumulator.accumulate(&camera, &black, &misses), black);
+    }
+
+    #[test]
+    fn temporal_motion() {
+        // The wall spans 2 units over 8 pixels, so moving the camera by a
+        // quarter unit shifts the image by one pixel.
+        let before = camera(0.0);
+        let after = camera(0.25);
+
+        // Color the previous frame by column.
+        let frame: Vec<Color> = (0..64)
+            .map(|i| Color::new((i % 8) as f32, 0.0, 0.0))
+            .collect();
+        let mut accumulator = TemporalAccumulator::new(0.0);
+        accumulator.accumulate(&before, &frame, &wall(&before));
+
+        let zero = vec![Color::new(0.0, 0.0, 0.0); 64];
+        let warped = accumulator.accumulate(&after, &zero, &wall(&after));
+        for (i, c) in warped.iter().enumerate() {
+            let col = i % 8;
+            let expected = if col < 7 { 0.5 * (col + 1) as f32 } else { 0.0 };
+            assert!((c.r() - expected).abs() < 1e-5);
+        }
+
+        // A surface that moved closer counts as disoccluded.
+        let closer: Vec<Option<Point3>> = wall(&after)
+            .iter()
+            .map(|p| p.map(|p| p + Vec3::new(0.0, 0.0, 0.5)))
+            .collect();
+        let mut accumulator = TemporalAccumulator::new(0.0);
+        accumulator.accumulate(&before, &frame, &wall(&before));
+        assert_eq!(accumulator.accumulate(&after, &zero, &closer), zero);
+    }
+}
@@ -0,0 +1,388 @@
+use crate::image::{self, ImageBuffer};
+use crate::noise::ValueNoise;
+use crate::{Color, Float, Point3, Vec3};
+use std::error::Error;
+use std::path::Path;
+
+/// Specifies the color of a surface at a particular point.
+pub trait Texture: Send + Sync {
+    /// Retrieves the texture color at surface coordinates `(u, v)` and
+    /// world-space point `p` with surface normal `normal`.
+    fn value(&self, u: Float, v: Float, p: &Point3, normal: &Vec3) -> Color;
+
+    /// Retrieves the color of a texture that is the same everywhere, if so.
+    fn constant(&self) -> Option<Color> {
+        None
+    }
+}
+
+/// Texture of a single uniform color.
+#[derive(Debug, Clone)]
+pub struct SolidColor {
+    /// Color of the texture.
+    albedo: Color,
+}
+
+impl SolidColor {
+    /// Creates a new solid color texture.
+    pub fn new(albedo: &Color) -> Self {
+        Self { albedo: *albedo }
+    }
+}
+
+impl Texture for SolidColor {
+    #[allow(unused)]
+    fn value(&self, u: Float, v: Float, p: &Point3, normal: &Vec3) -> Color {
+        self.albedo
+    }
+
+    fn constant(&self) -> Option<Color> {
+        Some(self.albedo)
+    }
+}
+
+/// Texture looked up from an image by surface coordinates, with `u` running
+/// left to right and `v` bottom to top. Uses the nearest pixel.
+#[derive(Debug, Clone)]
+pub struct ImageTexture {
+    /// Linear colors of the texture.
+    image: ImageBuffer,
+}
+
+impl ImageTexture {
+    /// Creates a new image texture.
+    pub fn new(image: ImageBuffer) -> Self {
+        Self { image }
+    }
+
+    /// Loads an image texture from a file (see `image::load`).
+    pub fn load<P>(path: P) -> Result<Self, Box<dyn Error>>
+    where
+        P: AsRef<Path>,
+    {
+        Ok(Self::new(image::load(path)?))
+    }
+}
+
+impl Texture for ImageTexture {
+    #[allow(unused)]
+    fn value(&self, u: Float, v: Float, p: &Point3, normal: &Vec3) -> Color {
+        let (width, height) = self.image.dim();
+        let u = u.clamp(0.0, 1.0);
+        let v = 1.0 - v.clamp(0.0, 1.0);
+        let x = ((u * width as Float) as u32).min(width - 1);
+        let y = ((v * height as Float) as u32).min(height - 1);
+        *self.image.get(x, y)
+    }
+}
+
+/// Piecewise-linear mapping from `[0, 1]` to colors.
+#[derive(Debug, Clone)]
+pub struct ColorRamp {
+    /// Sorted positions and their colors.
+    stops: Vec<(Float, Color)>,
+}
+
+impl ColorRamp {
+    /// Creates a new color ramp from `(position, color)` stops.
+    /// Stops are sorted by position.
+    pub fn new(stops: &[(Float, Color)]) -> Self {
+        assert!(!stops.is_empty(), "color ramp needs at least one stop");
+
+        let mut stops = stops.to_vec();
+        stops.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Self { stops }
+    }
+
+    /// Creates a two-stop ramp from `low` at 0 to `high` at 1.
+    pub fn linear(low: &Color, high: &Color) -> Self {
+        Self::new(&[(0.0, *low), (1.0, *high)])
+    }
+
+    /// Samples the ramp at `t`. Values beyond the first and last stops take on
+    /// their colors, and NaN takes on the first.
+    pub fn sample(&self, t: Float) -> Color {
+        let first = self.stops[0];
+        let last = self.stops[self.stops.len() - 1];
+        if t.is_nan() || t <= first.0 {
+            return first.1;
+        }
+        if t >= last.0 {
+            return last.1;
+        }
+
+        let i = self.stops.partition_point(|&(position, _)| position <= t);
+        let (t0, c0) = self.stops[i - 1];
+        let (t1, c1) = self.stops[i];
+        let s = ((t - t0) / (t1 - t0)) as f32;
+        (1.0 - s) * c0 + s * c1
+    }
+}
+
+/// Solid texture blending between two colors by fractal value noise.
+#[derive(Debug, Clone)]
+pub struct NoiseTexture {
+    /// Noise generator.
+    noise: ValueNoise,
+
+    /// Spatial frequency of the noise.
+    scale: Float,
+
+    /// Number of fractal noise octaves.
+    octaves: u32,
+
+    /// Color where the noise is 0.
+    low: Color,
+
+    /// Color where the noise is 1.
+    high: Color,
+}
+
+impl NoiseTexture {
+    /// Creates a new noise texture.
+    pub fn new(noise: ValueNoise, scale: Float, octaves: u32, low: &Color, high: &Color) -> Self {
+        Self {
+            noise,
+            scale,
+            octaves,
+            low: *low,
+            high: *high,
+        }
+    }
+}
+
+impl Texture for NoiseTexture {
+    #[allow(unused)]
+    fn value(&self, u: Float, v: Float, p: &Point3, normal: &Vec3) -> Color {
+        let t = self.noise.fbm(&(self.scale * p), self.octaves) as f32;
+        (1.0 - t) * self.low + t * self.high
+    }
+}
+
+/// Noise texture projected along the three world axes and blended by the
+/// surface normal. Avoids the stretching of a single planar projection and
+/// needs no surface parameterization.
+#[derive(Debug, Clone)]
+pub struct TriplanarNoise {
+    /// Noise generator.
+    noise: ValueNoise,
+
+    /// Spatial frequency of the noise.
+    scale: Float,
+
+    /// Number of fractal noise octaves.
+    octaves: u32,
+
+    /// Exponent applied to the blend weights. Higher values give sharper
+    /// transitions between projections.
+    sharpness: Float,
+
+    /// Color where the noise is 0.
+    low: Color,
+
+    /// Color where the noise is 1.
+    high: Color,
+}
+
+impl TriplanarNoise {
+    /// Creates a new triplanar noise texture.
+    pub fn new(
+        noise: ValueNoise,
+        scale: Float,
+        octaves: u32,
+        sharpness: Float,
+        low: &Color,
+        high: &Color,
+    ) -> Self {
+        Self {
+            noise,
+            scale,
+            octaves,
+            sharpness,
+            low: *low,
+            high: *high,
+        }
+    }
+
+    /// Samples planar noise. Each projection plane is offset so that the
+    /// three planes do not share the same pattern.
+    fn planar(&self, a: Float, b: Float, plane: Float) -> Float {
+        let p = Point3::new(self.scale * a, self.scale * b, 17.0 * plane);
+        self.noise.fbm(&p, self.octaves)
+    }
+}
+
+impl Texture for TriplanarNoise {
+    #[allow(unused)]
+    fn value(&self, u: Float, v: Float, p: &Point3, normal: &Vec3) -> Color {
+        let wx = normal.x().abs().powf(self.sharpness);
+        let wy = normal.y().abs().powf(self.sharpness);
+        let wz = normal.z().abs().powf(self.sharpness);
+        let sum = wx + wy + wz;
+
+        let t = if sum > 0.0 {
+            (wx * self.planar(p.y(), p.z(), 0.0)
+                + wy * self.planar(p.z(), p.x(), 1.0)
+                + wz * self.planar(p.x(), p.y(), 2.0))
+                / sum
+        } else {
+            self.planar(p.x(), p.y(), 2.0)
+        };
+
+        let t = t as f32;
+        (1.0 - t) * self.low + t * self.high
+    }
+}
+
+/// Procedural marble. Sinusoidal veins along an axis, perturbed by turbulence.
+#[derive(Debug, Clone)]
+pub struct Marble {
+    /// Noise generator.
+    noise: ValueNoise,
+
+    /// Vein frequency along the X axis.
+    scale: Float,
+
+    /// Amount that turbulence distorts the veins.
+    turbulence: Float,
+
+    /// Number of turbulence octaves.
+    octaves: u32,
+
+    /// Colors across a vein.
+    ramp: ColorRamp,
+}
+
+impl Marble {
+    /// Creates a new marble texture.
+    pub fn new(
+        noise: ValueNoise,
+        scale: Float,
+        turbulence: Float,
+        octaves: u32,
+        ramp: ColorRamp,
+    ) -> Self {
+        Self {
+            noise,
+            scale,
+            turbulence,
+            octaves,
+            ramp,
+        }
+    }
+}
+
+impl Texture for Marble {
+    #[allow(unused)]
+    fn value(&self, u: Float, v: Float, p: &Point3, normal: &Vec3) -> Color {
+        let turb = self.noise.turbulence(&(self.scale * p), self.octaves);
+        let t = 0.5 * (1.0 + Float::sin(self.scale * p.x() + self.turbulence * turb));
+        self.ramp.sample(t)
+    }
+}
+
+/// Procedural wood grain. Concentric rings around the Y axis, perturbed by turbulence.
+#[derive(Debug, Clone)]
+pub struct Wood {
+    /// Noise generator.
+    noise: ValueNoise,
+
+    /// Number of rings per unit distance from the axis.
+    ring_frequency: Float,
+
+    /// Amount that turbulence distorts the rings.
+    turbulence: Float,
+
+    /// Spatial frequency of the turbulence.
+    noise_scale: Float,
+
+    /// Number of turbulence octaves.
+    octaves: u32,
+
+    /// Colors across a single ring, from its inner to outer edge.
+    ramp: ColorRamp,
+}
+
+impl Wood {
+    /// Creates a new wood texture.
+    pub fn new(
+        noise: ValueNoise,
+        ring_frequency: Float,
+        turbulence: Float,
+        noise_scale: Float,
+        octaves: u32,
+        ramp: ColorRamp,
+    ) -> Self {
+        Self {
+            noise,
+            ring_frequency,
+            turbulence,
+            noise_scale,
+            octaves,
+            ramp,
+        }
+    }
+}
+
+impl Texture for Wood {
+    #[allow(unused)]
+    fn value(&self, u: Float, v: Float, p: &Point3, normal: &Vec3) -> Color {
+        let turb = self.noise.turbulence(&(self.noise_scale * p), self.octaves);
+        let r = Float::sqrt(p.x() * p.x() + p.z() * p.z());
+        let rings = self.ring_frequency * r + self.turbulence * turb;
+        self.ramp.sample(rings.fract())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn image_texture_lookup() {
+        let red = Color::new(1.0, 0.0, 0.0);
+        let blue = Color::new(0.0, 0.0, 1.0);
+        let image = ImageBuffer::new(2, 1, vec![red, blue]);
+        let texture = ImageTexture::new(image);
+
+        let p = Point3::new(0.0, 0.0, 0.0);
+        let n = Vec3::new(0.0, 1.0, 0.0);
+        assert_eq!(texture.value(0.25, 0.5, &p, &n), red);
+        assert_eq!(texture.value(0.75, 0.5, &p, &n), blue);
+        assert_eq!(texture.value(1.0, 1.0, &p, &n), blue);
+        assert_eq!(texture.value(-3.0, 0.0, &p, &n), red);
+    }
+
+    #[test]
+    fn color_ramp_sample() {
+        let black = Color::new(0.0, 0.0, 0.0);
+        let white = Color::new(1.0, 1.0, 1.0);
+        let red = Color::new(1.0, 0.0, 0.0);
+
+        let ramp = ColorRamp::linear(&black, &white);
+        assert!(ramp.sample(0.5).almost_eq(&Color::new(0.5, 0.5, 0.5)));
+        assert!(ramp.sample(-1.0).almost_eq(&black));
+        assert!(ramp.sample(2.0).almost_eq(&white));
+        assert!(ramp.sample(Float::NAN).almost_eq(&black));
+        assert!(ramp.sample(Float::INFINITY).almost_eq(&white));
+
+        // Stops are sorted regardless of the order given.
+        let ramp = ColorRamp::new(&[(1.0, white), (0.0, black), (0.5, red)]);
+        assert!(ramp.sample(0.5).almost_eq(&red));
+        assert!(ramp.sample(0.25).almost_eq(&Color::new(0.5, 0.0, 0.0)));
+        assert!(ramp.sample(0.75).almost_eq(&Color::new(1.0, 0.5, 0.5)));
+    }
+
+    #[test]
+    fn wood_rings() {
+        let ramp = ColorRamp::linear(&Color::new(0.0, 0.0, 0.0), &Color::new(1.0, 1.0, 1.0));
+        let wood = Wood::new(ValueNoise::new(0), 1.0, 0.0, 1.0, 1, ramp);
+        let n = Vec3::new(0.0, 1.0, 0.0);
+
+        // Without turbulence, rings repeat with distance from the Y axis.
+        let a = wood.value(0.0, 0.0, &Point3::new(0.25, 0.0, 0.0), &n);
+        let b = wood.value(0.0, 0.0, &Point3::new(0.0, 5.0, 1.25), &n);
+        assert!(a.almost_eq(&b));
+        assert!(a.almost_eq(&Color::new(0.25, 0.25, 0.25)));
+    }
+}
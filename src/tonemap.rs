@@ -0,0 +1,411 @@
+use crate::Color;
+
+/// Histogram of log-luminance over an HDR image.
+#[derive(Debug, Clone)]
+pub struct LuminanceHistogram {
+    /// Base-2 logarithm of the lowest non-zero luminance.
+    min_log: f32,
+
+    /// Base-2 logarithm of the highest luminance.
+    max_log: f32,
+
+    /// Pixel counts per bin, evenly spaced in log-luminance.
+    bins: Vec<u32>,
+
+    /// Number of pixels with non-zero luminance.
+    count: u32,
+}
+
+impl LuminanceHistogram {
+    /// Builds a histogram with `bins` bins over the non-zero luminances of `data`.
+    pub fn new(data: &[Color], bins: usize) -> Self {
+        assert!(bins > 0, "histogram needs at least one bin");
+
+        let logs: Vec<f32> = data
+            .iter()
+            .map(|color| color.luminance())
+            .filter(|&l| l > 0.0 && l.is_finite())
+            .map(f32::log2)
+            .collect();
+
+        let min_log = logs.iter().copied().fold(f32::INFINITY, f32::min);
+        let max_log = logs.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+
+        let mut histogram = Self {
+            min_log,
+            max_log,
+            bins: vec![0; bins],
+            count: logs.len() as u32,
+        };
+
+        for l in logs {
+            let i = histogram.bin(l);
+            histogram.bins[i] += 1;
+        }
+
+        histogram
+    }
+
+    /// Number of pixels with non-zero luminance.
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+
+    /// Bin index of a log-luminance value.
+    fn bin(&self, log: f32) -> usize {
+        let range = self.max_log - self.min_log;
+        if range <= 0.0 {
+            return 0;
+        }
+        let t = (log - self.min_log) / range;
+        usize::min((t * self.bins.len() as f32) as usize, self.bins.len() - 1)
+    }
+
+    /// Luminance below which the fraction `p` of non-zero pixels falls,
+    /// resolved to the upper edge of the containing bin. Returns 0 for an
+    /// image with no non-zero pixels.
+    pub fn percentile(&self, p: f32) -> f32 {
+        if self.count == 0 {
+            return 0.0;
+        }
+
+        let target = (p.clamp(0.0, 1.0) * self.count as f32).ceil() as u32;
+        let mut cumulative = 0;
+        let mut index = self.bins.len() - 1;
+        for (i, &n) in self.bins.iter().enumerate() {
+            cumulative += n;
+            if cumulative >= target {
+                index = i;
+                break;
+            }
+        }
+
+        let width = (self.max_log - self.min_log) / self.bins.len() as f32;
+        f32::exp2(self.min_log + (index + 1) as f32 * width)
+    }
+
+    /// Geometric mean luminance of the non-zero pixels between the fractions
+    /// `low` and `high` of the distribution, taking each pixel at its bin's
+    /// center. Returns 0 for an image with no non-zero pixels.
+    pub fn mean_between(&self, low: f32, high: f32) -> f32 {
+        let low = low.clamp(0.0, 1.0) * self.count as f32;
+        let high = high.clamp(0.0, 1.0) * self.count as f32;
+        let width = (self.max_log - self.min_log) / self.bins.len() as f32;
+
+        let (mut sum, mut weight, mut below) = (0.0, 0.0, 0.0);
+        for (i, &n) in self.bins.iter().enumerate() {
+            // The part of the bin's pixels within the kept range.
+            let above = below + n as f32;
+            let kept = (above.min(high) - below.max(low)).max(0.0);
+            sum += kept * (self.min_log + (i as f32 + 0.5) * width);
+            weight += kept;
+            below = above;
+        }
+
+        if weight > 0.0 {
+            f32::exp2(sum / weight)
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Photographic exposure, scaling linear radiance before tone mapping and
+/// quantization. Scenes lit with physical intensities, such as a sun in
+/// cd/m², come out usable by exposing them like a camera would instead of
+/// rescaling every emitter.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Exposure {
+    /// Factor applied to radiance.
+    scale: f32,
+}
+
+impl Exposure {
+    /// Calibration constant of the saturation-based speed model, where the
+    /// exposed luminance of 1 is the sensor's saturation point.
+    const SATURATION: f32 = 1.2;
+
+    /// Create an exposure compensated by `ev` stops, so every step up doubles
+    /// the brightness. Zero leaves radiance unchanged.
+    pub fn from_ev(ev: f32) -> Self {
+        Self { scale: ev.exp2() }
+    }
+
+    /// Create the exposure of a camera with the given sensitivity (ISO),
+    /// shutter time in seconds and f-number. A sunlit scene at ISO 100,
+    /// 1/100 s and f/16 comes out well exposed.
+    pub fn from_camera(iso: f32, shutter: f32, f_number: f32) -> Self {
+        let ev100 = Self::ev100(iso, shutter, f_number);
+        Self {
+            scale: 1.0 / (Self::SATURATION * ev100.exp2()),
+        }
+    }
+
+    /// Exposure value at ISO 100 of a camera setting. Settings with the same
+    /// value let in the same amount of light.
+    pub fn ev100(iso: f32, shutter: f32, f_number: f32) -> f32 {
+        (f_number * f_number / shutter * 100.0 / iso).log2()
+    }
+
+    /// Compensate the exposure by `ev` more stops.
+    pub fn with_compensation(mut self, ev: f32) -> Self {
+        self.scale *= ev.exp2();
+        self
+    }
+
+    /// Retrieve the factor applied to radiance.
+    pub fn scale(&self) -> f32 {
+        self.scale
+    }
+
+    /// Exposes an HDR image, ahead of tone mapping or quantization.
+    pub fn apply(&self, data: &[Color]) -> Vec<Color> {
+        data.iter().map(|color| color * self.scale).collect()
+    }
+}
+
+impl Default for Exposure {
+    fn default() -> Self {
+        Self::from_ev(0.0)
+    }
+}
+
+/// Automatic exposure from the image's luminance histogram, like a camera's
+/// metering: the average luminance of the image, ignoring its darkest and
+/// brightest pixels, is exposed to a middle gray key. Scenes of any
+/// brightness then come out reasonably exposed before tone mapping.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AutoExposure {
+    /// Fraction of the darkest pixels ignored, in `[0, 1]`.
+    low: f32,
+
+    /// Fraction of the pixels below which the brightest are ignored, in `[0, 1]`.
+    high: f32,
+
+    /// Exposed luminance of the average.
+    key: f32,
+}
+
+impl AutoExposure {
+    /// Number of histogram bins used to meter the image.
+    const HISTOGRAM_BINS: usize = 1024;
+
+    /// Create an automatic exposure metering the pixels between the
+    /// percentiles `low` and `high` (in `[0, 1]`), to a key of 18% gray.
+    pub fn new(low: f32, high: f32) -> Self {
+        Self {
+            low: low.clamp(0.0, 1.0),
+            high: high.clamp(low, 1.0),
+            key: 0.18,
+        }
+    }
+
+    /// Set the exposed luminance of the average, e.g. higher for snowy scenes.
+    pub fn with_key(mut self, key: f32) -> Self {
+        self.key = key;
+        self
+    }
+
+    /// Picks the exposure of an HDR image. Black images are left unchanged.
+    pub fn resolve(&self, data: &[Color]) -> Exposure {
+        let histogram = LuminanceHistogram::new(data, Self::HISTOGRAM_BINS);
+        let average = histogram.mean_between(self.low, self.high);
+        if average > 0.0 {
+            Exposure {
+                scale: self.key / average,
+            }
+        } else {
+            Exposure::default()
+        }
+    }
+}
+
+impl Default for AutoExposure {
+    /// Meter the pixels between the 50th and 98th percentiles.
+    fn default() -> Self {
+        Self::new(0.5, 0.98)
+    }
+}
+
+/// Luminance mapped to display white by the tone mapper.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WhitePoint {
+    /// Fixed white luminance.
+    Fixed(f32),
+
+    /// White luminance taken at a percentile (in `[0, 1]`) of the image's
+    /// luminance histogram, e.g. 0.995.
+    Auto { percentile: f32 },
+}
+
+impl WhitePoint {
+    /// Number of histogram bins used to select an automatic white point.
+    const HISTOGRAM_BINS: usize = 1024;
+
+    /// Resolves the white luminance for an image.
+    pub fn resolve(&self, data: &[Color]) -> f32 {
+        match *self {
+            Self::Fixed(white) => white,
+            Self::Auto { percentile } => {
+                LuminanceHistogram::new(data, Self::HISTOGRAM_BINS).percentile(percentile)
+            }
+        }
+    }
+}
+
+impl Default for WhitePoint {
+    fn default() -> Self {
+        Self::Auto { percentile: 0.995 }
+    }
+}
+
+/// Extended Reinhard operator on luminance. Luminance `white` maps to 1 and
+/// everything below it is compressed smoothly. Hue is preserved.
+pub fn reinhard_extended(color: &Color, white: f32) -> Color {
+    let l = color.luminance();
+    if l <= 0.0 {
+        return Color::new(0.0, 0.0, 0.0);
+    }
+
+    let white2 = if white > 0.0 {
+        white * white
+    } else {
+        f32::INFINITY
+    };
+    let mapped = l * (1.0 + l / white2) / (1.0 + l);
+    color * (mapped / l)
+}
+
+/// Tone maps an HDR image with the extended Reinhard operator. Luminance up
+/// to the white point lands in `[0, 1]`; brighter pixels exceed 1 and are left
+/// for the output encoding to clip.
+/// A fixed white point is in exposed units (see `Exposure::apply`).
+pub fn tonemap(data: &[Color], white_point: &WhitePoint) -> Vec<Color> {
+    let white = white_point.resolve(data);
+    data.iter()
+        .map(|color| reinhard_extended(color, white))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gray(l: f32) -> Color {
+        Color::new(l, l, l)
+    }
+
+    #[test]
+    fn histogram_percentile() {
+        let data: Vec<Color> = (1..=1000).map(|i| gray(i as f32 / 100.0)).collect();
+        let histogram = LuminanceHistogram::new(&data, 4096);
+        assert_eq!(histogram.count(), 1000);
+
+        let p50 = histogram.percentile(0.5);
+        assert!((p50 - 5.0).abs() < 0.05);
+        let p100 = histogram.percentile(1.0);
+        assert!((p100 - 10.0).abs() < 1e-3);
+
+        // Black pixels are ignored.
+        let histogram = LuminanceHistogram::new(&[gray(0.0), gray(0.0)], 16);
+        assert_eq!(histogram.count(), 0);
+        assert_eq!(histogram.percentile(0.5), 0.0);
+    }
+
+    #[test]
+    fn reinhard_white() {
+        // The white point maps to exactly 1.
+        assert!(reinhard_extended(&gray(4.0), 4.0).almost_eq(&gray(1.0)));
+
+        // Values below white are compressed, but ordering is preserved.
+        let a = reinhard_extended(&gray(0.5), 4.0);
+        let b = reinhard_extended(&gray(1.0), 4.0);
+        assert!(a.r() < b.r() && b.r() < 1.0);
+
+        // Hue is preserved.
+        let c = reinhard_extended(&Color::new(2.0, 1.0, 0.0), 4.0);
+        assert!((c.r() / c.g() - 2.0).abs() < 1e-5);
+        assert_eq!(c.b(), 0.0);
+    }
+
+    #[test]
+    fn exposure_stops() {
+        assert_eq!(Exposure::default().scale(), 1.0);
+        assert_eq!(Exposure::from_ev(1.0).scale(), 2.0);
+        assert_eq!(Exposure::from_ev(-2.0).with_compensation(1.0).scale(), 0.5);
+
+        // Sunny 16: a sunlit surface of 4000 cd/m² lands near 0.13.
+        let sunny = Exposure::from_camera(100.0, 0.01, 16.0);
+        assert!((Exposure::ev100(100.0, 0.01, 16.0) - 14.64).abs() < 0.01);
+        assert!((sunny.scale() * 4000.0 - 0.13).abs() < 0.01);
+
+        // Doubling the shutter time or the ISO adds a stop.
+        let slower = Exposure::from_camera(100.0, 0.02, 16.0).scale();
+        let faster_film = Exposure::from_camera(200.0, 0.01, 16.0).scale();
+        assert!((slower / sunny.scale() - 2.0).abs() < 1e-4);
+        assert!((faster_film / sunny.scale() - 2.0).abs() < 1e-4);
+
+        let exposed = Exposure::from_ev(1.0).apply(&[gray(0.25)]);
+        assert!(exposed[0].almost_eq(&gray(0.5)));
+    }
+
+    #[test]
+    fn histogram_mean_between() {
+        let data: Vec<Color> = [1.0, 4.0, 16.0, 64.0].map(gray).to_vec();
+        let histogram = LuminanceHistogram::new(&data, 4096);
+        assert!((histogram.mean_between(0.0, 1.0) - 8.0).abs() < 0.05);
+        assert!((histogram.mean_between(0.25, 0.75) - 8.0).abs() < 0.05);
+        assert!((histogram.mean_between(0.0, 0.5) - 2.0).abs() < 0.05);
+        assert_eq!(histogram.mean_between(0.5, 0.5), 0.0);
+    }
+
+    #[test]
+    fn exposure_auto() {
+        // Scenes far apart in brightness are exposed alike.
+        let dim: Vec<Color> = (1..=100).map(|i| gray(i as f32 * 1e-3)).collect();
+        let bright: Vec<Color> = dim.iter().map(|c| c * 1e4).collect();
+        let auto = AutoExposure::new(0.0, 1.0);
+        let exposed = auto.resolve(&dim).apply(&dim);
+        assert!(exposed
+            .iter()
+            .zip(auto.resolve(&bright).apply(&bright))
+            .all(|(a, b)| { (a.r() - b.r()).abs() < 1e-3 * a.r().max(1.0) }));
+
+        // The metered average lands on the key.
+        let histogram = LuminanceHistogram::new(&exposed, 1024);
+        assert!((histogram.mean_between(0.0, 1.0) - 0.18).abs() < 0.01);
+
+        // Clipping keeps a few highlights from darkening the image.
+        let mut data = vec![gray(0.5); 99];
+        data.push(gray(1e6));
+        let clipped = AutoExposure::default().resolve(&data).scale();
+        assert!((clipped * 0.5 - 0.18).abs() < 0.01);
+        assert!(AutoExposure::new(0.0, 1.0).resolve(&data).scale() < clipped);
+        assert_eq!(
+            AutoExposure::default()
+                .with_key(0.36)
+                .resolve(&data)
+                .scale(),
+            2.0 * clipped
+        );
+
+        assert_eq!(
+            AutoExposure::default().resolve(&[gray(0.0)]),
+            Exposure::default()
+        );
+    }
+
+    #[test]
+    fn tonemap_auto_white() {
+        // One very bright outlier does not set the white point.
+        let mut data = vec![gray(1.0); 999];
+        data.push(gray(1000.0));
+
+        let mapped = tonemap(&data, &WhitePoint::Auto { percentile: 0.99 });
+        assert!(mapped[0].r() > 0.9);
+        assert!(mapped[999].r() > 1.0);
+
+        let mapped = tonemap(&data, &WhitePoint::Fixed(1000.0));
+        assert!(mapped[0].r() < 0.6);
+    }
+}
@@ -0,0 +1,21 @@
+//! Instrumentation hooks. With the `tracing` feature, these forward to the
+//! `tracing` crate so renders can be monitored with any subscriber; without it,
+//! they compile to nothing.
+
+/// Enters a span at the given level for the rest of the enclosing scope.
+macro_rules! span {
+    ($level:ident, $($arg:tt)+) => {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::span!(tracing::Level::$level, $($arg)+).entered();
+    };
+}
+
+/// Emits an event at the given level.
+macro_rules! event {
+    ($level:ident, $($arg:tt)+) => {
+        #[cfg(feature = "tracing")]
+        tracing::event!(tracing::Level::$level, $($arg)+);
+    };
+}
+
+pub(crate) use {event, span};
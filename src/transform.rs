@@ -0,0 +1,297 @@
+use crate::aabb::Aabb;
+use crate::{Float, Point3, Ray, Vec3};
+
+/// Affine map stored as a 3x3 linear part (by rows) and a translation.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Affine {
+    /// Rows of the linear part.
+    rows: [Vec3; 3],
+
+    /// Translation applied after the linear part.
+    translation: Vec3,
+}
+
+impl Affine {
+    /// The identity map.
+    const IDENTITY: Self = Self {
+        rows: [
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            Vec3::new(0.0, 0.0, 1.0),
+        ],
+        translation: Vec3::new(0.0, 0.0, 0.0),
+    };
+
+    /// Applies the linear part to a vector.
+    fn linear(&self, v: &Vec3) -> Vec3 {
+        Vec3::new(
+            Vec3::dot(&self.rows[0], v),
+            Vec3::dot(&self.rows[1], v),
+            Vec3::dot(&self.rows[2], v),
+        )
+    }
+
+    /// Applies the transposed linear part to a vector.
+    fn linear_transposed(&self, v: &Vec3) -> Vec3 {
+        v[0] * self.rows[0] + v[1] * self.rows[1] + v[2] * self.rows[2]
+    }
+
+    /// Composes two maps, applying `self` first and `next` second.
+    fn then(&self, next: &Self) -> Self {
+        let columns = [
+            next.linear(&Vec3::new(
+                self.rows[0][0],
+                self.rows[1][0],
+                self.rows[2][0],
+            )),
+            next.linear(&Vec3::new(
+                self.rows[0][1],
+                self.rows[1][1],
+                self.rows[2][1],
+            )),
+            next.linear(&Vec3::new(
+                self.rows[0][2],
+                self.rows[1][2],
+                self.rows[2][2],
+            )),
+        ];
+
+        Self {
+            rows: transpose(&columns),
+            translation: next.linear(&self.translation) + next.translation,
+        }
+    }
+}
+
+/// Transposes a 3x3 matrix given by rows.
+fn transpose(m: &[Vec3; 3]) -> [Vec3; 3] {
+    [
+        Vec3::new(m[0][0], m[1][0], m[2][0]),
+        Vec3::new(m[0][1], m[1][1], m[2][1]),
+        Vec3::new(m[0][2], m[1][2], m[2][2]),
+    ]
+}
+
+/// Invertible affine transformation from object space to world space.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transform {
+    /// Object-to-world map.
+    forward: Affine,
+
+    /// World-to-object map.
+    inverse: Affine,
+}
+
+impl Transform {
+    /// The identity transformation.
+    pub const IDENTITY: Self = Self {
+        forward: Affine::IDENTITY,
+        inverse: Affine::IDENTITY,
+    };
+
+    /// Creates a transformation from the rows of a 3x3 linear map followed by a
+    /// translation. Returns `None` if the linear map is singular.
+    pub fn new(rows: [Vec3; 3], translation: &Vec3) -> Option<Self> {
+        let forward = Affine {
+            rows,
+            translation: *translation,
+        };
+
+        // The inverse of a 3x3 matrix has the cross products of its rows as columns.
+        let cofactors = [
+            Vec3::cross(&rows[1], &rows[2]),
+            Vec3::cross(&rows[2], &rows[0]),
+            Vec3::cross(&rows[0], &rows[1]),
+        ];
+        let det = Vec3::dot(&rows[0], &cofactors[0]);
+        if det == 0.0 || !det.is_finite() {
+            return None;
+        }
+
+        let inverse_rows = transpose(&cofactors).map(|row| row / det);
+        let mut inverse = Affine {
+            rows: inverse_rows,
+            translation: Vec3::new(0.0, 0.0, 0.0),
+        };
+        inverse.translation = -inverse.linear(translation);
+
+        Some(Self { forward, inverse })
+    }
+
+    /// Translation by `offset`.
+    pub fn translation(offset: &Vec3) -> Self {
+        let mut transform = Self::IDENTITY;
+        transform.forward.translation = *offset;
+        transform.inverse.translation = -offset;
+        transform
+    }
+
+    /// Non-uniform scaling along the coordinate axes. Panics if any factor is zero.
+    pub fn scaling(factors: &Vec3) -> Self {
+        let rows = [
+            Vec3::new(factors[0], 0.0, 0.0),
+            Vec3::new(0.0, factors[1], 0.0),
+            Vec3::new(0.0, 0.0, factors[2]),
+        ];
+        Self::new(rows, &Vec3::new(0.0, 0.0, 0.0)).expect("scale factors must be non-zero")
+    }
+
+    /// Uniform scaling by `factor`. Panics if the factor is zero.
+    pub fn uniform_scaling(factor: Float) -> Self {
+        Self::scaling(&Vec3::new(factor, factor, factor))
+    }
+
+    /// Counter-clockwise rotation by `degrees` about `axis`.
+    pub fn rotation(axis: &Vec3, degrees: Float) -> Self {
+        let a = axis.unit();
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        let k = 1.0 - cos;
+
+        // Rodrigues' rotation formula.
+        let rows = [
+            Vec3::new(
+                cos + a[0] * a[0] * k,
+                a[0] * a[1] * k - a[2] * sin,
+                a[0] * a[2] * k + a[1] * sin,
+            ),
+            Vec3::new(
+                a[1] * a[0] * k + a[2] * sin,
+                cos + a[1] * a[1] * k,
+                a[1] * a[2] * k - a[0] * sin,
+            ),
+            Vec3::new(
+                a[2] * a[0] * k - a[1] * sin,
+                a[2] * a[1] * k + a[0] * sin,
+                cos + a[2] * a[2] * k,
+            ),
+        ];
+
+        // Rotations are orthogonal, so the inverse is the transpose.
+        Self {
+            forward: Affine {
+                rows,
+                translation: Vec3::new(0.0, 0.0, 0.0),
+            },
+            inverse: Affine {
+                rows: transpose(&rows),
+                translation: Vec3::new(0.0, 0.0, 0.0),
+            },
+        }
+    }
+
+    /// Composes two transformations, applying `self` first and `next` second.
+    pub fn then(&self, next: &Self) -> Self {
+        Self {
+            forward: self.forward.then(&next.forward),
+            inverse: next.inverse.then(&self.inverse),
+        }
+    }
+
+    /// The inverse transformation.
+    pub fn inverse(&self) -> Self {
+        Self {
+            forward: self.inverse,
+            inverse: self.forward,
+        }
+    }
+
+    /// Maps a point from object space to world space.
+    pub fn point(&self, p: &Point3) -> Point3 {
+        self.forward.linear(p) + self.forward.translation
+    }
+
+    /// Maps a direction from object space to world space.
+    pub fn vector(&self, v: &Vec3) -> Vec3 {
+        self.forward.linear(v)
+    }
+
+    /// Maps a surface normal from object space to world space. The result is
+    /// not normalized.
+    pub fn normal(&self, n: &Vec3) -> Vec3 {
+        self.inverse.linear_transposed(n)
+    }
+
+    /// Maps a ray from world space to object space. The direction is not
+    /// renormalized, so ray parameters are shared between both spaces.
+    pub fn ray_to_object(&self, ray: &Ray) -> Ray {
+        let origin = self.inverse.linear(ray.origin()) + self.inverse.translation;
+        Ray::new(origin, self.inverse.linear(ray.direction()))
+            .with_t_range(*ray.t_range())
+            .with_time(ray.time())
+    }
+
+    /// Maps an object-space bounding box to a world-space box enclosing it.
+    pub fn bounding_box(&self, bbox: &Aabb) -> Aabb {
+        if bbox.is_empty() {
+            return Aabb::EMPTY;
+        }
+
+        let (min, max) = (bbox.min(), bbox.max());
+        let corners: Vec<Point3> = (0..8)
+            .map(|i| {
+                let x = if i & 1 == 0 { min[0] } else { max[0] };
+                let y = if i & 2 == 0 { min[1] } else { max[1] };
+                let z = if i & 4 == 0 { min[2] } else { max[2] };
+                self.point(&Point3::new(x, y, z))
+            })
+            .collect();
+        Aabb::from_points(corners.iter())
+    }
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transform_compose() {
+        let t = Transform::uniform_scaling(2.0)
+            .then(&Transform::rotation(&Vec3::new(0.0, 0.0, 1.0), 90.0))
+            .then(&Transform::translation(&Vec3::new(1.0, 0.0, 0.0)));
+
+        let p = t.point(&Point3::new(1.0, 0.0, 0.0));
+        assert!(p.almost_eq(&Point3::new(1.0, 2.0, 0.0)));
+        assert!(t.inverse().point(&p).almost_eq(&Point3::new(1.0, 0.0, 0.0)));
+
+        let v = t.vector(&Vec3::new(0.0, 1.0, 0.0));
+        assert!(v.almost_eq(&Vec3::new(-2.0, 0.0, 0.0)));
+    }
+
+    #[test]
+    fn transform_normal() {
+        // Under a non-uniform scale, normals must stay perpendicular to the surface.
+        let t = Transform::scaling(&Vec3::new(4.0, 1.0, 1.0));
+        let tangent = Vec3::new(1.0, -1.0, 0.0);
+        let normal = Vec3::new(1.0, 1.0, 0.0);
+        let dot = Vec3::dot(&t.vector(&tangent), &t.normal(&normal));
+        assert!(dot.abs() < 1e-12);
+    }
+
+    #[test]
+    fn transform_singular() {
+        let rows = [
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec3::new(2.0, 0.0, 0.0),
+            Vec3::new(0.0, 0.0, 1.0),
+        ];
+        assert!(Transform::new(rows, &Vec3::new(0.0, 0.0, 0.0)).is_none());
+    }
+
+    #[test]
+    fn transform_bounding_box() {
+        let bbox = Aabb::new(&Point3::new(-1.0, -1.0, -1.0), &Point3::new(1.0, 1.0, 1.0));
+        let t = Transform::rotation(&Vec3::new(0.0, 1.0, 0.0), 45.0)
+            .then(&Transform::translation(&Vec3::new(0.0, 5.0, 0.0)));
+        let world = t.bounding_box(&bbox);
+
+        let r = Float::sqrt(2.0);
+        assert!(world.min().almost_eq(&Point3::new(-r, 4.0, -r)));
+        assert!(world.max().almost_eq(&Point3::new(r, 6.0, r)));
+    }
+}
@@ -0,0 +1,225 @@
+use std::sync::Arc;
+
+use crate::aabb::Aabb;
+use crate::diagnostic::{self, Diagnostic};
+use crate::hittable::{HitRecord, Hittable, Orientation};
+use crate::material::Material;
+use crate::{Float, Interval, Point3, Ray, Vec3};
+
+/// Triangle in world space with a material.
+#[derive(Clone)]
+pub struct Triangle {
+    /// Corner positions, counter-clockwise around the front face.
+    vertices: [Point3; 3],
+
+    /// Optional per-vertex shading normals, interpolated across the face.
+    normals: Option<[Vec3; 3]>,
+
+    /// Surface material.
+    material: Arc<dyn Material>,
+}
+
+impl Triangle {
+    /// Creates a new triangle. The front face is the side from which the
+    /// vertices appear counter-clockwise.
+    pub fn new(a: Point3, b: Point3, c: Point3, material: Arc<dyn Material>) -> Self {
+        Self {
+            vertices: [a, b, c],
+            normals: None,
+            material,
+        }
+    }
+
+    /// Sets per-vertex shading normals. Hits interpolate them with barycentric
+    /// coordinates instead of using the flat face normal.
+    pub fn with_normals(mut self, na: Vec3, nb: Vec3, nc: Vec3) -> Self {
+        self.normals = Some([na, nb, nc]);
+        self
+    }
+
+    /// Retrieves the corner positions.
+    pub fn vertices(&self) -> &[Point3; 3] {
+        &self.vertices
+    }
+
+    /// Retrieves the per-vertex shading normals, if any.
+    pub fn normals(&self) -> Option<&[Vec3; 3]> {
+        self.normals.as_ref()
+    }
+
+    /// Shading normal at barycentric coordinates `(u, v)`. Falls back to the
+    /// face normal without vertex normals or when the interpolation degenerates.
+    pub fn shading_normal(&self, u: Float, v: Float) -> Vec3 {
+        if let Some([na, nb, nc]) = &self.normals {
+            let n = (1.0 - u - v) * na + u * nb + v * nc;
+            if !n.almost_zero() {
+                return n.unit();
+            }
+        }
+        self.face_normal().unit()
+    }
+
+    /// Unnormalized face normal. Its length is twice the triangle's area.
+    pub fn face_normal(&self) -> Vec3 {
+        let [a, b, c] = &self.vertices;
+        Vec3::cross(&(b - a), &(c - a))
+    }
+}
+
+impl Hittable for Triangle {
+    fn hit(&self, ray: &Ray, ray_t: &Interval) -> Option<HitRecord> {
+        let [a, b, c] = &self.vertices;
+        let (t, u, v) = intersect(ray, a, b, c)?;
+        if !ray_t.surrounds(t) {
+            return None;
+        }
+
+        let p = ray.at(t);
+        let outward_normal = self.face_normal().unit();
+        let mut rec = HitRecord::new(&p, &outward_normal, t, ray, &*self.material).with_uv(u, v);
+
+        // The face side is decided by the geometric normal; the shading normal
+        // only bends the normal within that side.
+        if self.normals.is_some() {
+            let shading = self.shading_normal(u, v);
+            rec.normal = if rec.orientation == Orientation::Exterior {
+                shading
+            } else {
+                -shading
+            };
+        }
+
+        (!rec.is_masked()).then_some(rec)
+    }
+
+    fn is_occluded(&self, ray: &Ray, ray_t: &Interval) -> bool {
+        let [a, b, c] = &self.vertices;
+        intersect(ray, a, b, c).is_some_and(|(t, u, v)| {
+            // The material needs the surface coordinates to decide on masking.
+            ray_t.surrounds(t) && {
+                let normal = self.face_normal().unit();
+                let rec = HitRecord::new(&ray.at(t), &normal, t, ray, &*self.material);
+                !rec.with_uv(u, v).is_masked()
+            }
+        })
+    }
+
+    fn validate(&self) -> Vec<Diagnostic> {
+        let mut diagnostics = self.material.validate();
+        if !self.vertices.iter().all(diagnostic::is_finite) {
+            diagnostics.push(Diagnostic::error("triangle vertices are not finite"));
+        } else if self.face_normal().almost_zero() {
+            diagnostics.push(Diagnostic::warning("triangle has no area"));
+        }
+        diagnostics
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        Aabb::from_points(self.vertices.iter())
+    }
+}
+
+/// Möller–Trumbore ray-triangle intersection. Returns the ray parameter of the
+/// hit and the barycentric coordinates of `b` and `c`.
+pub fn intersect(ray: &Ray, a: &Point3, b: &Point3, c: &Point3) -> Option<(Float, Float, Float)> {
+    let e1 = b - a;
+    let e2 = c - a;
+    let pvec = Vec3::cross(ray.direction(), &e2);
+    let det = Vec3::dot(&e1, &pvec);
+    if det.abs() < 1e-12 {
+        return None;
+    }
+
+    let inv_det = 1.0 / det;
+    let tvec = ray.origin() - a;
+    let u = Vec3::dot(&tvec, &pvec) * inv_det;
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+
+    let qvec = Vec3::cross(&tvec, &e1);
+    let v = Vec3::dot(ray.direction(), &qvec) * inv_det;
+    if v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+
+    Some((Vec3::dot(&e2, &qvec) * inv_det, u, v))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::Lambertian;
+    use crate::Color;
+
+    fn triangle() -> Triangle {
+        Triangle::new(
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(1.0, 0.0, 0.0),
+            Point3::new(0.0, 1.0, 0.0),
+            Arc::new(Lambertian::new(&Color::new(0.5, 0.5, 0.5))),
+        )
+    }
+
+    #[test]
+    fn triangle_hit() {
+        let tri = triangle();
+        let bounds = Interval::new(0.001, Float::INFINITY);
+
+        let ray = Ray::new(Point3::new(0.25, 0.25, 1.0), Vec3::new(0.0, 0.0, -1.0));
+        let rec = tri.hit(&ray, &bounds).unwrap();
+        assert_eq!(rec.t(), 1.0);
+        assert!(rec.p.almost_eq(&Point3::new(0.25, 0.25, 0.0)));
+        assert!(rec.normal.almost_eq(&Vec3::new(0.0, 0.0, 1.0)));
+        assert_eq!(rec.orientation, Orientation::Exterior);
+        assert_eq!((rec.u, rec.v), (0.25, 0.25));
+
+        // Hits from behind see the back face.
+        let ray = Ray::new(Point3::new(0.25, 0.25, -1.0), Vec3::new(0.0, 0.0, 1.0));
+        let rec = tri.hit(&ray, &bounds).unwrap();
+        assert_eq!(rec.orientation, Orientation::Interior);
+
+        // Outside the edges, parallel to the plane, and beyond the interval.
+        let ray = Ray::new(Point3::new(0.75, 0.75, 1.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(tri.hit(&ray, &bounds).is_none());
+        let ray = Ray::new(Point3::new(0.25, 0.25, 1.0), Vec3::new(1.0, 0.0, 0.0));
+        assert!(tri.hit(&ray, &bounds).is_none());
+        let ray = Ray::new(Point3::new(0.25, 0.25, 1.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(tri.hit(&ray, &Interval::new(0.001, 0.5)).is_none());
+        assert!(!tri.is_occluded(&ray, &Interval::new(0.001, 0.5)));
+        assert!(tri.is_occluded(&ray, &bounds));
+    }
+
+    #[test]
+    fn triangle_smooth_normals() {
+        let up = Vec3::new(0.0, 0.0, 1.0);
+        let tilted = Vec3::new(1.0, 0.0, 1.0).unit();
+        let tri = triangle().with_normals(up, tilted, up);
+        let bounds = Interval::new(0.001, Float::INFINITY);
+
+        // At a vertex, the shading normal is that vertex's normal.
+        assert!(tri.shading_normal(1.0, 0.0).almost_eq(&tilted));
+        assert!(tri.shading_normal(0.0, 0.0).almost_eq(&up));
+
+        // In between, it is interpolated and normalized.
+        let ray = Ray::new(Point3::new(0.5, 0.0, 1.0), Vec3::new(0.0, 0.0, -1.0));
+        let rec = tri.hit(&ray, &bounds).unwrap();
+        let expected = (0.5 * up + 0.5 * tilted).unit();
+        assert!(rec.normal.almost_eq(&expected));
+
+        // Back-face hits flip the shading normal too.
+        let ray = Ray::new(Point3::new(0.5, 0.0, -1.0), Vec3::new(0.0, 0.0, 1.0));
+        let rec = tri.hit(&ray, &bounds).unwrap();
+        assert!(rec.normal.almost_eq(&-expected));
+
+        // Without vertex normals, the face normal is used.
+        assert!(triangle().shading_normal(0.3, 0.3).almost_eq(&up));
+    }
+
+    #[test]
+    fn triangle_bounds() {
+        let bbox = triangle().bounding_box();
+        assert_eq!(*bbox.min(), Point3::new(0.0, 0.0, 0.0));
+        assert_eq!(*bbox.max(), Point3::new(1.0, 1.0, 0.0));
+    }
+}
@@ -1,11 +1,75 @@
 pub mod random {
-    use rand::{self, Rng};
+    use crate::Float;
+    use rand::rngs::StdRng;
+    use rand::{self, Rng, SeedableRng};
+    use std::cell::RefCell;
 
-    pub fn gen_unit() -> f64 {
-        rand::thread_rng().gen()
+    thread_local! {
+        /// Per-thread generator, seeded from entropy until `seed` is called.
+        static RNG: RefCell<StdRng> = RefCell::new(StdRng::from_entropy());
+
+        /// Samples handed out instead of fresh ones while replaying, and the
+        /// index of the next one.
+        static REPLAY: RefCell<Option<(Vec<Float>, usize)>> = const { RefCell::new(None) };
+    }
+
+    /// Reseeds the current thread's generator, making subsequent samples on
+    /// this thread deterministic.
+    pub fn seed(seed: u64) {
+        RNG.with(|rng| *rng.borrow_mut() = StdRng::seed_from_u64(seed));
+    }
+
+    /// Derives the seed of a single sample's random stream from a frame seed,
+    /// the sample's pixel at (x, y), and its index within the pixel. Seeding
+    /// with it before each sample makes every sample draw the same numbers no
+    /// matter which thread traces it or in what order.
+    pub fn sample_seed(seed: u64, x: u32, y: u32, index: u32) -> u64 {
+        // Mix in one coordinate at a time.
+        [x, y, index]
+            .iter()
+            .fold(mix(seed), |hash, &v| mix(hash ^ v as u64))
+    }
+
+    /// Scrambles `z` into a well-distributed seed with one step of
+    /// SplitMix64, so that nearby inputs give unrelated streams.
+    pub fn mix(z: u64) -> u64 {
+        let z = z.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        let z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Runs `f` with this thread's draws taken in order from `samples`, which
+    /// are extended with fresh draws when `f` needs more. This lets Metropolis
+    /// sampling mutate every random decision of a path.
+    pub(crate) fn replay<R>(samples: &mut Vec<Float>, f: impl FnOnce() -> R) -> R {
+        REPLAY.with(|replay| *replay.borrow_mut() = Some((std::mem::take(samples), 0)));
+        let result = f();
+        REPLAY.with(|replay| {
+            if let Some((replayed, _)) = replay.borrow_mut().take() {
+                *samples = replayed;
+            }
+        });
+        result
+    }
+
+    pub fn gen_unit() -> Float {
+        let replayed = REPLAY.with(|replay| {
+            replay.borrow_mut().as_mut().map(|(samples, next)| {
+                if *next == samples.len() {
+                    samples.push(RNG.with(|rng| rng.borrow_mut().gen()));
+                }
+                *next += 1;
+                samples[*next - 1]
+            })
+        });
+        replayed.unwrap_or_else(|| RNG.with(|rng| rng.borrow_mut().gen()))
     }
 
-    pub fn gen_range(min: f64, max: f64) -> f64 {
-        rand::thread_rng().gen_range(min..=max)
+    pub fn gen_range(min: Float, max: Float) -> Float {
+        if REPLAY.with(|replay| replay.borrow().is_some()) {
+            return min + (max - min) * gen_unit();
+        }
+        RNG.with(|rng| rng.borrow_mut().gen_range(min..=max))
     }
 }
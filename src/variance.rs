@@ -0,0 +1,120 @@
+use crate::Color;
+
+/// Running mean and variance of sample luminance (Welford's algorithm).
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SampleStats {
+    /// Number of samples seen.
+    count: u32,
+
+    /// Mean luminance of the samples.
+    mean: f32,
+
+    /// Sum of squared deviations from the mean.
+    m2: f32,
+}
+
+impl SampleStats {
+    /// Creates empty statistics.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a sample.
+    pub fn push(&mut self, sample: &Color) {
+        let x = sample.luminance();
+        self.count += 1;
+        let delta = x - self.mean;
+        self.mean += delta / self.count as f32;
+        self.m2 += delta * (x - self.mean);
+    }
+
+    /// Number of samples seen.
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+
+    /// Mean luminance of the samples.
+    pub fn mean(&self) -> f32 {
+        self.mean
+    }
+
+    /// Unbiased sample variance of the luminance. Zero with fewer than two samples.
+    pub fn variance(&self) -> f32 {
+        if self.count < 2 {
+            return 0.0;
+        }
+        self.m2 / (self.count - 1) as f32
+    }
+
+    /// Estimated standard deviation of the mean luminance from the true mean.
+    /// Zero with fewer than two samples.
+    pub fn standard_error(&self) -> f32 {
+        if self.count < 2 {
+            return 0.0;
+        }
+        (self.variance() / self.count as f32).sqrt()
+    }
+}
+
+/// Polynomial fit of the viridis colormap, for `t` in [0, 1].
+pub fn viridis(t: f32) -> Color {
+    const C: [[f32; 3]; 7] = [
+        [0.277_727_3, 0.005_407_345, 0.334_099_8],
+        [0.105_093_04, 1.404_613_5, 1.384_590_2],
+        [-0.330_861_83, 0.214_847_56, 0.095_095_16],
+        [-4.634_230_5, -5.799_101, -19.332_441],
+        [6.228_27, 14.179_933, 56.690_55],
+        [4.776_385, -13.745_145, -65.353_03],
+        [-5.435_456, 4.645_852_6, 26.312_435],
+    ];
+
+    let t = t.clamp(0.0, 1.0);
+    let channel = |i: usize| C.iter().rev().fold(0.0, |acc, c| acc * t + c[i]);
+    Color::new(channel(0), channel(1), channel(2))
+}
+
+/// Maps per-pixel values to a viridis heatmap, normalized by the largest
+/// finite value. An all-zero buffer maps to the bottom of the colormap.
+pub fn heatmap(values: &[f32]) -> Vec<Color> {
+    let max = values
+        .iter()
+        .copied()
+        .filter(|v| v.is_finite())
+        .fold(0.0, f32::max);
+    values
+        .iter()
+        .map(|&v| viridis(if max > 0.0 { v / max } else { 0.0 }))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn variance_stats() {
+        let mut stats = SampleStats::new();
+        assert_eq!(stats.variance(), 0.0);
+
+        // Luminance of a gray color is its value.
+        for x in [2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0] {
+            stats.push(&Color::new(x, x, x));
+        }
+        assert_eq!(stats.count(), 8);
+        assert!((stats.mean() - 5.0).abs() < 1e-5);
+        assert!((stats.variance() - 32.0 / 7.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn variance_heatmap() {
+        let low = viridis(0.0);
+        let high = viridis(1.0);
+        assert!(low.b() > low.g() && high.g() > high.b());
+        assert!(high.luminance() > low.luminance());
+
+        let map = heatmap(&[0.0, 2.0, 4.0]);
+        assert_eq!(map[0], low);
+        assert_eq!(map[2], high);
+        assert_eq!(heatmap(&[0.0, 0.0]), [low, low]);
+    }
+}
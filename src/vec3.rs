@@ -1,5 +1,6 @@
-use crate::almost::AlmostPartialEq;
+use crate::almost::{AlmostPartialEq, Tolerance};
 use crate::util::random;
+use crate::Float;
 use std::fmt;
 use std::ops;
 
@@ -7,61 +8,99 @@ use std::ops;
 #[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
 pub struct Vec3 {
     /// Array of vector components.
-    components: [f64; 3],
+    components: [Float; 3],
 }
 
 pub type Point3 = Vec3;
 
+/// Coordinate axis, for indexing vectors and boxes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Axis {
+    X,
+    Y,
+    Z,
+}
+
+impl Axis {
+    /// Every axis, in order.
+    pub const ALL: [Axis; 3] = [Axis::X, Axis::Y, Axis::Z];
+
+    /// Position of the axis' component in a vector.
+    pub fn index(&self) -> usize {
+        *self as usize
+    }
+
+    /// Following axis, wrapping from Z back to X. With `next().next()`, it
+    /// completes a right-handed set of axes.
+    pub fn next(&self) -> Axis {
+        Axis::ALL[(self.index() + 1) % 3]
+    }
+}
+
 /// Basic component functions.
 impl Vec3 {
     /// Creates a new 3-D vector.
-    pub fn new(x: f64, y: f64, z: f64) -> Self {
+    pub const fn new(x: Float, y: Float, z: Float) -> Self {
         Self {
             components: [x, y, z],
         }
     }
 
     /// Retrieves x component.
-    pub fn x(&self) -> f64 {
+    pub fn x(&self) -> Float {
         self[0]
     }
 
     /// Retrieves y component.
-    pub fn y(&self) -> f64 {
+    pub fn y(&self) -> Float {
         self[1]
     }
 
     /// Retrieves z component.
-    pub fn z(&self) -> f64 {
+    pub fn z(&self) -> Float {
         self[2]
     }
 
     /// Determines whether the given vector is approximately the zero vector.
     pub fn almost_zero(&self) -> bool {
-        self.components.iter().all(|&ui| ui.almost_zero())
+        self.almost_zero_with(&Float::TOLERANCE)
+    }
+
+    /// Determines whether the given vector is the zero vector within
+    /// `tolerance`.
+    pub fn almost_zero_with(&self, tolerance: &Tolerance<Float>) -> bool {
+        self.components
+            .iter()
+            .all(|ui| ui.almost_zero_with(tolerance))
     }
 
     /// Determines whether two vectors are approximately equal.
     pub fn almost_eq(&self, v: &Self) -> bool {
-        (self - v).almost_zero()
+        self.almost_eq_with(v, &Float::TOLERANCE)
+    }
+
+    /// Determines whether two vectors are equal within `tolerance`, relative
+    /// to their largest component.
+    pub fn almost_eq_with(&self, v: &Self, tolerance: &Tolerance<Float>) -> bool {
+        tolerance.accepts_all(&self.components, &v.components)
     }
 }
 
 /// Geometry operations.
 impl Vec3 {
     /// Dot product of two vectors.
-    pub fn dot(u: &Self, v: &Self) -> f64 {
-        u.x() * v.x() + u.y() * v.y() + u.z() * v.z()
+    pub fn dot(u: &Self, v: &Self) -> Float {
+        lanes::dot(&u.components, &v.components)
     }
 
     /// Square of the length of the vector.
-    pub fn len_sqr(&self) -> f64 {
+    pub fn len_sqr(&self) -> Float {
         Self::dot(self, self)
     }
 
     /// Length of the vector.
-    pub fn len(&self) -> f64 {
-        f64::sqrt(self.len_sqr())
+    pub fn len(&self) -> Float {
+        Float::sqrt(self.len_sqr())
     }
 
     /// Cross product of two vectors.
@@ -84,17 +123,86 @@ impl Vec3 {
     }
 
     /// Refracts the vector across the given normal with in and target refractive index.
-    pub fn refract(uv: &Self, normal: &Self, eta_i_over_eta_t: f64) -> Self {
-        let cos_theta = f64::min(Self::dot(&-uv, normal), 1.0);
+    pub fn refract(uv: &Self, normal: &Self, eta_i_over_eta_t: Float) -> Self {
+        let cos_theta = Float::min(Self::dot(&-uv, normal), 1.0);
 
         // Snell's law
         let ray_out_perp = eta_i_over_eta_t * (uv + cos_theta * normal);
-        let ray_out_para = -f64::sqrt(f64::abs(1.0 - ray_out_perp.len_sqr())) * normal;
+        let ray_out_para = -Float::sqrt(Float::abs(1.0 - ray_out_perp.len_sqr())) * normal;
 
         ray_out_perp + ray_out_para
     }
 }
 
+/// Component-wise operations.
+impl Vec3 {
+    /// Applies `f` to each component.
+    fn map<F: Fn(Float) -> Float>(&self, f: F) -> Self {
+        Self::new(f(self.x()), f(self.y()), f(self.z()))
+    }
+
+    /// Applies `f` to each pair of components.
+    fn zip<F: Fn(Float, Float) -> Float>(u: &Self, v: &Self, f: F) -> Self {
+        Self::new(f(u.x(), v.x()), f(u.y(), v.y()), f(u.z(), v.z()))
+    }
+
+    /// Component-wise minimum of two vectors.
+    pub fn min(u: &Self, v: &Self) -> Self {
+        Self::zip(u, v, Float::min)
+    }
+
+    /// Component-wise maximum of two vectors.
+    pub fn max(u: &Self, v: &Self) -> Self {
+        Self::zip(u, v, Float::max)
+    }
+
+    /// Component-wise absolute value.
+    pub fn abs(&self) -> Self {
+        self.map(Float::abs)
+    }
+
+    /// Clamps each component between the matching components of `min` and `max`.
+    pub fn clamp(&self, min: &Self, max: &Self) -> Self {
+        Self::min(&Self::max(self, min), max)
+    }
+
+    /// Linear interpolation from `u` at `t = 0` to `v` at `t = 1`.
+    pub fn lerp(u: &Self, v: &Self, t: Float) -> Self {
+        (1.0 - t) * u + t * v
+    }
+
+    /// Smallest component.
+    pub fn min_component(&self) -> Float {
+        self.x().min(self.y()).min(self.z())
+    }
+
+    /// Largest component.
+    pub fn max_component(&self) -> Float {
+        self.x().max(self.y()).max(self.z())
+    }
+
+    /// Axis of the largest component, the first one on ties.
+    pub fn max_axis(&self) -> Axis {
+        if self.x() >= self.y() && self.x() >= self.z() {
+            Axis::X
+        } else if self.y() >= self.z() {
+            Axis::Y
+        } else {
+            Axis::Z
+        }
+    }
+
+    /// Component of `v` along `onto`, which must not be zero.
+    pub fn project(v: &Self, onto: &Self) -> Self {
+        (Self::dot(v, onto) / onto.len_sqr()) * onto
+    }
+
+    /// Component of `v` perpendicular to `onto`, which must not be zero.
+    pub fn reject(v: &Self, onto: &Self) -> Self {
+        v - Self::project(v, onto)
+    }
+}
+
 /// Random generation.
 impl Vec3 {
     /// Generate a random unit vector.
@@ -130,7 +238,7 @@ impl Vec3 {
     }
 
     /// Generate a random vector scaled to within the given range.
-    fn random_in_range(min: f64, max: f64) -> Self {
+    fn random_in_range(min: Float, max: Float) -> Self {
         Self::new(
             random::gen_range(min, max),
             random::gen_range(min, max),
@@ -156,18 +264,118 @@ impl fmt::Display for Vec3 {
 }
 
 impl ops::Index<usize> for Vec3 {
-    type Output = f64;
-    fn index(&self, i: usize) -> &f64 {
+    type Output = Float;
+    fn index(&self, i: usize) -> &Float {
         &self.components[i]
     }
 }
 
 impl ops::IndexMut<usize> for Vec3 {
-    fn index_mut(&mut self, i: usize) -> &mut f64 {
+    fn index_mut(&mut self, i: usize) -> &mut Float {
         &mut self.components[i]
     }
 }
 
+impl ops::Index<Axis> for Vec3 {
+    type Output = Float;
+    fn index(&self, axis: Axis) -> &Float {
+        &self.components[axis.index()]
+    }
+}
+
+impl ops::IndexMut<Axis> for Vec3 {
+    fn index_mut(&mut self, axis: Axis) -> &mut Float {
+        &mut self.components[axis.index()]
+    }
+}
+
+/// Component-wise kernels behind the arithmetic operators. With the `simd`
+/// feature they run on 4-wide vectors with an unused padding lane.
+#[cfg(not(feature = "simd"))]
+mod lanes {
+    use crate::Float;
+
+    pub fn add(u: &[Float; 3], v: &[Float; 3]) -> [Float; 3] {
+        [u[0] + v[0], u[1] + v[1], u[2] + v[2]]
+    }
+
+    pub fn sub(u: &[Float; 3], v: &[Float; 3]) -> [Float; 3] {
+        [u[0] - v[0], u[1] - v[1], u[2] - v[2]]
+    }
+
+    pub fn mul(u: &[Float; 3], v: &[Float; 3]) -> [Float; 3] {
+        [u[0] * v[0], u[1] * v[1], u[2] * v[2]]
+    }
+
+    pub fn div(u: &[Float; 3], v: &[Float; 3]) -> [Float; 3] {
+        [u[0] / v[0], u[1] / v[1], u[2] / v[2]]
+    }
+
+    pub fn scale(u: &[Float; 3], s: Float) -> [Float; 3] {
+        [u[0] * s, u[1] * s, u[2] * s]
+    }
+
+    pub fn div_scalar(u: &[Float; 3], s: Float) -> [Float; 3] {
+        [u[0] / s, u[1] / s, u[2] / s]
+    }
+
+    pub fn dot(u: &[Float; 3], v: &[Float; 3]) -> Float {
+        u[0] * v[0] + u[1] * v[1] + u[2] * v[2]
+    }
+}
+
+#[cfg(feature = "simd")]
+pub(crate) mod lanes {
+    use crate::Float;
+
+    /// Four-wide vector of `Float`.
+    #[cfg(not(feature = "f32"))]
+    pub type Simd = wide::f64x4;
+
+    /// Four-wide vector of `Float`.
+    #[cfg(feature = "f32")]
+    pub type Simd = wide::f32x4;
+
+    /// Loads three components, filling the padding lane with `pad`.
+    pub fn load(u: &[Float; 3], pad: Float) -> Simd {
+        Simd::new([u[0], u[1], u[2], pad])
+    }
+
+    /// Stores the first three lanes.
+    pub fn store(v: Simd) -> [Float; 3] {
+        let [x, y, z, _] = v.to_array();
+        [x, y, z]
+    }
+
+    pub fn add(u: &[Float; 3], v: &[Float; 3]) -> [Float; 3] {
+        store(load(u, 0.0) + load(v, 0.0))
+    }
+
+    pub fn sub(u: &[Float; 3], v: &[Float; 3]) -> [Float; 3] {
+        store(load(u, 0.0) - load(v, 0.0))
+    }
+
+    pub fn mul(u: &[Float; 3], v: &[Float; 3]) -> [Float; 3] {
+        store(load(u, 0.0) * load(v, 0.0))
+    }
+
+    pub fn div(u: &[Float; 3], v: &[Float; 3]) -> [Float; 3] {
+        store(load(u, 0.0) / load(v, 1.0))
+    }
+
+    pub fn scale(u: &[Float; 3], s: Float) -> [Float; 3] {
+        store(load(u, 0.0) * Simd::splat(s))
+    }
+
+    pub fn div_scalar(u: &[Float; 3], s: Float) -> [Float; 3] {
+        store(load(u, 0.0) / Simd::splat(s))
+    }
+
+    pub fn dot(u: &[Float; 3], v: &[Float; 3]) -> Float {
+        (load(u, 0.0) * load(v, 0.0)).reduce_add()
+    }
+}
+
 macro_rules! negate {
     ( $exp:ty ) => {
         impl ops::Neg for $exp {
@@ -187,7 +395,9 @@ macro_rules! add {
         impl ops::Add<$rhs> for $lhs {
             type Output = Vec3;
             fn add(self, rhs: $rhs) -> Vec3 {
-                Vec3::new(self.x() + rhs.x(), self.y() + rhs.y(), self.z() + rhs.z())
+                Vec3 {
+                    components: lanes::add(&self.components, &rhs.components),
+                }
             }
         }
     };
@@ -203,7 +413,9 @@ macro_rules! subtract {
         impl ops::Sub<$rhs> for $lhs {
             type Output = Vec3;
             fn sub(self, rhs: $rhs) -> Vec3 {
-                Vec3::new(self.x() - rhs.x(), self.y() - rhs.y(), self.z() - rhs.z())
+                Vec3 {
+                    components: lanes::sub(&self.components, &rhs.components),
+                }
             }
         }
     };
@@ -215,43 +427,59 @@ subtract!(Vec3, &Vec3);
 subtract!(&Vec3, &Vec3);
 
 macro_rules! scalar_multiply_rhs {
-    ( $lhs:ty , $rhs:ty ) => {
-        impl ops::Mul<$rhs> for $lhs {
+    ( $lhs:ty ) => {
+        impl ops::Mul<Float> for $lhs {
             type Output = Vec3;
-            fn mul(self, rhs: $rhs) -> Vec3 {
-                Vec3::new(self.x() * rhs, self.y() * rhs, self.z() * rhs)
+            fn mul(self, rhs: Float) -> Vec3 {
+                Vec3 {
+                    components: lanes::scale(&self.components, rhs),
+                }
+            }
+        }
+
+        impl ops::Mul<&Float> for $lhs {
+            type Output = Vec3;
+            fn mul(self, rhs: &Float) -> Vec3 {
+                self * *rhs
             }
         }
     };
 }
 
-scalar_multiply_rhs!(Vec3, f64);
-scalar_multiply_rhs!(&Vec3, f64);
-scalar_multiply_rhs!(Vec3, &f64);
-scalar_multiply_rhs!(&Vec3, &f64);
+scalar_multiply_rhs!(Vec3);
+scalar_multiply_rhs!(&Vec3);
 
 macro_rules! scalar_multiply_lhs {
-    ( $lhs:ty , $rhs:ty ) => {
-        impl ops::Mul<$rhs> for $lhs {
+    ( $rhs:ty ) => {
+        impl ops::Mul<$rhs> for Float {
             type Output = Vec3;
             fn mul(self, rhs: $rhs) -> Vec3 {
-                Vec3::new(self * rhs.x(), self * rhs.y(), self * rhs.z())
+                Vec3 {
+                    components: lanes::scale(&rhs.components, self),
+                }
+            }
+        }
+
+        impl ops::Mul<$rhs> for &Float {
+            type Output = Vec3;
+            fn mul(self, rhs: $rhs) -> Vec3 {
+                *self * rhs
             }
         }
     };
 }
 
-scalar_multiply_lhs!(f64, Vec3);
-scalar_multiply_lhs!(&f64, Vec3);
-scalar_multiply_lhs!(f64, &Vec3);
-scalar_multiply_lhs!(&f64, &Vec3);
+scalar_multiply_lhs!(Vec3);
+scalar_multiply_lhs!(&Vec3);
 
 macro_rules! hadamard_multiply {
     ( $lhs:ty , $rhs:ty ) => {
         impl ops::Mul<$rhs> for $lhs {
             type Output = Vec3;
             fn mul(self, rhs: $rhs) -> Vec3 {
-                Vec3::new(self.x() * rhs.x(), self.y() * rhs.y(), self.z() * rhs.z())
+                Vec3 {
+                    components: lanes::mul(&self.components, &rhs.components),
+                }
             }
         }
     };
@@ -273,17 +501,19 @@ macro_rules! scalar_divide {
     };
 }
 
-scalar_divide!(Vec3, f64);
-scalar_divide!(&Vec3, f64);
-scalar_divide!(Vec3, &f64);
-scalar_divide!(&Vec3, &f64);
+scalar_divide!(Vec3, Float);
+scalar_divide!(&Vec3, Float);
+scalar_divide!(Vec3, &Float);
+scalar_divide!(&Vec3, &Float);
 
 macro_rules! hadamard_divide {
     ( $lhs:ty , $rhs:ty ) => {
         impl ops::Div<$rhs> for $lhs {
             type Output = Vec3;
             fn div(self, rhs: $rhs) -> Vec3 {
-                Vec3::new(self.x() / rhs.x(), self.y() / rhs.y(), self.z() / rhs.z())
+                Vec3 {
+                    components: lanes::div(&self.components, &rhs.components),
+                }
             }
         }
     };
@@ -298,9 +528,7 @@ macro_rules! add_assign {
     ( $rhs:ty ) => {
         impl ops::AddAssign<$rhs> for Vec3 {
             fn add_assign(&mut self, rhs: $rhs) {
-                self.components[0] = self.x() + rhs.x();
-                self.components[1] = self.y() + rhs.y();
-                self.components[2] = self.z() + rhs.z()
+                self.components = lanes::add(&self.components, &rhs.components)
             }
         }
     };
@@ -313,9 +541,7 @@ macro_rules! subtract_assign {
     ( $rhs:ty ) => {
         impl ops::SubAssign<$rhs> for Vec3 {
             fn sub_assign(&mut self, rhs: $rhs) {
-                self.components[0] = self.x() - rhs.x();
-                self.components[1] = self.y() - rhs.y();
-                self.components[2] = self.z() - rhs.z()
+                self.components = lanes::sub(&self.components, &rhs.components)
             }
         }
     };
@@ -324,28 +550,23 @@ macro_rules! subtract_assign {
 subtract_assign!(Vec3);
 subtract_assign!(&Vec3);
 
-macro_rules! scalar_multiply_assign {
-    ( $rhs:ty ) => {
-        impl ops::MulAssign<$rhs> for Vec3 {
-            fn mul_assign(&mut self, rhs: $rhs) {
-                self.components[0] = self.x() * rhs;
-                self.components[1] = self.y() * rhs;
-                self.components[2] = self.z() * rhs
-            }
-        }
-    };
+impl ops::MulAssign<Float> for Vec3 {
+    fn mul_assign(&mut self, rhs: Float) {
+        self.components = lanes::scale(&self.components, rhs)
+    }
 }
 
-scalar_multiply_assign!(f64);
-scalar_multiply_assign!(&f64);
+impl ops::MulAssign<&Float> for Vec3 {
+    fn mul_assign(&mut self, rhs: &Float) {
+        *self *= *rhs
+    }
+}
 
 macro_rules! hadamard_multiply_assign {
     ( $rhs:ty ) => {
         impl ops::MulAssign<$rhs> for Vec3 {
             fn mul_assign(&mut self, rhs: $rhs) {
-                self.components[0] = self.x() * rhs.x();
-                self.components[1] = self.y() * rhs.y();
-                self.components[2] = self.z() * rhs.z()
+                self.components = lanes::mul(&self.components, &rhs.components)
             }
         }
     };
@@ -354,28 +575,23 @@ macro_rules! hadamard_multiply_assign {
 hadamard_multiply_assign!(Vec3);
 hadamard_multiply_assign!(&Vec3);
 
-macro_rules! scalar_divide_assign {
-    ( $rhs:ty ) => {
-        impl ops::DivAssign<$rhs> for Vec3 {
-            fn div_assign(&mut self, rhs: $rhs) {
-                self.components[0] = self.x() / rhs;
-                self.components[1] = self.y() / rhs;
-                self.components[2] = self.z() / rhs
-            }
-        }
-    };
+impl ops::DivAssign<Float> for Vec3 {
+    fn div_assign(&mut self, rhs: Float) {
+        self.components = lanes::div_scalar(&self.components, rhs)
+    }
 }
 
-scalar_divide_assign!(f64);
-scalar_divide_assign!(&f64);
+impl ops::DivAssign<&Float> for Vec3 {
+    fn div_assign(&mut self, rhs: &Float) {
+        *self /= *rhs
+    }
+}
 
 macro_rules! hadamard_divide_assign {
     ( $rhs:ty ) => {
         impl ops::DivAssign<$rhs> for Vec3 {
             fn div_assign(&mut self, rhs: $rhs) {
-                self.components[0] = self.x() / rhs.x();
-                self.components[1] = self.y() / rhs.y();
-                self.components[2] = self.z() / rhs.z()
+                self.components = lanes::div(&self.components, &rhs.components)
             }
         }
     };
@@ -384,9 +600,39 @@ macro_rules! hadamard_divide_assign {
 hadamard_divide_assign!(Vec3);
 hadamard_divide_assign!(&Vec3);
 
+// `Float` may already match the glam scalar, making some casts redundant.
+#[cfg(feature = "glam")]
+macro_rules! glam_conversions {
+    ( $glam:ty , $scalar:ty ) => {
+        impl From<$glam> for Vec3 {
+            #[allow(clippy::unnecessary_cast)]
+            fn from(v: $glam) -> Vec3 {
+                let [x, y, z] = v.to_array();
+                Vec3::new(x as Float, y as Float, z as Float)
+            }
+        }
+
+        impl From<Vec3> for $glam {
+            #[allow(clippy::unnecessary_cast)]
+            fn from(v: Vec3) -> $glam {
+                <$glam>::new(v.x() as $scalar, v.y() as $scalar, v.z() as $scalar)
+            }
+        }
+    };
+}
+
+#[cfg(feature = "glam")]
+glam_conversions!(glam::DVec3, f64);
+#[cfg(feature = "glam")]
+glam_conversions!(glam::Vec3, f32);
+#[cfg(feature = "glam")]
+glam_conversions!(glam::Vec3A, f32);
+
 #[cfg(test)]
 mod tests {
-    use super::Vec3;
+    use super::{Axis, Vec3};
+    use crate::almost::Tolerance;
+    use crate::Float;
 
     #[test]
     fn vec3_components() {
@@ -431,12 +677,90 @@ mod tests {
         assert!(!x.almost_eq(&v));
         assert!(!x.almost_eq(&w));
 
-        let w = Vec3::new(1.0 + 1e-7, 2.0, 3.0);
-        let x = Vec3::new(1.0 + 1e-11, 2.0, 3.0);
+        // A difference of 1e-7 is below single precision.
+        #[cfg(not(feature = "f32"))]
+        {
+            let w = Vec3::new(1.0 + 1e-7, 2.0, 3.0);
+            let x = Vec3::new(1.0 + 1e-11, 2.0, 3.0);
 
-        assert!(v.almost_eq(&v));
-        assert!(!v.almost_eq(&w));
-        assert!(v.almost_eq(&x));
+            assert!(v.almost_eq(&v));
+            assert!(!v.almost_eq(&w));
+            assert!(v.almost_eq(&x));
+        }
+
+        // Far from the origin, differences are relative to the largest
+        // component, including the small ones.
+        let v = Vec3::new(1e4, 0.0, -2e4);
+        let w = Vec3::new(1e4 + 1e-4, 1e-4, -2e4);
+        assert!(v.almost_eq(&w) && w.almost_eq(&v));
+        assert!(!v.almost_eq(&Vec3::new(1e4, 1.0, -2e4)));
+        assert!(!v.almost_eq_with(&w, &Tolerance::new(1e-5, 0.0)));
+    }
+
+    #[test]
+    fn vec3_componentwise() {
+        let u = Vec3::new(1.0, -2.0, 3.0);
+        let v = Vec3::new(-1.0, 4.0, 2.0);
+        assert_eq!(Vec3::min(&u, &v), Vec3::new(-1.0, -2.0, 2.0));
+        assert_eq!(Vec3::max(&u, &v), Vec3::new(1.0, 4.0, 3.0));
+        assert_eq!(u.abs(), Vec3::new(1.0, 2.0, 3.0));
+
+        let (lo, hi) = (Vec3::new(0.0, 0.0, 0.0), Vec3::new(2.0, 2.0, 2.0));
+        assert_eq!(u.clamp(&lo, &hi), Vec3::new(1.0, 0.0, 2.0));
+        assert_eq!(Vec3::lerp(&u, &v, 0.0), u);
+        assert_eq!(Vec3::lerp(&u, &v, 0.5), Vec3::new(0.0, 1.0, 2.5));
+        assert_eq!(Vec3::lerp(&u, &v, 1.0), v);
+
+        assert_eq!((u.min_component(), u.max_component()), (-2.0, 3.0));
+        assert_eq!(u.max_axis(), Axis::Z);
+        assert_eq!(v.max_axis(), Axis::Y);
+        assert_eq!(Vec3::new(1.0, 1.0, 0.0).max_axis(), Axis::X);
+    }
+
+    #[test]
+    fn vec3_axis() {
+        let mut v = Vec3::new(1.0, 2.0, 3.0);
+        assert_eq!(Axis::ALL.map(|axis| v[axis]), [1.0, 2.0, 3.0]);
+        v[Axis::Y] = 5.0;
+        assert_eq!(v, Vec3::new(1.0, 5.0, 3.0));
+
+        assert_eq!(
+            Axis::ALL.map(|axis| axis.next()),
+            [Axis::Y, Axis::Z, Axis::X]
+        );
+        let (x, y) = (Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0));
+        assert_eq!(Vec3::cross(&x, &y).max_axis(), Axis::X.next().next());
+    }
+
+    #[test]
+    fn vec3_project() {
+        let v = Vec3::new(3.0, 4.0, 5.0);
+        let onto = Vec3::new(0.0, 2.0, 0.0);
+        assert_eq!(Vec3::project(&v, &onto), Vec3::new(0.0, 4.0, 0.0));
+        assert_eq!(Vec3::reject(&v, &onto), Vec3::new(3.0, 0.0, 5.0));
+
+        // The two parts add back up to the vector and are perpendicular.
+        let onto = Vec3::new(1.0, -1.0, 2.0);
+        let (along, across) = (Vec3::project(&v, &onto), Vec3::reject(&v, &onto));
+        assert!((along + across).almost_eq(&v));
+        assert!(Vec3::dot(&across, &onto).abs() < 1e-4);
+    }
+
+    #[cfg(feature = "glam")]
+    #[test]
+    fn vec3_glam() {
+        let v = Vec3::new(1.0, -2.0, 3.5);
+
+        let d: glam::DVec3 = v.into();
+        assert_eq!(d, glam::DVec3::new(1.0, -2.0, 3.5));
+        assert_eq!(Vec3::from(d), v);
+
+        let a: glam::Vec3A = v.into();
+        assert_eq!(a, glam::Vec3A::new(1.0, -2.0, 3.5));
+        assert_eq!(Vec3::from(a), v);
+
+        let f: glam::Vec3 = v.into();
+        assert_eq!(Vec3::from(f), v);
     }
 
     #[test]
@@ -499,6 +823,20 @@ mod tests {
         assert_eq!([u[0], u[1], u[2]], [5.0, 10.0, 15.0]);
         u /= 3.0;
         assert!(u.almost_eq(&Vec3::new(5.0 / 3.0, 10.0 / 3.0, 15.0 / 3.0)));
+
+        // Scalars may be borrowed, and division is exact rather than by a
+        // rounded reciprocal.
+        let two: &Float = &2.0;
+        let mut u = v * two;
+        assert_eq!([u[0], u[1], u[2]], [2.0, 4.0, 6.0]);
+        assert_eq!(two * v, u);
+        u *= two;
+        assert_eq!([u[0], u[1], u[2]], [4.0, 8.0, 12.0]);
+        u /= two;
+        assert_eq!([u[0], u[1], u[2]], [2.0, 4.0, 6.0]);
+        let mut u = Vec3::new(1.0, 7.0, 49.0);
+        u /= 49.0;
+        assert_eq!([u[0], u[1], u[2]], [1.0 / 49.0, 7.0 / 49.0, 1.0]);
     }
 
     #[test]
@@ -518,7 +856,7 @@ mod tests {
 
         assert_eq!(v.len_sqr(), 14.0);
         assert_eq!(v.len_sqr(), Vec3::dot(&v, &v));
-        assert_eq!(v.len(), f64::sqrt(14.0));
+        assert_eq!(v.len(), Float::sqrt(14.0));
     }
 
     #[test]